@@ -1,3 +1,9 @@
+use std::collections::BTreeMap;
+use std::path::Path;
+use std::path::PathBuf;
+use std::process::Command;
+use std::time::Duration;
+
 use rstest::rstest;
 use similar_asserts::assert_eq;
 
@@ -52,3 +58,3740 @@ fn get_position_of_tokens(
   let position = group.position_of_range(bounds);
   assert_eq!(position, expected);
 }
+
+#[rstest]
+#[case::shift_down("## Usage\n\ncontent", 1, "### Usage\n\ncontent")]
+#[case::shift_up("### Usage\n#### Example", -1, "## Usage\n### Example")]
+#[case::clamp_max("##### Deep\n###### Deeper", 3, "###### Deep\n###### Deeper")]
+#[case::clamp_min("## Usage", -4, "# Usage")]
+#[case::empty_heading("#\n##", 1, "##\n###")]
+#[case::not_a_heading("#hashtag\n    # indented code", 1, "#hashtag\n    # indented code")]
+#[case::fenced_code(
+  "# Title\n```sh\n# comment\n```\n## Next",
+  1,
+  "## Title\n```sh\n# comment\n```\n### Next"
+)]
+fn heading_shift_transformer(#[case] input: &str, #[case] shift: i64, #[case] expected: &str) {
+  let transformers = vec![Transformer {
+    r#type: TransformerType::HeadingShift,
+    args: vec![Argument::Number(shift as f64)],
+  }];
+  let result = apply_transformers(input, &transformers);
+  assert_eq!(result, expected);
+}
+
+#[rstest]
+#[case::trim_indent(
+  "\n  content\n\nmore\n",
+  "|trim|indent:\"/// \"",
+  "/// content\n///\n/// more"
+)]
+#[case::wrap("content", "|wrap:\"**\"", "**content**")]
+#[case::code_block("let a = 1;", "|codeBlock:\"rust\"", "```rust\nlet a = 1;\n```")]
+#[case::replace("hello world", "|replace:\"world\":\"mdt\"|code", "`hello mdt`")]
+#[case::heading_shift("# Title\n## Usage", "|headingShift:2", "### Title\n#### Usage")]
+#[case::heading_shift_negative("### Usage", "|headingShift:-1", "## Usage")]
+fn transformers_from_tags(
+  #[case] content: &str,
+  #[case] transformers: &str,
+  #[case] expected: &str,
+) -> MdtResult<()> {
+  let input = format!("<!-- {{=block{transformers}}} -->\n\n<!-- {{/block}} -->\n");
+  let blocks = parse(input)?;
+  let block = blocks.first().expect("a block should be parsed");
+  let result = apply_transformers(content, &block.transformers);
+  assert_eq!(result, expected);
+
+  Ok(())
+}
+
+#[test]
+fn parse_blocks() -> MdtResult<()> {
+  let input = "# Title\n\n<!-- {@provider} -->\n\ncontent\n\n<!-- {/provider} -->\n\n<!-- \
+               {=consumer|trim} -->\n<!-- {/consumer} -->\n";
+  let blocks = parse(input)?;
+  assert_eq!(blocks.len(), 2);
+
+  let provider = blocks.get(0).unwrap();
+  assert_eq!(provider.name, "provider");
+  assert_eq!(provider.r#type, BlockType::Provider);
+  assert_eq!(input.get(provider.content_range()), Some("\n\ncontent\n\n"));
+
+  let consumer = blocks.get(1).unwrap();
+  assert_eq!(consumer.name, "consumer");
+  assert_eq!(consumer.r#type, BlockType::Consumer);
+  assert_eq!(consumer.transformers.len(), 1);
+  assert_eq!(input.get(consumer.content_range()), Some("\n"));
+
+  Ok(())
+}
+
+#[rstest]
+#[case::unclosed("<!-- {=block} -->\n")]
+#[case::unknown_transformer("<!-- {=block|unknown} -->\n<!-- {/block} -->\n")]
+fn parse_errors(#[case] input: &str) {
+  assert!(parse(input).is_err());
+}
+
+#[test]
+fn inline_blocks_render_their_template() -> MdtResult<()> {
+  let readme = "Version <!-- {~version:\"{{ pkg.version }}\"|code} -->old<!-- {/version} \
+                -->\n<!-- {~name:'{{ pkg.name }}'} -->demo<!-- {/name} -->\n";
+  let mut project = Project::from_files(vec![(PathBuf::from("readme.md"), readme.to_string())])?;
+  project.data.insert(
+    "pkg".into(),
+    serde_json::json!({ "name": "demo", "version": "1.2.0" }),
+  );
+
+  let inline = project
+    .consumers
+    .first()
+    .expect("an inline block should be parsed");
+  assert_eq!(inline.block.r#type, BlockType::Inline);
+  assert_eq!(inline.block.template.as_deref(), Some("{{ pkg.version }}"));
+  assert_eq!(inline.block.transformers.len(), 1);
+
+  let result = compute_updates(&project)?;
+  assert_eq!(result.updated_count, 1);
+  assert_eq!(
+    result.updated_files.get(&PathBuf::from("readme.md")),
+    Some(&readme.replace("-->old<!--", "-->`1.2.0`<!--"))
+  );
+
+  let result = check_project(&project, CheckOptions::strict())?;
+  assert_eq!(
+    result.stale,
+    vec![(PathBuf::from("readme.md"), "version".to_string())]
+  );
+  assert!(result.diagnostics.is_empty());
+
+  // Without a template the tag isn't an inline block.
+  assert!(parse("<!-- {~version} -->\n<!-- {/version} -->\n")?.is_empty());
+
+  Ok(())
+}
+
+#[rstest]
+#[case::unknown_transformer("<!-- {~block:\"a\"|unknown} -->\n<!-- {/block} -->\n")]
+#[case::invalid_arguments("<!-- {~block:\"a\"|indent:1} -->\n<!-- {/block} -->\n")]
+fn inline_block_errors(#[case] input: &str) {
+  assert!(parse(input).is_err());
+}
+
+#[test]
+fn compute_updates_only_replaces_stale_regions() -> MdtResult<()> {
+  let readme = "# Readme\r\n\n<!-- {=first} -->old<!-- {/first} -->\nkeep   this\n<!-- {=second} \
+                -->up to date<!-- {/second} -->\n<!-- {=missing} -->untouched<!-- {/missing} -->\n";
+  let project = Project::from_files(vec![
+    (
+      PathBuf::from("template.t.md"),
+      "<!-- {@first} -->new<!-- {/first} -->\n<!-- {@second} -->up to date<!-- {/second} -->\n"
+        .to_string(),
+    ),
+    (PathBuf::from("readme.md"), readme.to_string()),
+  ])?;
+  let result = compute_updates(&project)?;
+
+  assert_eq!(result.updated_count, 1);
+  assert_eq!(
+    result.updated_files.get(&PathBuf::from("readme.md")),
+    Some(&readme.replace("-->old<!--", "-->new<!--"))
+  );
+
+  Ok(())
+}
+
+#[test]
+fn compute_updates_for_source_files() -> MdtResult<()> {
+  let source = "/// Docs: <!-- {=docs|trim|code} --><!-- {/docs} -->\nfn main() {}\n";
+  let project = Project::from_files(vec![
+    (
+      PathBuf::from("template.t.md"),
+      "<!-- {@docs} -->\n\nSome docs.\n\n<!-- {/docs} -->\n".to_string(),
+    ),
+    (PathBuf::from("main.rs"), source.to_string()),
+  ])?;
+  let result = compute_updates(&project)?;
+
+  assert_eq!(
+    result
+      .updated_files
+      .get(&PathBuf::from("main.rs"))
+      .map(String::as_str),
+    Some("/// Docs: <!-- {=docs|trim|code} -->`Some docs.`<!-- {/docs} -->\nfn main() {}\n")
+  );
+
+  Ok(())
+}
+
+#[test]
+fn duplicate_providers_are_rejected() {
+  let result = Project::from_files(vec![
+    (
+      PathBuf::from("a.t.md"),
+      "<!-- {@name} --><!-- {/name} -->\n".to_string(),
+    ),
+    (
+      PathBuf::from("b.t.md"),
+      "<!-- {@name} --><!-- {/name} -->\n".to_string(),
+    ),
+  ]);
+
+  assert!(matches!(result, Err(MdtError::DuplicateProvider { .. })));
+}
+
+#[rstest]
+#[case::shorter("short", "|truncate:10", "short")]
+#[case::chars("hello world", "|truncate:7", "hello w")]
+#[case::ellipsis("hello world", "|truncate:7:\"…\"", "hello w…")]
+#[case::words(
+  "hello wonderful world",
+  "|truncate:2:\"…\":\"words\"",
+  "hello wonderful…"
+)]
+#[case::words_fit("hello world", "|truncate:2:\"…\":\"words\"", "hello world")]
+#[case::words_keep_spacing(
+  "one  two\nthree four",
+  "|truncate:3:\"…\":\"words\"",
+  "one  two\nthree…"
+)]
+#[case::lines("one\ntwo\nthree", "|truncate:2:\"\\n…\":\"lines\"", "one\ntwo\n…")]
+#[case::lines_keep_carriage_returns(
+  "one\r\ntwo\r\nthree",
+  "|truncate:2:\"…\":\"lines\"",
+  "one\r\ntwo\r…"
+)]
+#[case::lines_trailing_newline("one\ntwo\n", "|truncate:2:\"…\":\"lines\"", "one\ntwo…")]
+#[case::word_boundary("hello wonderful world", "|truncate:9:\"…\":\"chars\":true", "hello…")]
+#[case::word_boundary_at_space(
+  "hello wonderful world",
+  "|truncate:5:\"…\":\"chars\":true",
+  "hello…"
+)]
+#[case::word_boundary_at_word_end(
+  "hello wonderful world",
+  "|truncate:15:\"…\":\"chars\":true",
+  "hello wonderful…"
+)]
+#[case::word_boundary_first_word("wonderful", "|truncate:3:\"…\":\"chars\":true", "won…")]
+#[case::without_word_boundary(
+  "hello wonderful world",
+  "|truncate:9:\"…\":\"chars\":false",
+  "hello won…"
+)]
+#[case::unicode("ünïcödé", "|truncate:3", "ünï")]
+#[case::chained_with_trim("\n  hello world  \n", "|trim|truncate:5:\"…\"", "hello…")]
+fn truncate_transformer(
+  #[case] content: &str,
+  #[case] transformers: &str,
+  #[case] expected: &str,
+) -> MdtResult<()> {
+  let input = format!("<!-- {{=block{transformers}}} -->\n<!-- {{/block}} -->\n");
+  let blocks = parse(input)?;
+  let block = blocks.first().expect("a block should be parsed");
+  assert_eq!(apply_transformers(content, &block.transformers), expected);
+
+  Ok(())
+}
+
+#[test]
+fn built_in_transformers_are_listed() -> MdtResult<()> {
+  for name in BUILT_IN_TRANSFORMERS {
+    assert_eq!(name.parse::<TransformerType>()?.name(), *name);
+  }
+
+  Ok(())
+}
+
+#[rstest]
+#[case::missing_argument("|truncate")]
+#[case::wrong_type("|truncate:\"ten\"")]
+#[case::fraction("|truncate:1.5")]
+#[case::zero_width("|wrapWidth:0")]
+#[case::dedent_argument("|dedent:2")]
+#[case::unknown_unit("|truncate:10:\"…\":\"pages\"")]
+#[case::word_boundary_type("|truncate:10:\"…\":\"chars\":\"yes\"")]
+#[case::too_many_arguments("|trim:\"x\"")]
+#[case::replace_missing_replacement("|replace:\"a\"")]
+#[case::lines_start_at_one("|lines:0:3")]
+#[case::lines_reversed("|lines:5:3")]
+#[case::negative_line_count("|firstLines:-1")]
+fn invalid_transformer_arguments(#[case] transformers: &str) {
+  let input = format!("<!-- {{=block{transformers}}} -->\n<!-- {{/block}} -->\n");
+  let result = parse(input);
+  assert!(
+    matches!(result, Err(MdtError::InvalidTransformerArguments { .. })),
+    "{result:?}"
+  );
+}
+
+#[test]
+fn escaped_arguments_keep_block_positions() -> MdtResult<()> {
+  let input = "<!-- {=block|replace:\"\\n\":\" \"} -->old<!-- {/block} -->\n";
+  let blocks = parse(input)?;
+  let block = blocks.first().expect("a block should be parsed");
+  assert_eq!(input.get(block.content_range()), Some("old"));
+  assert_eq!(apply_transformers("a\nb", &block.transformers), "a b");
+
+  Ok(())
+}
+
+#[test]
+fn compute_patches_returns_byte_ranges() -> MdtResult<()> {
+  let readme = "# Ünïcode\n\n<!-- {=first} -->old<!-- {/first} -->\n";
+  let source = "// <!-- {=first|code} --><!-- {/first} -->\n";
+  let project = Project::from_files(vec![
+    (
+      PathBuf::from("template.t.md"),
+      "<!-- {@first} -->new<!-- {/first} -->\n".to_string(),
+    ),
+    (PathBuf::from("readme.md"), readme.to_string()),
+    (PathBuf::from("lib.rs"), source.to_string()),
+  ])?;
+  let patches = compute_patches(&project)?;
+  let start = readme.find("old").unwrap();
+
+  assert_eq!(
+    patches,
+    vec![
+      Patch {
+        file: PathBuf::from("lib.rs"),
+        name: "first".into(),
+        range: 25..25,
+        replacement: "`new`".into(),
+      },
+      Patch {
+        file: PathBuf::from("readme.md"),
+        name: "first".into(),
+        range: start..start + 3,
+        replacement: "new".into(),
+      },
+    ]
+  );
+
+  let patched = apply_patches(
+    readme,
+    patches
+      .iter()
+      .filter(|patch| patch.file.ends_with("readme.md")),
+  );
+  assert_eq!(patched, readme.replace("old", "new"));
+
+  Ok(())
+}
+
+#[rstest]
+#[case::short_lines("a short line", 20, "a short line")]
+#[case::reflow(
+  "one two three\nfour five six seven",
+  14,
+  "one two three\nfour five six\nseven"
+)]
+#[case::paragraphs("one two three\n\nfour five", 7, "one two\nthree\n\nfour\nfive")]
+#[case::long_word(
+  "tiny supercalifragilistic word",
+  10,
+  "tiny\nsupercalifragilistic\nword"
+)]
+#[case::list_items(
+  "- first item is long\n- second",
+  12,
+  "- first item\n  is long\n- second"
+)]
+#[case::ordered_list("10. alpha beta gamma", 12, "10. alpha\n    beta\n    gamma")]
+#[case::fenced_code(
+  "words that wrap\n```sh\nthis line stays as it is\n```",
+  10,
+  "words that\nwrap\n```sh\nthis line stays as it is\n```"
+)]
+#[case::headings_and_tables(
+  "# A long heading line\n| a | table |",
+  5,
+  "# A long heading line\n| a | table |"
+)]
+fn wrap_width_transformer(#[case] content: &str, #[case] width: usize, #[case] expected: &str) {
+  let transformers = vec![Transformer {
+    r#type: TransformerType::WrapWidth,
+    args: vec![Argument::Number(width as f64)],
+  }];
+  assert_eq!(apply_transformers(content, &transformers), expected);
+}
+
+#[test]
+fn wrap_width_before_indent() -> MdtResult<()> {
+  let input = "<!-- {=block|trim|wrapWidth:12|indent:\"/// \"} -->\n<!-- {/block} -->\n";
+  let blocks = parse(input)?;
+  let block = blocks.first().expect("a block should be parsed");
+  let result = apply_transformers("\nwrap these docs please\n", &block.transformers);
+  assert_eq!(result, "/// wrap these\n/// docs please");
+
+  Ok(())
+}
+
+#[test]
+fn compute_rename_patches_every_tag() -> MdtResult<()> {
+  let template = "<!-- {@old} -->content<!-- {/old} -->\n";
+  let readme = "<!-- {=old|trim} -->content<!-- {/old } -->\n<!-- {=other} --><!-- {/other} -->\n";
+  let project = Project::from_files(vec![
+    (PathBuf::from("template.t.md"), template.to_string()),
+    (PathBuf::from("readme.md"), readme.to_string()),
+  ])?;
+  let patches = compute_rename(&project, "old", "renamed")?;
+  assert_eq!(patches.len(), 4);
+
+  let result = apply_project_patches(&project, &patches);
+  assert_eq!(
+    result.updated_files.get(&PathBuf::from("template.t.md")),
+    Some(&template.replace("old", "renamed"))
+  );
+  assert_eq!(
+    result.updated_files.get(&PathBuf::from("readme.md")),
+    Some(&readme.replace("old", "renamed"))
+  );
+
+  Ok(())
+}
+
+#[test]
+fn compute_rename_patches_concatenated_and_include_tags() -> MdtResult<()> {
+  let template = "<!-- {@intro} -->hello<!-- {/intro} -->\n<!-- {@install} -->install<!-- \
+                  {/install} -->\n<!-- {@usage} --><!-- {> intro} --><!-- {/usage} -->\n";
+  let readme = "<!-- {=intro+install} --><!-- {/intro+install} -->\n<!-- {=install+intro} --><!-- \
+                {/install+intro} -->\n";
+  let project = Project::from_files(vec![
+    (PathBuf::from("template.t.md"), template.to_string()),
+    (PathBuf::from("readme.md"), readme.to_string()),
+  ])?;
+  let patches = compute_rename(&project, "intro", "greeting")?;
+  let result = apply_project_patches(&project, &patches);
+
+  assert_eq!(
+    result.updated_files.get(&PathBuf::from("template.t.md")),
+    Some(&template.replace("intro", "greeting"))
+  );
+  assert_eq!(
+    result.updated_files.get(&PathBuf::from("readme.md")),
+    Some(&readme.replace("intro", "greeting"))
+  );
+
+  Ok(())
+}
+
+#[test]
+fn block_name_at_finds_the_name_under_the_offset() -> MdtResult<()> {
+  let template = "<!-- {@intro} -->hello<!-- {/intro} -->\n<!-- {@usage} --><!-- {> intro} \
+                  --><!-- {/usage} -->\n";
+  let readme = "<!-- {=intro+usage} --><!-- {/intro+usage} -->\n";
+  let project = Project::from_files(vec![
+    (PathBuf::from("template.t.md"), template.to_string()),
+    (PathBuf::from("readme.md"), readme.to_string()),
+  ])?;
+  // The offset is `from` bytes into the first occurrence of the text.
+  let name_at = |file: &str, text: &str, from: usize| {
+    let content = if file == "readme.md" {
+      readme
+    } else {
+      template
+    };
+    let offset = content.find(text).unwrap_or_default() + from;
+    block_name_at(&project, Path::new(file), offset)
+  };
+
+  assert_eq!(name_at("template.t.md", "intro", 0)?, Some("intro".into()));
+  assert_eq!(
+    name_at("template.t.md", "{/intro", 3)?,
+    Some("intro".into())
+  );
+  assert_eq!(
+    name_at("template.t.md", "{> intro", 4)?,
+    Some("intro".into())
+  );
+  assert_eq!(name_at("readme.md", "+usage", 3)?, Some("usage".into()));
+  assert_eq!(name_at("readme.md", "intro+", 2)?, Some("intro".into()));
+  assert_eq!(name_at("template.t.md", "hello", 2)?, None);
+  assert_eq!(block_name_at(&project, Path::new("missing.md"), 0)?, None);
+
+  Ok(())
+}
+
+#[test]
+fn scan_project_prefers_overlays() -> AnyEmptyResult {
+  let directory = tempfile::tempdir()?;
+  let root = directory.path();
+  let template = root.join("template.t.md");
+  let readme = root.join("readme.md");
+  let unsaved = root.join("unsaved.md");
+  std::fs::write(&template, "<!-- {@block} -->disk<!-- {/block} -->\n")?;
+  std::fs::write(&readme, "<!-- {=block} -->disk<!-- {/block} -->\n")?;
+
+  let overlays = std::collections::BTreeMap::from([
+    (
+      template,
+      "<!-- {@block} -->editor<!-- {/block} -->\n".to_string(),
+    ),
+    (
+      unsaved.clone(),
+      "<!-- {=block} --><!-- {/block} -->\n".to_string(),
+    ),
+  ]);
+  let project = scan_project_with_overlays(root, &overlays)?;
+  let result = compute_updates(&project)?;
+
+  assert_eq!(result.updated_count, 2);
+  assert_eq!(
+    result.updated_files.get(&readme).map(String::as_str),
+    Some("<!-- {=block} -->editor<!-- {/block} -->\n")
+  );
+  assert_eq!(
+    result.updated_files.get(&unsaved).map(String::as_str),
+    Some("<!-- {=block} -->editor<!-- {/block} -->\n")
+  );
+
+  Ok(())
+}
+
+#[rstest]
+#[case::spaces("    - one\n      - two\n    - three", "- one\n  - two\n- three")]
+#[case::blank_lines("  first\n\n    \n  second", "first\n\n\nsecond")]
+#[case::no_indent("first\n  second", "first\n  second")]
+#[case::tabs("\tfirst\n\t\tsecond", "first\n\tsecond")]
+#[case::mixed_tabs_and_spaces("\t  first\n  \tsecond", "\t  first\n  \tsecond")]
+#[case::shared_mixed_prefix("\t  first\n\t    second\n\t third", " first\n   second\nthird")]
+#[case::empty("", "")]
+fn dedent_transformer(#[case] content: &str, #[case] expected: &str) -> MdtResult<()> {
+  let blocks = parse("<!-- {=block|dedent} -->\n<!-- {/block} -->\n")?;
+  let block = blocks.first().expect("a block should be parsed");
+  assert_eq!(apply_transformers(content, &block.transformers), expected);
+
+  Ok(())
+}
+
+#[test]
+fn dedent_reverses_indent() -> MdtResult<()> {
+  let blocks = parse("<!-- {=block|indent:\"    \"|dedent} -->\n<!-- {/block} -->\n")?;
+  let block = blocks.first().expect("a block should be parsed");
+  let content = "# Title\n  nested";
+  assert_eq!(apply_transformers(content, &block.transformers), content);
+
+  Ok(())
+}
+
+#[rstest]
+#[case::valid("exampleName", true)]
+#[case::underscore("_private_name", true)]
+#[case::digits("block2", true)]
+#[case::leading_digit("2block", false)]
+#[case::dash("my-block", false)]
+#[case::space("my block", false)]
+#[case::empty("", false)]
+#[case::namespaced("docs/overview", true)]
+#[case::colon_namespace("pkg:install", true)]
+#[case::nested_namespace("pkg/docs/overview", true)]
+#[case::trailing_separator("docs/", false)]
+#[case::leading_separator("/docs", false)]
+fn valid_block_names(#[case] name: &str, #[case] expected: bool) {
+  assert_eq!(is_valid_block_name(name), expected);
+}
+
+#[rstest]
+#[case::dash("my-block", Some("myBlock"))]
+#[case::spaces("install  steps", Some("installSteps"))]
+#[case::leading_digit("2fast", Some("fast"))]
+#[case::symbols("@@@", None)]
+#[case::namespaced("my-pkg/install-steps", Some("myPkg/installSteps"))]
+fn suggested_block_names(#[case] name: &str, #[case] expected: Option<&str>) {
+  assert_eq!(suggest_block_name(name).as_deref(), expected);
+}
+
+#[test]
+fn rename_validation() -> MdtResult<()> {
+  let project = Project::from_files(vec![(
+    PathBuf::from("template.t.md"),
+    "<!-- {@first} --><!-- {/first} -->\n<!-- {@second} --><!-- {/second} -->\n".to_string(),
+  )])?;
+
+  assert!(matches!(
+    compute_rename(&project, "missing", "other"),
+    Err(MdtError::UnknownBlock(_))
+  ));
+  assert!(matches!(
+    compute_rename(&project, "first", "new-name"),
+    Err(MdtError::InvalidBlockName { suggestion: Some(ref suggestion), .. }) if suggestion == "newName"
+  ));
+  assert!(matches!(
+    compute_rename(&project, "first", "second"),
+    Err(MdtError::BlockNameCollision { ref suggestion, .. }) if suggestion == "second2"
+  ));
+  assert_eq!(compute_rename(&project, "first", "first")?, vec![]);
+  assert_eq!(compute_rename(&project, "first", "third")?.len(), 2);
+
+  Ok(())
+}
+
+#[rstest]
+#[case::simple("Installation", "installation")]
+#[case::spaces_and_punctuation("What's new in v2.0?", "whats-new-in-v20")]
+#[case::code_and_emphasis("The `mdt` **CLI**", "the-mdt-cli")]
+#[case::links("See [the docs](https://example.com)", "see-the-docs")]
+#[case::unicode("Über Café", "über-café")]
+#[case::keeps_dashes_and_underscores("snake_case - kebab", "snake_case---kebab")]
+fn slugify_headings(#[case] text: &str, #[case] expected: &str) {
+  assert_eq!(slugify(text), expected);
+}
+
+#[test]
+fn toc_transformer() -> MdtResult<()> {
+  let blocks = parse("<!-- {=bookIntro|toc} -->\n<!-- {/bookIntro} -->\n")?;
+  let block = blocks.first().expect("a block should be parsed");
+  let content = "## Introduction\n\ntext\n\n### Usage ###\n\n```md\n## Not a heading\n```\n\n#### \
+                 Example\n\n## Usage\n\n### Usage\n";
+  let expected = "- [Introduction](#introduction)\n  - [Usage](#usage)\n    - \
+                  [Example](#example)\n- [Usage](#usage-1)\n  - [Usage](#usage-2)";
+  assert_eq!(apply_transformers(content, &block.transformers), expected);
+  assert_eq!(apply_transformers("no headings", &block.transformers), "");
+
+  Ok(())
+}
+
+#[rstest]
+#[case::matches_pattern("mdtReadme", true)]
+#[case::violates_pattern("mdt_readme", false)]
+#[case::reserved_prefix("internalNotes", false)]
+fn naming_policy(#[case] name: &str, #[case] expected: bool) -> MdtResult<()> {
+  let config = MdtConfig::from_toml(
+    "[naming]\npattern = \"^[a-z][A-Za-z0-9]*$\"\nreserved_prefixes = [\"internal\"]\n",
+  )?;
+  assert_eq!(config.naming.check(name).is_ok(), expected);
+
+  Ok(())
+}
+
+#[test]
+fn naming_policy_is_enforced() -> MdtResult<()> {
+  let config = MdtConfig::from_toml("[naming]\npattern = \"^[a-z][A-Za-z0-9]*$\"\n")?;
+  let template = (
+    PathBuf::from("template.t.md"),
+    "<!-- {@first} --><!-- {/first} -->\n".to_string(),
+  );
+  let project = Project::from_files_with_config(vec![template], config.clone())?;
+
+  assert!(matches!(
+    compute_rename(&project, "first", "snake_case"),
+    Err(MdtError::BlockNamePolicy { .. })
+  ));
+  assert!(matches!(
+    Project::from_files_with_config(
+      vec![(
+        PathBuf::from("template.t.md"),
+        "<!-- {@snake_case} --><!-- {/snake_case} -->\n".to_string(),
+      )],
+      config,
+    ),
+    Err(MdtError::BlockNamePolicy { .. })
+  ));
+  assert!(matches!(
+    MdtConfig::from_toml("[naming]\npattern = \"(\"\n"),
+    Err(MdtError::InvalidConfig(_))
+  ));
+
+  Ok(())
+}
+
+#[rstest]
+#[case::until_same_level("Installation", Some("## Installation\n\n### Cargo\n\nrun it"))]
+#[case::until_higher_level("Cargo", Some("### Cargo\n\nrun it"))]
+#[case::to_the_end("Usage", Some("## Usage\n\n```md\n## Not a heading\n```"))]
+#[case::missing("Contributing", None)]
+fn section_transformer(#[case] title: &str, #[case] expected: Option<&str>) {
+  let content = "# Title\n\n## Installation\n\n### Cargo\n\nrun it\n\n## Usage\n\n```md\n## Not a \
+                 heading\n```\n";
+  assert_eq!(find_section(content, title).as_deref(), expected);
+}
+
+#[test]
+fn missing_sections_are_reported() -> MdtResult<()> {
+  let project = Project::from_files(vec![
+    (
+      PathBuf::from("template.t.md"),
+      "<!-- {@readme} -->\n## Usage\n\ntext\n<!-- {/readme} -->\n".to_string(),
+    ),
+    (
+      PathBuf::from("readme.md"),
+      "<!-- {=readme|section:\"Install\"} -->\n<!-- {/readme} -->\n".to_string(),
+    ),
+  ])?;
+
+  assert!(matches!(
+    compute_patches(&project),
+    Err(MdtError::MissingSection { ref section, .. }) if section == "Install"
+  ));
+
+  Ok(())
+}
+
+#[rstest]
+#[case::none("\n## Hello\n", None, "\n## Hello\n")]
+#[case::dashes(
+  "\n{#- doc: Say hello -#}\n\n## Hello\n",
+  Some("Say hello"),
+  "\n\n## Hello\n"
+)]
+#[case::no_dashes("{# doc: Say hello #}\nHello", Some("Say hello"), "Hello")]
+#[case::other_comment("\n{# note #}\nHello", None, "\n{# note #}\nHello")]
+fn doc_comments(#[case] content: &str, #[case] doc: Option<&str>, #[case] rest: &str) {
+  let (description, content) = split_doc_comment(content);
+  assert_eq!(description.as_deref(), doc);
+  assert_eq!(content, rest);
+}
+
+#[rstest]
+#[case::range("|lines:2:3", "two\nthree")]
+#[case::open_range("|lines:4", "four\nfive")]
+#[case::past_the_end("|lines:4:10", "four\nfive")]
+#[case::first_lines("|firstLines:2", "one\ntwo")]
+#[case::last_lines("|lastLines:2", "four\nfive")]
+#[case::more_than_available("|lastLines:10", "one\ntwo\nthree\nfour\nfive")]
+fn line_transformers(#[case] transformers: &str, #[case] expected: &str) -> MdtResult<()> {
+  let input = format!("<!-- {{=block{transformers}}} -->\n<!-- {{/block}} -->\n");
+  let blocks = parse(input)?;
+  let block = blocks.first().expect("a block should be parsed");
+  let content = "one\ntwo\nthree\nfour\nfive";
+  assert_eq!(apply_transformers(content, &block.transformers), expected);
+
+  Ok(())
+}
+
+#[test]
+fn search_providers_by_name_and_content() -> MdtResult<()> {
+  let project = Project::from_files(vec![
+    (
+      PathBuf::from("template.t.md"),
+      "<!-- {@install} -->\nRun `cargo install mdt`.\n<!-- {/install} -->\n<!-- {@usage} \
+       -->\nInstall first, then install the hooks.\n<!-- {/usage} -->\n<!-- {@license} \
+       -->\nUnlicense\n<!-- {/license} -->\n"
+        .to_string(),
+    ),
+    (
+      PathBuf::from("readme.md"),
+      "<!-- {=install} -->\n<!-- {/install} -->\n".to_string(),
+    ),
+  ])?;
+
+  let results = search_providers(&project, "INSTALL", &[]);
+  let names = results
+    .iter()
+    .map(|result| result.name.as_str())
+    .collect::<Vec<_>>();
+  assert_eq!(names, vec!["install", "usage"]);
+
+  let install = results.first().expect("install should match");
+  assert!(install.name_match);
+  assert_eq!(install.consumer_count, 1);
+
+  let usage = results.get(1).expect("usage should match");
+  let snippet = usage.snippets.first().expect("a snippet should match");
+  assert_eq!(snippet.line, 2);
+  assert_eq!(
+    snippet.highlighted("**"),
+    "**Install** first, then **install** the hooks."
+  );
+  assert!(search_providers(&project, "  ", &[]).is_empty());
+
+  Ok(())
+}
+
+#[rstest]
+#[case::yaml_inline("---\ndescription: \"Shared\"\ntags: [readme, 'legal']\n---\n", Some("Shared"), &["readme", "legal"])]
+#[case::yaml_list("---\ntags:\n  - rust\n  - legal\ntitle: x\n---\n", None, &["rust", "legal"])]
+#[case::toml("+++\ndescription = \"Shared\"\ntags = [\"readme\"]\n+++\n", Some("Shared"), &["readme"])]
+#[case::none("# Title\n", None, &[])]
+fn front_matter(#[case] content: &str, #[case] description: Option<&str>, #[case] tags: &[&str]) {
+  let front_matter = FrontMatter::parse(content);
+  assert_eq!(front_matter.description.as_deref(), description);
+  assert_eq!(front_matter.tags, tags);
+}
+
+#[test]
+fn search_providers_by_tag() -> MdtResult<()> {
+  let project = Project::from_files(vec![
+    (
+      PathBuf::from("legal.t.md"),
+      "---\ntags: [legal]\n---\n<!-- {@license} -->\nMIT\n<!-- {/license} -->\n".to_string(),
+    ),
+    (
+      PathBuf::from("template.t.md"),
+      "<!-- {@badge} -->\nMIT\n<!-- {/badge} -->\n".to_string(),
+    ),
+  ])?;
+
+  let results = search_providers(&project, "mit", &["legal".to_string()]);
+  let names = results
+    .iter()
+    .map(|result| result.name.as_str())
+    .collect::<Vec<_>>();
+  assert_eq!(names, vec!["license"]);
+
+  Ok(())
+}
+
+#[rstest]
+#[case::tags("<b>bold</b> and <a href=\"#x\">link</a>", "bold and link")]
+#[case::self_closing("badge: <img src=\"x.svg\" />!", "badge: !")]
+#[case::details(
+  "<details>\n<summary>More</summary>\n\ntext\n</details>",
+  "\nMore\n\ntext\n"
+)]
+#[case::comments("a<!-- <b>hidden</b> -->b", "ab")]
+#[case::not_a_tag("a < b and 1<2", "a < b and 1<2")]
+#[case::unclosed("<b unclosed", "<b unclosed")]
+fn strip_html_transformer(#[case] content: &str, #[case] expected: &str) {
+  assert_eq!(strip_html(content), expected);
+}
+
+#[test]
+fn providers_render_data() -> MdtResult<()> {
+  let mut project = Project::from_files(vec![
+    (
+      PathBuf::from("template.t.md"),
+      "<!-- {@install} -->\n{{ pkg.name }}@{{ pkg.version }}\n<!-- {/install} -->\n".to_string(),
+    ),
+    (
+      PathBuf::from("readme.md"),
+      "<!-- {=install|trim} -->\n<!-- {/install} -->\n".to_string(),
+    ),
+  ])?;
+  project.data.insert(
+    "pkg".into(),
+    serde_json::json!({ "name": "mdt", "version": "1.0.0" }),
+  );
+
+  let consumer = project.consumers.first().expect("a consumer should exist");
+  assert_eq!(
+    render_consumer(&project, consumer)?.as_deref(),
+    Some("mdt@1.0.0")
+  );
+
+  Ok(())
+}
+
+#[test]
+fn consumers_override_data() -> MdtResult<()> {
+  let mut project = Project::from_files(vec![
+    (
+      PathBuf::from("template.t.md"),
+      "<!-- {@install} -->\n{{ pm }} add {{ pkg.name }}@{{ pkg.version }}\n<!-- {/install} -->\n"
+        .to_string(),
+    ),
+    (
+      PathBuf::from("readme.md"),
+      "<!-- {=install|trim} -->\n<!-- {/install} -->\n<!-- {=install data:{\"pm\": \"pnpm\",        \"pkg\": {\"version\": \"2.0.0\"}}|trim} -->\n<!-- {/install} -->\n"
+        .to_string(),
+    ),
+  ])?;
+  project.data.insert("pm".into(), serde_json::json!("npm"));
+  project.data.insert(
+    "pkg".into(),
+    serde_json::json!({ "name": "mdt", "version": "1.0.0" }),
+  );
+
+  let rendered = project
+    .consumers
+    .iter()
+    .map(|consumer| render_consumer(&project, consumer))
+    .collect::<MdtResult<Vec<_>>>()?;
+
+  assert_eq!(
+    rendered,
+    vec![
+      Some("npm add mdt@1.0.0".to_string()),
+      Some("pnpm add mdt@2.0.0".to_string()),
+    ]
+  );
+  assert!(matches!(
+    parse("<!-- {=install data:[1]} --><!-- {/install} -->"),
+    Ok(blocks) if blocks.is_empty()
+  ));
+  assert!(matches!(
+    parse("<!-- {=install data:{\"pm\"}} --><!-- {/install} -->"),
+    Err(MdtError::InvalidBlockData { name, .. }) if name == "install"
+  ));
+
+  Ok(())
+}
+
+#[test]
+fn data_usage_analysis() -> MdtResult<()> {
+  let mut project = Project::from_files(vec![(
+    PathBuf::from("template.t.md"),
+    "<!-- {@a} -->\n{{ pkg.verison }}{% for i in range(2) %}{{ i }}{% endfor %}\n<!-- {/a} \
+     -->\n<!-- {@b} -->\n{{ pkg.version }} {{ site }}\n<!-- {/b} -->\n"
+      .to_string(),
+  )])?;
+  project
+    .data
+    .insert("pkg".into(), serde_json::json!({ "version": "1.0.0" }));
+  project.data.insert("unused".into(), serde_json::json!({}));
+
+  let usage = analyze_data_usage(&project);
+  assert_eq!(usage.unused, vec!["unused".to_string()]);
+  assert_eq!(
+    usage.missing,
+    vec![
+      MissingData {
+        provider: "a".into(),
+        variable: "pkg.verison".into(),
+      },
+      MissingData {
+        provider: "b".into(),
+        variable: "site".into(),
+      },
+    ]
+  );
+
+  Ok(())
+}
+
+#[rstest]
+#[case::heading("## Usage", "<h2>Usage</h2>")]
+#[case::paragraph("some *emphasis*", "<p>some <em>emphasis</em></p>")]
+#[case::gfm_table(
+  "| a |\n| - |\n| b |",
+  "<table>\n<thead>\n<tr>\n<th>a</th>\n</tr>\n</thead>\n<tbody>\n<tr>\n<td>b</td>\n</tr>\n</\
+   tbody>\n</table>"
+)]
+#[case::raw_html("<details>x</details>", "<details>x</details>")]
+fn markdown_to_html_transformer(#[case] content: &str, #[case] expected: &str) {
+  assert_eq!(markdown_to_html(content), expected);
+}
+
+#[test]
+fn template_variables_with_ranges() {
+  let template =
+    "{{ pkg.version | default(\"x.y\") }} {% if pkg.private %}{{ upper(name) }}{% endif %}";
+  let variables = find_template_variables(template)
+    .into_iter()
+    .map(|(variable, range)| (variable, template.get(range).map(String::from)))
+    .collect::<Vec<_>>();
+
+  assert_eq!(
+    variables,
+    vec![
+      ("pkg.version".to_string(), Some("pkg.version".to_string())),
+      ("pkg.private".to_string(), Some("pkg.private".to_string())),
+      ("name".to_string(), Some("name".to_string())),
+    ]
+  );
+}
+
+#[rstest]
+#[case::json("{\n  \"name\": \"x\",\n  \"repository\": { \"url\": \"u\" }\n}", &["repository", "url"], "\"url\"")]
+#[case::toml_table("[package]\nname = \"mdt\"\nversion = \"1.0.0\"\n", &["package", "version"], "version")]
+#[case::toml_nested_header("[workspace.package]\nversion = \"1\"\n", &["workspace", "package"], "package")]
+fn data_key_ranges(#[case] content: &str, #[case] keys: &[&str], #[case] expected: &str) {
+  let range = find_key_range(content, keys).expect("the key should be found");
+  assert_eq!(content.get(range), Some(expected));
+}
+
+#[rstest]
+#[case::csv("matrix.csv", "name,os\nmdt,\"linux, macos\"\nmdt_lsp,windows\n")]
+#[case::tsv("matrix.tsv", "name\tos\nmdt\tlinux, macos\nmdt_lsp\twindows\n")]
+fn delimited_data_files(#[case] name: &str, #[case] content: &str) -> AnyEmptyResult {
+  let directory = tempfile::tempdir()?;
+  let path = directory.path().join(name);
+  std::fs::write(&path, content)?;
+
+  let mut data = DataMap::new();
+  data.insert("matrix".into(), load_data_file(&path)?);
+
+  assert_eq!(
+    render_template(
+      "table",
+      "{% for row in matrix %}| {{ row.name }} | {{ row.os }} |\n{% endfor %}",
+      &data
+    )?,
+    "| mdt | linux, macos |\n| mdt_lsp | windows |\n"
+  );
+
+  Ok(())
+}
+
+#[test]
+fn xml_data_files() -> AnyEmptyResult {
+  let directory = tempfile::tempdir()?;
+  let pom = directory.path().join("pom.xml");
+  let csproj = directory.path().join("App.csproj");
+  std::fs::write(
+    &pom,
+    "<?xml version=\"1.0\"?>\n<project xmlns=\"http://maven.apache.org/POM/4.0.0\">\n  \
+     <version>1.4.0</version>\n  <dependencies>\n    \
+     <dependency><artifactId>a</artifactId></dependency>\n    \
+     <dependency><artifactId>b</artifactId></dependency>\n  </dependencies>\n</project>\n",
+  )?;
+  std::fs::write(
+    &csproj,
+    "<Project Sdk=\"Microsoft.NET.Sdk\">\n  \
+     <PropertyGroup><Version>2.0.1</Version></PropertyGroup>\n</Project>\n",
+  )?;
+
+  assert_eq!(
+    load_data_file(&pom)?,
+    serde_json::json!({
+      "version": "1.4.0",
+      "dependencies": {
+        "dependency": [{ "artifactId": "a" }, { "artifactId": "b" }]
+      }
+    })
+  );
+  assert_eq!(
+    load_data_file(&csproj)?,
+    serde_json::json!({
+      "@Sdk": "Microsoft.NET.Sdk",
+      "PropertyGroup": { "Version": "2.0.1" }
+    })
+  );
+
+  std::fs::write(&pom, "<project>")?;
+  assert!(matches!(
+    load_data_file(&pom),
+    Err(MdtError::DataFile { .. })
+  ));
+
+  Ok(())
+}
+
+#[test]
+fn dotenv_data_files() -> AnyEmptyResult {
+  let directory = tempfile::tempdir()?;
+  let root = directory.path();
+  std::fs::write(
+    root.join(CONFIG_FILE_NAME),
+    "[data]\nsecrets = { path = \".env.public\", format = \"dotenv\" }\nvars = { path = \
+     \"deploy.txt\", format = \"dotenv\" }\n",
+  )?;
+  std::fs::write(
+    root.join(".env.public"),
+    "# deployment\nexport API_URL=https://example.com # \
+     production\nGREETING=\"Hello\\n\\\"you\\\"\"\nRAW='a # b'\n",
+  )?;
+  std::fs::write(root.join("deploy.txt"), "REGION=eu\n")?;
+
+  let project = scan_project(root)?;
+
+  assert_eq!(
+    project.data.get("secrets"),
+    Some(&serde_json::json!({
+      "API_URL": "https://example.com",
+      "GREETING": "Hello\n\"you\"",
+      "RAW": "a # b"
+    }))
+  );
+  assert_eq!(
+    project.data.get("vars"),
+    Some(&serde_json::json!({ "REGION": "eu" }))
+  );
+  assert!(matches!(
+    MdtConfig::from_toml("[data]\nsecrets = { path = \".env\", format = \"ini\" }\n"),
+    Err(MdtError::InvalidConfig(_))
+  ));
+
+  Ok(())
+}
+
+#[test]
+fn data_sources_reject_unknown_keys() -> AnyEmptyResult {
+  let config = MdtConfig::from_toml(
+    "[data]\npkg = { path = \"package.json\", format = \"json\", select = \"name\" }\ncrates = { \
+     glob = \"crates/*/Cargo.toml\" }\n",
+  )?;
+  assert_eq!(config.data.len(), 2);
+
+  for (source, key) in [
+    ("{ path = \"package.json\", fromat = \"json\" }", "fromat"),
+    (
+      "{ glob = \"crates/*/Cargo.toml\", selct = \"package\" }",
+      "selct",
+    ),
+    ("{ cargo = \"Cargo.toml\", path = \"Cargo.toml\" }", "path"),
+  ] {
+    assert!(matches!(
+      MdtConfig::from_toml(&format!("[data]\npkg = {source}\n")),
+      Err(MdtError::InvalidConfig(message)) if message.contains(key)
+    ));
+  }
+
+  Ok(())
+}
+
+#[test]
+fn frontmatter_data_files() -> AnyEmptyResult {
+  let directory = tempfile::tempdir()?;
+  let root = directory.path();
+  std::fs::write(
+    root.join(CONFIG_FILE_NAME),
+    "[data]\npost = { path = \"announcement.md\", format = \"frontmatter\" }\nhugo = { path = \
+     \"hugo.md\", format = \"frontmatter\" }\nplain = { path = \"plain.md\", format = \
+     \"frontmatter\" }\n",
+  )?;
+  std::fs::write(
+    root.join("announcement.md"),
+    "---\ntitle: Hello\ndate: 2024-01-02\ntags:\n  - release\n---\n\n# Hello\n",
+  )?;
+  std::fs::write(root.join("hugo.md"), "+++\ntitle = \"Hugo\"\n+++\nBody\n")?;
+  std::fs::write(root.join("plain.md"), "# No front matter\n")?;
+
+  let project = scan_project(root)?;
+
+  assert_eq!(
+    project.data.get("post"),
+    Some(&serde_json::json!({ "title": "Hello", "date": "2024-01-02", "tags": ["release"] }))
+  );
+  assert_eq!(
+    project.data.get("hugo"),
+    Some(&serde_json::json!({ "title": "Hugo" }))
+  );
+  assert_eq!(project.data.get("plain"), Some(&serde_json::json!({})));
+
+  std::fs::write(root.join("plain.md"), "---\ntitle: Unclosed\n")?;
+  assert!(matches!(scan_project(root), Err(MdtError::DataFile { .. })));
+
+  Ok(())
+}
+
+#[test]
+fn cargo_metadata_data_source() -> AnyEmptyResult {
+  let directory = tempfile::tempdir()?;
+  let root = directory.path();
+  std::fs::write(
+    root.join(CONFIG_FILE_NAME),
+    "[data]\nworkspace = { cargo = \"Cargo.toml\" }\n",
+  )?;
+  std::fs::write(
+    root.join("Cargo.toml"),
+    "[workspace]\nmembers = [\"crates/*\"]\nresolver = \"2\"\n",
+  )?;
+
+  for (name, manifest) in [
+    (
+      "app",
+      "[package]\nname = \"app\"\nversion = \"0.2.0\"\nedition = \"2021\"\nrust-version = \
+       \"1.70\"\ndescription = \"The app\"\n\n[features]\ndefault = [\"fast\"]\nfast = \
+       []\n\n[dependencies]\ncore = { path = \"../core\", version = \"0.1\" }\n",
+    ),
+    (
+      "core",
+      "[package]\nname = \"core\"\nversion = \"0.1.0\"\nedition = \"2021\"\n",
+    ),
+  ] {
+    std::fs::create_dir_all(root.join("crates").join(name).join("src"))?;
+    std::fs::write(root.join("crates").join(name).join("Cargo.toml"), manifest)?;
+    std::fs::write(root.join("crates").join(name).join("src/lib.rs"), "")?;
+  }
+
+  let project = scan_project(root)?;
+  let workspace = project.data.get("workspace");
+  let members = workspace
+    .and_then(|workspace| workspace.get("members"))
+    .and_then(serde_json::Value::as_array)
+    .map(|members| {
+      members
+        .iter()
+        .filter_map(|member| member.get("name")?.as_str())
+        .collect::<Vec<_>>()
+    });
+
+  assert_eq!(members, Some(vec!["app", "core"]));
+  assert_eq!(
+    workspace.and_then(|workspace| workspace.get("packages")?.get("app")),
+    Some(&serde_json::json!({
+      "name": "app",
+      "version": "0.2.0",
+      "description": "The app",
+      "rust_version": "1.70",
+      "directory": "crates/app",
+      "features": { "default": ["fast"], "fast": [] },
+      "dependencies": [{ "name": "core", "req": "^0.1", "kind": "normal", "optional": false }]
+    }))
+  );
+
+  Ok(())
+}
+
+#[test]
+fn data_schemas_are_validated() -> AnyEmptyResult {
+  let directory = tempfile::tempdir()?;
+  let root = directory.path();
+  std::fs::write(
+    root.join(CONFIG_FILE_NAME),
+    "[data.pkg]\npath = \"package.json\"\n\n[data.pkg.schema]\nversion = \
+     \"string\"\n\"repository.url\" = \"string\"\nprivate = \"boolean\"\n",
+  )?;
+  let message = |root: &Path| {
+    match scan_project(root) {
+      Err(MdtError::DataFile { message, .. }) => Some(message),
+      _ => None,
+    }
+  };
+
+  std::fs::write(
+    root.join("package.json"),
+    r#"{ "version": "1.0.0", "repository": { "url": "https://example.com" }, "private": true }"#,
+  )?;
+  assert!(scan_project(root).is_ok());
+
+  std::fs::write(
+    root.join("package.json"),
+    r#"{ "version": "", "repository": { "url": "https://example.com" }, "private": true }"#,
+  )?;
+  assert_eq!(
+    message(root).as_deref(),
+    Some("the key `version` is an empty string")
+  );
+
+  std::fs::write(
+    root.join("package.json"),
+    r#"{ "version": "1.0.0", "repository": {}, "private": true }"#,
+  )?;
+  assert_eq!(
+    message(root).as_deref(),
+    Some("the key `repository.url` is missing")
+  );
+
+  std::fs::write(
+    root.join("package.json"),
+    r#"{ "version": "1.0.0", "repository": { "url": "https://example.com" }, "private": "yes" }"#,
+  )?;
+  assert_eq!(
+    message(root).as_deref(),
+    Some("the key `private` should be a boolean but is a string")
+  );
+
+  Ok(())
+}
+
+#[test]
+fn data_sources_can_select_a_path() -> AnyEmptyResult {
+  let directory = tempfile::tempdir()?;
+  let root = directory.path();
+  std::fs::write(
+    root.join(CONFIG_FILE_NAME),
+    "[data]\ndeps = { path = \"Cargo.toml\", select = \"dependencies\" }\ntitles = { glob = \
+     \"examples/*.toml\", select = \"meta.title\" }\n",
+  )?;
+  std::fs::write(
+    root.join("Cargo.toml"),
+    "[package]\nname = \"demo\"\n\n[dependencies]\nserde = \"1\"\n",
+  )?;
+  std::fs::create_dir_all(root.join("examples"))?;
+  std::fs::write(
+    root.join("examples/basic.toml"),
+    "[meta]\ntitle = \"Basic\"\n",
+  )?;
+
+  let project = scan_project(root)?;
+
+  assert_eq!(
+    project.data.get("deps"),
+    Some(&serde_json::json!({ "serde": "1" }))
+  );
+  assert_eq!(
+    project.data.get("titles"),
+    Some(&serde_json::json!([
+      { "path": "examples/basic.toml", "stem": "basic", "content": "Basic" }
+    ]))
+  );
+
+  let definition =
+    find_data_definition(&project, "deps.serde").expect("a definition should be found");
+  let manifest = std::fs::read_to_string(&definition.file)?;
+  assert_eq!(manifest.get(definition.range), Some("serde"));
+
+  std::fs::write(root.join("examples/empty.toml"), "")?;
+  assert!(matches!(
+    scan_project(root),
+    Err(MdtError::DataFile { message, .. }) if message == "the selected key `meta.title` doesn't exist"
+  ));
+
+  Ok(())
+}
+
+#[test]
+fn configuration_can_extend_a_base() -> AnyEmptyResult {
+  let directory = tempfile::tempdir()?;
+  let root = directory.path().join("packages/app");
+  std::fs::create_dir_all(&root)?;
+  std::fs::create_dir_all(directory.path().join("shared"))?;
+  std::fs::write(
+    directory.path().join("shared/mdt.base.toml"),
+    "[naming]\nreserved_prefixes = [\"internal\"]\n\n[check]\nundefined_variables = \
+     true\n\n[chains]\nrustdoc = \"trim\"\n\n[data.pkg]\npath = \
+     \"package.json\"\n\n[data.pkg.schema]\nversion = \"string\"\n",
+  )?;
+  std::fs::write(
+    root.join(CONFIG_FILE_NAME),
+    "extends = \"../../shared/mdt.base.toml\"\n\n[data]\npkg = \
+     \"Cargo.toml\"\n\n[transformer_aliases]\nshort = \"firstLines:1\"\n",
+  )?;
+
+  let config = MdtConfig::load(&root)?;
+
+  assert_eq!(
+    config.naming.reserved_prefixes,
+    vec!["internal".to_string()]
+  );
+  assert!(config.check.undefined_variables);
+  // Data namespaces are replaced whole rather than merged.
+  assert_eq!(
+    config.data.get("pkg"),
+    Some(&DataSource::Path(PathBuf::from("Cargo.toml")))
+  );
+  assert_eq!(
+    config.transformer_aliases.keys().collect::<Vec<_>>(),
+    vec!["rustdoc", "short"]
+  );
+
+  let origins = MdtConfig::resolve(&root, None)?
+    .into_iter()
+    .map(|value| (value.key, value.origin))
+    .collect::<BTreeMap<_, _>>();
+  assert_eq!(
+    origins.get("naming.reserved_prefixes"),
+    Some(&ConfigOrigin::File(PathBuf::from(
+      "../../shared/mdt.base.toml"
+    )))
+  );
+  assert_eq!(
+    origins.get("transformer_aliases.short"),
+    Some(&ConfigOrigin::File(PathBuf::from(CONFIG_FILE_NAME)))
+  );
+
+  std::fs::write(
+    directory.path().join("shared/mdt.base.toml"),
+    "extends = \"../packages/app/mdt.toml\"\n",
+  )?;
+  assert!(matches!(
+    MdtConfig::load(&root),
+    Err(MdtError::InvalidConfig(message)) if message.contains("extends itself")
+  ));
+
+  std::fs::remove_file(directory.path().join("shared/mdt.base.toml"))?;
+  assert!(matches!(
+    MdtConfig::load(&root),
+    Err(MdtError::InvalidConfig(message)) if message.contains("doesn't exist")
+  ));
+
+  Ok(())
+}
+
+#[test]
+fn profiles_override_data_sources() -> MdtResult<()> {
+  let config = MdtConfig::from_toml(
+    "[data]\npkg = \"package.json\"\nrelease = \"draft.toml\"\n\n[profile.release.data]\nrelease \
+     = \"release.toml\"\n",
+  )?;
+  let release = config.clone().with_profile("release")?;
+
+  assert_eq!(
+    release.data.get("release"),
+    Some(&DataSource::Path(PathBuf::from("release.toml")))
+  );
+  assert_eq!(release.data.get("pkg"), config.data.get("pkg"));
+  assert!(matches!(
+    config.with_profile("nightly"),
+    Err(MdtError::InvalidConfig(_))
+  ));
+
+  Ok(())
+}
+
+#[test]
+fn glob_data_sources() -> AnyEmptyResult {
+  let directory = tempfile::tempdir()?;
+  let root = directory.path();
+  std::fs::write(
+    root.join(CONFIG_FILE_NAME),
+    "[data]\nexamples = { glob = \"examples/*.toml\" }\n",
+  )?;
+  std::fs::create_dir_all(root.join("examples/nested"))?;
+  std::fs::write(root.join("examples/basic.toml"), "title = \"Basic\"\n")?;
+  std::fs::write(
+    root.join("examples/advanced.toml"),
+    "title = \"Advanced\"\n",
+  )?;
+  std::fs::write(root.join("examples/notes.md"), "# Notes\n")?;
+  std::fs::write(root.join("examples/nested/deep.toml"), "title = \"Deep\"\n")?;
+  std::fs::write(
+    root.join("template.t.md"),
+    "<!-- {@examples} -->\n{% for example in examples %}| {{ example.stem }} | {{ \
+     example.content.title }} |\n{% endfor %}<!-- {/examples} -->\n",
+  )?;
+  std::fs::write(
+    root.join("readme.md"),
+    "<!-- {=examples} -->\n<!-- {/examples} -->\n",
+  )?;
+
+  let project = scan_project(root)?;
+
+  assert_eq!(
+    project.data.get("examples"),
+    Some(&serde_json::json!([
+      { "path": "examples/advanced.toml", "stem": "advanced", "content": { "title": "Advanced" } },
+      { "path": "examples/basic.toml", "stem": "basic", "content": { "title": "Basic" } }
+    ]))
+  );
+
+  let updates = compute_updates(&project)?;
+  let content = updates.updated_files.get(&root.join("readme.md"));
+  assert_eq!(
+    content.map(String::as_str),
+    Some(
+      "<!-- {=examples} -->\n| advanced | Advanced |\n| basic | Basic |\n<!-- {/examples} -->\n"
+    )
+  );
+
+  Ok(())
+}
+
+#[test]
+fn variable_index_links_templates_and_data() -> AnyEmptyResult {
+  let directory = tempfile::tempdir()?;
+  let root = directory.path();
+  std::fs::write(root.join("mdt.toml"), "[data]\npkg = \"package.json\"\n")?;
+  std::fs::write(
+    root.join("package.json"),
+    "{\n  \"name\": \"mdt\",\n  \"version\": \"1.0.0\"\n}\n",
+  )?;
+  let template =
+    "<!-- {@install} -->\n{{ pkg.name }}@{{ pkg.version }} {{ other }}\n<!-- {/install} -->\n";
+  std::fs::write(root.join("template.t.md"), template)?;
+
+  let project = scan_project(root)?;
+  let index = VariableIndex::build(&project);
+  let file = root.join("template.t.md");
+  let offset = template.find("pkg.version").expect("the variable exists") + 4;
+  let usage = index
+    .usage_at(&file, offset)
+    .expect("a variable should be found");
+  assert_eq!(usage.variable, "pkg.version");
+  assert_eq!(index.usages.len(), 2);
+  assert_eq!(index.references("pkg").count(), 2);
+
+  let definition =
+    find_data_definition(&project, &usage.variable).expect("a definition should be found");
+  let data = std::fs::read_to_string(&definition.file)?;
+  assert_eq!(data.get(definition.range), Some("\"version\""));
+
+  Ok(())
+}
+
+#[rstest]
+#[case::html(
+  "|escapeHtml",
+  "<a href=\"x\">Tom & Jerry's</a>",
+  "&lt;a href=&quot;x&quot;&gt;Tom &amp; Jerry&#39;s&lt;/a&gt;"
+)]
+#[case::json("|escapeJson", "say \"hi\"\n\\o/\t", "say \\\"hi\\\"\\n\\\\o/\\t")]
+#[case::toml(
+  "|escapeToml",
+  "say \"hi\"\n\\o/\u{1}",
+  "say \\\"hi\\\"\\n\\\\o/\\u0001"
+)]
+fn escape_transformers(
+  #[case] transformers: &str,
+  #[case] content: &str,
+  #[case] expected: &str,
+) -> MdtResult<()> {
+  let input = format!("<!-- {{=block{transformers}}} -->\n<!-- {{/block}} -->\n");
+  let blocks = parse(input)?;
+  let block = blocks.first().expect("a block should be parsed");
+  assert_eq!(apply_transformers(content, &block.transformers), expected);
+
+  Ok(())
+}
+
+#[test]
+fn check_project_reports_problems() -> MdtResult<()> {
+  let project = Project::from_files(vec![
+    (
+      PathBuf::from("template.t.md"),
+      "<!-- {@greeting} -->\nHello\n<!-- {/greeting} -->\n".to_string(),
+    ),
+    (
+      PathBuf::from("readme.md"),
+      "<!-- {=greeting} -->\nHello  \n<!-- {/greeting} -->\n<!-- {=orphan} --><!-- {/orphan} -->\n"
+        .to_string(),
+    ),
+  ])?;
+
+  let result = check_project(&project, CheckOptions::default())?;
+  assert!(result.stale.is_empty());
+  assert!(!result.is_failure());
+  assert_eq!(
+    result.diagnostics,
+    vec![CheckDiagnostic {
+      severity: Severity::Warning,
+      file: PathBuf::from("readme.md"),
+      message: "consumer `orphan` has no provider".into(),
+    }]
+  );
+
+  let result = check_project(&project, CheckOptions::strict())?;
+  assert_eq!(
+    result.stale,
+    vec![(PathBuf::from("readme.md"), "greeting".to_string())]
+  );
+  assert!(result.is_failure());
+
+  Ok(())
+}
+
+#[test]
+fn budgets_are_checked_between_consumers() -> MdtResult<()> {
+  let project = Project::from_files(vec![
+    (
+      PathBuf::from("template.t.md"),
+      "<!-- {@greeting} -->\nHello\n<!-- {/greeting} -->\n".to_string(),
+    ),
+    (
+      PathBuf::from("readme.md"),
+      "<!-- {=greeting} -->\nStale\n<!-- {/greeting} -->\n<!-- {=greeting} -->\nStale\n<!-- \
+       {/greeting} -->\n"
+        .to_string(),
+    ),
+  ])?;
+  let result = check_project_within(&project, CheckOptions::default(), Duration::ZERO)?;
+
+  assert_eq!(result.stale.len(), 1);
+  assert_eq!(
+    result.coverage,
+    Some(Coverage {
+      checked: 0,
+      skipped: vec![PathBuf::from("readme.md")],
+    })
+  );
+
+  Ok(())
+}
+
+#[test]
+fn sampling_consumers_is_deterministic() -> MdtResult<()> {
+  let mut files = vec![(
+    PathBuf::from("template.t.md"),
+    "<!-- {@greeting} -->\nHello\n<!-- {/greeting} -->\n".to_string(),
+  )];
+
+  for index in 0..20 {
+    files.push((
+      PathBuf::from(format!("{index}.md")),
+      "<!-- {=greeting} -->\nStale\n<!-- {/greeting} -->\n".to_string(),
+    ));
+  }
+
+  let project = Project::from_files(files)?;
+  let sample = Sample {
+    percent: 10.0,
+    seed: 7,
+  };
+  let sampled = sample_consumers(&project, sample);
+  assert_eq!(sampled.len(), 2);
+  assert_eq!(sample_consumers(&project, sample), sampled);
+
+  let scope = CheckScope {
+    sample: Some(sample),
+    ..CheckScope::default()
+  };
+  let result = check_project_scoped(&project, CheckOptions::default(), scope)?;
+  assert_eq!(result.stale, sampled);
+  assert_eq!(
+    result.sample,
+    Some(SampleReport {
+      seed: 7,
+      sampled,
+      total: 20,
+    })
+  );
+
+  let none = Sample {
+    percent: 0.0,
+    seed: 7,
+  };
+  assert!(sample_consumers(&project, none).is_empty());
+
+  Ok(())
+}
+
+#[rstest]
+#[case::same_depth(
+  "templates/a.t.md",
+  "docs/readme.md",
+  "[guide](../docs/guide.md)",
+  "[guide](guide.md)"
+)]
+#[case::root(
+  "templates/a.t.md",
+  "readme.md",
+  "![logo](../assets/logo.svg \"Logo\")",
+  "![logo](assets/logo.svg \"Logo\")"
+)]
+#[case::deeper(
+  "a.t.md",
+  "docs/api/readme.md",
+  "[x](docs/guide.md#usage)",
+  "[x](../guide.md#usage)"
+)]
+#[case::skipped(
+  "a.t.md",
+  "docs/readme.md",
+  "[a](https://x.dev) [b](#top) [c](/abs.md)",
+  "[a](https://x.dev) [b](#top) [c](/abs.md)"
+)]
+#[case::reference(
+  "templates/a.t.md",
+  "readme.md",
+  "[guide]: ../docs/guide.md \"Guide\"",
+  "[guide]: docs/guide.md \"Guide\""
+)]
+#[case::code_block(
+  "templates/a.t.md",
+  "readme.md",
+  "[a](../a.md)\n```md\n[b](../b.md)\n[c]: ../c.md\n```\n[d](../d.md)",
+  "[a](a.md)\n```md\n[b](../b.md)\n[c]: ../c.md\n```\n[d](d.md)"
+)]
+#[case::inline_code(
+  "templates/a.t.md",
+  "readme.md",
+  "`[a](../a.md)` [b](../b.md) ``[c](../c.md)`` [d](../d.md)",
+  "`[a](../a.md)` [b](b.md) ``[c](../c.md)`` [d](d.md)"
+)]
+#[case::angle_brackets(
+  "templates/a.t.md",
+  "docs/readme.md",
+  "[a](<../my guide.md> \"Guide\")\n[b]: <../other file.md>",
+  "[a](<../my guide.md> \"Guide\")\n[b]: <../other file.md>"
+)]
+#[case::angle_brackets_rewritten(
+  "templates/a.t.md",
+  "readme.md",
+  "[a](<../my guide.md#usage>)\n[b]: <../other file.md> \"Other\"",
+  "[a](<my guide.md#usage>)\n[b]: <other file.md> \"Other\""
+)]
+fn relative_links(
+  #[case] provider: &str,
+  #[case] consumer: &str,
+  #[case] content: &str,
+  #[case] expected: &str,
+) {
+  assert_eq!(
+    rewrite_relative_links(content, Path::new(provider), Path::new(consumer)),
+    expected
+  );
+}
+
+#[test]
+fn relative_links_are_rewritten_per_consumer() -> MdtResult<()> {
+  let project = Project::from_files(vec![
+    (
+      PathBuf::from(".templates/template.t.md"),
+      "<!-- {@guide} -->See [the guide](../docs/guide.md)<!-- {/guide} -->\n".to_string(),
+    ),
+    (
+      PathBuf::from("readme.md"),
+      "<!-- {=guide|relativeLinks} --><!-- {/guide} -->\n".to_string(),
+    ),
+    (
+      PathBuf::from("docs/api/readme.md"),
+      "<!-- {=guide|relativeLinks} --><!-- {/guide} -->\n".to_string(),
+    ),
+  ])?;
+
+  let rendered = project
+    .consumers
+    .iter()
+    .map(|consumer| render_consumer(&project, consumer))
+    .collect::<MdtResult<Vec<_>>>()?;
+  assert_eq!(
+    rendered,
+    vec![
+      Some("See [the guide](../guide.md)".to_string()),
+      Some("See [the guide](docs/guide.md)".to_string()),
+    ]
+  );
+
+  Ok(())
+}
+
+#[rstest]
+#[case::since_newer(
+  "2.1.0",
+  "{% if v is since(\"2.0\") %}new{% else %}old{% endif %}",
+  "new"
+)]
+#[case::since_older(
+  "1.9.3",
+  "{% if v is since(\"2.0\") %}new{% else %}old{% endif %}",
+  "old"
+)]
+#[case::until("v1", "{% if v is until(\"2.0.0\") %}legacy{% endif %}", "legacy")]
+#[case::prerelease(
+  "2.0.0-beta.1",
+  "{% if v is until(\"2.0.0\") %}legacy{% endif %}",
+  "legacy"
+)]
+#[case::satisfies("1.4.2", "{% if v is satisfies(\">=1.2, <2\") %}1.x{% endif %}", "1.x")]
+fn versioned_content(
+  #[case] version: &str,
+  #[case] template: &str,
+  #[case] expected: &str,
+) -> MdtResult<()> {
+  let data = DataMap::from([("v".to_string(), serde_json::json!(version))]);
+  assert_eq!(render_template("block", template, &data)?, expected);
+
+  Ok(())
+}
+
+#[test]
+fn invalid_versions_fail_to_render() {
+  let data = DataMap::from([("v".to_string(), serde_json::json!("latest"))]);
+  let result = render_template("block", "{% if v is since(\"2\") %}x{% endif %}", &data);
+  assert!(matches!(result, Err(MdtError::Template { .. })));
+}
+
+#[rstest]
+#[case::runs("a\n\n\n\nb\n \n\t\nc", "a\n\nb\n\nc")]
+#[case::single_blank_line("a\n\nb", "a\n\nb")]
+#[case::edges("\n\n\na\n\n\n", "\na\n")]
+#[case::fenced_code("```\na\n\n\nb\n```\n\n\nc", "```\na\n\n\nb\n```\n\nc")]
+fn squash_blank_lines_transformer(#[case] content: &str, #[case] expected: &str) {
+  assert_eq!(squash_blank_lines(content), expected);
+}
+
+#[test]
+fn changelog_entries() {
+  let changelog = "# Changelog\n\n## [Unreleased]\n\n- Next\n\n## [1.1.0] - 2024-02-01\n\n### \
+                   Added\n\n- Search\n\n```md\n## Not a release\n```\n\n## 1.0.0\n\n- First\n";
+  let entries = parse_changelog(changelog);
+  let versions = entries
+    .iter()
+    .map(|entry| entry.version.as_str())
+    .collect::<Vec<_>>();
+  assert_eq!(versions, vec!["Unreleased", "1.1.0", "1.0.0"]);
+
+  let latest = find_changelog_entry(&entries, None).expect("a release should exist");
+  assert_eq!(latest.version, "1.1.0");
+  assert_eq!(latest.date.as_deref(), Some("2024-02-01"));
+  assert_eq!(
+    latest.content,
+    "### Added\n\n- Search\n\n```md\n## Not a release\n```"
+  );
+
+  let first = find_changelog_entry(&entries, Some("v1.0.0")).expect("1.0.0 should exist");
+  assert_eq!(first.content, "- First");
+  assert!(find_changelog_entry(&entries, Some("2.0.0")).is_none());
+}
+
+#[test]
+fn changelog_data_namespace() -> AnyEmptyResult {
+  let directory = tempfile::tempdir()?;
+  let root = directory.path();
+  std::fs::write(
+    root.join("mdt.toml"),
+    "[data]\npkg = \"package.json\"\n\n[changelog]\npath = \"CHANGELOG.md\"\nversion = \
+     \"pkg.version\"\n",
+  )?;
+  std::fs::write(root.join("package.json"), r#"{ "version": "1.0.0" }"#)?;
+  std::fs::write(
+    root.join("CHANGELOG.md"),
+    "## Unreleased\n\n- Next\n\n## [1.1.0]\n\n- Newer\n\n## [1.0.0] - 2024-01-01\n\n- First\n",
+  )?;
+  std::fs::write(
+    root.join("template.t.md"),
+    "<!-- {@latest} -->\n## {{ changelog.version }} ({{ changelog.date }})\n\n{{ \
+     changelog.content }}\n<!-- {/latest} -->\n",
+  )?;
+  std::fs::write(
+    root.join("readme.md"),
+    "<!-- {=latest} -->\n<!-- {/latest} -->\n",
+  )?;
+
+  let project = scan_project(root)?;
+  let consumer = project.consumers.first().expect("a consumer should exist");
+  assert_eq!(
+    render_consumer(&project, consumer)?.as_deref(),
+    Some("\n## 1.0.0 (2024-01-01)\n\n- First\n")
+  );
+  assert_eq!(
+    lookup_variable(&project.data, "changelog.unreleased"),
+    Some(&serde_json::json!("- Next"))
+  );
+
+  Ok(())
+}
+
+#[rstest]
+#[case::slug("|slug", "  Getting Started!\n", "getting-started")]
+#[case::anchor("|anchor", "The `mdt` CLI", "#the-mdt-cli")]
+#[case::chained(
+  "|trim|slug|replace:\"-\":\"_\"",
+  " Getting Started ",
+  "getting_started"
+)]
+fn slug_transformers(
+  #[case] transformers: &str,
+  #[case] content: &str,
+  #[case] expected: &str,
+) -> MdtResult<()> {
+  let input = format!("<!-- {{=block{transformers}}} -->\n<!-- {{/block}} -->\n");
+  let blocks = parse(input)?;
+  let block = blocks.first().expect("a block should be parsed");
+  assert_eq!(apply_transformers(content, &block.transformers), expected);
+
+  Ok(())
+}
+
+#[rstest]
+#[case::iso_date("2024-01-31", "|date:\"%B %e, %Y\"", "January 31, 2024")]
+#[case::rfc3339(
+  "2024-02-29T13:05:09+02:00",
+  "|date:\"%d/%m/%y %H:%M:%S\"",
+  "29/02/24 13:05:09"
+)]
+#[case::default_format(" 2024-03-01 08:00:00 ", "|date", "2024-03-01")]
+#[case::unix_seconds("1706704200", "|date:\"%Y-%m-%dT%H:%M:%S\"", "2024-01-31T12:30:00")]
+#[case::unix_millis("1706704200000", "|date:\"%a %b %e\"", "Wed Jan 31")]
+#[case::day_of_year("2024-12-31", "|date:\"%j %A %%\"", "366 Tuesday %")]
+#[case::not_a_date("soon", "|date", "soon")]
+#[case::invalid_month("2024-13-01", "|date", "2024-13-01")]
+fn date_transformer(
+  #[case] content: &str,
+  #[case] transformers: &str,
+  #[case] expected: &str,
+) -> MdtResult<()> {
+  let input = format!("<!-- {{=block{transformers}}} -->\n<!-- {{/block}} -->\n");
+  let blocks = parse(input)?;
+  let block = blocks.first().expect("a block should be parsed");
+  assert_eq!(apply_transformers(content, &block.transformers), expected);
+
+  Ok(())
+}
+
+const ANNOUNCEMENT: &str =
+  "# Release\n\nWe shipped **search** & _tags_, see [the docs](https://mdt.dev).\n\n- `mdt \
+   search`\n- <b>tags</b>\n\n```sh\nmdt list\n```\n";
+
+#[test]
+fn plain_text_transformer() {
+  assert_eq!(
+    markdown_to_text(ANNOUNCEMENT, TextStyle::Plain),
+    "Release\n\nWe shipped search & tags, see the docs (https://mdt.dev).\n\n- mdt search\n- \
+     tags\n\nmdt list"
+  );
+}
+
+#[test]
+fn slack_format_transformer() {
+  assert_eq!(
+    markdown_to_text(ANNOUNCEMENT, TextStyle::Slack),
+    "*Release*\n\nWe shipped *search* &amp; _tags_, see <https://mdt.dev|the docs>.\n\n• `mdt \
+     search`\n• tags\n\n```\nmdt list\n```"
+  );
+}
+
+#[test]
+fn render_provider_with_transformers() -> MdtResult<()> {
+  let project = Project::from_files(vec![(
+    PathBuf::from("template.t.md"),
+    "<!-- {@news} -->\n## News\n\n**Big** release\n<!-- {/news} -->\n".to_string(),
+  )])?;
+
+  assert_eq!(
+    render_provider_with(&project, "news", "|slackFormat")?,
+    "*News*\n\n*Big* release"
+  );
+  assert_eq!(
+    render_provider_with(&project, "news", "section:\"News\"|plainText")?,
+    "News\n\nBig release"
+  );
+  assert!(matches!(
+    render_provider_with(&project, "missing", ""),
+    Err(MdtError::UnknownBlock(_))
+  ));
+
+  Ok(())
+}
+
+#[test]
+fn encrypted_providers_are_decrypted_with_the_key_command() -> MdtResult<()> {
+  let files = vec![
+    (
+      PathBuf::from("template.t.md"),
+      "<!-- {@secret} -->\n-----BEGIN AGE ENCRYPTED FILE-----\nInternal only\n-----END AGE \
+       ENCRYPTED FILE-----\n<!-- {/secret} -->\n"
+        .to_string(),
+    ),
+    (
+      PathBuf::from("readme.md"),
+      "<!-- {=secret} -->\n<!-- {/secret} -->\n".to_string(),
+    ),
+  ];
+  let render = |config: &str| -> MdtResult<Option<String>> {
+    let project = Project::from_files_with_config(files.clone(), MdtConfig::from_toml(config)?)?;
+    let consumer = project
+      .consumers
+      .first()
+      .ok_or(MdtError::InvalidTokenSequence(0))?;
+
+    render_consumer(&project, consumer)
+  };
+
+  assert_eq!(
+    render("[encryption]\ncommand = \"sed '1d;$d'\"\n")?,
+    Some("\nInternal only\n".to_string())
+  );
+  assert!(matches!(
+    render(""),
+    Err(MdtError::Decryption { name, .. }) if name == "secret"
+  ));
+  assert!(matches!(
+    render("[encryption]\ncommand = \"echo 'no identity' >&2; exit 1\"\n"),
+    Err(MdtError::Decryption { message, .. }) if message.contains("no identity")
+  ));
+  assert!(is_encrypted(
+    "{\"data\": \"ENC[AES256_GCM]\", \"sops\": {}}"
+  ));
+  assert!(!is_encrypted("{\"data\": \"plain\"}"));
+
+  Ok(())
+}
+
+#[test]
+fn commands_run_with_input() -> MdtResult<()> {
+  let output = run_with_input(Command::new("tr").arg("a-z").arg("A-Z"), "notes")?;
+  assert_eq!(String::from_utf8_lossy(&output.stdout), "NOTES");
+
+  let result = run_with_input(
+    Command::new("sh")
+      .arg("-c")
+      .arg("echo 'not found' >&2; exit 1"),
+    "notes",
+  );
+  assert!(matches!(
+    result,
+    Err(MdtError::CommandFailed { command, message })
+      if command == "sh -c echo 'not found' >&2; exit 1" && message.contains("not found")
+  ));
+
+  Ok(())
+}
+
+#[test]
+fn custom_transformers() -> MdtResult<()> {
+  let config = MdtConfig::from_toml(
+    "[transformers.shout]\ncommand = \"tr a-z A-Z\"\n\n[transformers.callout]\ntemplate = \"> \
+     **{{ args[0] }}:** {{ content }}\"\nargs = 1\n",
+  )?;
+  let files = vec![
+    (
+      PathBuf::from("template.t.md"),
+      "<!-- {@note} -->\nkeep it secret\n<!-- {/note} -->\n".to_string(),
+    ),
+    (
+      PathBuf::from("readme.md"),
+      "<!-- {=note|trim|shout} -->\n<!-- {/note} -->\n\n<!-- {=note|trim|callout:\"Tip\"} \
+       -->\n<!-- {/note} -->\n"
+        .to_string(),
+    ),
+  ];
+  let project = Project::from_files_with_config(files.clone(), config.clone())?;
+  let rendered = project
+    .consumers
+    .iter()
+    .map(|consumer| render_consumer(&project, consumer))
+    .collect::<MdtResult<Vec<_>>>()?;
+
+  assert_eq!(
+    rendered,
+    vec![
+      Some("KEEP IT SECRET".to_string()),
+      Some("> **Tip:** keep it secret".to_string())
+    ]
+  );
+  assert!(matches!(
+    Project::from_files(files),
+    Err(MdtError::UnknownTransformer(name)) if name == "shout"
+  ));
+  assert!(matches!(
+    Project::from_files_with_config(
+      vec![(
+        PathBuf::from("readme.md"),
+        "<!-- {=note|callout} -->\n<!-- {/note} -->\n".to_string(),
+      )],
+      config,
+    ),
+    Err(MdtError::InvalidTransformerArguments { .. })
+  ));
+  assert!(matches!(
+    MdtConfig::from_toml("[transformers.trim]\ncommand = \"cat\"\n"),
+    Err(MdtError::InvalidConfig(_))
+  ));
+  assert!(matches!(
+    MdtConfig::from_toml("[transformers.empty]\n"),
+    Err(MdtError::InvalidConfig(_))
+  ));
+
+  Ok(())
+}
+
+#[test]
+fn provider_edits_have_an_impact() -> MdtResult<()> {
+  let template = PathBuf::from("template.t.md");
+  let mut project = Project::from_files(vec![
+    (
+      template.clone(),
+      "<!-- {@install} -->\ncargo install mdt\n<!-- {/install} -->\n<!-- {@usage} -->\nmdt \
+       check\n<!-- {/usage} -->\n"
+        .to_string(),
+    ),
+    (
+      PathBuf::from("readme.md"),
+      "<!-- {=install|trim} -->\n<!-- {/install} -->\n<!-- {=usage} -->\n<!-- {/usage} -->\n<!-- \
+       {=install|frozen} -->\n<!-- {/install} -->\n"
+        .to_string(),
+    ),
+    (
+      PathBuf::from("docs.md"),
+      "<!-- {=usage+install} -->\n<!-- {/usage+install} -->\n".to_string(),
+    ),
+  ])?;
+  let impacts = provider_impact(
+    &mut project,
+    &template,
+    "<!-- {@install} -->\ncargo install mdt_cli\n<!-- {/install} -->\n<!-- {@usage} -->\nmdt \
+     check\n<!-- {/usage} -->\n",
+  )?;
+
+  assert_eq!(
+    impacts
+      .iter()
+      .map(|impact| {
+        (
+          impact.name.as_str(),
+          impact.consumers.len(),
+          impact.file_count(),
+        )
+      })
+      .collect::<Vec<_>>(),
+    vec![("install", 2, 2), ("usage", 0, 0)]
+  );
+  assert_eq!(project.providers.len(), 2);
+  assert_eq!(
+    project
+      .providers
+      .get("install")
+      .map(|provider| provider.content.as_str()),
+    Some("\ncargo install mdt\n")
+  );
+  assert_eq!(
+    provider_impact(&mut project, Path::new("readme.md"), "")?,
+    vec![]
+  );
+
+  Ok(())
+}
+
+#[test]
+fn transformers_can_be_denied_and_allowed() -> MdtResult<()> {
+  let files = |tag: &str| {
+    vec![
+      (
+        PathBuf::from("template.t.md"),
+        "<!-- {@note} -->\nkeep it secret\n<!-- {/note} -->\n".to_string(),
+      ),
+      (
+        PathBuf::from("readme.md"),
+        format!("<!-- {{={tag}}} -->\n<!-- {{/note}} -->\n"),
+      ),
+    ]
+  };
+  let denied = MdtConfig::from_toml(
+    "[transformers]\ndeny = [\"replace\"]\n\n[transformer_aliases]\nquiet = \"trim\"\n",
+  )?;
+
+  assert!(!denied.transformers.is_allowed("replace"));
+  assert!(Project::from_files_with_config(files("note|@quiet"), denied.clone()).is_ok());
+  assert!(matches!(
+    Project::from_files_with_config(files("note|replace:\"a\":\"b\""), denied),
+    Err(MdtError::DeniedTransformer(name)) if name == "replace"
+  ));
+
+  let allowed = MdtConfig::from_toml(
+    "[transformers]\nallow = [\"trim\", \"shout\"]\n\n[transformers.shout]\ncommand = \"tr a-z \
+     A-Z\"\n",
+  )?;
+
+  assert!(Project::from_files_with_config(files("note|trim|shout"), allowed.clone()).is_ok());
+  assert!(matches!(
+    Project::from_files_with_config(files("note|indent:\"  \""), allowed),
+    Err(MdtError::DeniedTransformer(name)) if name == "indent"
+  ));
+  assert!(matches!(
+    MdtConfig::from_toml(
+      "[transformers]\ndeny = [\"replace\"]\n\n[transformer_aliases]\nfix = \
+       'replace:\"a\":\"b\"'\n"
+    ),
+    Err(MdtError::InvalidConfig(_))
+  ));
+  assert!(matches!(
+    MdtConfig::from_toml("[transformers]\ndeny = [\"missing\"]\n"),
+    Err(MdtError::InvalidConfig(_))
+  ));
+
+  Ok(())
+}
+
+#[test]
+fn strict_only_enables_its_own_checks() {
+  let options = CheckOptions::strict();
+  assert!(options.undefined_variables);
+  assert!(options.orphan_consumers);
+  assert!(options.unused_providers);
+  assert!(options.strict_whitespace);
+  assert!(options.check_links);
+  assert!(!options.alt_text);
+  assert!(!options.deny_deprecated);
+  assert_eq!(options.max_sentence_length, None);
+
+  let options = CheckOptions {
+    strict: true,
+    alt_text: true,
+    deny_deprecated: true,
+    ..CheckOptions::default()
+  }
+  .resolved();
+  assert!(options.check_links);
+  assert!(options.alt_text);
+  assert!(options.deny_deprecated);
+}
+
+#[rstest]
+#[case::markdown("![](logo.svg) ![Logo](logo.svg)", vec!["logo.svg"])]
+#[case::html(
+  "<img src=\"a.png\">\n<img alt=\"\" src=\"b.png\">\n<img alt=\"C\" src=\"c.png\">",
+  vec!["a.png"]
+)]
+#[case::code("```md\n![](logo.svg)\n```", vec![])]
+fn missing_alt_text(#[case] content: &str, #[case] expected: Vec<&str>) {
+  assert_eq!(find_missing_alt_text(content), expected);
+}
+
+#[rstest]
+#[case::short("One two three. Four five.", vec![])]
+#[case::long(
+  "Short one. This sentence has far too many words in it! Fine?",
+  vec!["This sentence has far too many words in it!"]
+)]
+#[case::code("```txt\nthis code block has many words but is ignored\n```", vec![])]
+fn long_sentences(#[case] content: &str, #[case] expected: Vec<&str>) {
+  assert_eq!(find_long_sentences(content, 5), expected);
+}
+
+#[test]
+fn check_project_reports_readability() -> MdtResult<()> {
+  let project = Project::from_files(vec![
+    (
+      PathBuf::from("template.t.md"),
+      "<!-- {@logo} -->\n![](logo.svg)\n<!-- {/logo} -->\n".to_string(),
+    ),
+    (
+      PathBuf::from("readme.md"),
+      "<!-- {=logo} -->\n![](logo.svg)\n<!-- {/logo} -->\n".to_string(),
+    ),
+  ])?;
+  let options = CheckOptions {
+    alt_text: true,
+    ..CheckOptions::default()
+  };
+
+  assert_eq!(
+    check_project(&project, options)?.diagnostics,
+    vec![CheckDiagnostic {
+      severity: Severity::Error,
+      file: PathBuf::from("readme.md"),
+      message: "consumer `logo` has an image without alt text: `logo.svg`".into(),
+    }]
+  );
+
+  Ok(())
+}
+
+#[test]
+fn transformer_aliases() -> MdtResult<()> {
+  let config = MdtConfig::from_toml("[transformer_aliases]\nrustdoc = 'trim|indent:\"//! \"'\n")?;
+  let blocks = parse_with_config(
+    "<!-- {=docs|@rustdoc|wrap:\"\\n\"} -->\n<!-- {/docs} -->\n",
+    &config,
+  )?;
+  let block = blocks.first().unwrap();
+
+  assert_eq!(
+    block
+      .transformers
+      .iter()
+      .map(|transformer| transformer.r#type.name())
+      .collect::<Vec<_>>(),
+    vec!["trim", "indent", "wrap"]
+  );
+  assert_eq!(
+    apply_transformers("\nline\n", &block.transformers),
+    "\n//! line\n"
+  );
+  assert!(matches!(
+    parse_with_config("<!-- {=docs|@missing} --><!-- {/docs} -->", &config),
+    Err(MdtError::UnknownTransformerAlias(name)) if name == "missing"
+  ));
+  assert!(matches!(
+    parse_with_config("<!-- {=docs|@rustdoc:1} --><!-- {/docs} -->", &config),
+    Err(MdtError::InvalidTransformerArguments { .. })
+  ));
+  assert!(matches!(
+    MdtConfig::from_toml("[transformer_aliases]\na = \"trim\"\nb = \"@a\"\n"),
+    Err(MdtError::InvalidConfig(_))
+  ));
+
+  let config = MdtConfig::from_toml("[chains]\nrustdoc = 'trim|indent:\"//! \"'\n")?;
+  assert_eq!(
+    config
+      .transformer_aliases
+      .get("rustdoc")
+      .map(String::as_str),
+    Some("trim|indent:\"//! \"")
+  );
+
+  Ok(())
+}
+
+#[rstest]
+#[case::truthy_path("{=a|if:\"pkg.enabled\"}", "content")]
+#[case::comparison("{=a|if:\"pkg.count >= 2 and not pkg.private\"}", "content")]
+#[case::version("{=a|if:\"pkg.version is since('2.0')\"}", "")]
+#[case::unless("{=a|unless:\"pkg.private\"}", "content")]
+#[case::missing_variable("{=a|unless:\"pkg.missing\"}", "content")]
+fn conditional_transformers(#[case] tag: &str, #[case] expected: &str) -> MdtResult<()> {
+  let mut data = DataMap::new();
+  data.insert(
+    "pkg".into(),
+    serde_json::json!({ "enabled": true, "private": false, "count": 3, "version": "1.4.0" }),
+  );
+  let blocks = parse(format!("<!-- {tag} -->\n<!-- {{/a}} -->"))?;
+  let context = TransformContext {
+    data: Some(&data),
+    ..TransformContext::default()
+  };
+
+  assert_eq!(
+    apply_transformers_with_context("content", &blocks.first().unwrap().transformers, &context)?,
+    expected
+  );
+
+  Ok(())
+}
+
+#[test]
+fn invalid_conditions_are_reported() {
+  assert!(matches!(
+    parse("<!-- {=a|if:\"pkg.version >=\"} --><!-- {/a} -->"),
+    Err(MdtError::InvalidExpression { .. })
+  ));
+}
+
+#[rstest]
+#[case::markdown(
+  "![Logo](assets/logo.svg \"Logo\")",
+  "![Logo](https://mdt.dev/docs/assets/logo.svg \"Logo\")"
+)]
+#[case::parent("![Logo](../logo.svg#dark)", "![Logo](https://mdt.dev/logo.svg#dark)")]
+#[case::html(
+  "<img src=\"logo.svg\" alt=\"Logo\">",
+  "<img src=\"https://mdt.dev/docs/logo.svg\" alt=\"Logo\">"
+)]
+#[case::absolute(
+  "![Logo](https://example.com/logo.svg)",
+  "![Logo](https://example.com/logo.svg)"
+)]
+#[case::link("[guide](guide.md)", "[guide](guide.md)")]
+fn image_url_rewriting(#[case] content: &str, #[case] expected: &str) {
+  assert_eq!(
+    rewrite_image_urls(content, Path::new("docs/template.t.md"), "https://mdt.dev/"),
+    expected
+  );
+}
+
+#[test]
+fn missing_images_are_reported() -> MdtResult<()> {
+  let directory = tempfile::tempdir()?;
+  let root = directory.path();
+  std::fs::write(root.join("logo.svg"), "<svg></svg>")?;
+  std::fs::write(
+    root.join("template.t.md"),
+    "<!-- {@images} -->\n<img src=\"logo.svg\"> <img src=\"missing.svg\">\n<!-- {/images} -->\n",
+  )?;
+  std::fs::write(
+    root.join("readme.md"),
+    "<!-- {=images} -->\n<!-- {/images} -->\n",
+  )?;
+  let project = scan_project(root)?;
+
+  assert_eq!(
+    find_missing_images(
+      &root.join("readme.md"),
+      "<img src=\"logo.svg\"> <img src=\"missing.svg\">"
+    ),
+    vec!["missing.svg"]
+  );
+  assert!(check_project(&project, CheckOptions::strict())?
+    .diagnostics
+    .iter()
+    .any(|diagnostic| {
+      diagnostic
+        .message
+        .contains("`missing.svg` which doesn't exist")
+    }));
+
+  Ok(())
+}
+
+#[test]
+fn inline_images_transformer() -> MdtResult<()> {
+  let directory = tempfile::tempdir()?;
+  let root = directory.path();
+  std::fs::write(root.join("badge.svg"), "<svg/>")?;
+  std::fs::write(root.join("small.png"), "ab")?;
+  std::fs::write(
+    root.join("template.t.md"),
+    "<!-- {@badges} -->\n![Badge](badge.svg) <img src=\"small.png\"> \
+     ![Missing](missing.svg)\n<!-- {/badges} -->\n",
+  )?;
+  std::fs::write(
+    root.join("readme.md"),
+    "<!-- {=badges|trim|inlineImages:5} -->\n<!-- {/badges} -->\n",
+  )?;
+  let project = scan_project(root)?;
+  let consumer = project.consumers.first().unwrap();
+
+  assert_eq!(
+    render_consumer(&project, consumer)?.as_deref(),
+    Some("![Badge](badge.svg) <img src=\"data:image/png;base64,YWI=\"> ![Missing](missing.svg)")
+  );
+
+  Ok(())
+}
+
+#[test]
+fn nested_blocks() -> MdtResult<()> {
+  let blocks = parse(
+    "<!-- {@outer} -->\nbefore\n<!-- {=inner} -->\nstale\n<!-- {/inner} -->\nafter\n<!-- {/outer} \
+     -->\n",
+  )?;
+
+  let [outer, inner] = blocks.as_slice() else {
+    panic!("expected two blocks");
+  };
+
+  assert_eq!(
+    (outer.name.as_str(), inner.name.as_str()),
+    ("outer", "inner")
+  );
+  assert!(outer.contains(inner));
+  assert!(!inner.contains(outer));
+  assert!(matches!(
+    parse("<!-- {=a} --><!-- {=b} --><!-- {/a} --><!-- {/b} -->"),
+    Err(MdtError::MissingClosingTag(name)) if name == "b"
+  ));
+
+  Ok(())
+}
+
+#[test]
+fn mismatched_closing_tags_are_recovered() -> MdtResult<()> {
+  let (blocks, diagnostics) = parse_with_diagnostics(
+    "<!-- {@outer} -->\n<!-- {@a} -->\nhello\n<!-- {/b} -->\n<!-- {/outer} -->\n",
+    &MdtConfig::default(),
+  )?;
+
+  let [outer, a] = blocks.as_slice() else {
+    panic!("expected two blocks");
+  };
+
+  assert_eq!((outer.name.as_str(), a.name.as_str()), ("outer", "a"));
+  assert!(outer.contains(a));
+  assert_eq!(
+    diagnostics,
+    vec![ParseDiagnostic::MismatchedClosingTag {
+      expected: "a".into(),
+      found: "b".into(),
+      position: a.closing_name,
+    }]
+  );
+  assert_eq!(
+    (a.closing_name.start.line, a.closing_name.start.column),
+    (4, 8)
+  );
+
+  let project = Project::from_files(vec![
+    (
+      PathBuf::from("template.t.md"),
+      "<!-- {@a} -->hello<!-- {/a} -->\n".to_string(),
+    ),
+    (
+      PathBuf::from("readme.md"),
+      "<!-- {=a} -->hello<!-- {/b} -->\n".to_string(),
+    ),
+  ])?;
+  let result = check_project(&project, CheckOptions::default())?;
+
+  assert!(result.is_failure());
+  assert_eq!(
+    result.diagnostics,
+    vec![CheckDiagnostic {
+      severity: Severity::Error,
+      file: PathBuf::from("readme.md"),
+      message: "the closing tag `b` at 1:26 doesn't match the block `a`".into(),
+    }]
+  );
+
+  Ok(())
+}
+
+#[test]
+fn nested_blocks_are_resolved_inside_out() -> MdtResult<()> {
+  let project = Project::from_files(vec![
+    (
+      PathBuf::from("template.t.md"),
+      "<!-- {@install} -->\n{#- doc: Installation -#}\n## Install\n\n<!-- {=command|trim|code} \
+       -->\nstale\n<!-- {/command} -->\n<!-- {/install} -->\n\n<!-- {@command} -->\ncargo install \
+       mdt\n<!-- {/command} -->\n"
+        .to_string(),
+    ),
+    (
+      PathBuf::from("readme.md"),
+      "<!-- {=install} -->\n<!-- {/install} -->\n".to_string(),
+    ),
+  ])?;
+  let result = compute_updates(&project)?;
+
+  assert_eq!(
+    result
+      .updated_files
+      .get(Path::new("readme.md"))
+      .map(String::as_str),
+    Some(
+      "<!-- {=install} -->\n## Install\n\n<!-- {=command|trim|code} -->`cargo install mdt`<!-- \
+       {/command} -->\n<!-- {/install} -->\n"
+    )
+  );
+
+  let circular = Project::from_files(vec![(
+    PathBuf::from("template.t.md"),
+    "<!-- {@loop} -->\n<!-- {=loop} --><!-- {/loop} -->\n<!-- {/loop} -->\n".to_string(),
+  )])?;
+  assert!(matches!(
+    compute_updates(&circular),
+    Err(MdtError::CircularBlock(name)) if name == "loop"
+  ));
+
+  Ok(())
+}
+
+#[rstest]
+#[case::fenced(
+  "```rust\nfn main() {}\n```",
+  "|codeBlock:\"rust\"",
+  Some("fenced-code-block"),
+  ""
+)]
+#[case::repeated_indent(
+  "text",
+  "|indent:\"  \"|indent:\"> \"",
+  Some("repeated-indent"),
+  "|indent:\">   \""
+)]
+#[case::trim_after_indent(
+  "text",
+  "|indent:\"//! \"|trim",
+  Some("trim-after-indent"),
+  "|trim|indent:\"//! \""
+)]
+#[case::wrap_after_indent(
+  "text",
+  "|indent:\"  \"|wrapWidth:80",
+  Some("wrap-width-after-indent"),
+  "|wrapWidth:80|indent:\"  \""
+)]
+#[case::fine("text", "|trim|indent:\"  \"", None, "")]
+fn transformer_chain_lints(
+  #[case] content: &str,
+  #[case] chain: &str,
+  #[case] rule: Option<&str>,
+  #[case] suggestion: &str,
+) -> MdtResult<()> {
+  let transformers = parse_transformers(chain, &MdtConfig::default())?;
+  let advice = lint_transformer_chain(content, &transformers);
+
+  assert_eq!(advice.first().map(|advice| advice.rule), rule);
+  assert_eq!(
+    advice
+      .first()
+      .and_then(ChainAdvice::suggested_chain)
+      .unwrap_or_default(),
+    suggestion
+  );
+
+  Ok(())
+}
+
+#[test]
+fn namespaced_blocks() -> MdtResult<()> {
+  let project = Project::from_files(vec![
+    (
+      PathBuf::from("template.t.md"),
+      "<!-- {@docs/install} -->\ndocs\n<!-- {/docs/install} -->\n<!-- {@pkg:install} \
+       -->\npkg\n<!-- {/pkg:install} -->\n"
+        .to_string(),
+    ),
+    (
+      PathBuf::from("readme.md"),
+      "<!-- {=pkg:install|trim} -->\n<!-- {/pkg:install} -->\n".to_string(),
+    ),
+  ])?;
+  let consumer = project.consumers.first().unwrap();
+
+  assert_eq!(consumer.block.name, "pkg:install");
+  assert_eq!(render_consumer(&project, consumer)?.as_deref(), Some("pkg"));
+  assert_eq!(block_namespace("pkg/docs/overview"), Some("pkg/docs"));
+  assert_eq!(block_namespace("overview"), None);
+  assert!(is_in_namespace("pkg/docs/overview", "pkg"));
+  assert!(!is_in_namespace("pkgs/overview", "pkg"));
+
+  Ok(())
+}
+
+#[test]
+fn default_transformers_per_extension() -> MdtResult<()> {
+  let config = MdtConfig::from_toml("[defaults.transformers]\nrs = 'trim|indent:\"//! \"'\n")?;
+  let project = Project::from_files_with_config(
+    vec![
+      (
+        PathBuf::from("template.t.md"),
+        "<!-- {@docs} -->\nDocs\n<!-- {/docs} -->\n".to_string(),
+      ),
+      (
+        PathBuf::from("lib.rs"),
+        "// <!-- {=docs} -->\n// <!-- {/docs} -->\n// <!-- {=docs|trim} -->\n// <!-- {/docs} -->\n"
+          .to_string(),
+      ),
+      (
+        PathBuf::from("readme.md"),
+        "<!-- {=docs} -->\n<!-- {/docs} -->\n".to_string(),
+      ),
+    ],
+    config,
+  )?;
+  let rendered = project
+    .consumers
+    .iter()
+    .map(|consumer| render_consumer(&project, consumer))
+    .collect::<MdtResult<Vec<_>>>()?;
+
+  assert_eq!(
+    rendered,
+    vec![
+      Some("//! Docs".to_string()),
+      Some("Docs".to_string()),
+      Some("\nDocs\n".to_string())
+    ]
+  );
+  assert!(matches!(
+    MdtConfig::from_toml("[defaults.transformers]\nrs = \"unknown\"\n"),
+    Err(MdtError::InvalidConfig(_))
+  ));
+
+  Ok(())
+}
+
+#[test]
+fn provider_includes() -> MdtResult<()> {
+  let project = Project::from_files(vec![(
+    PathBuf::from("template.t.md"),
+    "<!-- {@intro} -->\nmdt keeps markdown in sync.\n<!-- {/intro} -->\n\n<!-- {@install} \
+     -->\ncargo install mdt\n<!-- {/install} -->\n\n<!-- {@readme} -->\n<!-- {> intro} \
+     -->\n\n<!-- {> install|trim|code} -->\n<!-- {/readme} -->\n"
+      .to_string(),
+  )])?;
+  let readme = project
+    .providers
+    .get("readme")
+    .ok_or_else(|| MdtError::UnknownBlock("readme".into()))?;
+
+  assert_eq!(
+    project.render_provider(readme)?,
+    "\n\nmdt keeps markdown in sync.\n\n\n`cargo install mdt`\n"
+  );
+
+  let circular = Project::from_files(vec![(
+    PathBuf::from("template.t.md"),
+    "<!-- {@a} -->\n<!-- {> b} -->\n<!-- {/a} -->\n<!-- {@b} -->\n<!-- {> a} -->\n<!-- {/b} -->\n"
+      .to_string(),
+  )])?;
+  let a = circular
+    .providers
+    .get("a")
+    .ok_or_else(|| MdtError::UnknownBlock("a".into()))?;
+  assert!(matches!(
+    circular.render_provider(a),
+    Err(MdtError::CircularInclude(chain)) if chain == "a -> b -> a"
+  ));
+
+  Ok(())
+}
+
+#[test]
+fn concatenated_consumers() -> MdtResult<()> {
+  let project = Project::from_files(vec![
+    (
+      PathBuf::from("template.t.md"),
+      "<!-- {@intro} -->\nIntro\n<!-- {/intro} -->\n<!-- {@install} -->\nInstall\n<!-- {/install} \
+       -->\n"
+        .to_string(),
+    ),
+    (
+      PathBuf::from("readme.md"),
+      "<!-- {=intro+install|trim} -->\n<!-- {/intro+install} -->\n<!-- {=intro+missing} -->\n<!-- \
+       {/intro+missing} -->\n"
+        .to_string(),
+    ),
+  ])?;
+  let rendered = project
+    .consumers
+    .iter()
+    .map(|consumer| render_consumer(&project, consumer))
+    .collect::<MdtResult<Vec<_>>>()?;
+
+  assert_eq!(rendered, vec![Some("Intro\n\nInstall".to_string()), None]);
+
+  let result = check_project(&project, CheckOptions::default())?;
+  assert_eq!(
+    result.stale,
+    vec![(PathBuf::from("readme.md"), "intro+install".to_string())]
+  );
+  assert!(!result
+    .diagnostics
+    .iter()
+    .any(|diagnostic| diagnostic.message.contains("has no consumers")));
+
+  assert!(matches!(
+    Project::from_files(vec![(
+      PathBuf::from("template.t.md"),
+      "<!-- {@a+b} -->\n<!-- {/a+b} -->\n".to_string(),
+    )]),
+    Err(MdtError::InvalidBlockName { .. })
+  ));
+
+  Ok(())
+}
+
+#[test]
+fn optional_consumers() -> MdtResult<()> {
+  let project = Project::from_files(vec![
+    (
+      PathBuf::from("template.t.md"),
+      "<!-- {@badge} -->\nBadge\n<!-- {/badge} -->\n".to_string(),
+    ),
+    (
+      PathBuf::from("readme.md"),
+      "<!-- {=badge?|trim} -->\n<!-- {/badge} -->\n<!-- {=sponsors?} -->\nstale\n<!-- {/sponsors} \
+       -->\n"
+        .to_string(),
+    ),
+  ])?;
+
+  let optional = project
+    .consumers
+    .iter()
+    .map(|consumer| consumer.block.optional)
+    .collect::<Vec<_>>();
+  assert_eq!(optional, vec![true, true]);
+
+  let rendered = project
+    .consumers
+    .iter()
+    .map(|consumer| render_consumer(&project, consumer))
+    .collect::<MdtResult<Vec<_>>>()?;
+  assert_eq!(
+    rendered,
+    vec![Some("Badge".to_string()), Some(String::new())]
+  );
+
+  let result = check_project(&project, CheckOptions::strict())?;
+  assert!(result.diagnostics.is_empty());
+  assert_eq!(
+    result.stale,
+    vec![
+      (PathBuf::from("readme.md"), "badge".to_string()),
+      (PathBuf::from("readme.md"), "sponsors".to_string())
+    ]
+  );
+
+  Ok(())
+}
+
+#[test]
+fn content_hash_detects_manual_edits() -> MdtResult<()> {
+  let config = MdtConfig::from_toml("[update]\ncontent_hash = true\n")?;
+  let template = (
+    PathBuf::from("template.t.md"),
+    "<!-- {@greeting} -->\nHello\n<!-- {/greeting} -->\n".to_string(),
+  );
+  let project = Project::from_files_with_config(
+    vec![
+      template.clone(),
+      (
+        PathBuf::from("readme.md"),
+        "<!-- {=greeting} -->\n<!-- {/greeting} -->\n".to_string(),
+      ),
+    ],
+    config.clone(),
+  )?;
+  let result = compute_updates(&project)?;
+  let hash = content_hash("\nHello\n");
+  let updated = format!("<!-- {{=greeting}} -->\nHello\n<!-- {{/greeting|hash:\"{hash}\"}} -->\n");
+
+  assert_eq!(
+    result
+      .updated_files
+      .get(Path::new("readme.md"))
+      .map(String::as_str),
+    Some(updated.as_str())
+  );
+
+  let project = Project::from_files_with_config(
+    vec![
+      template.clone(),
+      (PathBuf::from("readme.md"), updated.clone()),
+    ],
+    config.clone(),
+  )?;
+  assert!(compute_updates(&project)?.is_empty());
+
+  let edited = updated.replace("Hello", "Hello, edited");
+  let project =
+    Project::from_files_with_config(vec![template, (PathBuf::from("readme.md"), edited)], config)?;
+  let result = compute_updates(&project)?;
+  assert!(result.is_empty());
+  assert_eq!(
+    result.conflicts,
+    vec![(PathBuf::from("readme.md"), "greeting".to_string())]
+  );
+
+  let check = check_project(&project, CheckOptions::default())?;
+  assert!(check.stale.is_empty());
+  assert!(check.is_failure());
+
+  Ok(())
+}
+
+#[rstest]
+#[case::outer("quickstart", Some("\nInstall\nRun it\n"))]
+#[case::nested("run", Some("\nRun it\n"))]
+#[case::missing("unknown", None)]
+fn region_selection(#[case] region: &str, #[case] expected: Option<&str>) {
+  let content = "\nIntro\n<!-- {#region: quickstart} -->\nInstall\n<!-- {#region: run} -->\nRun \
+                 it\n<!-- {/region} -->\n<!-- {/region} -->\nOutro\n";
+
+  assert_eq!(select_region(content, region).as_deref(), expected);
+  assert_eq!(
+    strip_region_markers(content),
+    "\nIntro\nInstall\nRun it\nOutro\n"
+  );
+}
+
+#[test]
+fn consumers_can_select_regions() -> MdtResult<()> {
+  let mut project = Project::from_files(vec![
+    (
+      PathBuf::from("template.t.md"),
+      "<!-- {@guide} -->\n# Guide\n<!-- {#region: quickstart} -->\nInstall {{ pkg.name }}\n<!-- \
+       {/region} -->\n<!-- {/guide} -->\n"
+        .to_string(),
+    ),
+    (
+      PathBuf::from("readme.md"),
+      "<!-- {=guide#quickstart|trim} -->\n<!-- {/guide} -->\n<!-- {=guide} -->\n<!-- {/guide} \
+       -->\n<!-- {=guide#missing} -->\n<!-- {/guide} -->\n"
+        .to_string(),
+    ),
+  ])?;
+  project
+    .data
+    .insert("pkg".into(), serde_json::json!({ "name": "mdt" }));
+
+  let [quickstart, guide, missing] = project.consumers.as_slice() else {
+    panic!("expected three consumers");
+  };
+
+  assert_eq!(quickstart.block.region.as_deref(), Some("quickstart"));
+  assert_eq!(
+    render_consumer(&project, quickstart)?,
+    Some("Install mdt".to_string())
+  );
+  assert_eq!(
+    render_consumer(&project, guide)?,
+    Some("\n# Guide\nInstall mdt\n".to_string())
+  );
+  assert!(matches!(
+    render_consumer(&project, missing),
+    Err(MdtError::MissingRegion { ref region, .. }) if region == "missing"
+  ));
+
+  Ok(())
+}
+
+#[test]
+fn escaped_tags() -> MdtResult<()> {
+  let example = "<!-- {{=example|trim}} -->\n<!-- {{/example}} -->\n";
+  assert_eq!(parse(example)?, vec![]);
+
+  let project = Project::from_files(vec![
+    (
+      PathBuf::from("template.t.md"),
+      format!("<!-- {{@syntax}} -->\n{example}<!-- {{/syntax}} -->\n"),
+    ),
+    (
+      PathBuf::from("readme.md"),
+      "<!-- {=syntax|unescape|codeBlock:\"md\"} -->\n<!-- {/syntax} -->\n".to_string(),
+    ),
+  ])?;
+  let [consumer] = project.consumers.as_slice() else {
+    panic!("expected one consumer");
+  };
+
+  assert_eq!(
+    render_consumer(&project, consumer)?,
+    Some("```md\n\n<!-- {=example|trim} -->\n<!-- {/example} -->\n\n```".to_string())
+  );
+
+  Ok(())
+}
+
+#[test]
+fn injected_tags_are_escaped() -> MdtResult<()> {
+  let files = vec![
+    (
+      PathBuf::from("template.t.md"),
+      "<!-- {@snippet} -->\nbefore\n<!-- {{/snippet}} -->\n<!-- {{@spoof}} -->\nafter\n<!-- \
+       {/snippet} -->\n"
+        .to_string(),
+    ),
+    (
+      PathBuf::from("readme.md"),
+      "<!-- {=snippet|unescape} -->\n<!-- {/snippet} -->\n".to_string(),
+    ),
+  ];
+  let project = Project::from_files(files.clone())?;
+  let [consumer] = project.consumers.as_slice() else {
+    panic!("expected one consumer");
+  };
+
+  assert_eq!(
+    render_guarded_consumer(&project, consumer)?,
+    Some(GuardedContent {
+      content: "\nbefore\n<!-- {{/snippet}} -->\n<!-- {{@spoof}} -->\nafter\n".into(),
+      escaped: vec!["{/snippet}".into(), "{@spoof}".into()],
+    })
+  );
+
+  let updated = compute_updates(&project)?.updated_files;
+  let readme = updated
+    .get(Path::new("readme.md"))
+    .cloned()
+    .unwrap_or_default();
+  let [template, _] = files.as_slice() else {
+    panic!("expected two files");
+  };
+  let project = Project::from_files(vec![template.clone(), (PathBuf::from("readme.md"), readme)])?;
+  let result = check_project(&project, CheckOptions::default())?;
+
+  assert_eq!(project.consumers.len(), 1);
+  assert!(result.stale.is_empty());
+  assert_eq!(
+    result
+      .diagnostics
+      .iter()
+      .filter(|diagnostic| diagnostic.message.contains("is escaped"))
+      .count(),
+    2
+  );
+
+  Ok(())
+}
+
+#[test]
+fn adoption_finds_the_closest_passage() {
+  let report = compare_for_adoption(
+    "# Title\n\nInstall it.\n\n  cargo install mdt\nThen run it.\n",
+    "Install it.\ncargo install mdt_cli\n\nThen run it.\n",
+  );
+
+  assert_eq!(
+    report,
+    Some(AdoptionReport {
+      lines: 3..7,
+      similarity: 2.0 / 3.0,
+      differences: vec![
+        LineDifference::Missing("cargo install mdt_cli".into()),
+        LineDifference::Extra("cargo install mdt".into()),
+      ],
+    })
+  );
+  assert!(report.map_or(false, |report| report.is_within(1.0 / 3.0)));
+  assert_eq!(compare_for_adoption("unrelated\n", "Install it.\n"), None);
+}
+
+#[cfg(feature = "git")]
+#[test]
+fn git_operations() -> AnyEmptyResult {
+  let directory = tempfile::tempdir()?;
+  let root = directory.path();
+  let git = |args: &[&str]| {
+    std::process::Command::new("git")
+      .args(args)
+      .current_dir(root)
+      .output()
+  };
+
+  assert!(detect_vcs(root).is_none());
+  git(&["init", "--quiet"])?;
+  git(&["config", "user.name", "mdt"])?;
+  git(&["config", "user.email", "mdt@example.com"])?;
+
+  let Some(vcs) = detect_vcs(root) else {
+    panic!("expected a git repository");
+  };
+
+  std::fs::write(root.join("readme.md"), "# Title\n")?;
+  assert_eq!(vcs.last_modified(Path::new("readme.md"))?, None);
+  vcs.commit(&[PathBuf::from("readme.md")], "initial")?;
+  assert!(vcs.last_modified(Path::new("readme.md"))?.is_some());
+
+  std::fs::write(root.join("readme.md"), "# Changed\n")?;
+  std::fs::write(root.join("new.md"), "new\n")?;
+  git(&["add", "readme.md"])?;
+  std::fs::write(root.join("readme.md"), "# Unstaged\n")?;
+
+  assert_eq!(
+    vcs.changed_files("HEAD")?,
+    vec![PathBuf::from("new.md"), PathBuf::from("readme.md")]
+  );
+  assert_eq!(
+    vcs.staged_content(Path::new("readme.md"))?,
+    Some("# Changed\n".into())
+  );
+  assert_eq!(vcs.staged_content(Path::new("new.md"))?, None);
+
+  Ok(())
+}
+
+#[cfg(feature = "git")]
+#[test]
+fn git_data_namespace() -> AnyEmptyResult {
+  let directory = tempfile::tempdir()?;
+  let root = directory.path();
+  let git = |args: &[&str]| {
+    std::process::Command::new("git")
+      .args(args)
+      .current_dir(root)
+      .output()
+  };
+
+  std::fs::write(root.join(CONFIG_FILE_NAME), "[git]\ntags = \"v*\"\n")?;
+  std::fs::write(
+    root.join("template.t.md"),
+    "<!-- {@release} -->\nLatest release: {{ git.latest_tag }}\n<!-- {/release} -->\n",
+  )?;
+  std::fs::write(
+    root.join("readme.md"),
+    "<!-- {=release} -->\n<!-- {/release} -->\n",
+  )?;
+  assert!(matches!(
+    scan_project(root),
+    Err(MdtError::InvalidConfig(_))
+  ));
+
+  git(&["init", "--quiet"])?;
+  git(&["config", "user.name", "mdt"])?;
+  git(&["config", "user.email", "mdt@example.com"])?;
+  git(&["add", "."])?;
+  git(&["commit", "--quiet", "-m", "initial"])?;
+  git(&["tag", "v1.2.0"])?;
+  git(&["tag", "nightly"])?;
+
+  let project = scan_project(root)?;
+  let value = project.data.get(GIT_NAMESPACE).cloned().unwrap_or_default();
+
+  assert_eq!(value.get("latest_tag"), Some(&"v1.2.0".into()));
+  assert_eq!(value.get("contributor_count"), Some(&1.into()));
+  assert_eq!(
+    value
+      .get("short_sha")
+      .and_then(serde_json::Value::as_str)
+      .map(str::len),
+    Some(7)
+  );
+  assert!(value
+    .get("commit_date")
+    .map_or(false, serde_json::Value::is_string));
+
+  let [consumer] = project.consumers.as_slice() else {
+    panic!("expected one consumer");
+  };
+  assert_eq!(
+    render_consumer(&project, consumer)?,
+    Some("\nLatest release: v1.2.0\n".into())
+  );
+
+  Ok(())
+}
+
+#[test]
+fn custom_comment_styles() -> MdtResult<()> {
+  let config = MdtConfig::from_toml(
+    "[comments.jinja]\nopen = \"{#\"\nclose = \"#}\"\n\n[comments.tex]\nopen = \"%\"\n",
+  )?;
+  let project = Project::from_files_with_config(
+    vec![
+      (
+        PathBuf::from("template.t.md"),
+        "<!-- {@greeting} -->\nHello!\n<!-- {/greeting} -->\n".to_string(),
+      ),
+      (
+        PathBuf::from("page.jinja"),
+        "<p>{# {=greeting|trim} #}stale{# {/greeting} #}</p>\n".to_string(),
+      ),
+      (
+        PathBuf::from("paper.tex"),
+        "% {=greeting|trimStart}\nstale\n% {/greeting}\n\\end\n".to_string(),
+      ),
+    ],
+    config,
+  )?;
+  let result = compute_updates(&project)?;
+
+  assert_eq!(
+    result
+      .updated_files
+      .get(Path::new("page.jinja"))
+      .map(String::as_str),
+    Some("<p>{# {=greeting|trim} #}Hello!{# {/greeting} #}</p>\n")
+  );
+  assert_eq!(
+    result
+      .updated_files
+      .get(Path::new("paper.tex"))
+      .map(String::as_str),
+    Some("% {=greeting|trimStart}\nHello!\n% {/greeting}\n\\end\n")
+  );
+  assert!(MdtConfig::from_toml("[comments.tex]\nopen = \" \"\n").is_err());
+
+  Ok(())
+}
+
+#[test]
+fn nested_projects_are_scanned_on_their_own() -> AnyEmptyResult {
+  let directory = tempfile::tempdir()?;
+  let root = directory.path();
+  let provider = "<!-- {@greeting} -->\nHello\n<!-- {/greeting} -->\n";
+  std::fs::write(
+    root.join(CONFIG_FILE_NAME),
+    "[workspace]\nmembers = [\"packages/*\"]\n",
+  )?;
+  std::fs::write(root.join("template.t.md"), provider)?;
+
+  for member in ["packages/a", "packages/a/nested", "tools/b"] {
+    std::fs::create_dir_all(root.join(member))?;
+    std::fs::write(root.join(member).join(CONFIG_FILE_NAME), "")?;
+    std::fs::write(root.join(member).join("template.t.md"), provider)?;
+  }
+
+  let project = scan_project(root)?;
+  assert_eq!(project.files.len(), 1);
+
+  let config = MdtConfig::load(root)?;
+  assert_eq!(
+    workspace_members(root, &config, false)?,
+    vec![PathBuf::from("packages/a")]
+  );
+  assert_eq!(
+    workspace_members(root, &config, true)?,
+    vec![
+      PathBuf::from("packages/a"),
+      PathBuf::from("packages/a/nested"),
+      PathBuf::from("tools/b"),
+    ]
+  );
+
+  Ok(())
+}
+
+#[test]
+fn workspace_providers_are_scoped() -> AnyEmptyResult {
+  let directory = tempfile::tempdir()?;
+  let root = directory.path();
+  let member = root.join("packages/cli");
+  std::fs::create_dir_all(&member)?;
+  std::fs::write(
+    root.join(CONFIG_FILE_NAME),
+    "[workspace]\nmembers = [\"packages/*\"]\n",
+  )?;
+  std::fs::write(
+    root.join("template.t.md"),
+    "<!-- {@install} -->\nnpm install\n<!-- {/install} -->\n<!-- {@license} -->\nMIT\n<!-- \
+     {/license} -->\n",
+  )?;
+  std::fs::write(
+    root.join("readme.md"),
+    "<!-- {=packages/cli/install} -->\n<!-- {/packages/cli/install} -->\n",
+  )?;
+  std::fs::write(member.join(CONFIG_FILE_NAME), "")?;
+  std::fs::write(
+    member.join("template.t.md"),
+    "<!-- {@install} -->\ncargo install\n<!-- {/install} -->\n",
+  )?;
+  std::fs::write(
+    member.join("local.t.md"),
+    "---\nscope: local\n---\n<!-- {@internal} -->\nsecret\n<!-- {/internal} -->\n",
+  )?;
+  std::fs::write(
+    member.join("readme.md"),
+    "<!-- {=install} -->\n<!-- {/install} -->\n<!-- {=license} -->\n<!-- {/license} -->\n<!-- \
+     {=internal} -->\n<!-- {/internal} -->\n",
+  )?;
+
+  let projects = scan_workspace(root, false, &PathFilter::default(), None)?;
+  let paths = projects
+    .iter()
+    .map(|(member, _)| member.clone())
+    .collect::<Vec<_>>();
+  assert_eq!(paths, vec![PathBuf::new(), PathBuf::from("packages/cli")]);
+
+  let (_, workspace) = projects.first().ok_or("missing the root")?;
+  let (_, cli) = projects.get(1).ok_or("missing the member")?;
+
+  // The member's own `install` takes precedence over the root's.
+  let install = cli.providers.get("install").ok_or("missing install")?;
+  assert_eq!(install.imported_from, None);
+  assert_eq!(
+    cli
+      .providers
+      .get("license")
+      .map(|provider| &provider.imported_from),
+    Some(&Some(PathBuf::new()))
+  );
+  assert!(workspace.providers.contains_key("packages/cli/install"));
+  assert!(!workspace.providers.contains_key("packages/cli/internal"));
+
+  let updates = compute_updates(workspace)?;
+  assert_eq!(
+    updates.updated_files.get(&root.join("readme.md")),
+    Some(
+      &"<!-- {=packages/cli/install} -->\ncargo install\n<!-- {/packages/cli/install} -->\n"
+        .to_string()
+    )
+  );
+  assert_eq!(compute_updates(cli)?.updated_count, 3);
+
+  // The root's `license` is consumed by the member, while its `install` is
+  // shadowed by the member's own.
+  let result = check_project(workspace, CheckOptions::default())?;
+  let messages = result
+    .diagnostics
+    .iter()
+    .map(|diagnostic| diagnostic.message.as_str())
+    .collect::<Vec<_>>();
+  assert_eq!(messages, vec!["provider `install` has no consumers"]);
+
+  Ok(())
+}
+
+#[test]
+fn include_patterns_restrict_scanning() -> AnyEmptyResult {
+  let directory = tempfile::tempdir()?;
+  let root = directory.path();
+  let consumer = "<!-- {=greeting} -->\n<!-- {/greeting} -->\n";
+  std::fs::create_dir_all(root.join("docs/guides"))?;
+  std::fs::create_dir_all(root.join("crates/core/src"))?;
+  std::fs::write(
+    root.join(CONFIG_FILE_NAME),
+    "[include]\npatterns = [\"docs/**\", \"crates/*/src/lib.rs\", \"*.t.md\"]\n",
+  )?;
+  std::fs::write(
+    root.join("template.t.md"),
+    "<!-- {@greeting} -->\nHello\n<!-- {/greeting} -->\n",
+  )?;
+  std::fs::write(root.join("readme.md"), consumer)?;
+  std::fs::write(root.join("docs/guides/intro.md"), consumer)?;
+  std::fs::write(root.join("crates/core/src/lib.rs"), consumer)?;
+  std::fs::write(root.join("crates/core/src/main.rs"), consumer)?;
+
+  let project = scan_project(root)?;
+  let files = project
+    .files
+    .keys()
+    .map(|file| file.strip_prefix(root).unwrap_or(file).to_path_buf())
+    .collect::<Vec<_>>();
+  assert_eq!(
+    files,
+    vec![
+      PathBuf::from("crates/core/src/lib.rs"),
+      PathBuf::from("docs/guides/intro.md"),
+      PathBuf::from("template.t.md"),
+    ]
+  );
+
+  std::fs::write(
+    root.join(CONFIG_FILE_NAME),
+    "[include]\npatterns = [\"[\"]\n",
+  )?;
+  assert!(matches!(
+    scan_project(root),
+    Err(MdtError::InvalidConfig(message)) if message.contains("include pattern `[`")
+  ));
+
+  Ok(())
+}
+
+#[test]
+fn scanned_extensions_can_be_configured() -> AnyEmptyResult {
+  let directory = tempfile::tempdir()?;
+  let root = directory.path();
+  let consumer = "<!-- {=greeting} -->\n<!-- {/greeting} -->\n";
+  std::fs::write(
+    root.join(CONFIG_FILE_NAME),
+    "[scan]\nextensions = [\"rs\"]\nextra_extensions = [\".PROTO\"]\n",
+  )?;
+  std::fs::write(
+    root.join("template.t.md"),
+    "<!-- {@greeting} -->\nHello\n<!-- {/greeting} -->\n",
+  )?;
+
+  for file in ["lib.rs", "api.proto", "config.yaml", "index.ts"] {
+    std::fs::write(root.join(file), consumer)?;
+  }
+
+  let project = scan_project(root)?;
+  let files = project
+    .files
+    .keys()
+    .filter_map(|file| file.file_name())
+    .map(|name| name.to_string_lossy().into_owned())
+    .collect::<Vec<_>>();
+  assert_eq!(files, vec!["api.proto", "lib.rs", "template.t.md"]);
+
+  Ok(())
+}
+
+#[test]
+fn ignore_files_skip_paths() -> AnyEmptyResult {
+  let directory = tempfile::tempdir()?;
+  let root = directory.path();
+  let consumer = "<!-- {=greeting} -->\n<!-- {/greeting} -->\n";
+  std::fs::create_dir_all(root.join("docs/api"))?;
+  std::fs::create_dir_all(root.join("fixtures"))?;
+  std::fs::write(
+    root.join(IGNORE_FILE_NAME),
+    "# Stale on purpose\nfixtures/\n",
+  )?;
+  std::fs::write(
+    root.join("docs").join(IGNORE_FILE_NAME),
+    "api/*.md\n!api/index.md\n",
+  )?;
+  std::fs::write(
+    root.join("template.t.md"),
+    "<!-- {@greeting} -->\nHello\n<!-- {/greeting} -->\n",
+  )?;
+  std::fs::write(root.join("readme.md"), consumer)?;
+  std::fs::write(root.join("fixtures/stale.md"), consumer)?;
+  std::fs::write(root.join("docs/api/generated.md"), consumer)?;
+  std::fs::write(root.join("docs/api/index.md"), consumer)?;
+
+  let overlays = BTreeMap::from([
+    (root.join("fixtures/open.md"), consumer.to_string()),
+    (root.join("docs/open.md"), consumer.to_string()),
+  ]);
+  let project = scan_project_with_overlays(root, &overlays)?;
+  let files = project
+    .files
+    .keys()
+    .map(|file| file.strip_prefix(root).unwrap_or(file).to_path_buf())
+    .collect::<Vec<_>>();
+  assert_eq!(
+    files,
+    vec![
+      PathBuf::from("docs/api/index.md"),
+      PathBuf::from("docs/open.md"),
+      PathBuf::from("readme.md"),
+      PathBuf::from("template.t.md"),
+    ]
+  );
+
+  Ok(())
+}
+
+#[test]
+fn user_configuration_is_merged_below_the_project() -> AnyEmptyResult {
+  let directory = tempfile::tempdir()?;
+  let root = directory.path().join("project");
+  let user = directory.path().join("config.toml");
+  std::fs::create_dir_all(&root)?;
+  std::fs::write(
+    &user,
+    "[check]\nundefined_variables = true\n\n[transformer_aliases]\nrustdoc = \"trim\"\nshort = \
+     \"firstLines:1\"\n",
+  )?;
+  std::fs::write(
+    root.join(CONFIG_FILE_NAME),
+    "[transformer_aliases]\nrustdoc = 'trim|indent:\"//! \"'\n",
+  )?;
+
+  let config = MdtConfig::load_with_user_config(&root, Some(&user), None)?;
+  assert!(config.check.undefined_variables);
+  assert_eq!(
+    config
+      .transformer_aliases
+      .get("rustdoc")
+      .map(String::as_str),
+    Some("trim|indent:\"//! \"")
+  );
+  assert_eq!(
+    config.transformer_aliases.get("short").map(String::as_str),
+    Some("firstLines:1")
+  );
+
+  let missing = directory.path().join("missing.toml");
+  let config = MdtConfig::load_with_user_config(&root, Some(&missing), None)?;
+  assert!(!config.check.undefined_variables);
+
+  Ok(())
+}
+
+#[test]
+fn user_settings_are_only_read_from_the_user_configuration() -> AnyEmptyResult {
+  let directory = tempfile::tempdir()?;
+  let root = directory.path().join("project");
+  let user = directory.path().join("config.toml");
+  std::fs::create_dir_all(&root)?;
+  std::fs::write(
+    &user,
+    "[check]\nundefined_variables = true\n\n[user]\ncolor = \"never\"\neditor = \"code \
+     --wait\"\nskip = [\"drafts/**\"]\ncache_dir = \"/tmp/mdt\"\n",
+  )?;
+
+  let settings = UserSettings::load(Some(&user))?;
+  assert_eq!(
+    settings,
+    UserSettings {
+      color: ColorChoice::Never,
+      editor: Some("code --wait".into()),
+      skip: vec!["drafts/**".into()],
+      cache_dir: Some(PathBuf::from("/tmp/mdt")),
+    }
+  );
+  assert_eq!(settings.cache_dir(), Some(PathBuf::from("/tmp/mdt")));
+  assert_eq!(UserSettings::load(None)?, UserSettings::default());
+
+  let config = MdtConfig::load_with_user_config(&root, Some(&user), None)?;
+  assert!(config.check.undefined_variables);
+
+  std::fs::write(&user, "[user]\ncolour = \"never\"\n")?;
+  assert!(matches!(
+    UserSettings::load(Some(&user)),
+    Err(MdtError::InvalidConfig(message)) if message.contains("colour")
+  ));
+
+  std::fs::write(root.join(CONFIG_FILE_NAME), "[user]\ncolor = \"never\"\n")?;
+  assert!(matches!(
+    MdtConfig::load_with_user_config(&root, None, None),
+    Err(MdtError::InvalidConfig(message)) if message.contains("only read from the user configuration")
+  ));
+
+  Ok(())
+}
+
+#[test]
+fn filters_can_be_defined_in_the_config() -> AnyEmptyResult {
+  let config = MdtConfig::from_toml(
+    "[filters.shield]\ntemplate = \"![{{ value }}](https://img.shields.io/badge/{{ value }}-{{ \
+     args[0] }}-blue)\"\n\n[filters.status]\nmap = { stable = \"stable ✅\" }\ndefault = \
+     \"unknown\"\n",
+  )?;
+  let project = Project::from_files_with_config(
+    [(
+      PathBuf::from("template.t.md"),
+      "<!-- {@badges} -->\n{{ \"build\" | shield(\"passing\") }}\n{{ \"stable\" | status }} {{ \
+       \"beta\" | status }}\n<!-- {/badges} -->\n"
+        .to_string(),
+    )],
+    config,
+  )?;
+  let provider = project.providers.get("badges").ok_or("missing")?;
+
+  assert_eq!(
+    project.render_provider(provider)?,
+    "\n![build](https://img.shields.io/badge/build-passing-blue)\nstable ✅ unknown\n"
+  );
+
+  for (content, message) in [
+    (
+      "[filters.empty]\n",
+      "must define either a `template` or a `map`",
+    ),
+    (
+      "[filters.broken]\ntemplate = \"{{ value\"\n",
+      "the filter `broken` is invalid",
+    ),
+  ] {
+    assert!(matches!(
+      MdtConfig::from_toml(content),
+      Err(MdtError::InvalidConfig(error)) if error.contains(message)
+    ));
+  }
+
+  Ok(())
+}
+
+#[test]
+fn block_updates_only_touch_the_block() -> AnyEmptyResult {
+  let project = Project::from_files([
+    (
+      PathBuf::from("template.t.md"),
+      "<!-- {@install} -->\nnpm i\n<!-- {/install} -->\n<!-- {@usage} -->\nrun\n<!-- {/usage} \
+       -->\n"
+        .to_string(),
+    ),
+    (
+      PathBuf::from("readme.md"),
+      "<!-- {=install} -->\n<!-- {/install} -->\n<!-- {=usage} -->\n<!-- {/usage} -->\n"
+        .to_string(),
+    ),
+    (
+      PathBuf::from("docs.md"),
+      "<!-- {=usage+install} -->\n<!-- {/usage+install} -->\n".to_string(),
+    ),
+  ])?;
+
+  let result = compute_block_updates(&project, "install", None)?;
+  assert_eq!(result.updated_count, 2);
+  assert_eq!(
+    result.updated_files.get(Path::new("readme.md")),
+    Some(
+      &"<!-- {=install} -->\nnpm i\n<!-- {/install} -->\n<!-- {=usage} -->\n<!-- {/usage} -->\n"
+        .to_string()
+    )
+  );
+
+  let result = compute_block_updates(&project, "install", Some(Path::new("docs.md")))?;
+  assert_eq!(
+    result.updated_files.keys().collect::<Vec<_>>(),
+    vec![Path::new("docs.md")]
+  );
+
+  assert!(matches!(
+    compute_block_updates(&project, "missing", None),
+    Err(MdtError::UnknownBlock(name)) if name == "missing"
+  ));
+
+  Ok(())
+}
+
+#[test]
+fn scans_can_be_filtered_by_path() -> AnyEmptyResult {
+  let directory = tempfile::tempdir()?;
+  let root = directory.path();
+  let consumer = "<!-- {=greeting} -->\n<!-- {/greeting} -->\n";
+  std::fs::create_dir_all(root.join("docs/drafts"))?;
+  std::fs::create_dir_all(root.join("templates"))?;
+  std::fs::write(
+    root.join("templates/greeting.t.md"),
+    "<!-- {@greeting} -->\nHello\n<!-- {/greeting} -->\n",
+  )?;
+  std::fs::write(root.join("readme.md"), consumer)?;
+  std::fs::write(root.join("docs/guide.md"), consumer)?;
+  std::fs::write(root.join("docs/drafts/next.md"), consumer)?;
+
+  let filter = PathFilter::new(
+    root,
+    &["docs/**".to_string()],
+    &["docs/drafts/**".to_string()],
+  )?;
+  let project = scan_project_filtered(root, &BTreeMap::new(), &filter, None)?;
+  let files = project
+    .files
+    .keys()
+    .map(|file| file.strip_prefix(root).unwrap_or(file).to_path_buf())
+    .collect::<Vec<_>>();
+  assert_eq!(
+    files,
+    vec![
+      PathBuf::from("docs/guide.md"),
+      PathBuf::from("templates/greeting.t.md"),
+    ]
+  );
+
+  assert!(matches!(
+    PathFilter::new(root, &["[".to_string()], &[]),
+    Err(MdtError::InvalidGlob { glob, .. }) if glob == "["
+  ));
+
+  Ok(())
+}
+
+#[test]
+fn providers_can_be_scaffolded() -> AnyEmptyResult {
+  let project = Project::from_files(vec![(
+    PathBuf::from(".templates/template.t.md"),
+    "<!-- {@install} -->\nnpm i\n<!-- {/install} -->".to_string(),
+  )])?;
+  let file = Path::new(".templates/template.t.md");
+  let content = project.files.get(file).cloned().unwrap_or_default();
+
+  assert_eq!(
+    scaffold_provider(&project, file, &content, "usage")?,
+    "<!-- {@install} -->\nnpm i\n<!-- {/install} -->\n\n<!-- {@usage} -->\n\n<!-- {/usage} -->\n"
+  );
+  assert!(matches!(
+    scaffold_provider(&project, file, &content, "install"),
+    Err(MdtError::BlockNameCollision { ref suggestion, .. }) if suggestion == "install2"
+  ));
+  assert!(matches!(
+    scaffold_provider(&project, file, &content, "my-usage"),
+    Err(MdtError::InvalidBlockName { suggestion: Some(ref suggestion), .. }) if suggestion == "myUsage"
+  ));
+  assert!(matches!(
+    scaffold_provider(&project, Path::new("readme.md"), "", "usage"),
+    Err(MdtError::NotATemplateFile(_))
+  ));
+
+  Ok(())
+}
+
+#[rstest]
+#[case::end(AttachAnchor::End, "# Title\ntext\n\n<!-- {=a} --><!-- {/a} -->\n")]
+#[case::heading(
+  AttachAnchor::Heading("Title".into()),
+  "# Title\n\n<!-- {=a} --><!-- {/a} -->\n\ntext\n"
+)]
+#[case::line(AttachAnchor::Line(2), "# Title\n<!-- {=a} --><!-- {/a} -->\ntext\n")]
+#[case::after_last_line(AttachAnchor::Line(3), "# Title\ntext\n<!-- {=a} --><!-- {/a} -->\n")]
+fn consumers_can_be_inserted(
+  #[case] anchor: AttachAnchor,
+  #[case] expected: &str,
+) -> MdtResult<()> {
+  let outcome = insert_consumer(
+    Path::new("readme.md"),
+    "# Title\ntext\n",
+    "a",
+    "",
+    &anchor,
+    &MdtConfig::default(),
+  )?;
+  assert_eq!(outcome, AttachOutcome::Attached(expected.to_string()));
+
+  Ok(())
+}
+
+#[test]
+fn inserted_consumers_match_the_file() -> MdtResult<()> {
+  let config = MdtConfig::default();
+  let (prefix, chain) = consumer_style(Path::new("src/lib.rs"));
+  assert_eq!(prefix, "/// ");
+  assert_eq!(consumer_style(Path::new("readme.md")), ("", ""));
+
+  assert_eq!(
+    insert_consumer(
+      Path::new("src/lib.rs"),
+      "fn main() {}\n",
+      "docs",
+      chain,
+      &AttachAnchor::Line(1),
+      &config
+    )?,
+    AttachOutcome::Attached(format!(
+      "/// <!-- {{=docs|{chain}}} --><!-- {{/docs}} -->\nfn main() {{}}\n"
+    ))
+  );
+  assert_eq!(
+    insert_consumer(
+      Path::new("readme.md"),
+      "text\n",
+      "docs",
+      "",
+      &AttachAnchor::Line(5),
+      &config
+    )?,
+    AttachOutcome::AnchorNotFound
+  );
+
+  Ok(())
+}
+
+#[test]
+fn providers_can_be_removed() -> MdtResult<()> {
+  let project = Project::from_files(vec![
+    (
+      PathBuf::from("template.t.md"),
+      "<!-- {@install} -->npm i<!-- {/install} -->\n".to_string(),
+    ),
+    (
+      PathBuf::from("readme.md"),
+      "Run <!-- {=install} -->npm i<!-- {/install} --> first.\n".to_string(),
+    ),
+  ])?;
+
+  let removal = compute_removal(&project, "install", ConsumerRemoval::Keep)?;
+  assert_eq!(removal.patches.len(), 1);
+  assert_eq!(
+    removal.orphans,
+    vec![(PathBuf::from("readme.md"), "install".to_string())]
+  );
+
+  let removal = compute_removal(&project, "install", ConsumerRemoval::Strip)?;
+  let result = apply_project_patches(&project, &removal.patches);
+  assert_eq!(
+    result.updated_files,
+    BTreeMap::from([
+      (PathBuf::from("readme.md"), "Run npm i first.\n".to_string()),
+      (PathBuf::from("template.t.md"), String::new()),
+    ])
+  );
+  assert!(matches!(
+    compute_removal(&project, "missing", ConsumerRemoval::Keep),
+    Err(MdtError::UnknownBlock(_))
+  ));
+
+  Ok(())
+}
+
+#[test]
+fn updates_write_many_files_and_only_splice_stale_blocks() -> AnyEmptyResult {
+  let directory = tempfile::tempdir()?;
+  let root = directory.path();
+  std::fs::write(
+    root.join("template.t.md"),
+    "<!-- {@install} -->npm i mdt<!-- {/install} -->\n<!-- {@usage} -->mdt update<!-- {/usage} \
+     -->\n",
+  )?;
+
+  // Irregular spacing, windows line endings and trailing whitespace around
+  // the blocks must survive the update byte for byte.
+  let original = |index: usize| {
+    format!(
+      "# File {index}  \r\n\r\n<!--   {{=install}}   -->stale {index}<!-- {{/install}} \
+       -->\t\r\nkeep this  \r\n<!-- {{=usage}} -->mdt update<!-- {{/usage}} -->"
+    )
+  };
+  let expected = |index: usize| {
+    format!(
+      "# File {index}  \r\n\r\n<!--   {{=install}}   -->npm i mdt<!-- {{/install}} -->\t\r\nkeep \
+       this  \r\n<!-- {{=usage}} -->mdt update<!-- {{/usage}} -->"
+    )
+  };
+
+  for index in 0..24 {
+    std::fs::write(root.join(format!("file{index}.md")), original(index))?;
+  }
+
+  let project = scan_project(root)?;
+  let result = compute_updates(&project)?;
+  assert_eq!(result.updated_files.len(), 24);
+  assert_eq!(result.updated_count, 24);
+  write_updates(&result)?;
+
+  for index in 0..24 {
+    assert_eq!(
+      std::fs::read_to_string(root.join(format!("file{index}.md")))?,
+      expected(index)
+    );
+  }
+
+  Ok(())
+}
+
+#[test]
+fn custom_transformers_stream_large_content() -> MdtResult<()> {
+  let config = MdtConfig::from_toml("[transformers.shout]\ncommand = \"tr a-z A-Z\"\n")?;
+  let line = "the pipe buffer is far smaller than this content\n";
+  let project = Project::from_files_with_config(
+    vec![
+      (
+        PathBuf::from("template.t.md"),
+        format!(
+          "<!-- {{@large}} -->{}<!-- {{/large}} -->\n",
+          line.repeat(20_000)
+        ),
+      ),
+      (
+        PathBuf::from("readme.md"),
+        "<!-- {=large|shout} --><!-- {/large} -->\n".to_string(),
+      ),
+    ],
+    config,
+  )?;
+  let consumer = project
+    .consumers
+    .first()
+    .ok_or(MdtError::InvalidTokenSequence(0))?;
+
+  assert_eq!(
+    render_consumer(&project, consumer)?,
+    Some(line.to_uppercase().repeat(20_000))
+  );
+
+  Ok(())
+}
+
+#[test]
+fn decryption_runs_once_per_provider() -> AnyEmptyResult {
+  let directory = tempfile::tempdir()?;
+  let root = directory.path();
+  let line = "a secret line which is repeated past the pipe buffer\n";
+  std::fs::write(
+    root.join("mdt.toml"),
+    "[encryption]\ncommand = \"echo run >> runs.log; sed '1d;$d'\"\n",
+  )?;
+  std::fs::write(
+    root.join("template.t.md"),
+    format!(
+      "<!-- {{@secret}} -->\n-----BEGIN AGE ENCRYPTED FILE-----\n{}-----END AGE ENCRYPTED \
+       FILE-----\n<!-- {{/secret}} -->\n",
+      line.repeat(5_000)
+    ),
+  )?;
+  std::fs::write(
+    root.join("readme.md"),
+    "<!-- {=secret} -->\n<!-- {/secret} -->\n<!-- {=secret|trim} -->\n<!-- {/secret} -->\n",
+  )?;
+
+  let project = scan_project(root)?;
+  let rendered = project
+    .consumers
+    .iter()
+    .map(|consumer| render_consumer(&project, consumer))
+    .collect::<MdtResult<Vec<_>>>()?;
+
+  assert_eq!(
+    rendered,
+    vec![
+      Some(format!("\n{}", line.repeat(5_000))),
+      Some(line.repeat(5_000).trim().to_string()),
+    ]
+  );
+  assert_eq!(std::fs::read_to_string(root.join("runs.log"))?, "run\n");
+
+  Ok(())
+}
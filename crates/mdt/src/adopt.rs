@@ -0,0 +1,154 @@
+use std::ops::Range;
+
+/// A difference between the rendered provider and the text in a document.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LineDifference {
+  /// A line of the rendered provider which the document doesn't have.
+  Missing(String),
+  /// A line of the document which the rendered provider doesn't have.
+  Extra(String),
+}
+
+/// How closely the text in a document matches a rendered provider, before
+/// the text is converted to a consumer.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AdoptionReport {
+  /// The lines of the document which best match the provider, numbered from
+  /// one with an exclusive end.
+  pub lines: Range<usize>,
+  /// The share of lines which match, from `0.0` to `1.0`.
+  pub similarity: f64,
+  pub differences: Vec<LineDifference>,
+}
+
+impl AdoptionReport {
+  /// Whether the text differs from the provider by no more than the
+  /// tolerance, e.g. `0.1` allows a tenth of the lines to differ.
+  pub fn is_within(&self, tolerance: f64) -> bool {
+    1.0 - self.similarity <= tolerance + f64::EPSILON
+  }
+}
+
+/// Find the passage of the document which best matches the rendered provider
+/// and report the differences. Lines are compared without their surrounding
+/// whitespace and blank lines are ignored. Returns `None` when no line
+/// matches.
+pub fn compare_for_adoption(content: &str, rendered: &str) -> Option<AdoptionReport> {
+  let expected = significant_lines(rendered)
+    .map(|(_, line)| line)
+    .collect::<Vec<_>>();
+  let actual = significant_lines(content).collect::<Vec<_>>();
+
+  if expected.is_empty() || actual.is_empty() {
+    return None;
+  }
+
+  let width = expected.len().min(actual.len());
+  let mut best: Option<(usize, usize)> = None;
+
+  for start in 0..=(actual.len() - width) {
+    let window = actual
+      .get(start..start + width)
+      .unwrap_or_default()
+      .iter()
+      .map(|(_, line)| *line)
+      .collect::<Vec<_>>();
+    let matched = common_lines(&expected, &window).len();
+
+    if best.map_or(true, |(_, most)| matched > most) {
+      best = Some((start, matched));
+    }
+  }
+
+  let (start, matched) = best.filter(|(_, matched)| *matched > 0)?;
+  let window = actual.get(start..start + width).unwrap_or_default();
+  let lines = window.iter().map(|(_, line)| *line).collect::<Vec<_>>();
+  let first = window.first().map_or(0, |(number, _)| *number);
+  let last = window.last().map_or(0, |(number, _)| *number);
+  let similarity = (2 * matched) as f64 / (expected.len() + lines.len()) as f64;
+
+  Some(AdoptionReport {
+    lines: first..last + 1,
+    similarity,
+    differences: differences(&expected, &lines),
+  })
+}
+
+/// The non-blank lines, trimmed and numbered from one.
+fn significant_lines(content: &str) -> impl Iterator<Item = (usize, &str)> {
+  content
+    .lines()
+    .enumerate()
+    .map(|(index, line)| (index + 1, line.trim()))
+    .filter(|(_, line)| !line.is_empty())
+}
+
+/// The longest common subsequence of the lines as index pairs.
+fn common_lines(left: &[&str], right: &[&str]) -> Vec<(usize, usize)> {
+  let mut lengths = vec![vec![0_usize; right.len() + 1]; left.len() + 1];
+
+  for (i, a) in left.iter().enumerate().rev() {
+    for (j, b) in right.iter().enumerate().rev() {
+      let length = if a == b {
+        cell(&lengths, i + 1, j + 1) + 1
+      } else {
+        cell(&lengths, i + 1, j).max(cell(&lengths, i, j + 1))
+      };
+
+      if let Some(row) = lengths.get_mut(i) {
+        if let Some(value) = row.get_mut(j) {
+          *value = length;
+        }
+      }
+    }
+  }
+
+  let mut pairs = vec![];
+  let (mut i, mut j) = (0, 0);
+
+  while i < left.len() && j < right.len() {
+    if left.get(i) == right.get(j) {
+      pairs.push((i, j));
+      i += 1;
+      j += 1;
+    } else if cell(&lengths, i + 1, j) >= cell(&lengths, i, j + 1) {
+      i += 1;
+    } else {
+      j += 1;
+    }
+  }
+
+  pairs
+}
+
+fn cell(lengths: &[Vec<usize>], i: usize, j: usize) -> usize {
+  lengths
+    .get(i)
+    .and_then(|row| row.get(j))
+    .copied()
+    .unwrap_or_default()
+}
+
+/// The lines which aren't shared, in the order they appear.
+fn differences(expected: &[&str], actual: &[&str]) -> Vec<LineDifference> {
+  let mut differences = vec![];
+  let (mut i, mut j) = (0, 0);
+  let pairs = common_lines(expected, actual)
+    .into_iter()
+    .chain([(expected.len(), actual.len())]);
+
+  for (next_i, next_j) in pairs {
+    for line in expected.get(i..next_i).unwrap_or_default() {
+      differences.push(LineDifference::Missing((*line).to_string()));
+    }
+
+    for line in actual.get(j..next_j).unwrap_or_default() {
+      differences.push(LineDifference::Extra((*line).to_string()));
+    }
+
+    i = next_i + 1;
+    j = next_j + 1;
+  }
+
+  differences
+}
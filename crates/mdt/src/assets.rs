@@ -0,0 +1,234 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+use std::path::PathBuf;
+
+use regex::Captures;
+use regex::Regex;
+
+use crate::normalize_path;
+use crate::MdtResult;
+use crate::Project;
+
+/// Markdown images, e.g. `![logo](assets/logo.svg "Logo")`.
+const MARKDOWN_IMAGE: &str = r"(!\[[^\]]*\]\(\s*<?)([^)\s>]+)";
+/// Html images, e.g. `<img src="assets/logo.svg">`.
+const HTML_IMAGE: &str = r#"(?i)(<img\b[^>]*?\bsrc\s*=\s*["']?)([^"'\s>]+)"#;
+
+/// An image which has to be copied next to a consumer so that the relative
+/// source from the provider still resolves.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AssetCopy {
+  pub from: PathBuf,
+  pub to: PathBuf,
+}
+
+/// The sources of the markdown and html images in the content.
+pub fn image_sources(content: &str) -> Vec<String> {
+  [MARKDOWN_IMAGE, HTML_IMAGE]
+    .iter()
+    .filter_map(|pattern| Regex::new(pattern).ok())
+    .flat_map(|pattern| {
+      pattern
+        .captures_iter(content)
+        .filter_map(|captures| captures.get(2).map(|source| source.as_str().to_string()))
+        .collect::<Vec<_>>()
+    })
+    .collect()
+}
+
+/// The path of a relative image source without its query or fragment.
+/// Absolute paths, urls and data uris return `None`.
+pub fn relative_image_path(source: &str) -> Option<&str> {
+  let is_relative = !source.is_empty()
+    && !source.starts_with(['/', '#', '?'])
+    && !source.contains("://")
+    && !source.starts_with("data:");
+
+  is_relative
+    .then(|| source.split(['#', '?']).next().unwrap_or_default())
+    .filter(|path| !path.is_empty())
+}
+
+/// Find the relative images in the content which don't exist relative to the
+/// file.
+pub fn find_missing_images(file: &Path, content: &str) -> Vec<String> {
+  let directory = file.parent().unwrap_or_else(|| Path::new(""));
+
+  image_sources(content)
+    .into_iter()
+    .filter(|source| {
+      relative_image_path(source).map_or(false, |path| !directory.join(path).exists())
+    })
+    .collect()
+}
+
+/// Rewrite the relative image sources to absolute urls under the base url.
+/// The `provider_file` is relative to the project root, so `assets/logo.svg`
+/// in `docs/template.t.md` becomes `{base_url}/docs/assets/logo.svg`.
+pub fn rewrite_image_urls(content: &str, provider_file: &Path, base_url: &str) -> String {
+  let base_url = base_url.trim_end_matches('/');
+
+  replace_image_sources(content, provider_file, |target, suffix| {
+    let target = target.to_string_lossy().replace('\\', "/");
+    Some(format!("{base_url}/{target}{suffix}"))
+  })
+}
+
+/// Replace the relative image sources with `data:` uris so the images are
+/// embedded in the content. Images are resolved from the provider file and
+/// images which are missing or larger than `max_bytes` are left as they are.
+pub fn inline_images(content: &str, provider_file: &Path, max_bytes: Option<usize>) -> String {
+  replace_image_sources(content, provider_file, |target, _| {
+    let bytes = fs::read(target).ok()?;
+
+    if max_bytes.map_or(false, |max_bytes| bytes.len() > max_bytes) {
+      return None;
+    }
+
+    Some(format!(
+      "data:{};base64,{}",
+      mime_type(target),
+      encode_base64(&bytes)
+    ))
+  })
+}
+
+/// Call `replace` with the resolved path and the query or fragment of every
+/// relative image source. Sources are left as they are when it returns
+/// `None`.
+fn replace_image_sources(
+  content: &str,
+  file: &Path,
+  mut replace: impl FnMut(&Path, &str) -> Option<String>,
+) -> String {
+  let directory = file.parent().unwrap_or_else(|| Path::new(""));
+  let mut content = content.to_string();
+
+  for pattern in [MARKDOWN_IMAGE, HTML_IMAGE] {
+    let Ok(pattern) = Regex::new(pattern) else {
+      continue;
+    };
+
+    content = pattern
+      .replace_all(&content, |captures: &Captures| {
+        let prefix = captures.get(1).map_or("", |value| value.as_str());
+        let source = captures.get(2).map_or("", |value| value.as_str());
+        let replacement = relative_image_path(source).and_then(|path| {
+          let suffix = source.get(path.len()..).unwrap_or_default();
+          replace(&normalize_path(&directory.join(path)), suffix)
+        });
+
+        format!("{prefix}{}", replacement.as_deref().unwrap_or(source))
+      })
+      .to_string();
+  }
+
+  content
+}
+
+fn mime_type(path: &Path) -> &'static str {
+  let extension = path
+    .extension()
+    .map(|extension| extension.to_string_lossy().to_ascii_lowercase())
+    .unwrap_or_default();
+
+  match extension.as_str() {
+    "svg" => "image/svg+xml",
+    "png" => "image/png",
+    "jpg" | "jpeg" => "image/jpeg",
+    "gif" => "image/gif",
+    "webp" => "image/webp",
+    "avif" => "image/avif",
+    "ico" => "image/x-icon",
+    _ => "application/octet-stream",
+  }
+}
+
+fn encode_base64(bytes: &[u8]) -> String {
+  const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+  let mut encoded = String::with_capacity((bytes.len() + 2) / 3 * 4);
+
+  for chunk in bytes.chunks(3) {
+    let mut buffer = [0u8; 3];
+    buffer
+      .iter_mut()
+      .zip(chunk)
+      .for_each(|(slot, byte)| *slot = *byte);
+    let [a, b, c] = buffer;
+    let value = (u32::from(a) << 16) | (u32::from(b) << 8) | u32::from(c);
+
+    for index in 0..4 {
+      if index <= chunk.len() {
+        let sextet = (value >> (18 - 6 * index)) & 0x3f;
+        encoded.push(char::from(
+          ALPHABET.get(sextet as usize).copied().unwrap_or(b'='),
+        ));
+      } else {
+        encoded.push('=');
+      }
+    }
+  }
+
+  encoded
+}
+
+/// The images referenced by providers which are missing next to their
+/// consumers. Each image is copied to the same relative location from the
+/// consumer as it has from the provider.
+pub fn compute_asset_copies(project: &Project) -> Vec<AssetCopy> {
+  let mut copies = BTreeMap::new();
+
+  let consumers = project
+    .consumers
+    .iter()
+    .filter(|consumer| !consumer.block.is_inline());
+
+  for (consumer, provider) in consumers.flat_map(|consumer| {
+    project
+      .providers_for(&consumer.block.name)
+      .unwrap_or_default()
+      .into_iter()
+      .map(move |provider| (consumer, provider))
+  }) {
+    let provider_directory = provider.file.parent().unwrap_or_else(|| Path::new(""));
+    let consumer_directory = consumer.file.parent().unwrap_or_else(|| Path::new(""));
+
+    for source in image_sources(&provider.content) {
+      let Some(path) = relative_image_path(&source) else {
+        continue;
+      };
+
+      let from = normalize_path(&provider_directory.join(path));
+      let to = normalize_path(&consumer_directory.join(path));
+
+      if from == to || !from.is_file() || is_same_file_content(&from, &to) {
+        continue;
+      }
+
+      copies.entry(to.clone()).or_insert(AssetCopy { from, to });
+    }
+  }
+
+  copies.into_values().collect()
+}
+
+/// Copy the assets, creating any missing directories.
+pub fn copy_assets(copies: &[AssetCopy]) -> MdtResult<()> {
+  for copy in copies {
+    if let Some(parent) = copy.to.parent() {
+      fs::create_dir_all(parent)?;
+    }
+
+    fs::copy(&copy.from, &copy.to)?;
+  }
+
+  Ok(())
+}
+
+fn is_same_file_content(a: &Path, b: &Path) -> bool {
+  match (fs::read(a), fs::read(b)) {
+    (Ok(a), Ok(b)) => a == b,
+    _ => false,
+  }
+}
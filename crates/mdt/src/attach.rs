@@ -0,0 +1,209 @@
+use std::path::Path;
+
+use crate::concatenated_names;
+use crate::parse_file;
+use crate::BlockType;
+use crate::MdtConfig;
+use crate::MdtResult;
+
+/// Where `mdt attach` and `mdt add` insert a consumer within a file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AttachAnchor {
+  /// At the end of the file.
+  End,
+  /// After the first line which starts with the text, e.g. `# ` for the first
+  /// heading or `<!-- badges -->` for a marker comment.
+  After(String),
+  /// After the first markdown heading with the text, e.g. `Installation`.
+  Heading(String),
+  /// Before the line, starting at 1. No blank lines are added around the
+  /// consumer.
+  Line(usize),
+}
+
+/// The result of attaching a consumer to a file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AttachOutcome {
+  /// The new content of the file.
+  Attached(String),
+  /// The file already has a consumer for the block.
+  AlreadyAttached,
+  /// The anchor doesn't exist within the file.
+  AnchorNotFound,
+}
+
+/// The prefix of the tag lines and the transformer chain of the consumers
+/// which `mdt add` inserts into source files, by extension.
+const SOURCE_CONSUMERS: &[(&str, &str, &str)] =
+  &[("rs", "/// ", r#"trim|replace:"\n":"\n/// "|wrap:"\n/// ""#)];
+
+/// The prefix of the tag lines and the default transformer chain of a
+/// consumer inserted into the file, e.g. rustdoc comments for `.rs` files.
+/// Both are empty for markdown files.
+pub fn consumer_style(file: &Path) -> (&'static str, &'static str) {
+  let extension = file
+    .extension()
+    .and_then(|extension| extension.to_str())
+    .unwrap_or_default();
+
+  SOURCE_CONSUMERS
+    .iter()
+    .find(|(candidate, ..)| candidate.eq_ignore_ascii_case(extension))
+    .map_or(("", ""), |(_, prefix, chain)| (prefix, chain))
+}
+
+/// Insert a consumer for the block, containing the rendered content, into the
+/// file at the anchor.
+pub fn attach_consumer(
+  file: &Path,
+  content: &str,
+  name: &str,
+  rendered: &str,
+  anchor: &AttachAnchor,
+  config: &MdtConfig,
+) -> MdtResult<AttachOutcome> {
+  if has_consumer(file, content, name, config)? {
+    return Ok(AttachOutcome::AlreadyAttached);
+  }
+
+  let style = config.comment_style(file);
+  let consumer = format!(
+    "{}{rendered}{}",
+    style.wrap(&format!("{{={name}}}")),
+    style.wrap(&format!("{{/{name}}}"))
+  );
+
+  Ok(splice(content, consumer, anchor))
+}
+
+/// Insert an empty consumer for the block with the transformer chain into the
+/// file at the anchor. The tags are prefixed for source files, see
+/// [`consumer_style`]. The consumer is filled in by updating the file.
+pub fn insert_consumer(
+  file: &Path,
+  content: &str,
+  name: &str,
+  chain: &str,
+  anchor: &AttachAnchor,
+  config: &MdtConfig,
+) -> MdtResult<AttachOutcome> {
+  if has_consumer(file, content, name, config)? {
+    return Ok(AttachOutcome::AlreadyAttached);
+  }
+
+  let style = config.comment_style(file);
+  let (prefix, _) = consumer_style(file);
+  let opening = if chain.is_empty() {
+    format!("{{={name}}}")
+  } else {
+    format!("{{={name}|{chain}}}")
+  };
+  let consumer = format!(
+    "{prefix}{}{}",
+    style.wrap(&opening),
+    style.wrap(&format!("{{/{name}}}"))
+  );
+
+  Ok(splice(content, consumer, anchor))
+}
+
+/// Insert the consumer at the anchor. A blank line separates it from the rest
+/// of the file unless it is inserted at a line.
+fn splice(content: &str, mut consumer: String, anchor: &AttachAnchor) -> AttachOutcome {
+  if !consumer.ends_with('\n') {
+    consumer.push('\n');
+  }
+
+  let offset = match anchor {
+    AttachAnchor::End => Some(content.len()),
+    AttachAnchor::After(prefix) => line_end_after(content, prefix),
+    AttachAnchor::Heading(heading) => heading_end(content, heading),
+    AttachAnchor::Line(line) => line_start(content, *line),
+  };
+  let Some(offset) = offset else {
+    return AttachOutcome::AnchorNotFound;
+  };
+
+  let padded = !matches!(anchor, AttachAnchor::Line(_));
+  let (before, after) = content.split_at(offset);
+  let mut updated = before.to_string();
+
+  if !updated.is_empty() && !updated.ends_with('\n') {
+    updated.push('\n');
+  }
+
+  if padded && !updated.is_empty() {
+    updated.push('\n');
+  }
+
+  updated.push_str(&consumer);
+
+  if padded && !after.is_empty() && !after.starts_with('\n') {
+    updated.push('\n');
+  }
+
+  updated.push_str(after);
+
+  AttachOutcome::Attached(updated)
+}
+
+/// Whether the file already has a consumer for the block.
+pub fn has_consumer(file: &Path, content: &str, name: &str, config: &MdtConfig) -> MdtResult<bool> {
+  let attached = parse_file(file, content, config)?.iter().any(|block| {
+    block.r#type == BlockType::Consumer
+      && concatenated_names(&block.name).any(|candidate| candidate == name)
+  });
+
+  Ok(attached)
+}
+
+/// The offset after the line break of the first line which starts with the
+/// prefix.
+fn line_end_after(content: &str, prefix: &str) -> Option<usize> {
+  let mut offset = 0;
+
+  for line in content.split_inclusive('\n') {
+    offset += line.len();
+
+    if line.starts_with(prefix) {
+      return Some(offset);
+    }
+  }
+
+  None
+}
+
+/// The offset after the line break of the first markdown heading with the
+/// text.
+fn heading_end(content: &str, heading: &str) -> Option<usize> {
+  let mut offset = 0;
+
+  for line in content.split_inclusive('\n') {
+    offset += line.len();
+    let text = line.trim();
+
+    if text.starts_with('#') && text.trim_start_matches('#').trim() == heading.trim() {
+      return Some(offset);
+    }
+  }
+
+  None
+}
+
+/// The offset of the start of the line, starting at 1. The line after the
+/// last one is the end of the content.
+fn line_start(content: &str, line: usize) -> Option<usize> {
+  let mut offset = 0;
+  let mut current = 1;
+
+  for text in content.split_inclusive('\n') {
+    if current == line {
+      return Some(offset);
+    }
+
+    offset += text.len();
+    current += 1;
+  }
+
+  (current == line).then_some(offset)
+}
@@ -0,0 +1,119 @@
+use std::path::Path;
+use std::process::Command;
+
+use serde_json::json;
+use serde_json::Map;
+use serde_json::Value;
+
+use crate::MdtError;
+use crate::MdtResult;
+
+/// Load the packages of a Cargo workspace with `cargo metadata`. The manifest
+/// is relative to the root. Dependencies aren't resolved, so their versions
+/// are the requirements from the manifests.
+///
+/// The value has the `members` of the workspace sorted by name, and the same
+/// `packages` keyed by name. Each has its `name`, `version`, `description`,
+/// `rust_version`, `directory`, `features` and `dependencies`.
+pub fn load_cargo_metadata(root: &Path, manifest: &Path) -> MdtResult<Value> {
+  let path = root.join(manifest);
+  let error = |message: String| {
+    MdtError::DataFile {
+      path: path.clone(),
+      message,
+    }
+  };
+  let output = Command::new("cargo")
+    .args([
+      "metadata",
+      "--format-version",
+      "1",
+      "--no-deps",
+      "--manifest-path",
+    ])
+    .arg(&path)
+    .current_dir(root)
+    .output()
+    .map_err(|cause| error(format!("`cargo metadata` couldn't be run: {cause}")))?;
+
+  if !output.status.success() {
+    return Err(error(
+      String::from_utf8_lossy(&output.stderr).trim().to_string(),
+    ));
+  }
+
+  let metadata: Value =
+    serde_json::from_slice(&output.stdout).map_err(|cause| error(cause.to_string()))?;
+  let workspace_root = metadata
+    .get("workspace_root")
+    .and_then(Value::as_str)
+    .map(Path::new)
+    .unwrap_or(root);
+  let mut members = metadata
+    .get("packages")
+    .and_then(Value::as_array)
+    .map(|packages| {
+      packages
+        .iter()
+        .map(|package| cargo_package(package, workspace_root))
+        .collect::<Vec<_>>()
+    })
+    .unwrap_or_default();
+  members.sort_by_key(|member| member.get("name").and_then(Value::as_str).map(String::from));
+
+  let packages = members
+    .iter()
+    .filter_map(|member| {
+      let name = member.get("name")?.as_str()?;
+      Some((name.to_string(), member.clone()))
+    })
+    .collect::<Map<_, _>>();
+
+  Ok(json!({ "members": members, "packages": packages }))
+}
+
+/// The details of a package from `cargo metadata`. The directory is relative
+/// to the workspace root.
+fn cargo_package(package: &Value, workspace_root: &Path) -> Value {
+  let field = |key: &str| package.get(key).cloned().unwrap_or(Value::Null);
+  let directory = package
+    .get("manifest_path")
+    .and_then(Value::as_str)
+    .and_then(|manifest| Path::new(manifest).parent())
+    .map(|directory| {
+      directory
+        .strip_prefix(workspace_root)
+        .unwrap_or(directory)
+        .components()
+        .map(|component| component.as_os_str().to_string_lossy())
+        .collect::<Vec<_>>()
+        .join("/")
+    });
+  let dependencies = package
+    .get("dependencies")
+    .and_then(Value::as_array)
+    .map(|dependencies| {
+      dependencies
+        .iter()
+        .map(|dependency| {
+          json!({
+            "name": dependency.get("name"),
+            "req": dependency.get("req"),
+            "kind": dependency.get("kind").and_then(Value::as_str).unwrap_or("normal"),
+            "optional": dependency.get("optional").and_then(Value::as_bool).unwrap_or(false),
+          })
+        })
+        .collect::<Vec<_>>()
+    })
+    .unwrap_or_default();
+
+  json!({
+    "name": field("name"),
+    "version": field("version"),
+    "description": field("description"),
+    "rust_version": field("rust_version"),
+    "directory": directory,
+    "features": package.get("features").cloned().unwrap_or_else(|| json!({})),
+    "dependencies": dependencies,
+  })
+}
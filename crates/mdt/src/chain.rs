@@ -0,0 +1,175 @@
+use crate::apply_transformer;
+use crate::Argument;
+use crate::Transformer;
+use crate::TransformerType;
+
+/// Advice about a transformer chain which probably doesn't do what was
+/// intended.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ChainAdvice {
+  /// The name of the rule which produced the advice.
+  pub rule: &'static str,
+  pub message: String,
+  /// A chain which avoids the problem, when one can be suggested.
+  pub suggestion: Option<Vec<Transformer>>,
+}
+
+impl ChainAdvice {
+  /// The suggested chain written the way it appears in a tag, e.g.
+  /// `|trim|indent:"  "`.
+  pub fn suggested_chain(&self) -> Option<String> {
+    self.suggestion.as_ref().map(|chain| format_chain(chain))
+  }
+}
+
+/// A rule which inspects the transformer at `index` of the chain together
+/// with the content it receives.
+#[derive(Debug, Clone, Copy)]
+pub struct ChainRule {
+  pub name: &'static str,
+  check: fn(&[Transformer], usize, &str) -> Option<ChainAdvice>,
+}
+
+/// The rules used to find suspicious transformer chains.
+pub const CHAIN_RULES: &[ChainRule] = &[
+  ChainRule {
+    name: "fenced-code-block",
+    check: fenced_code_block,
+  },
+  ChainRule {
+    name: "repeated-indent",
+    check: repeated_indent,
+  },
+  ChainRule {
+    name: "trim-after-indent",
+    check: trim_after_indent,
+  },
+  ChainRule {
+    name: "wrap-width-after-indent",
+    check: wrap_width_after_indent,
+  },
+];
+
+/// Check the transformer chain against every rule. The content is what the
+/// first transformer receives and is transformed as the chain is walked.
+pub fn lint_transformer_chain(content: &str, transformers: &[Transformer]) -> Vec<ChainAdvice> {
+  let mut advice = vec![];
+  let mut content = content.to_string();
+
+  for (index, transformer) in transformers.iter().enumerate() {
+    advice.extend(
+      CHAIN_RULES
+        .iter()
+        .filter_map(|rule| (rule.check)(transformers, index, &content)),
+    );
+    content = apply_transformer(&content, transformer);
+  }
+
+  advice
+}
+
+/// Format the chain the way it is written in a tag, e.g. `|trim|code`.
+pub fn format_chain(transformers: &[Transformer]) -> String {
+  transformers
+    .iter()
+    .map(|transformer| format!("|{transformer}"))
+    .collect()
+}
+
+fn fenced_code_block(chain: &[Transformer], index: usize, content: &str) -> Option<ChainAdvice> {
+  let transformer = chain.get(index)?;
+  let trimmed = content.trim_start();
+
+  if transformer.r#type != TransformerType::CodeBlock
+    || !(trimmed.starts_with("```") || trimmed.starts_with("~~~"))
+  {
+    return None;
+  }
+
+  Some(ChainAdvice {
+    rule: "fenced-code-block",
+    message: "`codeBlock` wraps content which is already a fenced code block".into(),
+    suggestion: Some(without(chain, index)),
+  })
+}
+
+fn repeated_indent(chain: &[Transformer], index: usize, _: &str) -> Option<ChainAdvice> {
+  let transformer = chain.get(index)?;
+  let previous = index.checked_sub(1).and_then(|index| chain.get(index))?;
+
+  if transformer.r#type != TransformerType::Indent || previous.r#type != TransformerType::Indent {
+    return None;
+  }
+
+  let outer = transformer.args.first().and_then(Argument::as_str)?;
+  let inner = previous.args.first().and_then(Argument::as_str)?;
+  let mut suggestion = without(chain, index);
+
+  if let Some(merged) = suggestion.get_mut(index - 1) {
+    merged.args = vec![Argument::String(format!("{outer}{inner}"))];
+  }
+
+  Some(ChainAdvice {
+    rule: "repeated-indent",
+    message: "`indent` is applied twice in a row".into(),
+    suggestion: Some(suggestion),
+  })
+}
+
+fn trim_after_indent(chain: &[Transformer], index: usize, _: &str) -> Option<ChainAdvice> {
+  let transformer = chain.get(index)?;
+  let is_trim = matches!(
+    transformer.r#type,
+    TransformerType::Trim | TransformerType::TrimStart
+  );
+  let indent = chain
+    .get(..index)?
+    .iter()
+    .rposition(|previous| previous.r#type == TransformerType::Indent)?;
+
+  if !is_trim {
+    return None;
+  }
+
+  Some(ChainAdvice {
+    rule: "trim-after-indent",
+    message: format!(
+      "`{}` after `indent` removes the indentation of the first line",
+      transformer.r#type
+    ),
+    suggestion: Some(moved_before(chain, index, indent)),
+  })
+}
+
+fn wrap_width_after_indent(chain: &[Transformer], index: usize, _: &str) -> Option<ChainAdvice> {
+  let transformer = chain.get(index)?;
+  let indent = chain
+    .get(..index)?
+    .iter()
+    .rposition(|previous| previous.r#type == TransformerType::Indent)?;
+
+  if transformer.r#type != TransformerType::WrapWidth {
+    return None;
+  }
+
+  Some(ChainAdvice {
+    rule: "wrap-width-after-indent",
+    message: "`wrapWidth` after `indent` treats the indentation as words".into(),
+    suggestion: Some(moved_before(chain, index, indent)),
+  })
+}
+
+/// The chain without the transformer at `index`.
+fn without(chain: &[Transformer], index: usize) -> Vec<Transformer> {
+  let mut chain = chain.to_vec();
+  chain.remove(index);
+  chain
+}
+
+/// The chain with the transformer at `index` moved to `target`.
+fn moved_before(chain: &[Transformer], index: usize, target: usize) -> Vec<Transformer> {
+  let mut chain = chain.to_vec();
+  let transformer = chain.remove(index);
+  chain.insert(target, transformer);
+  chain
+}
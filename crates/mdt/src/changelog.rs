@@ -0,0 +1,91 @@
+use serde::Serialize;
+
+/// A release in a changelog which follows the Keep a Changelog format.
+///
+/// ```md
+/// ## [1.0.0] - 2024-01-01
+///
+/// ### Added
+///
+/// - Everything.
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ChangelogEntry {
+  /// The version without brackets, e.g. `1.0.0` or `Unreleased`.
+  pub version: String,
+  pub date: Option<String>,
+  /// The content between this release heading and the next one.
+  pub content: String,
+}
+
+impl ChangelogEntry {
+  pub fn is_unreleased(&self) -> bool {
+    self.version.eq_ignore_ascii_case("unreleased")
+  }
+}
+
+/// Split the changelog into its releases. Each release starts with a level
+/// two heading, e.g. `## [1.0.0] - 2024-01-01` or `## 1.0.0`.
+pub fn parse_changelog(content: &str) -> Vec<ChangelogEntry> {
+  let mut entries: Vec<ChangelogEntry> = vec![];
+  let mut lines: Vec<&str> = vec![];
+  let mut in_fence = false;
+
+  for line in content.lines() {
+    let trimmed = line.trim_start();
+
+    if trimmed.starts_with("```") || trimmed.starts_with("~~~") {
+      in_fence = !in_fence;
+    }
+
+    let heading = (!in_fence).then(|| line.strip_prefix("## ")).flatten();
+
+    let Some(heading) = heading else {
+      lines.push(line);
+      continue;
+    };
+
+    finish_entry(&mut entries, &mut lines);
+
+    let (version, date) = match heading.split_once(" - ") {
+      Some((version, date)) => (version, Some(date.trim().to_string())),
+      None => (heading, None),
+    };
+    let version = version.trim().trim_start_matches('[');
+    let version = version.split(']').next().unwrap_or(version).trim();
+
+    entries.push(ChangelogEntry {
+      version: version.to_string(),
+      date,
+      content: String::new(),
+    });
+  }
+
+  finish_entry(&mut entries, &mut lines);
+  entries
+}
+
+fn finish_entry(entries: &mut [ChangelogEntry], lines: &mut Vec<&str>) {
+  if let Some(entry) = entries.last_mut() {
+    entry.content = lines.join("\n").trim_matches('\n').to_string();
+  }
+
+  lines.clear();
+}
+
+/// Find the entry for the version, ignoring a leading `v`. When no version is
+/// given the latest released entry is returned.
+pub fn find_changelog_entry<'a>(
+  entries: &'a [ChangelogEntry],
+  version: Option<&str>,
+) -> Option<&'a ChangelogEntry> {
+  let Some(version) = version else {
+    return entries.iter().find(|entry| !entry.is_unreleased());
+  };
+
+  let version = version.trim_start_matches('v');
+
+  entries
+    .iter()
+    .find(|entry| entry.version.trim_start_matches('v') == version)
+}
@@ -0,0 +1,623 @@
+use std::cmp::Reverse;
+use std::collections::BTreeSet;
+use std::collections::HashMap;
+use std::collections::HashSet;
+use std::fs;
+use std::path::Path;
+use std::path::PathBuf;
+use std::sync::OnceLock;
+use std::time::Duration;
+use std::time::Instant;
+
+use markdown::mdast::Node;
+use markdown::to_mdast;
+use markdown::ParseOptions;
+use regex::Regex;
+
+use crate::analyze_data_usage;
+use crate::concatenated_names;
+use crate::detect_vcs;
+use crate::find_missing_images;
+use crate::lint_transformer_chain;
+use crate::render_guarded_consumer;
+use crate::render_provider_index;
+use crate::CheckOptions;
+use crate::ConsumerEntry;
+use crate::GuardedContent;
+use crate::MdtResult;
+use crate::Project;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Severity {
+  Warning,
+  Error,
+}
+
+/// A problem found while checking the project.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CheckDiagnostic {
+  pub severity: Severity,
+  pub file: PathBuf,
+  pub message: String,
+}
+
+/// The outcome of checking a project.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CheckResult {
+  /// The consumers which are out of date as `(file, name)` pairs.
+  pub stale: Vec<(PathBuf, String)>,
+  /// The frozen consumers, which are never reported as stale.
+  pub held: Vec<(PathBuf, String)>,
+  pub diagnostics: Vec<CheckDiagnostic>,
+  /// The files which were checked and skipped when checking within a budget.
+  pub coverage: Option<Coverage>,
+  /// The seed and the consumers which were checked when sampling.
+  pub sample: Option<SampleReport>,
+}
+
+/// A deterministic sample of the consumers to check. The same seed selects
+/// the same consumers so a failure can be reproduced.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Sample {
+  /// The percentage of consumers to check, between 0 and 100.
+  pub percent: f64,
+  pub seed: u64,
+}
+
+/// The consumers which were checked by a sample.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SampleReport {
+  pub seed: u64,
+  /// The sampled consumers as `(file, name)` pairs.
+  pub sampled: Vec<(PathBuf, String)>,
+  /// The number of consumers in the project.
+  pub total: usize,
+}
+
+/// Limits on how much of the project is checked. The default checks
+/// everything.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct CheckScope {
+  /// Stop checking new files once the time is spent.
+  pub budget: Option<Duration>,
+  /// Only check a sample of the consumers.
+  pub sample: Option<Sample>,
+}
+
+/// How much of the project was checked within the budget.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Coverage {
+  /// The number of files whose consumers were all checked.
+  pub checked: usize,
+  /// The files with consumers which were skipped once the budget was spent,
+  /// including files which were only partly checked.
+  pub skipped: Vec<PathBuf>,
+}
+
+impl CheckResult {
+  /// Whether the check failed, because consumers are stale or an error was
+  /// reported.
+  pub fn is_failure(&self) -> bool {
+    !self.stale.is_empty()
+      || self
+        .diagnostics
+        .iter()
+        .any(|diagnostic| diagnostic.severity == Severity::Error)
+  }
+}
+
+/// Check that every consumer is up to date and report problems with the
+/// project. The options are resolved first so `strict` enables its checks.
+pub fn check_project(project: &Project, options: CheckOptions) -> MdtResult<CheckResult> {
+  check_project_scoped(project, options, CheckScope::default())
+}
+
+/// Check the project within a time budget. Files changed in version control
+/// are checked first, followed by the most recently modified files. Once the
+/// budget is spent the remaining consumers are skipped and their files are
+/// reported in the coverage, although the first consumer is always checked.
+pub fn check_project_within(
+  project: &Project,
+  options: CheckOptions,
+  budget: Duration,
+) -> MdtResult<CheckResult> {
+  let scope = CheckScope {
+    budget: Some(budget),
+    ..CheckScope::default()
+  };
+
+  check_project_scoped(project, options, scope)
+}
+
+/// Check the project within the scope, which can limit the check to a time
+/// budget and a sample of the consumers. The sample is taken first.
+pub fn check_project_scoped(
+  project: &Project,
+  options: CheckOptions,
+  scope: CheckScope,
+) -> MdtResult<CheckResult> {
+  let CheckScope { budget, sample } = scope;
+  let started = Instant::now();
+  let options = options.resolved();
+  let mut result = CheckResult::default();
+  let consumed = project
+    .consumers
+    .iter()
+    .filter(|consumer| !consumer.block.is_inline())
+    .flat_map(|consumer| concatenated_names(&consumer.block.name))
+    .collect::<HashSet<_>>();
+  let severity = |as_error: bool| {
+    if as_error {
+      Severity::Error
+    } else {
+      Severity::Warning
+    }
+  };
+
+  for deprecation in &project.deprecations {
+    result.diagnostics.push(CheckDiagnostic {
+      severity: severity(options.deny_deprecated),
+      file: project.root.join(&deprecation.file),
+      message: deprecation.to_string(),
+    });
+  }
+
+  for (file, diagnostic) in &project.parse_diagnostics {
+    result.diagnostics.push(CheckDiagnostic {
+      severity: Severity::Error,
+      file: file.clone(),
+      message: diagnostic.to_string(),
+    });
+  }
+
+  let mut consumers = match budget {
+    Some(_) => prioritized_consumers(project),
+    None => project.consumers.iter().collect(),
+  };
+
+  if let Some(sample) = sample {
+    let sampled = sample_consumers(project, sample);
+    let lookup = sampled
+      .iter()
+      .map(|(file, name)| (file.as_path(), name.as_str()))
+      .collect::<HashSet<_>>();
+    consumers
+      .retain(|consumer| lookup.contains(&(consumer.file.as_path(), consumer.block.name.as_str())));
+    result.sample = Some(SampleReport {
+      seed: sample.seed,
+      sampled,
+      total: project.consumers.len(),
+    });
+  }
+
+  let mut checked = BTreeSet::new();
+  let mut skipped = BTreeSet::new();
+  // Providers are rendered once for every consumer which lints its chain.
+  let mut rendered = HashMap::new();
+
+  for consumer in consumers {
+    let spent = budget.map_or(false, |budget| started.elapsed() >= budget);
+
+    if spent && !checked.is_empty() {
+      skipped.insert(consumer.file.clone());
+      continue;
+    }
+
+    checked.insert(consumer.file.clone());
+
+    if consumer.is_frozen() {
+      result
+        .held
+        .push((consumer.file.clone(), consumer.block.name.clone()));
+      continue;
+    }
+
+    if project.config.update.content_hash && consumer.is_edited() {
+      result.diagnostics.push(CheckDiagnostic {
+        severity: Severity::Error,
+        file: consumer.file.clone(),
+        message: format!(
+          "consumer `{}` was edited by hand since it was last updated, remove the hash from its \
+           closing tag to let `mdt update` overwrite it",
+          consumer.block.name
+        ),
+      });
+      continue;
+    }
+
+    let Some(GuardedContent { content, escaped }) = render_guarded_consumer(project, consumer)?
+    else {
+      result.diagnostics.push(CheckDiagnostic {
+        severity: severity(options.orphan_consumers),
+        file: consumer.file.clone(),
+        message: format!("consumer `{}` has no provider", consumer.block.name),
+      });
+      continue;
+    };
+
+    for tag in escaped {
+      result.diagnostics.push(CheckDiagnostic {
+        severity: Severity::Warning,
+        file: consumer.file.clone(),
+        message: format!(
+          "consumer `{}` renders the tag `{tag}` which is escaped so it can't change the blocks \
+           of the file, write it as `{{{tag}}}` in the provider if it's meant to be shown",
+          consumer.block.name
+        ),
+      });
+    }
+
+    let is_stale = if options.strict_whitespace {
+      content != consumer.content
+    } else {
+      normalize_whitespace(&content) != normalize_whitespace(&consumer.content)
+    };
+
+    if is_stale {
+      result
+        .stale
+        .push((consumer.file.clone(), consumer.block.name.clone()));
+    }
+
+    if let Some(providers) = project
+      .providers_for(&consumer.block.name)
+      .filter(|_| !consumer.block.is_inline())
+    {
+      let mut provider_content = String::new();
+
+      for provider in providers {
+        let name = provider.block.name.as_str();
+
+        if !rendered.contains_key(name) {
+          rendered.insert(name, project.render_provider(provider)?);
+        }
+
+        provider_content.push_str(rendered.get(name).map_or("", String::as_str));
+      }
+
+      for advice in lint_transformer_chain(&provider_content, &consumer.block.transformers) {
+        let suggestion = advice
+          .suggested_chain()
+          .map(|chain| format!(", try `{{={}{chain}}}`", consumer.block.name))
+          .unwrap_or_default();
+
+        result.diagnostics.push(CheckDiagnostic {
+          severity: Severity::Warning,
+          file: consumer.file.clone(),
+          message: format!(
+            "consumer `{}`: {}{suggestion}",
+            consumer.block.name, advice.message
+          ),
+        });
+      }
+    }
+
+    if options.check_links {
+      let broken = find_broken_links(&consumer.file, &content);
+
+      for link in &broken {
+        result.diagnostics.push(CheckDiagnostic {
+          severity: Severity::Error,
+          file: consumer.file.clone(),
+          message: format!(
+            "consumer `{}` links to `{link}` which doesn't exist",
+            consumer.block.name
+          ),
+        });
+      }
+
+      for source in find_missing_images(&consumer.file, &content) {
+        if broken.contains(&source) {
+          continue;
+        }
+
+        result.diagnostics.push(CheckDiagnostic {
+          severity: Severity::Error,
+          file: consumer.file.clone(),
+          message: format!(
+            "consumer `{}` references the image `{source}` which doesn't exist",
+            consumer.block.name
+          ),
+        });
+      }
+    }
+
+    if options.alt_text {
+      for source in find_missing_alt_text(&content) {
+        result.diagnostics.push(CheckDiagnostic {
+          severity: Severity::Error,
+          file: consumer.file.clone(),
+          message: format!(
+            "consumer `{}` has an image without alt text: `{source}`",
+            consumer.block.name
+          ),
+        });
+      }
+    }
+
+    if let Some(max_words) = options.max_sentence_length {
+      for sentence in find_long_sentences(&content, max_words) {
+        let preview = sentence.split_whitespace().take(6).collect::<Vec<_>>();
+        result.diagnostics.push(CheckDiagnostic {
+          severity: Severity::Warning,
+          file: consumer.file.clone(),
+          message: format!(
+            "consumer `{}` has a sentence longer than {max_words} words: `{} …`",
+            consumer.block.name,
+            preview.join(" ")
+          ),
+        });
+      }
+    }
+  }
+
+  if budget.is_some() {
+    result.coverage = Some(Coverage {
+      checked: checked.difference(&skipped).count(),
+      skipped: skipped.into_iter().collect(),
+    });
+  }
+
+  let mut providers = project.providers.values().collect::<Vec<_>>();
+  providers.sort_by(|a, b| a.block.name.cmp(&b.block.name));
+
+  for provider in providers {
+    let name = provider.block.name.as_str();
+
+    if provider.imported_from.is_none()
+      && !consumed.contains(name)
+      && !project.workspace_consumed.contains(name)
+    {
+      result.diagnostics.push(CheckDiagnostic {
+        severity: severity(options.unused_providers),
+        file: provider.file.clone(),
+        message: format!("provider `{}` has no consumers", provider.block.name),
+      });
+    }
+  }
+
+  if let Some(index) = project.config.docs.index.as_ref() {
+    let path = project.root.join(index);
+    let expected = render_provider_index(project, &path)?;
+
+    if fs::read_to_string(&path).ok().as_deref() != Some(expected.as_str()) {
+      result.diagnostics.push(CheckDiagnostic {
+        severity: Severity::Error,
+        file: path,
+        message: "the provider index is out of date, run `mdt docs sync`".into(),
+      });
+    }
+  }
+
+  if options.undefined_variables && !project.data.is_empty() {
+    for missing in analyze_data_usage(project).missing {
+      let Some(provider) = project.providers.get(&missing.provider) else {
+        continue;
+      };
+
+      result.diagnostics.push(CheckDiagnostic {
+        severity: Severity::Error,
+        file: provider.file.clone(),
+        message: format!(
+          "provider `{}` uses the undefined variable `{}`",
+          missing.provider, missing.variable
+        ),
+      });
+    }
+  }
+
+  Ok(result)
+}
+
+/// Select the consumers for the sample as `(file, name)` pairs. Each consumer
+/// is ranked by a hash of the seed, its file and its name, so the selection
+/// doesn't depend on the order the files were scanned in. At least one
+/// consumer is selected unless the percentage is zero.
+pub fn sample_consumers(project: &Project, sample: Sample) -> Vec<(PathBuf, String)> {
+  let total = project.consumers.len();
+  let count = ((total as f64) * sample.percent.clamp(0.0, 100.0) / 100.0).ceil() as usize;
+  let mut ranked = project
+    .consumers
+    .iter()
+    .map(|consumer| {
+      let key = format!("{}\0{}", consumer.file.display(), consumer.block.name);
+      (
+        seeded_hash(sample.seed, &key),
+        consumer.file.clone(),
+        consumer.block.name.clone(),
+      )
+    })
+    .collect::<Vec<_>>();
+  ranked.sort();
+  ranked.truncate(count.min(total));
+
+  let mut sampled = ranked
+    .into_iter()
+    .map(|(_, file, name)| (file, name))
+    .collect::<Vec<_>>();
+  sampled.sort();
+  sampled
+}
+
+/// A 64 bit FNV-1a hash of the text, seeded and then mixed with the
+/// `splitmix64` finalizer so nearby seeds give unrelated rankings.
+fn seeded_hash(seed: u64, text: &str) -> u64 {
+  let hash = seed
+    .to_le_bytes()
+    .into_iter()
+    .chain(text.bytes())
+    .fold(0xcbf2_9ce4_8422_2325_u64, |hash, byte| {
+      (hash ^ u64::from(byte)).wrapping_mul(0x0100_0000_01b3)
+    });
+  let hash = (hash ^ (hash >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+  let hash = (hash ^ (hash >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+
+  hash ^ (hash >> 31)
+}
+
+/// The consumers ordered so files changed in version control come first,
+/// followed by the most recently modified files. The consumers of a file stay
+/// together.
+fn prioritized_consumers(project: &Project) -> Vec<&ConsumerEntry> {
+  let changed = detect_vcs(&project.root)
+    .and_then(|vcs| vcs.changed_files("HEAD").ok())
+    .unwrap_or_default()
+    .into_iter()
+    .map(|file| project.root.join(file))
+    .collect::<HashSet<_>>();
+  let modified = |file: &Path| {
+    fs::metadata(file)
+      .and_then(|metadata| metadata.modified())
+      .ok()
+  };
+  let mut consumers = project.consumers.iter().collect::<Vec<_>>();
+  consumers.sort_by_cached_key(|consumer| {
+    (
+      Reverse(changed.contains(&consumer.file)),
+      Reverse(modified(&consumer.file)),
+      consumer.file.clone(),
+    )
+  });
+
+  consumers
+}
+
+/// Remove trailing whitespace from each line and blank lines from the start
+/// and end of the content.
+fn normalize_whitespace(content: &str) -> String {
+  content
+    .lines()
+    .map(str::trim_end)
+    .collect::<Vec<_>>()
+    .join("\n")
+    .trim_matches('\n')
+    .to_string()
+}
+
+/// Find the relative markdown links which point to files that don't exist
+/// relative to the file. Links with a scheme and anchors are skipped.
+pub fn find_broken_links(file: &Path, content: &str) -> Vec<String> {
+  let directory = file.parent().unwrap_or_else(|| Path::new(""));
+
+  link_pattern()
+    .captures_iter(content)
+    .filter_map(|captures| captures.get(1).map(|link| link.as_str().to_string()))
+    .filter(|link| {
+      let path = link.split(['#', '?']).next().unwrap_or_default();
+
+      !path.is_empty()
+        && !link.contains("://")
+        && !link.starts_with("mailto:")
+        && !directory.join(path).exists()
+    })
+    .collect()
+}
+
+/// The target of a markdown link, e.g. `guide.md` in `[guide](guide.md)`.
+fn link_pattern() -> &'static Regex {
+  static PATTERN: OnceLock<Regex> = OnceLock::new();
+
+  PATTERN.get_or_init(|| {
+    Regex::new(r"\]\(\s*<?([^)\s>]+)>?(?:\s+[^)]*)?\)").expect("the link pattern is valid")
+  })
+}
+
+/// The patterns for an html image tag, its `alt` attribute and the value of
+/// its `src` attribute.
+fn image_patterns() -> &'static (Regex, Regex, Regex) {
+  static PATTERNS: OnceLock<(Regex, Regex, Regex)> = OnceLock::new();
+
+  PATTERNS.get_or_init(|| {
+    (
+      Regex::new(r"(?i)<img\b[^>]*>").expect("the image pattern is valid"),
+      Regex::new(r"(?i)\balt\s*=").expect("the alt pattern is valid"),
+      Regex::new(r#"(?i)\bsrc\s*=\s*["']?([^"'\s>]+)"#).expect("the source pattern is valid"),
+    )
+  })
+}
+
+/// Find the images in the markdown content which have no alt text and return
+/// their sources. Html images with an empty `alt` attribute are treated as
+/// decorative.
+pub fn find_missing_alt_text(content: &str) -> Vec<String> {
+  let Ok(root) = to_mdast(content, &ParseOptions::gfm()) else {
+    return vec![];
+  };
+  let (image, alt, source) = image_patterns();
+  let mut sources = vec![];
+
+  visit_nodes(&root, &mut |node| {
+    match node {
+      Node::Image(node) if node.alt.trim().is_empty() => sources.push(node.url.clone()),
+      Node::ImageReference(node) if node.alt.trim().is_empty() => {
+        sources.push(node.identifier.clone());
+      }
+      Node::Html(node) => {
+        for tag in image.find_iter(&node.value) {
+          if !alt.is_match(tag.as_str()) {
+            let url = source
+              .captures(tag.as_str())
+              .and_then(|captures| captures.get(1))
+              .map_or(tag.as_str(), |url| url.as_str());
+            sources.push(url.to_string());
+          }
+        }
+      }
+      _ => {}
+    }
+  });
+
+  sources
+}
+
+/// Find the sentences in the paragraphs of the markdown content which have
+/// more than `max_words` words. Code blocks and html are ignored.
+pub fn find_long_sentences(content: &str, max_words: usize) -> Vec<String> {
+  let Ok(root) = to_mdast(content, &ParseOptions::gfm()) else {
+    return vec![];
+  };
+  let mut sentences = vec![];
+
+  visit_nodes(&root, &mut |node| {
+    if let Node::Paragraph(_) = node {
+      sentences.extend(
+        split_sentences(&node.to_string())
+          .into_iter()
+          .filter(|sentence| sentence.split_whitespace().count() > max_words),
+      );
+    }
+  });
+
+  sentences
+}
+
+/// Split text into sentences which end with `.`, `!` or `?` followed by
+/// whitespace.
+fn split_sentences(text: &str) -> Vec<String> {
+  let mut sentences = vec![];
+  let mut current = String::new();
+  let mut chars = text.chars().peekable();
+
+  while let Some(char) = chars.next() {
+    current.push(char);
+
+    if matches!(char, '.' | '!' | '?') && chars.peek().map_or(true, |next| next.is_whitespace()) {
+      sentences.push(current.trim().to_string());
+      current.clear();
+    }
+  }
+
+  if !current.trim().is_empty() {
+    sentences.push(current.trim().to_string());
+  }
+
+  sentences
+}
+
+fn visit_nodes(node: &Node, visit: &mut impl FnMut(&Node)) {
+  visit(node);
+
+  for child in node.children().map(Vec::as_slice).unwrap_or_default() {
+    visit_nodes(child, visit);
+  }
+}
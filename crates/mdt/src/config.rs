@@ -0,0 +1,1393 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+use std::path::PathBuf;
+
+use globset::GlobBuilder;
+use globset::GlobSet;
+use globset::GlobSetBuilder;
+use regex::Regex;
+use serde::Deserialize;
+use serde::Deserializer;
+use serde::Serialize;
+use serde::Serializer;
+
+use crate::parse_transformers;
+use crate::MdtError;
+use crate::MdtResult;
+use crate::TransformerType;
+use crate::SOURCE_EXTENSIONS;
+
+/// The name of the configuration file which is read from the project root.
+pub const CONFIG_FILE_NAME: &str = "mdt.toml";
+
+/// The environment variable which selects a profile, e.g.
+/// `MDT_PROFILE=release`.
+pub const PROFILE_ENV: &str = "MDT_PROFILE";
+
+/// The environment variable with the path of the user configuration file. An
+/// empty value disables the user configuration.
+pub const USER_CONFIG_ENV: &str = "MDT_USER_CONFIG";
+
+/// The table of the user configuration file which holds the personal
+/// settings.
+const USER_TABLE: &str = "user";
+
+/// The configuration loaded from `mdt.toml`.
+///
+/// ```toml
+/// extends = "../mdt.base.toml"
+///
+/// [naming]
+/// pattern = "^[a-z][A-Za-z0-9]*$"
+/// reserved_prefixes = ["internal"]
+///
+/// [data]
+/// pkg = "package.json"
+///
+/// [check]
+/// strict = true
+///
+/// [transformers.shout]
+/// command = "tr a-z A-Z"
+///
+/// [transformer_aliases]
+/// rustdoc = 'trim|indent:"//! "'
+/// ```
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct MdtConfig {
+  /// A configuration file, relative to this one, whose settings apply unless
+  /// they are overridden. Tables are merged key by key and data namespaces
+  /// replace the inherited ones. Paths within it are relative to the root.
+  pub extends: Option<PathBuf>,
+  /// Rules which every block name must follow.
+  pub naming: NamingConfig,
+  /// Restrict scanning to the files which match.
+  pub include: IncludeConfig,
+  /// The extensions of the source files which are scanned.
+  pub scan: ScanConfig,
+  /// Data files, relative to the root, which are available to provider
+  /// templates under the given namespace, e.g. `{{ pkg.version }}`.
+  pub data: BTreeMap<String, DataSource>,
+  /// The options used by `mdt check`.
+  pub check: CheckOptions,
+  /// Expose an entry of a Keep a Changelog file to templates as `changelog`.
+  pub changelog: Option<ChangelogConfig>,
+  /// Expose details of the git repository to templates as `git`.
+  pub git: Option<GitDataConfig>,
+  /// The options used by `mdt release-notes`.
+  pub release: ReleaseConfig,
+  /// Transformers defined by the project which can be used in tags like the
+  /// builtin ones, e.g. `{=install|shout}`, and which transformers are
+  /// allowed.
+  pub transformers: TransformersConfig,
+  /// Filters for provider templates, by name, e.g. `{{ "build" | shield }}`.
+  pub filters: BTreeMap<String, TemplateFilter>,
+  /// Named transformer chains which consumers can reference with `@`, e.g.
+  /// `{=docs|@rustdoc}`. The table can also be written as `[chains]`.
+  #[serde(alias = "chains")]
+  pub transformer_aliases: BTreeMap<String, String>,
+  /// How images referenced by providers are handled in consumers.
+  pub assets: AssetsConfig,
+  /// Defaults which apply when a block doesn't configure its own.
+  pub defaults: DefaultsConfig,
+  /// The options used by `mdt update`.
+  pub update: UpdateConfig,
+  /// The comment delimiters which contain tags, by file extension, for files
+  /// which can't contain html comments.
+  pub comments: BTreeMap<String, CommentStyle>,
+  /// How encrypted provider content is decrypted at render time.
+  pub encryption: EncryptionConfig,
+  /// The index of the providers for repositories which are template packs.
+  pub docs: DocsConfig,
+  /// Overrides which apply when the profile is selected, by name.
+  pub profile: BTreeMap<String, ProfileConfig>,
+  /// The sub-projects which `mdt check` checks along with this one.
+  pub workspace: WorkspaceConfig,
+}
+
+impl MdtConfig {
+  /// Load the configuration file from the root directory, merged over the
+  /// user configuration from [`user_config_path`]. The default configuration
+  /// is returned when neither file exists. The profile named by the
+  /// `MDT_PROFILE` environment variable is applied when it is set.
+  pub fn load(root: impl AsRef<Path>) -> MdtResult<Self> {
+    Self::load_with_profile(root, env_profile().as_deref())
+  }
+
+  /// Load the configuration like [`MdtConfig::load`] with the given profile
+  /// applied instead of the one from the environment.
+  pub fn load_with_profile(root: impl AsRef<Path>, profile: Option<&str>) -> MdtResult<Self> {
+    Self::load_with_user_config(root, user_config_path().as_deref(), profile)
+  }
+
+  /// Load the configuration file from the root directory, merged over the
+  /// user configuration file when there is one, and apply the profile.
+  pub fn load_with_user_config(
+    root: impl AsRef<Path>,
+    user: Option<&Path>,
+    profile: Option<&str>,
+  ) -> MdtResult<Self> {
+    let config = Self::from_layers(config_layers(root.as_ref(), user)?)?;
+
+    match profile {
+      Some(profile) => config.with_profile(profile),
+      None => Ok(config),
+    }
+  }
+
+  /// Apply the overrides of the profile.
+  pub fn with_profile(mut self, name: &str) -> MdtResult<Self> {
+    let Some(profile) = self.profile.get(name).cloned() else {
+      return Err(MdtError::InvalidConfig(format!(
+        "the profile `{name}` doesn't exist"
+      )));
+    };
+
+    self.data.extend(profile.data);
+
+    Ok(self)
+  }
+
+  /// The comment style which contains tags in the file. Files without a
+  /// configured style use html comments.
+  pub fn comment_style(&self, file: &Path) -> CommentStyle {
+    file
+      .extension()
+      .and_then(|extension| extension.to_str())
+      .and_then(|extension| {
+        self
+          .comments
+          .iter()
+          .find(|(candidate, _)| candidate.eq_ignore_ascii_case(extension))
+      })
+      .map_or_else(CommentStyle::html, |(_, style)| style.clone())
+  }
+
+  /// Parse the configuration from the content of an `mdt.toml` file. The
+  /// `extends` setting is only followed by [`MdtConfig::load`].
+  pub fn from_toml(content: &str) -> MdtResult<Self> {
+    let config: Self =
+      toml::from_str(content).map_err(|error| MdtError::InvalidConfig(error.to_string()))?;
+
+    config.validate()
+  }
+
+  /// Merge the configuration files, each overriding the ones before it.
+  fn from_layers(layers: Vec<(PathBuf, toml::Value)>) -> MdtResult<Self> {
+    let mut merged = toml::Value::Table(toml::Table::new());
+
+    for (_, layer) in layers {
+      merge_toml(&mut merged, layer, "");
+    }
+
+    let config: Self = merged
+      .try_into()
+      .map_err(|error| MdtError::InvalidConfig(error.to_string()))?;
+
+    config.validate()
+  }
+
+  fn validate(self) -> MdtResult<Self> {
+    let config = self;
+
+    for (name, transformer) in &config.transformers.custom {
+      if name.parse::<TransformerType>().is_ok() {
+        return Err(MdtError::InvalidConfig(format!(
+          "the transformer `{name}` is builtin and can't be redefined"
+        )));
+      }
+
+      if transformer.command.is_some() == transformer.template.is_some() {
+        return Err(MdtError::InvalidConfig(format!(
+          "the transformer `{name}` must define either a `command` or a `template`"
+        )));
+      }
+    }
+
+    let listed = config
+      .transformers
+      .deny
+      .iter()
+      .chain(config.transformers.allow.iter().flatten());
+
+    for name in listed {
+      if name.parse::<TransformerType>().is_err() && !config.transformers.custom.contains_key(name)
+      {
+        return Err(MdtError::InvalidConfig(format!(
+          "the transformer `{name}` is allowed or denied but doesn't exist"
+        )));
+      }
+    }
+
+    for (extension, style) in &config.comments {
+      if style.open.trim().is_empty()
+        || style
+          .close
+          .as_ref()
+          .map_or(false, |close| close.trim().is_empty())
+      {
+        return Err(MdtError::InvalidConfig(format!(
+          "the comment delimiters for `{extension}` files can't be empty"
+        )));
+      }
+    }
+
+    config.include.matcher()?;
+
+    for (name, filter) in &config.filters {
+      filter.validate(name)?;
+    }
+
+    // Aliases are parsed without the other aliases so they can't nest.
+    let without_aliases = Self {
+      transformers: config.transformers.clone(),
+      ..Self::default()
+    };
+
+    for (name, chain) in &config.transformer_aliases {
+      parse_transformers(chain, &without_aliases).map_err(|error| {
+        MdtError::InvalidConfig(format!(
+          "the transformer alias `{name}` is invalid: {error}"
+        ))
+      })?;
+    }
+
+    for (extension, chain) in &config.defaults.transformers {
+      parse_transformers(chain, &config).map_err(|error| {
+        MdtError::InvalidConfig(format!(
+          "the default transformers for `{extension}` files are invalid: {error}"
+        ))
+      })?;
+    }
+
+    Ok(config)
+  }
+
+  /// Every effective configuration value of the project with the profile
+  /// applied, along with where it comes from, ordered by key.
+  pub fn resolve(root: impl AsRef<Path>, profile: Option<&str>) -> MdtResult<Vec<ResolvedValue>> {
+    let layers = config_layers(root.as_ref(), user_config_path().as_deref())?;
+    let files = layers
+      .iter()
+      .map(|(path, layer)| (path.clone(), flatten_toml(layer)))
+      .collect::<Vec<_>>();
+
+    let mut config = Self::from_layers(layers)?;
+
+    if let Some(profile) = profile {
+      config = config.with_profile(profile)?;
+    }
+
+    config.check = config.check.resolved();
+
+    let defaults = flatten_toml(&to_toml(&Self::default())?);
+    let values = flatten_toml(&to_toml(&config)?)
+      .into_iter()
+      .map(|(key, value)| {
+        // A value overridden by the profile comes from the file which defines
+        // the override.
+        let overridden = profile.map(|profile| format!("profile.{profile}.{key}"));
+        let file = files
+          .iter()
+          .rev()
+          .find(|(_, values)| {
+            overridden
+              .as_ref()
+              .map_or(false, |key| values.contains_key(key))
+          })
+          .or_else(|| {
+            files
+              .iter()
+              .rev()
+              .find(|(_, values)| values.contains_key(&key))
+          });
+        let origin = if let Some((path, _)) = file {
+          ConfigOrigin::File(path.clone())
+        } else if defaults.get(&key) == Some(&value) {
+          ConfigOrigin::Default
+        } else {
+          ConfigOrigin::Derived
+        };
+
+        ResolvedValue {
+          key,
+          value: value.to_string(),
+          origin,
+        }
+      })
+      .collect();
+
+    Ok(values)
+  }
+}
+
+/// A configuration value after every source has been applied.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ResolvedValue {
+  /// The dotted key, e.g. `check.strict`.
+  pub key: String,
+  /// The value formatted as toml.
+  pub value: String,
+  pub origin: ConfigOrigin,
+}
+
+/// Where a resolved configuration value comes from.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ConfigOrigin {
+  /// The builtin default.
+  Default,
+  /// A configuration file, relative to the root.
+  File(PathBuf),
+  /// Set by another value, e.g. `check.strict` enables `check.check_links`.
+  Derived,
+}
+
+impl std::fmt::Display for ConfigOrigin {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    match self {
+      ConfigOrigin::Default => write!(f, "default"),
+      ConfigOrigin::File(path) => write!(f, "{}", path.display()),
+      ConfigOrigin::Derived => write!(f, "derived"),
+    }
+  }
+}
+
+/// The profile named by the `MDT_PROFILE` environment variable, unless it is
+/// empty.
+pub fn env_profile() -> Option<String> {
+  std::env::var(PROFILE_ENV)
+    .ok()
+    .filter(|profile| !profile.is_empty())
+}
+
+/// The user configuration file, which applies to every project and is
+/// overridden by the project configuration. It is the `MDT_USER_CONFIG`
+/// environment variable when it is set, or else `mdt/config.toml` within
+/// `$XDG_CONFIG_HOME` or `~/.config`.
+pub fn user_config_path() -> Option<PathBuf> {
+  if let Some(path) = std::env::var_os(USER_CONFIG_ENV) {
+    return (!path.is_empty()).then(|| PathBuf::from(path));
+  }
+
+  let directory = std::env::var_os("XDG_CONFIG_HOME")
+    .filter(|directory| !directory.is_empty())
+    .map(PathBuf::from)
+    .or_else(|| {
+      std::env::var_os("HOME")
+        .filter(|home| !home.is_empty())
+        .map(|home| PathBuf::from(home).join(".config"))
+    })?;
+
+  Some(directory.join("mdt").join("config.toml"))
+}
+
+/// The user configuration and the configuration file of the root along with
+/// the files they extend, ordered from the furthest base to the file itself.
+/// The paths of the project files are relative to the root and the user
+/// files are absolute. The `[chains]` table is renamed to
+/// `[transformer_aliases]` so the files merge consistently and the `[user]`
+/// table is removed from the user files, since it isn't project
+/// configuration.
+fn config_layers(root: &Path, user: Option<&Path>) -> MdtResult<Vec<(PathBuf, toml::Value)>> {
+  let mut layers = user_layers(user)?;
+
+  for (_, layer) in &mut layers {
+    if let Some(table) = layer.as_table_mut() {
+      table.remove(USER_TABLE);
+    }
+  }
+
+  if root.join(CONFIG_FILE_NAME).is_file() {
+    let start = layers.len();
+    collect_layers(
+      root,
+      PathBuf::from(CONFIG_FILE_NAME),
+      &mut vec![],
+      &mut layers,
+    )?;
+
+    if let Some((path, _)) = layers
+      .iter()
+      .skip(start)
+      .find(|(_, layer)| layer.get(USER_TABLE).is_some())
+    {
+      return Err(MdtError::InvalidConfig(format!(
+        "`[{USER_TABLE}]` in `{}` is only read from the user configuration",
+        path.display()
+      )));
+    }
+  }
+
+  Ok(layers)
+}
+
+/// The user configuration file along with the files it extends, ordered from
+/// the furthest base to the file itself.
+fn user_layers(user: Option<&Path>) -> MdtResult<Vec<(PathBuf, toml::Value)>> {
+  let mut layers = vec![];
+
+  if let Some(user) = user.filter(|user| user.is_file()) {
+    let user = user.canonicalize()?;
+    collect_layers(Path::new(""), user, &mut vec![], &mut layers)?;
+  }
+
+  Ok(layers)
+}
+
+fn collect_layers(
+  root: &Path,
+  relative: PathBuf,
+  visited: &mut Vec<PathBuf>,
+  layers: &mut Vec<(PathBuf, toml::Value)>,
+) -> MdtResult<()> {
+  let path = root.join(&relative);
+  let Ok(canonical) = path.canonicalize() else {
+    return Err(MdtError::InvalidConfig(format!(
+      "the extended configuration `{}` doesn't exist",
+      relative.display()
+    )));
+  };
+
+  if visited.contains(&canonical) {
+    return Err(MdtError::InvalidConfig(format!(
+      "the configuration `{}` extends itself",
+      relative.display()
+    )));
+  }
+
+  visited.push(canonical);
+
+  let mut layer = toml::from_str::<toml::Value>(&fs::read_to_string(&path)?)
+    .map_err(|error| MdtError::InvalidConfig(format!("{}: {error}", relative.display())))?;
+
+  if let Some(table) = layer.as_table_mut() {
+    if let Some(chains) = table.remove("chains") {
+      table.insert("transformer_aliases".into(), chains);
+    }
+  }
+
+  if let Some(extends) = layer.get("extends") {
+    let Some(extends) = extends.as_str() else {
+      return Err(MdtError::InvalidConfig(format!(
+        "`extends` in `{}` must be a path",
+        relative.display()
+      )));
+    };
+
+    let base = relative.parent().unwrap_or(Path::new("")).join(extends);
+    collect_layers(root, base, visited, layers)?;
+  }
+
+  layers.push((relative, layer));
+
+  Ok(())
+}
+
+/// Merge the overlay into the base. Tables are merged key by key, except for
+/// data namespaces which are replaced whole, and other values are replaced.
+fn merge_toml(base: &mut toml::Value, overlay: toml::Value, path: &str) {
+  match (base, overlay) {
+    (toml::Value::Table(base), toml::Value::Table(overlay)) if !path.starts_with("data.") => {
+      for (key, value) in overlay {
+        let path = if path.is_empty() {
+          key.clone()
+        } else {
+          format!("{path}.{key}")
+        };
+
+        match base.get_mut(&key) {
+          Some(existing) => merge_toml(existing, value, &path),
+          None => {
+            base.insert(key, value);
+          }
+        }
+      }
+    }
+    (base, overlay) => *base = overlay,
+  }
+}
+
+fn to_toml(config: &MdtConfig) -> MdtResult<toml::Value> {
+  toml::Value::try_from(config).map_err(|error| MdtError::InvalidConfig(error.to_string()))
+}
+
+/// Flatten the nested tables into dotted keys. Empty tables are dropped.
+fn flatten_toml(value: &toml::Value) -> BTreeMap<String, toml::Value> {
+  fn flatten(prefix: &str, value: &toml::Value, values: &mut BTreeMap<String, toml::Value>) {
+    let toml::Value::Table(table) = value else {
+      values.insert(prefix.to_string(), value.clone());
+      return;
+    };
+
+    for (key, value) in table {
+      let key = if prefix.is_empty() {
+        key.clone()
+      } else {
+        format!("{prefix}.{key}")
+      };
+
+      flatten(&key, value, values);
+    }
+  }
+
+  let mut values = BTreeMap::new();
+  flatten("", value, &mut values);
+  values
+}
+
+/// The delimiters of the comments which contain tags.
+///
+/// ```toml
+/// [comments.jinja]
+/// open = "{#"
+/// close = "#}"
+///
+/// [comments.tex]
+/// open = "%"
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct CommentStyle {
+  pub open: String,
+  /// The closing delimiter. Comments without one end at the end of the line.
+  pub close: Option<String>,
+}
+
+impl CommentStyle {
+  /// Html comments, `<!-- {=block} -->`.
+  pub fn html() -> Self {
+    Self {
+      open: "<!--".into(),
+      close: Some("-->".into()),
+    }
+  }
+
+  /// Wrap the tag in a comment, e.g. `<!-- {=block} -->`. Line comments end
+  /// with a line break.
+  pub fn wrap(&self, tag: &str) -> String {
+    match self.close.as_deref() {
+      Some(close) => format!("{} {tag} {close}", self.open),
+      None => format!("{} {tag}\n", self.open),
+    }
+  }
+
+  /// The closing delimiter, which is a line break for line comments.
+  pub fn close_delimiter(&self) -> &str {
+    self.close.as_deref().unwrap_or("\n")
+  }
+}
+
+/// Defaults for blocks which don't declare their own settings.
+///
+/// ```toml
+/// [defaults.transformers]
+/// rs = 'trim|indent:"//! "'
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct DefaultsConfig {
+  /// The transformer chain for consumers without transformers, by the
+  /// extension of the file which contains them.
+  pub transformers: BTreeMap<String, String>,
+}
+
+impl DefaultsConfig {
+  /// The default transformer chain for consumers in the file.
+  pub fn transformers_for(&self, file: &Path) -> Option<&str> {
+    let extension = file.extension()?.to_str()?;
+
+    self
+      .transformers
+      .iter()
+      .find(|(candidate, _)| candidate.eq_ignore_ascii_case(extension))
+      .map(|(_, chain)| chain.as_str())
+  }
+}
+
+/// Personal settings from the `[user]` table of the user configuration file.
+/// Projects can't set them.
+///
+/// ```toml
+/// [user]
+/// color = "never"
+/// editor = "code --wait"
+/// skip = ["drafts/**"]
+/// cache_dir = "~/.cache/mdt"
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct UserSettings {
+  /// Whether the cli colors its output. The `--color` flag takes precedence.
+  pub color: ColorChoice,
+  /// The command which opens a file for interactive flows, e.g.
+  /// `code --wait`.
+  pub editor: Option<String>,
+  /// Globs of the files which `check` and `update` skip, along with the ones
+  /// passed to `--skip`.
+  pub skip: Vec<String>,
+  /// The directory where mdt keeps cached data. A leading `~` is the home
+  /// directory.
+  pub cache_dir: Option<PathBuf>,
+}
+
+impl UserSettings {
+  /// Load the settings from the user configuration file and the files it
+  /// extends. The defaults are returned when there is no file.
+  pub fn load(user: Option<&Path>) -> MdtResult<Self> {
+    let mut merged = toml::Value::Table(toml::Table::new());
+
+    for (_, mut layer) in user_layers(user)? {
+      if let Some(settings) = layer
+        .as_table_mut()
+        .and_then(|table| table.remove(USER_TABLE))
+      {
+        merge_toml(&mut merged, settings, "");
+      }
+    }
+
+    merged
+      .try_into()
+      .map_err(|error| MdtError::InvalidConfig(format!("[{USER_TABLE}]: {error}")))
+  }
+
+  /// The cache directory. It is `cache_dir` when it is set, or else `mdt`
+  /// within `$XDG_CACHE_HOME` or `~/.cache`.
+  pub fn cache_dir(&self) -> Option<PathBuf> {
+    let home = std::env::var_os("HOME")
+      .filter(|home| !home.is_empty())
+      .map(PathBuf::from);
+
+    if let Some(directory) = &self.cache_dir {
+      return match directory.strip_prefix("~") {
+        Ok(relative) => home.map(|home| home.join(relative)),
+        Err(_) => Some(directory.clone()),
+      };
+    }
+
+    let directory = std::env::var_os("XDG_CACHE_HOME")
+      .filter(|directory| !directory.is_empty())
+      .map(PathBuf::from)
+      .or_else(|| home.map(|home| home.join(".cache")))?;
+
+    Some(directory.join("mdt"))
+  }
+}
+
+/// When output is colored.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ColorChoice {
+  /// Color output written to a terminal unless `NO_COLOR` is set.
+  #[default]
+  Auto,
+  Always,
+  Never,
+}
+
+impl ColorChoice {
+  /// Whether output to the stream is colored.
+  pub fn enabled(self, terminal: bool) -> bool {
+    match self {
+      Self::Auto => terminal && std::env::var_os("NO_COLOR").map_or(true, |value| value.is_empty()),
+      Self::Always => true,
+      Self::Never => false,
+    }
+  }
+}
+
+/// Overrides for a profile, which is selected with `--profile` or the
+/// `MDT_PROFILE` environment variable. Data namespaces replace the ones with
+/// the same name.
+///
+/// ```toml
+/// [data]
+/// release = "release.draft.toml"
+///
+/// [profile.release.data]
+/// release = "release.toml"
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ProfileConfig {
+  pub data: BTreeMap<String, DataSource>,
+}
+
+/// The transformers defined in the `[transformers]` table, by name.
+pub type CustomTransformers = BTreeMap<String, CustomTransformer>;
+
+/// The `[transformers]` table. It holds the transformers defined by the
+/// project and which transformers tags may use, so that e.g. `replace` can be
+/// banned for reviewability.
+///
+/// ```toml
+/// [transformers]
+/// deny = ["replace"]
+/// # Only these transformers can be used when the list is set.
+/// allow = ["trim", "indent", "shout"]
+///
+/// [transformers.shout]
+/// command = "tr a-z A-Z"
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct TransformersConfig {
+  /// The transformers which tags can't use.
+  pub deny: Vec<String>,
+  /// The only transformers which tags can use, when set.
+  pub allow: Option<Vec<String>>,
+  #[serde(flatten)]
+  pub custom: CustomTransformers,
+}
+
+impl TransformersConfig {
+  /// Whether tags can use the transformer with the name.
+  pub fn is_allowed(&self, name: &str) -> bool {
+    !self.deny.iter().any(|denied| denied == name)
+      && self.allow.as_ref().map_or(true, |allowed| {
+        allowed.iter().any(|allowed| allowed == name)
+      })
+  }
+}
+
+/// A transformer defined in `mdt.toml`. Either a shell command, which receives
+/// the content on stdin and the arguments as positional parameters, or a
+/// template which can reference `content` and `args`.
+///
+/// ```toml
+/// [transformers.shout]
+/// command = "tr a-z A-Z"
+///
+/// [transformers.callout]
+/// template = "> **{{ args[0] }}:** {{ content }}"
+/// args = 1
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct CustomTransformer {
+  /// The command which is run with `sh -c`. Its stdout replaces the content.
+  pub command: Option<String>,
+  /// The minijinja template which replaces the content.
+  pub template: Option<String>,
+  /// The number of arguments the transformer requires.
+  pub args: usize,
+}
+
+/// A filter for provider templates. Either a template which can reference the
+/// filtered `value` and the `args` of the filter, or a table which maps each
+/// value to its replacement.
+///
+/// ```toml
+/// [filters.shield]
+/// template = "![{{ value }}](https://img.shields.io/badge/{{ value }}-{{ args[0] }}-blue)"
+///
+/// [filters.status]
+/// map = { stable = "✅", beta = "🧪" }
+/// default = "❔"
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct TemplateFilter {
+  /// The minijinja template which replaces the value.
+  pub template: Option<String>,
+  /// The replacement for each value.
+  pub map: BTreeMap<String, String>,
+  /// The replacement for values missing from the `map`. Defaults to the
+  /// value itself.
+  pub default: Option<String>,
+}
+
+impl TemplateFilter {
+  fn validate(&self, name: &str) -> MdtResult<()> {
+    if self.template.is_some() != self.map.is_empty() {
+      return Err(MdtError::InvalidConfig(format!(
+        "the filter `{name}` must define either a `template` or a `map`"
+      )));
+    }
+
+    if let Some(template) = self.template.as_deref() {
+      minijinja::Environment::new()
+        .template_from_str(template)
+        .map_err(|error| {
+          MdtError::InvalidConfig(format!("the filter `{name}` is invalid: {error}"))
+        })?;
+    }
+
+    Ok(())
+  }
+}
+
+/// A data file in the `[data]` section. The format is chosen from the
+/// extension unless one is given. A glob loads every matching file as an
+/// array of objects with the `path`, `stem` and parsed `content` of the file.
+/// A Cargo manifest loads the workspace packages with `cargo metadata`.
+///
+/// Files and globs can `select` a dotted path within each document to narrow
+/// the namespace, and declare a schema of the keys the selected value must
+/// have, which is checked when the data is loaded.
+///
+/// ```toml
+/// [data]
+/// deps = { path = "Cargo.toml", select = "dependencies" }
+/// secrets = { path = ".env.public", format = "dotenv" }
+/// post = { path = "blog/announcement.md", format = "frontmatter" }
+/// examples = { glob = "examples/*.toml" }
+/// workspace = { cargo = "Cargo.toml" }
+///
+/// [data.pkg]
+/// path = "package.json"
+///
+/// [data.pkg.schema]
+/// version = "string"
+/// "repository.url" = "string"
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(untagged)]
+pub enum DataSource {
+  Path(PathBuf),
+  File {
+    path: PathBuf,
+    format: Option<DataFormat>,
+    select: Option<String>,
+    #[serde(default)]
+    schema: DataSchema,
+  },
+  Glob {
+    glob: String,
+    format: Option<DataFormat>,
+    select: Option<String>,
+    #[serde(default)]
+    schema: DataSchema,
+  },
+  Cargo {
+    cargo: PathBuf,
+  },
+}
+
+impl<'de> Deserialize<'de> for DataSource {
+  /// Tables are matched to a kind of source by their `cargo`, `glob` or `path`
+  /// key and reject unknown keys, so that a misspelled key such as `fromat` is
+  /// reported rather than ignored.
+  fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+  where
+    D: Deserializer<'de>,
+  {
+    #[derive(Deserialize)]
+    #[serde(deny_unknown_fields)]
+    struct File {
+      path: PathBuf,
+      format: Option<DataFormat>,
+      select: Option<String>,
+      #[serde(default)]
+      schema: DataSchema,
+    }
+
+    #[derive(Deserialize)]
+    #[serde(deny_unknown_fields)]
+    struct Glob {
+      glob: String,
+      format: Option<DataFormat>,
+      select: Option<String>,
+      #[serde(default)]
+      schema: DataSchema,
+    }
+
+    #[derive(Deserialize)]
+    #[serde(deny_unknown_fields)]
+    struct Cargo {
+      cargo: PathBuf,
+    }
+
+    let table = match toml::Value::deserialize(deserializer)? {
+      toml::Value::String(path) => return Ok(Self::Path(path.into())),
+      toml::Value::Table(table) => table,
+      _ => {
+        return Err(serde::de::Error::custom(
+          "a data source must be a path or a table",
+        ))
+      }
+    };
+    let kind = |key: &str| table.contains_key(key);
+    let value = toml::Value::Table(table.clone());
+
+    let source = if kind("cargo") {
+      value
+        .try_into()
+        .map(|Cargo { cargo }| Self::Cargo { cargo })
+    } else if kind("glob") {
+      value.try_into().map(
+        |Glob {
+           glob,
+           format,
+           select,
+           schema,
+         }| {
+          Self::Glob {
+            glob,
+            format,
+            select,
+            schema,
+          }
+        },
+      )
+    } else {
+      value.try_into().map(
+        |File {
+           path,
+           format,
+           select,
+           schema,
+         }| {
+          Self::File {
+            path,
+            format,
+            select,
+            schema,
+          }
+        },
+      )
+    };
+
+    source.map_err(|error| serde::de::Error::custom(error.message()))
+  }
+}
+
+impl DataSource {
+  /// The path of the data file, relative to the root. Globs and Cargo
+  /// workspaces have no single data file.
+  pub fn path(&self) -> Option<&Path> {
+    match self {
+      Self::Path(path) | Self::File { path, .. } => Some(path),
+      Self::Glob { .. } | Self::Cargo { .. } => None,
+    }
+  }
+
+  /// The configured format of the data files.
+  pub fn format(&self) -> Option<DataFormat> {
+    match self {
+      Self::Path(_) | Self::Cargo { .. } => None,
+      Self::File { format, .. } | Self::Glob { format, .. } => *format,
+    }
+  }
+
+  /// The dotted path within each document which the namespace is narrowed
+  /// to, e.g. `dependencies`.
+  pub fn select(&self) -> Option<&str> {
+    match self {
+      Self::Path(_) | Self::Cargo { .. } => None,
+      Self::File { select, .. } | Self::Glob { select, .. } => select.as_deref(),
+    }
+  }
+}
+
+/// The types of the keys of a data file by dotted key, e.g. `pkg.version`.
+pub type DataSchema = BTreeMap<String, DataType>;
+
+/// The type a key of a data file must have. Strings must not be empty.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DataType {
+  String,
+  Number,
+  Integer,
+  Boolean,
+  Array,
+  Object,
+}
+
+impl DataType {
+  /// Whether the value has the type.
+  pub fn matches(self, value: &serde_json::Value) -> bool {
+    match self {
+      Self::String => value.as_str().map_or(false, |value| !value.is_empty()),
+      Self::Number => value.is_number(),
+      Self::Integer => value.is_i64() || value.is_u64(),
+      Self::Boolean => value.is_boolean(),
+      Self::Array => value.is_array(),
+      Self::Object => value.is_object(),
+    }
+  }
+}
+
+/// The formats which data files can be parsed from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DataFormat {
+  Json,
+  Toml,
+  Csv,
+  Tsv,
+  Xml,
+  Dotenv,
+  /// The YAML or TOML front matter of a markdown file. It is never chosen
+  /// from the extension.
+  Frontmatter,
+}
+
+impl DataFormat {
+  /// The format for the extension of the file. Files named `.env` or
+  /// `.env.*` are dotenv files.
+  pub fn from_path(path: &Path) -> Option<Self> {
+    let name = path.file_name()?.to_string_lossy();
+
+    if name == ".env" || name.starts_with(".env.") {
+      return Some(Self::Dotenv);
+    }
+
+    let extension = path.extension()?.to_string_lossy().to_lowercase();
+    let format = match extension.as_str() {
+      "json" => Self::Json,
+      "toml" => Self::Toml,
+      "csv" => Self::Csv,
+      "tsv" => Self::Tsv,
+      "xml" | "csproj" | "fsproj" | "vbproj" | "props" => Self::Xml,
+      "env" => Self::Dotenv,
+      _ => return None,
+    };
+
+    Some(format)
+  }
+}
+
+/// The changelog which provides the `changelog` data namespace.
+///
+/// ```toml
+/// [changelog]
+/// path = "CHANGELOG.md"
+/// version = "pkg.version"
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct ChangelogConfig {
+  /// The path to the changelog, relative to the root.
+  pub path: PathBuf,
+  /// The data variable which holds the current version, e.g. `pkg.version`.
+  /// The latest released entry is used when this is not set.
+  pub version: Option<String>,
+}
+
+/// The git repository which provides the `git` data namespace, with the
+/// `latest_tag`, `short_sha`, `commit_date` and `contributor_count` of the
+/// current commit.
+///
+/// ```toml
+/// [git]
+/// tags = "v*"
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct GitDataConfig {
+  /// Only tags which match the glob are used for `latest_tag`.
+  pub tags: Option<String>,
+}
+
+/// The handling of images referenced by providers.
+///
+/// ```toml
+/// [assets]
+/// base_url = "https://raw.githubusercontent.com/ifiokjr/mdt/main"
+/// copy = false
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct AssetsConfig {
+  /// Rewrite relative image sources to absolute urls under this url, using
+  /// the location of the image relative to the root.
+  pub base_url: Option<String>,
+  /// Copy the images referenced by providers next to their consumers when
+  /// running `mdt update`.
+  pub copy: bool,
+}
+
+/// The provider index written by `mdt docs sync`.
+///
+/// ```toml
+/// [docs]
+/// index = "readme.md"
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct DocsConfig {
+  /// The path of the index, relative to the root. `mdt check` reports an
+  /// error when it is out of date.
+  pub index: Option<PathBuf>,
+}
+
+/// The files which are scanned for blocks. Every file is scanned when there
+/// are no patterns, including definition files.
+///
+/// ```toml
+/// [include]
+/// patterns = ["docs/**", "crates/*/src/lib.rs", "readme.md", "*.t.md"]
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct IncludeConfig {
+  /// Globs of the files to scan, relative to the root. `*` doesn't match
+  /// `/`, so use `**` to match nested directories.
+  pub patterns: Vec<String>,
+}
+
+impl IncludeConfig {
+  /// The matcher for the patterns, or `None` when every file is included.
+  pub fn matcher(&self) -> MdtResult<Option<GlobSet>> {
+    if self.patterns.is_empty() {
+      return Ok(None);
+    }
+
+    let mut globs = GlobSetBuilder::new();
+
+    for pattern in &self.patterns {
+      let glob = GlobBuilder::new(pattern)
+        .literal_separator(true)
+        .build()
+        .map_err(|error| {
+          MdtError::InvalidConfig(format!(
+            "the include pattern `{pattern}` isn't a valid glob: {error}"
+          ))
+        })?;
+      globs.add(glob);
+    }
+
+    globs
+      .build()
+      .map(Some)
+      .map_err(|error| MdtError::InvalidConfig(error.to_string()))
+  }
+}
+
+/// The extensions of the source files which are scanned for tags. Markdown
+/// files and files with a configured comment style are always scanned.
+///
+/// ```toml
+/// [scan]
+/// extensions = ["rs", "ts", "py"]
+/// extra_extensions = ["proto"]
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ScanConfig {
+  /// Replaces the builtin source extensions, e.g. to skip `yaml` files.
+  pub extensions: Option<Vec<String>>,
+  /// Extensions which are scanned along with the others.
+  pub extra_extensions: Vec<String>,
+}
+
+impl ScanConfig {
+  /// Whether files with the extension are scanned as source files. The
+  /// extension is compared without case and a leading `.` is ignored.
+  pub fn is_source_extension(&self, extension: &str) -> bool {
+    let matches = |candidate: &str| {
+      candidate
+        .trim_start_matches('.')
+        .eq_ignore_ascii_case(extension)
+    };
+
+    let listed = match &self.extensions {
+      Some(extensions) => extensions.iter().any(|candidate| matches(candidate)),
+      None => SOURCE_EXTENSIONS.iter().any(|candidate| matches(candidate)),
+    };
+
+    listed
+      || self
+        .extra_extensions
+        .iter()
+        .any(|candidate| matches(candidate))
+  }
+}
+
+/// The sub-projects of a monorepo. Each member is a directory with its own
+/// `mdt.toml`, which is scanned with its own configuration.
+///
+/// ```toml
+/// [workspace]
+/// members = ["packages/*"]
+/// scope = "local"
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct WorkspaceConfig {
+  /// Globs of the member directories, relative to the root.
+  pub members: Vec<String>,
+  /// The scope of the providers of this project. The `scope` in the front
+  /// matter of a definition file overrides it.
+  pub scope: ProviderScope,
+}
+
+/// Where the providers of a project in a workspace can be consumed.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ProviderScope {
+  /// Other projects of the workspace can consume the provider. The providers
+  /// of the root keep their name and the providers of a member are
+  /// namespaced by its directory, e.g. `packages/cli/install`.
+  #[default]
+  Workspace,
+  /// Only the project itself can consume the provider.
+  Local,
+}
+
+/// The options for `mdt update`.
+///
+/// ```toml
+/// [update]
+/// content_hash = true
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct UpdateConfig {
+  /// Record a hash of the written content in the closing tag of each
+  /// consumer, e.g. `{/block|hash:"1a2b3c4d"}`, so that consumers edited by
+  /// hand are reported as conflicts instead of being overwritten.
+  pub content_hash: bool,
+}
+
+/// The key command which decrypts providers stored encrypted with `age` or
+/// `sops`. It receives the encrypted content on stdin and prints the plain
+/// text.
+///
+/// ```toml
+/// [encryption]
+/// command = "age --decrypt --identity ~/.config/mdt/key.txt"
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct EncryptionConfig {
+  /// The command which is run with `sh -c` from the project root.
+  pub command: Option<String>,
+}
+
+/// The options for `mdt release-notes`.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ReleaseConfig {
+  /// The name of the provider which renders the release notes.
+  pub provider: Option<String>,
+}
+
+/// The options for `mdt check`. Every option is off by default so that
+/// adopting mdt is painless. `strict` turns on the block and link checks, while
+/// `alt_text`, `max_sentence_length` and `deny_deprecated` are opted into on
+/// their own.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct CheckOptions {
+  /// Enable `undefined_variables`, `orphan_consumers`, `unused_providers`,
+  /// `strict_whitespace` and `check_links`.
+  pub strict: bool,
+  /// Report template variables which don't exist in the data as errors.
+  pub undefined_variables: bool,
+  /// Report consumers without a provider as errors instead of warnings.
+  pub orphan_consumers: bool,
+  /// Report providers without a consumer as errors instead of warnings.
+  pub unused_providers: bool,
+  /// Treat differences in trailing whitespace and surrounding blank lines as
+  /// stale content.
+  pub strict_whitespace: bool,
+  /// Report relative links in consumers which point to missing files.
+  pub check_links: bool,
+  /// Report images in consumers which have no alt text. `strict` doesn't
+  /// enable it.
+  pub alt_text: bool,
+  /// Warn about sentences in consumers with more words than this. `strict`
+  /// doesn't set a limit.
+  pub max_sentence_length: Option<usize>,
+  /// Report deprecated features as errors instead of warnings. `strict`
+  /// doesn't enable it.
+  pub deny_deprecated: bool,
+}
+
+impl CheckOptions {
+  /// The options with the `strict` checks enabled.
+  pub fn strict() -> Self {
+    Self {
+      strict: true,
+      ..Self::default()
+    }
+    .resolved()
+  }
+
+  /// Expand the `strict` profile into the individual options. The options
+  /// outside of the profile keep their own values.
+  #[must_use]
+  pub fn resolved(self) -> Self {
+    if !self.strict {
+      return self;
+    }
+
+    Self {
+      strict: true,
+      undefined_variables: true,
+      orphan_consumers: true,
+      unused_providers: true,
+      strict_whitespace: true,
+      check_links: true,
+      alt_text: self.alt_text,
+      max_sentence_length: self.max_sentence_length,
+      deny_deprecated: self.deny_deprecated,
+    }
+  }
+}
+
+/// The block naming policy, e.g. to standardize on camel case names.
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct NamingConfig {
+  /// A regular expression which every block name must match.
+  #[serde(
+    deserialize_with = "deserialize_regex",
+    serialize_with = "serialize_regex"
+  )]
+  pub pattern: Option<Regex>,
+  /// Block names may not start with any of these prefixes.
+  pub reserved_prefixes: Vec<String>,
+}
+
+impl NamingConfig {
+  /// Check that the name follows the naming policy.
+  pub fn check(&self, name: &str) -> MdtResult<()> {
+    let error = |message: String| {
+      Err(MdtError::BlockNamePolicy {
+        name: name.to_string(),
+        message,
+      })
+    };
+
+    if let Some(pattern) = self.pattern.as_ref() {
+      if !pattern.is_match(name) {
+        return error(format!("it must match the pattern `{pattern}`"));
+      }
+    }
+
+    if let Some(prefix) = self
+      .reserved_prefixes
+      .iter()
+      .find(|prefix| name.starts_with(prefix.as_str()))
+    {
+      return error(format!("the prefix `{prefix}` is reserved"));
+    }
+
+    Ok(())
+  }
+}
+
+fn serialize_regex<S>(pattern: &Option<Regex>, serializer: S) -> Result<S::Ok, S::Error>
+where
+  S: Serializer,
+{
+  pattern.as_ref().map(Regex::as_str).serialize(serializer)
+}
+
+fn deserialize_regex<'de, D>(deserializer: D) -> Result<Option<Regex>, D::Error>
+where
+  D: Deserializer<'de>,
+{
+  let Some(pattern) = Option::<String>::deserialize(deserializer)? else {
+    return Ok(None);
+  };
+
+  Regex::new(&pattern)
+    .map(Some)
+    .map_err(serde::de::Error::custom)
+}
@@ -0,0 +1,711 @@
+use std::collections::BTreeMap;
+use std::collections::BTreeSet;
+use std::fs;
+use std::path::Path;
+use std::path::PathBuf;
+
+use globset::GlobBuilder;
+use minijinja::value::Rest;
+use minijinja::Environment;
+use semver::Version;
+use semver::VersionReq;
+use serde_json::Value;
+
+use crate::detect_vcs;
+use crate::find_changelog_entry;
+use crate::load_cargo_metadata;
+use crate::parse_changelog;
+use crate::ChangelogConfig;
+use crate::DataFormat;
+use crate::DataSchema;
+use crate::DataSource;
+use crate::DataType;
+use crate::MdtConfig;
+use crate::MdtError;
+use crate::MdtResult;
+use crate::Project;
+use crate::TemplateFilter;
+use crate::IGNORED_DIRECTORIES;
+
+/// The data available to provider templates, indexed by namespace.
+pub type DataMap = BTreeMap<String, Value>;
+
+/// Globals provided by the template engine which aren't data.
+const TEMPLATE_GLOBALS: &[&str] = &["range", "dict", "debug", "namespace"];
+
+/// Load every data file from the `[data]` section of the configuration. Paths
+/// are relative to the project root.
+pub fn load_data(root: &Path, config: &MdtConfig) -> MdtResult<DataMap> {
+  let mut data = DataMap::new();
+
+  for (namespace, source) in &config.data {
+    let value = match source {
+      DataSource::Path(path) => load_data_file(&root.join(path))?,
+      DataSource::File {
+        path,
+        format,
+        select,
+        schema,
+      } => load_selected_data(&root.join(path), *format, select.as_deref(), schema)?,
+      DataSource::Glob {
+        glob,
+        format,
+        select,
+        schema,
+      } => {
+        load_data_glob(root, glob, |file| {
+          load_selected_data(file, *format, select.as_deref(), schema)
+        })?
+      }
+      DataSource::Cargo { cargo } => load_cargo_metadata(root, cargo)?,
+    };
+    data.insert(namespace.clone(), value);
+  }
+
+  if let Some(changelog) = config.changelog.as_ref() {
+    if data.contains_key(CHANGELOG_NAMESPACE) {
+      return Err(MdtError::InvalidConfig(format!(
+        "the `{CHANGELOG_NAMESPACE}` data namespace is reserved for the changelog"
+      )));
+    }
+
+    let value = load_changelog(root, changelog, &data)?;
+    data.insert(CHANGELOG_NAMESPACE.into(), value);
+  }
+
+  if let Some(git) = config.git.as_ref() {
+    if data.contains_key(GIT_NAMESPACE) {
+      return Err(MdtError::InvalidConfig(format!(
+        "the `{GIT_NAMESPACE}` data namespace is reserved for the `[git]` section"
+      )));
+    }
+
+    let Some(vcs) = detect_vcs(root) else {
+      return Err(MdtError::InvalidConfig(
+        "the `[git]` section requires the project to be within a git repository".into(),
+      ));
+    };
+
+    let metadata = vcs.metadata(git.tags.as_deref())?;
+    let value =
+      serde_json::to_value(metadata).map_err(|error| MdtError::InvalidConfig(error.to_string()))?;
+    data.insert(GIT_NAMESPACE.into(), value);
+  }
+
+  Ok(data)
+}
+
+/// The namespace which holds the details of the git repository.
+pub const GIT_NAMESPACE: &str = "git";
+
+/// The namespace which holds the changelog entry.
+pub const CHANGELOG_NAMESPACE: &str = "changelog";
+
+/// Load the changelog entry for the current version. The value contains the
+/// `version`, `date` and `content` of the entry along with the content of the
+/// `unreleased` entry.
+fn load_changelog(root: &Path, config: &ChangelogConfig, data: &DataMap) -> MdtResult<Value> {
+  let path = root.join(&config.path);
+  let entries = parse_changelog(&fs::read_to_string(&path)?);
+  let version = match config.version.as_ref() {
+    Some(variable) => {
+      let value = lookup_variable(data, variable).and_then(Value::as_str);
+      let Some(value) = value else {
+        return Err(MdtError::InvalidConfig(format!(
+          "the changelog version `{variable}` doesn't exist in the data"
+        )));
+      };
+
+      Some(value)
+    }
+    None => None,
+  };
+
+  let Some(entry) = find_changelog_entry(&entries, version) else {
+    return Err(MdtError::DataFile {
+      path,
+      message: format!(
+        "no changelog entry for version `{}`",
+        version.unwrap_or("latest")
+      ),
+    });
+  };
+
+  let mut value = serde_json::to_value(entry).unwrap_or_default();
+
+  if let Some(object) = value.as_object_mut() {
+    let unreleased = entries
+      .iter()
+      .find(|entry| entry.is_unreleased())
+      .map(|entry| entry.content.clone());
+    object.insert("unreleased".into(), unreleased.into());
+  }
+
+  Ok(value)
+}
+
+/// Look up a dotted variable, e.g. `pkg.version`, in the data.
+pub fn lookup_variable<'a>(data: &'a DataMap, variable: &str) -> Option<&'a Value> {
+  let mut segments = variable.split('.');
+  let value = data.get(segments.next()?)?;
+
+  segments.try_fold(value, |value, key| value.get(key))
+}
+
+/// Load a data file, narrow it to the selected dotted path and check it
+/// against the schema.
+fn load_selected_data(
+  path: &Path,
+  format: Option<DataFormat>,
+  select: Option<&str>,
+  schema: &DataSchema,
+) -> MdtResult<Value> {
+  let mut value = load_data_file_as(path, format)?;
+
+  if let Some(select) = select {
+    let Some(selected) = select_value(&value, select) else {
+      return Err(MdtError::DataFile {
+        path: path.to_path_buf(),
+        message: format!("the selected key `{select}` doesn't exist"),
+      });
+    };
+
+    value = selected.clone();
+  }
+
+  validate_data_schema(path, &value, schema)?;
+
+  Ok(value)
+}
+
+/// The value at the dotted path, e.g. `package.metadata`.
+fn select_value<'a>(value: &'a Value, path: &str) -> Option<&'a Value> {
+  path
+    .split('.')
+    .try_fold(value, |value, segment| value.get(segment))
+}
+
+/// Check that the value has every key of the schema with the right type. The
+/// error names the first key which is missing or has the wrong type.
+pub fn validate_data_schema(path: &Path, value: &Value, schema: &DataSchema) -> MdtResult<()> {
+  for (key, r#type) in schema {
+    let message = match select_value(value, key) {
+      None | Some(Value::Null) => format!("the key `{key}` is missing"),
+      Some(Value::String(text)) if text.is_empty() && *r#type == DataType::String => {
+        format!("the key `{key}` is an empty string")
+      }
+      Some(found) if !r#type.matches(found) => {
+        format!(
+          "the key `{key}` should be {} but is {}",
+          describe_type(*r#type),
+          describe_value(found)
+        )
+      }
+      Some(_) => continue,
+    };
+
+    return Err(MdtError::DataFile {
+      path: path.to_path_buf(),
+      message,
+    });
+  }
+
+  Ok(())
+}
+
+fn describe_type(r#type: DataType) -> &'static str {
+  match r#type {
+    DataType::String => "a string",
+    DataType::Number => "a number",
+    DataType::Integer => "an integer",
+    DataType::Boolean => "a boolean",
+    DataType::Array => "an array",
+    DataType::Object => "an object",
+  }
+}
+
+fn describe_value(value: &Value) -> &'static str {
+  match value {
+    Value::Null => "null",
+    Value::Bool(_) => "a boolean",
+    Value::Number(_) => "a number",
+    Value::String(_) => "a string",
+    Value::Array(_) => "an array",
+    Value::Object(_) => "an object",
+  }
+}
+
+/// Load every file below the root which matches the glob, sorted by path.
+/// Each file becomes an object with its relative `path`, its `stem` and the
+/// `content` returned by `load`.
+fn load_data_glob(
+  root: &Path,
+  glob: &str,
+  load: impl Fn(&Path) -> MdtResult<Value>,
+) -> MdtResult<Value> {
+  let matcher = GlobBuilder::new(glob)
+    .literal_separator(true)
+    .build()
+    .map_err(|error| MdtError::InvalidConfig(format!("invalid data glob `{glob}`: {error}")))?
+    .compile_matcher();
+  let mut files = vec![];
+  collect_data_files(root, &mut files)?;
+  files.retain(|file| matcher.is_match(file.strip_prefix(root).unwrap_or(file)));
+  files.sort();
+
+  let mut entries = vec![];
+
+  for file in files {
+    let relative = file.strip_prefix(root).unwrap_or(&file);
+    let path = relative
+      .components()
+      .map(|component| component.as_os_str().to_string_lossy())
+      .collect::<Vec<_>>()
+      .join("/");
+    let stem = file
+      .file_stem()
+      .map(|stem| stem.to_string_lossy().into_owned())
+      .unwrap_or_default();
+    let content = load(&file)?;
+
+    entries.push(serde_json::json!({ "path": path, "stem": stem, "content": content }));
+  }
+
+  Ok(Value::Array(entries))
+}
+
+fn collect_data_files(directory: &Path, files: &mut Vec<PathBuf>) -> MdtResult<()> {
+  for entry in fs::read_dir(directory)? {
+    let entry = entry?;
+    let name = entry.file_name();
+    let name = name.to_string_lossy();
+    let file_type = entry.file_type()?;
+
+    if file_type.is_dir() {
+      if !name.starts_with('.') && !IGNORED_DIRECTORIES.contains(&name.as_ref()) {
+        collect_data_files(&entry.path(), files)?;
+      }
+    } else if file_type.is_file() {
+      files.push(entry.path());
+    }
+  }
+
+  Ok(())
+}
+
+/// Load a data file, choosing the format from its extension. CSV and TSV
+/// files are loaded as an array of objects keyed by the header row. XML
+/// files, including `.csproj` projects, are loaded from their root element.
+pub fn load_data_file(path: &Path) -> MdtResult<Value> {
+  load_data_file_as(path, None)
+}
+
+/// Load a data file in the format, or the format for its extension when none
+/// is given.
+pub fn load_data_file_as(path: &Path, format: Option<DataFormat>) -> MdtResult<Value> {
+  let content = fs::read_to_string(path)?;
+  let format = format.or_else(|| DataFormat::from_path(path));
+
+  let result = match format {
+    Some(DataFormat::Json) => serde_json::from_str(&content).map_err(|error| error.to_string()),
+    Some(DataFormat::Toml) => toml::from_str(&content).map_err(|error| error.to_string()),
+    Some(DataFormat::Csv) => parse_delimited(&content, b','),
+    Some(DataFormat::Tsv) => parse_delimited(&content, b'\t'),
+    Some(DataFormat::Xml) => parse_xml(&content),
+    Some(DataFormat::Dotenv) => Ok(parse_dotenv(&content)),
+    Some(DataFormat::Frontmatter) => parse_frontmatter(&content),
+    None => {
+      let extension = path
+        .extension()
+        .map(|extension| extension.to_string_lossy().to_lowercase())
+        .unwrap_or_default();
+      Err(format!("unsupported data file extension `{extension}`"))
+    }
+  };
+
+  result.map_err(|message| {
+    MdtError::DataFile {
+      path: path.to_path_buf(),
+      message,
+    }
+  })
+}
+
+/// Parse the front matter at the start of a markdown file, which is YAML
+/// between `---` lines or TOML between `+++` lines. A file without front
+/// matter is an empty object.
+fn parse_frontmatter(content: &str) -> Result<Value, String> {
+  let content = content.strip_prefix('\u{feff}').unwrap_or(content);
+  let mut lines = content.lines();
+  let fence = match lines.next().map(str::trim_end) {
+    Some(fence @ ("---" | "+++")) => fence,
+    _ => return Ok(Value::Object(serde_json::Map::new())),
+  };
+
+  let mut matter = String::new();
+  let mut closed = false;
+
+  for line in lines {
+    if line.trim_end() == fence {
+      closed = true;
+      break;
+    }
+
+    matter.push_str(line);
+    matter.push('\n');
+  }
+
+  if !closed {
+    return Err(format!("the front matter is missing its closing `{fence}`"));
+  }
+
+  let value: Value = if fence == "---" {
+    serde_yaml::from_str(&matter).map_err(|error| error.to_string())?
+  } else {
+    toml::from_str(&matter).map_err(|error| error.to_string())?
+  };
+
+  Ok(if value.is_null() {
+    Value::Object(serde_json::Map::new())
+  } else {
+    value
+  })
+}
+
+/// Parse `KEY=VALUE` lines into an object of strings. Blank lines, comments
+/// and an `export` prefix are ignored. Double quoted values support `\n`
+/// escapes, single quoted values are literal and unquoted values end at a
+/// ` #` comment.
+fn parse_dotenv(content: &str) -> Value {
+  let mut object = serde_json::Map::new();
+
+  for line in content.lines() {
+    let line = line.trim();
+
+    if line.is_empty() || line.starts_with('#') {
+      continue;
+    }
+
+    let line = line.strip_prefix("export ").unwrap_or(line);
+    let Some((key, value)) = line.split_once('=') else {
+      continue;
+    };
+
+    let value = value.trim();
+    let value = if let Some(quoted) = value.strip_prefix('"') {
+      unescape_dotenv(quoted)
+    } else if let Some(quoted) = value.strip_prefix('\'') {
+      quoted
+        .split_once('\'')
+        .map_or(quoted, |(quoted, _)| quoted)
+        .to_string()
+    } else {
+      value
+        .split_once(" #")
+        .map_or(value, |(value, _)| value)
+        .trim_end()
+        .to_string()
+    };
+
+    object.insert(key.trim().to_string(), Value::String(value));
+  }
+
+  Value::Object(object)
+}
+
+/// The content of a double quoted value up to its closing quote, with `\n`,
+/// `\"` and `\\` escapes replaced.
+fn unescape_dotenv(quoted: &str) -> String {
+  let mut value = String::new();
+  let mut characters = quoted.chars();
+
+  while let Some(character) = characters.next() {
+    match character {
+      '"' => break,
+      '\\' => {
+        match characters.next() {
+          Some('n') => value.push('\n'),
+          Some(escaped) => value.push(escaped),
+          None => value.push('\\'),
+        }
+      }
+      _ => value.push(character),
+    }
+  }
+
+  value
+}
+
+/// Parse delimited rows into an array of objects, using the header row as the
+/// keys. Every value is a string.
+fn parse_delimited(content: &str, delimiter: u8) -> Result<Value, String> {
+  let mut reader = csv::ReaderBuilder::new()
+    .delimiter(delimiter)
+    .from_reader(content.as_bytes());
+  let headers = reader.headers().map_err(|error| error.to_string())?.clone();
+  let mut rows = vec![];
+
+  for record in reader.records() {
+    let record = record.map_err(|error| error.to_string())?;
+    let row = headers
+      .iter()
+      .zip(record.iter())
+      .map(|(header, value)| (header.to_string(), Value::String(value.to_string())))
+      .collect::<serde_json::Map<_, _>>();
+    rows.push(Value::Object(row));
+  }
+
+  Ok(Value::Array(rows))
+}
+
+/// Parse an XML document into the value of its root element, e.g. the
+/// `<version>` of a `pom.xml` is available as `version`.
+fn parse_xml(content: &str) -> Result<Value, String> {
+  let document = roxmltree::Document::parse(content).map_err(|error| error.to_string())?;
+
+  Ok(xml_element_value(document.root_element()))
+}
+
+/// The value of an element. Elements with only text become strings. Otherwise
+/// attributes are prefixed with `@`, child elements are keyed by name and
+/// repeated children become arrays, with any text under `#text`.
+fn xml_element_value(element: roxmltree::Node<'_, '_>) -> Value {
+  let children = element
+    .children()
+    .filter(roxmltree::Node::is_element)
+    .collect::<Vec<_>>();
+  let text = element
+    .children()
+    .filter(roxmltree::Node::is_text)
+    .filter_map(|node| node.text())
+    .collect::<String>();
+  let text = text.trim();
+
+  if children.is_empty() && element.attributes().len() == 0 {
+    return Value::String(text.to_string());
+  }
+
+  let mut object = serde_json::Map::new();
+
+  for attribute in element.attributes() {
+    object.insert(
+      format!("@{}", attribute.name()),
+      Value::String(attribute.value().to_string()),
+    );
+  }
+
+  for child in children {
+    let name = child.tag_name().name().to_string();
+    let value = xml_element_value(child);
+
+    match object.get_mut(&name) {
+      Some(Value::Array(values)) => values.push(value),
+      Some(existing) => *existing = Value::Array(vec![existing.take(), value]),
+      None => {
+        object.insert(name, value);
+      }
+    }
+  }
+
+  if !text.is_empty() {
+    object.insert("#text".into(), Value::String(text.to_string()));
+  }
+
+  Value::Object(object)
+}
+
+/// Render the provider content as a template with the data as its context.
+pub fn render_template(name: &str, content: &str, data: &DataMap) -> MdtResult<String> {
+  render_template_with_filters(name, content, data, &BTreeMap::new())
+}
+
+/// Render the provider content as a template with the filters from the
+/// `[filters]` section of the configuration.
+pub fn render_template_with_filters(
+  name: &str,
+  content: &str,
+  data: &DataMap,
+  filters: &BTreeMap<String, TemplateFilter>,
+) -> MdtResult<String> {
+  let mut environment = create_environment();
+
+  for (filter_name, filter) in filters {
+    add_template_filter(&mut environment, filter_name, filter.clone());
+  }
+
+  environment.render_str(content, data).map_err(|error| {
+    MdtError::Template {
+      name: name.to_string(),
+      message: error.to_string(),
+    }
+  })
+}
+
+/// Evaluate an expression, e.g. `pkg.version is since("2.0")`, against the
+/// data and return whether the result is truthy.
+pub fn evaluate_condition(expression: &str, data: &DataMap) -> MdtResult<bool> {
+  let environment = create_environment();
+  let invalid = |error: minijinja::Error| {
+    MdtError::InvalidExpression {
+      expression: expression.to_string(),
+      message: error.to_string(),
+    }
+  };
+
+  environment
+    .compile_expression(expression)
+    .and_then(|compiled| compiled.eval(data))
+    .map(|value| value.is_true())
+    .map_err(invalid)
+}
+
+/// Check that the expression can be compiled without evaluating it.
+pub fn validate_expression(expression: &str) -> MdtResult<()> {
+  create_environment()
+    .compile_expression(expression)
+    .map(|_| ())
+    .map_err(|error| {
+      MdtError::InvalidExpression {
+        expression: expression.to_string(),
+        message: error.to_string(),
+      }
+    })
+}
+
+/// The data variables referenced by the template, e.g. `pkg.version`. Nested
+/// attribute lookups are joined with `.`.
+pub fn template_variables(content: &str) -> BTreeSet<String> {
+  let environment = create_environment();
+  let Ok(template) = environment.template_from_str(content) else {
+    return BTreeSet::new();
+  };
+
+  template
+    .undeclared_variables(true)
+    .into_iter()
+    .filter(|variable| {
+      let namespace = variable.split('.').next().unwrap_or_default();
+      !TEMPLATE_GLOBALS.contains(&namespace)
+    })
+    .collect()
+}
+
+/// Create the template environment. Besides the builtins it provides tests
+/// for selecting content by version so that docs for upcoming and legacy
+/// releases can live in one template:
+///
+/// ```md
+/// {% if pkg.version is since("2.0.0") %}new api{% else %}old api{% endif %}
+/// {% if pkg.version is until("2.0.0") %}deprecated{% endif %}
+/// {% if pkg.version is satisfies(">=1.2, <2") %}1.x only{% endif %}
+/// ```
+fn create_environment() -> Environment<'static> {
+  let mut environment = Environment::new();
+  environment.set_keep_trailing_newline(true);
+  environment.add_test("satisfies", |version: String, requirement: String| {
+    let requirement = VersionReq::parse(&requirement).map_err(invalid_version)?;
+    Ok(requirement.matches(&parse_version(&version)?))
+  });
+  environment.add_test("since", |version: String, since: String| {
+    Ok(parse_version(&version)? >= parse_version(&since)?)
+  });
+  environment.add_test("until", |version: String, until: String| {
+    Ok(parse_version(&version)? < parse_version(&until)?)
+  });
+  environment
+}
+
+/// Register a filter defined in the configuration. Template filters are
+/// rendered on their own, so they can't use the data or other custom filters.
+fn add_template_filter(environment: &mut Environment<'static>, name: &str, filter: TemplateFilter) {
+  environment.add_filter(
+    name.to_string(),
+    move |value: minijinja::Value, args: Rest<minijinja::Value>| {
+      let Some(template) = filter.template.as_deref() else {
+        let key = value
+          .as_str()
+          .map_or_else(|| value.to_string(), String::from);
+
+        return Ok(
+          filter
+            .map
+            .get(&key)
+            .or(filter.default.as_ref())
+            .map_or(value, |replacement| {
+              minijinja::Value::from(replacement.as_str())
+            }),
+        );
+      };
+
+      Environment::new()
+        .render_str(template, minijinja::context! { value, args => args.0 })
+        .map(minijinja::Value::from)
+    },
+  );
+}
+
+/// Parse a version leniently, e.g. `v2` and `2.1` are read as `2.0.0` and
+/// `2.1.0`.
+fn parse_version(version: &str) -> Result<Version, minijinja::Error> {
+  let version = version.trim().trim_start_matches('v');
+  let (core, rest) = version
+    .find(['-', '+'])
+    .map_or((version, ""), |index| version.split_at(index));
+  let missing = 2usize.saturating_sub(core.matches('.').count());
+  let padded = format!("{core}{}{rest}", ".0".repeat(missing));
+
+  Version::parse(&padded).map_err(invalid_version)
+}
+
+fn invalid_version(error: semver::Error) -> minijinja::Error {
+  minijinja::Error::new(minijinja::ErrorKind::InvalidOperation, error.to_string())
+}
+
+/// How the configured data is used by the provider templates.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct DataUsage {
+  /// Namespaces which are configured but never referenced.
+  pub unused: Vec<String>,
+  /// Variables which are referenced but don't exist in the data.
+  pub missing: Vec<MissingData>,
+}
+
+/// A variable referenced by a provider which doesn't exist in the data.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MissingData {
+  /// The name of the provider which references the variable.
+  pub provider: String,
+  pub variable: String,
+}
+
+/// Find the data namespaces which are never used and the variables which are
+/// used but missing, e.g. the typo `pkg.verison`.
+pub fn analyze_data_usage(project: &Project) -> DataUsage {
+  let mut usage = DataUsage::default();
+  let mut used = BTreeSet::new();
+  let mut providers = project.providers.values().collect::<Vec<_>>();
+  providers.sort_by(|a, b| a.block.name.cmp(&b.block.name));
+
+  for provider in providers {
+    for variable in template_variables(&provider.content) {
+      let namespace = variable.split('.').next().unwrap_or_default();
+      used.insert(namespace.to_string());
+
+      if lookup_variable(&project.data, &variable).is_none() {
+        usage.missing.push(MissingData {
+          provider: provider.block.name.clone(),
+          variable,
+        });
+      }
+    }
+  }
+
+  usage.unused = project
+    .data
+    .keys()
+    .filter(|namespace| !used.contains(*namespace))
+    .cloned()
+    .collect();
+
+  usage
+}
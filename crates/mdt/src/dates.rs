@@ -0,0 +1,205 @@
+const MONTHS: &[&str] = &[
+  "January",
+  "February",
+  "March",
+  "April",
+  "May",
+  "June",
+  "July",
+  "August",
+  "September",
+  "October",
+  "November",
+  "December",
+];
+
+const WEEKDAYS: &[&str] = &[
+  "Monday",
+  "Tuesday",
+  "Wednesday",
+  "Thursday",
+  "Friday",
+  "Saturday",
+  "Sunday",
+];
+
+/// A calendar date and time without a timezone. Timestamps with an offset
+/// keep the wall clock time they were written with.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DateTime {
+  pub year: i64,
+  /// The month from 1 to 12.
+  pub month: u32,
+  /// The day of the month from 1 to 31.
+  pub day: u32,
+  pub hour: u32,
+  pub minute: u32,
+  pub second: u32,
+}
+
+impl DateTime {
+  /// Parse a date in one of the common formats:
+  ///
+  /// - `2024-01-31`
+  /// - `2024-01-31T12:30:00Z`, `2024-01-31 12:30:00+02:00` or with fractional
+  ///   seconds
+  /// - unix timestamps in seconds, e.g. `1706704200`, or milliseconds
+  pub fn parse(value: &str) -> Option<Self> {
+    let value = value.trim();
+
+    if !value.is_empty() && value.bytes().all(|byte| byte.is_ascii_digit()) {
+      let timestamp = value.parse::<i64>().ok()?;
+      // Anything above this is too far in the future to be seconds.
+      let seconds = if timestamp > 99_999_999_999 {
+        timestamp / 1000
+      } else {
+        timestamp
+      };
+
+      return Some(Self::from_unix(seconds));
+    }
+
+    let date = value.get(..10)?;
+    let mut parts = date.split('-');
+    let year = parts.next()?.parse().ok()?;
+    let month = parts.next()?.parse().ok()?;
+    let day = parts.next()?.parse().ok()?;
+    let mut result = Self {
+      year,
+      month,
+      day,
+      hour: 0,
+      minute: 0,
+      second: 0,
+    };
+
+    let time = value.get(10..).unwrap_or_default();
+
+    if let Some(time) = time.strip_prefix(['T', 't', ' ']) {
+      let mut parts = time.get(..8)?.split(':');
+      result.hour = parts.next()?.parse().ok()?;
+      result.minute = parts.next()?.parse().ok()?;
+      result.second = parts.next()?.parse().ok()?;
+    } else if !time.is_empty() {
+      return None;
+    }
+
+    result.is_valid().then_some(result)
+  }
+
+  /// Convert seconds since the unix epoch into a UTC date.
+  pub fn from_unix(seconds: i64) -> Self {
+    let days = seconds.div_euclid(86_400);
+    let remainder = seconds.rem_euclid(86_400);
+    let (year, month, day) = civil_from_days(days);
+
+    Self {
+      year,
+      month,
+      day,
+      hour: (remainder / 3600) as u32,
+      minute: (remainder % 3600 / 60) as u32,
+      second: (remainder % 60) as u32,
+    }
+  }
+
+  fn is_valid(&self) -> bool {
+    (1..=12).contains(&self.month)
+      && (1..=31).contains(&self.day)
+      && self.hour < 24
+      && self.minute < 60
+      && self.second < 61
+  }
+
+  /// The day of the week, where 0 is Monday.
+  fn weekday(&self) -> usize {
+    let days = days_from_civil(self.year, self.month, self.day);
+    // The unix epoch was a Thursday.
+    (days + 3).rem_euclid(7) as usize
+  }
+
+  /// Format the date with `strftime` style specifiers. The supported
+  /// specifiers are `%Y %y %m %d %e %H %M %S %B %b %A %a %j %%`.
+  pub fn format(&self, format: &str) -> String {
+    let mut result = String::with_capacity(format.len());
+    let mut chars = format.chars();
+    let month_name = MONTHS
+      .get(self.month as usize - 1)
+      .copied()
+      .unwrap_or_default();
+    let weekday_name = WEEKDAYS.get(self.weekday()).copied().unwrap_or_default();
+
+    while let Some(ch) = chars.next() {
+      if ch != '%' {
+        result.push(ch);
+        continue;
+      }
+
+      match chars.next() {
+        Some('Y') => result.push_str(&self.year.to_string()),
+        Some('y') => result.push_str(&format!("{:02}", self.year.rem_euclid(100))),
+        Some('m') => result.push_str(&format!("{:02}", self.month)),
+        Some('d') => result.push_str(&format!("{:02}", self.day)),
+        Some('e') => result.push_str(&self.day.to_string()),
+        Some('H') => result.push_str(&format!("{:02}", self.hour)),
+        Some('M') => result.push_str(&format!("{:02}", self.minute)),
+        Some('S') => result.push_str(&format!("{:02}", self.second)),
+        Some('B') => result.push_str(month_name),
+        Some('b') => result.push_str(month_name.get(..3).unwrap_or_default()),
+        Some('A') => result.push_str(weekday_name),
+        Some('a') => result.push_str(weekday_name.get(..3).unwrap_or_default()),
+        Some('j') => {
+          let start = days_from_civil(self.year, 1, 1);
+          let day = days_from_civil(self.year, self.month, self.day) - start + 1;
+          result.push_str(&format!("{day:03}"));
+        }
+        Some('%') => result.push('%'),
+        Some(other) => {
+          result.push('%');
+          result.push(other);
+        }
+        None => result.push('%'),
+      }
+    }
+
+    result
+  }
+}
+
+/// Reformat a date, returning `None` when it can't be parsed.
+pub fn format_date(value: &str, format: &str) -> Option<String> {
+  DateTime::parse(value).map(|date| date.format(format))
+}
+
+// The conversions between days since the epoch and civil dates are from
+// http://howardhinnant.github.io/date_algorithms.html
+fn civil_from_days(days: i64) -> (i64, u32, u32) {
+  let days = days + 719_468;
+  let era = days.div_euclid(146_097);
+  let day_of_era = days.rem_euclid(146_097);
+  let year_of_era =
+    (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+  let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+  let month_index = (5 * day_of_year + 2) / 153;
+  let day = (day_of_year - (153 * month_index + 2) / 5 + 1) as u32;
+  let month = if month_index < 10 {
+    month_index + 3
+  } else {
+    month_index - 9
+  } as u32;
+  let year = year_of_era + era * 400 + i64::from(month <= 2);
+
+  (year, month, day)
+}
+
+fn days_from_civil(year: i64, month: u32, day: u32) -> i64 {
+  let year = if month <= 2 { year - 1 } else { year };
+  let era = year.div_euclid(400);
+  let year_of_era = year.rem_euclid(400);
+  let month = i64::from(month);
+  let month_index = if month > 2 { month - 3 } else { month + 9 };
+  let day_of_year = (153 * month_index + 2) / 5 + i64::from(day) - 1;
+  let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+
+  era * 146_097 + day_of_era - 719_468
+}
@@ -0,0 +1,50 @@
+use std::fmt;
+use std::path::PathBuf;
+
+use crate::CONFIG_FILE_NAME;
+
+/// A deprecated feature which the project still uses.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Deprecation {
+  /// The file which uses the feature, relative to the root.
+  pub file: PathBuf,
+  /// The deprecated feature, e.g. the `[chains]` table.
+  pub feature: String,
+  /// The version which deprecated the feature.
+  pub since: &'static str,
+  /// The version which will remove the feature.
+  pub removal: &'static str,
+  /// How to migrate away from the feature.
+  pub hint: String,
+}
+
+impl fmt::Display for Deprecation {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    write!(
+      f,
+      "{} is deprecated since {} and will be removed in {}, {}",
+      self.feature, self.since, self.removal, self.hint
+    )
+  }
+}
+
+/// Find the deprecated settings within the content of an `mdt.toml` file.
+pub fn find_config_deprecations(content: &str) -> Vec<Deprecation> {
+  let Ok(toml::Value::Table(table)) = toml::from_str::<toml::Value>(content) else {
+    return vec![];
+  };
+
+  let mut deprecations = vec![];
+
+  if table.contains_key("chains") {
+    deprecations.push(Deprecation {
+      file: PathBuf::from(CONFIG_FILE_NAME),
+      feature: "the `[chains]` table".into(),
+      since: "0.1.0",
+      removal: "1.0.0",
+      hint: "rename it to `[transformer_aliases]`".into(),
+    });
+  }
+
+  deprecations
+}
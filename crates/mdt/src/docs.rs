@@ -0,0 +1,48 @@
+use std::path::Path;
+
+use crate::content_hash;
+use crate::guard_injected_tags;
+use crate::MdtResult;
+use crate::Project;
+
+/// The file which `mdt docs sync` writes when no index is configured.
+pub const DEFAULT_INDEX_FILE: &str = "readme.md";
+
+/// Render the index of a template pack, which lists every provider with its
+/// description, a preview and an example of how to consume it. Each preview
+/// is a consumer, so `mdt update` keeps it current between syncs.
+pub fn render_provider_index(project: &Project, index: &Path) -> MdtResult<String> {
+  let style = project.config.comment_style(index);
+  let mut providers = project.providers.values().collect::<Vec<_>>();
+  providers.sort_by(|a, b| a.block.name.cmp(&b.block.name));
+
+  let mut content = String::from(
+    "# Providers\n\nThis index is generated with `mdt docs sync`. Each preview is kept up to date \
+     by `mdt update`.\n",
+  );
+
+  for provider in providers {
+    let name = &provider.block.name;
+    let rendered = project.render_provider(provider)?;
+    let preview = guard_injected_tags(index, &rendered, &project.config).content;
+    let closing = if project.config.update.content_hash {
+      format!("{{/{name}|hash:\"{}\"}}", content_hash(&preview))
+    } else {
+      format!("{{/{name}}}")
+    };
+
+    content.push_str(&format!("\n## `{name}`\n\n"));
+
+    if let Some(description) = provider.description.as_deref() {
+      content.push_str(&format!("{description}\n\n"));
+    }
+
+    content.push_str(&format!(
+      "{}{preview}{}\n\n```md\n<!-- {{={name}}} -->\n<!-- {{/{name}}} -->\n```\n",
+      style.wrap(&format!("{{={name}}}")),
+      style.wrap(&closing),
+    ));
+  }
+
+  Ok(content)
+}
@@ -0,0 +1,73 @@
+use std::path::Path;
+use std::process::Command;
+
+use serde_json::Value;
+
+use crate::output_with_input;
+use crate::EncryptionConfig;
+use crate::MdtError;
+use crate::MdtResult;
+
+/// The first line of content encrypted with `age --armor`.
+pub const AGE_ARMOR_HEADER: &str = "-----BEGIN AGE ENCRYPTED FILE-----";
+
+/// Whether the provider content is stored encrypted, either armored by `age`
+/// or as a `sops` document.
+pub fn is_encrypted(content: &str) -> bool {
+  let content = content.trim();
+
+  content.starts_with(AGE_ARMOR_HEADER)
+    || (content.starts_with('{')
+      && serde_json::from_str::<Value>(content).map_or(false, |value| value.get("sops").is_some()))
+}
+
+/// Decrypt the content of the provider with the configured key command,
+/// which receives the encrypted content on stdin and prints the plain text.
+/// The whitespace around the encrypted content is kept so the provider
+/// renders like an unencrypted one.
+pub fn decrypt_content(
+  name: &str,
+  content: &str,
+  config: &EncryptionConfig,
+  root: &Path,
+) -> MdtResult<String> {
+  let error = |message: String| {
+    MdtError::Decryption {
+      name: name.to_string(),
+      message,
+    }
+  };
+  let Some(key_command) = config.command.as_deref() else {
+    return Err(error("no key command is configured".into()));
+  };
+
+  let mut command = Command::new("sh");
+  command.arg("-c").arg(key_command);
+
+  if !root.as_os_str().is_empty() {
+    command.current_dir(root);
+  }
+
+  let (output, written) =
+    output_with_input(&mut command, content.trim()).map_err(|e| error(e.to_string()))?;
+
+  // A command which fails can exit before reading its input, so the failure
+  // is reported from its exit status rather than the broken pipe.
+  if !output.status.success() {
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    return Err(error(format!("{}: {}", output.status, stderr.trim())));
+  }
+
+  written.map_err(|e| error(format!("the content couldn't be written: {e}")))?;
+
+  let decrypted = String::from_utf8(output.stdout).map_err(|e| error(e.to_string()))?;
+  let leading = content
+    .get(..content.len() - content.trim_start().len())
+    .unwrap_or_default();
+  let trailing = content.get(content.trim_end().len()..).unwrap_or_default();
+
+  Ok(format!(
+    "{leading}{}{trailing}",
+    decrypted.trim_matches('\n')
+  ))
+}
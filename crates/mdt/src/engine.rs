@@ -0,0 +1,564 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::ops::Range;
+use std::path::Path;
+use std::path::PathBuf;
+use std::thread;
+
+use crate::apply_transformers_with_context;
+use crate::check_sections;
+use crate::concatenated_names;
+use crate::find_includes;
+use crate::guard_injected_tags;
+use crate::is_valid_block_name;
+use crate::parse_transformers;
+use crate::suggest_block_name;
+use crate::ConsumerEntry;
+use crate::GuardedContent;
+use crate::MdtError;
+use crate::MdtResult;
+use crate::Project;
+use crate::CONCAT_SEPARATOR;
+
+/// The files which need to be written to bring every consumer up to date.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct UpdateResult {
+  /// The new content for each file that changed, ordered by path.
+  pub updated_files: BTreeMap<PathBuf, String>,
+  /// The number of patches which were applied.
+  pub updated_count: usize,
+  /// The consumers which were edited by hand since they were last written, as
+  /// `(file, name)` pairs. They are left untouched.
+  pub conflicts: Vec<(PathBuf, String)>,
+}
+
+impl UpdateResult {
+  pub fn is_empty(&self) -> bool {
+    self.updated_files.is_empty()
+  }
+}
+
+/// Render the content a consumer should contain. Returns `None` when there is
+/// no provider with a matching name. Tags in the content which would change
+/// the blocks of the file are escaped.
+pub fn render_consumer(project: &Project, consumer: &ConsumerEntry) -> MdtResult<Option<String>> {
+  Ok(render_guarded_consumer(project, consumer)?.map(|guarded| guarded.content))
+}
+
+/// Render the content a consumer should contain along with the tags which
+/// were escaped so they can't change the blocks of the file.
+pub fn render_guarded_consumer(
+  project: &Project,
+  consumer: &ConsumerEntry,
+) -> MdtResult<Option<GuardedContent>> {
+  let rendered = project.render_nested_consumer(consumer, &mut vec![])?;
+
+  Ok(rendered.map(|content| guard_injected_tags(&consumer.file, &content, &project.config)))
+}
+
+/// Render a provider by name and then apply a transformer chain, e.g.
+/// `slackFormat`, as if it were rendered into a consumer with those
+/// transformers.
+pub fn render_provider_with(project: &Project, name: &str, chain: &str) -> MdtResult<String> {
+  let Some(provider) = project.providers.get(name) else {
+    return Err(MdtError::UnknownBlock(name.to_string()));
+  };
+
+  let transformers = parse_transformers(chain, &project.config)?;
+  let content = project.render_provider(provider)?;
+  check_sections(name, &content, &transformers)?;
+  let context = project.transform_context(provider, None);
+
+  apply_transformers_with_context(content, &transformers, &context)
+}
+
+/// The namespace which holds the tag and version while rendering release
+/// notes.
+pub const RELEASE_NAMESPACE: &str = "release";
+
+/// Render the provider which holds the release notes for the tag. The tag is
+/// available to the template as `release.tag` and as `release.version`
+/// without the leading `v`.
+pub fn render_release_notes(project: &Project, name: &str, tag: &str) -> MdtResult<String> {
+  let Some(provider) = project.providers.get(name) else {
+    return Err(MdtError::UnknownBlock(name.to_string()));
+  };
+
+  let mut release_project = project.clone();
+  release_project.data.insert(
+    RELEASE_NAMESPACE.into(),
+    serde_json::json!({
+      "tag": tag,
+      "version": tag.trim_start_matches('v'),
+    }),
+  );
+
+  let notes = release_project.render_provider(provider)?;
+
+  Ok(notes.trim_matches('\n').to_string())
+}
+
+/// A replacement of a byte range within a file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Patch {
+  pub file: PathBuf,
+  /// The name of the block being updated.
+  pub name: String,
+  /// The byte range of the content being replaced.
+  pub range: Range<usize>,
+  /// The content which should replace the range.
+  pub replacement: String,
+}
+
+/// Compute a patch for every stale consumer which isn't frozen. When
+/// `content_hash` is enabled in the `[update]` configuration the closing tag
+/// records the hash of the new content and consumers which were edited by
+/// hand are skipped. The patches are ordered by file and then by their
+/// position within the file. An error is returned when a provider template
+/// fails to render or a consumer selects a section which its provider doesn't
+/// contain.
+pub fn compute_patches(project: &Project) -> MdtResult<Vec<Patch>> {
+  compute_patches_for(project, |_| true)
+}
+
+/// Compute the patches for the stale consumers which match the filter.
+fn compute_patches_for(
+  project: &Project,
+  filter: impl Fn(&ConsumerEntry) -> bool,
+) -> MdtResult<Vec<Patch>> {
+  let mut patches = vec![];
+
+  let hashed = project.config.update.content_hash;
+
+  for consumer in project.consumers.iter().filter(|consumer| filter(consumer)) {
+    if consumer.is_frozen() || (hashed && consumer.is_edited()) {
+      continue;
+    }
+
+    let Some(content) = render_consumer(project, consumer)? else {
+      continue;
+    };
+
+    if !hashed {
+      if content == consumer.content {
+        continue;
+      }
+
+      patches.push(Patch {
+        file: consumer.file.clone(),
+        name: consumer.block.name.clone(),
+        range: consumer.block.content_range(),
+        replacement: content,
+      });
+      continue;
+    }
+
+    let hash = content_hash(&content);
+
+    if content == consumer.content && consumer.block.hash.as_ref() == Some(&hash) {
+      continue;
+    }
+
+    // The closing tag is rewritten to record the hash of the new content.
+    let name = &consumer.block.name;
+    let closing = project
+      .config
+      .comment_style(&consumer.file)
+      .wrap(&format!("{{/{name}|hash:\"{hash}\"}}"));
+    patches.push(Patch {
+      file: consumer.file.clone(),
+      name: name.clone(),
+      range: consumer.block.content_range().start..consumer.block.closing.end.offset,
+      replacement: format!("{content}{closing}"),
+    });
+  }
+
+  patches.sort_by(|a, b| a.file.cmp(&b.file).then(a.range.start.cmp(&b.range.start)));
+
+  // The content of nested consumers is already part of the patch for the
+  // consumer which contains them.
+  let mut outer: Option<(PathBuf, Range<usize>)> = None;
+  patches.retain(|patch| {
+    let is_nested = outer.as_ref().map_or(false, |(file, range)| {
+      file == &patch.file && range.start <= patch.range.start && patch.range.end <= range.end
+    });
+
+    if !is_nested {
+      outer = Some((patch.file.clone(), patch.range.clone()));
+    }
+
+    !is_nested
+  });
+
+  Ok(patches)
+}
+
+/// Compute the patches which rename a block. The name is replaced in the
+/// opening and closing tags of the provider and of every consumer, in the
+/// matching segment of concatenated consumers, e.g. `{=old+other}`, and in the
+/// include tags of providers, e.g. `{> old}`.
+///
+/// The new name must be a valid block name which follows the configured naming
+/// policy and must not be used by another provider. The returned errors include
+/// a suggested alternative.
+pub fn compute_rename(project: &Project, old_name: &str, new_name: &str) -> MdtResult<Vec<Patch>> {
+  validate_rename(project, old_name, new_name)?;
+
+  if old_name == new_name {
+    return Ok(vec![]);
+  }
+
+  let provider = project.providers.get(old_name);
+  let blocks = provider
+    .map(|provider| (&provider.file, &provider.block))
+    .into_iter()
+    .chain(
+      project
+        .consumers
+        .iter()
+        .filter(|consumer| {
+          !consumer.block.is_inline()
+            && concatenated_names(&consumer.block.name).any(|name| name == old_name)
+        })
+        .map(|consumer| (&consumer.file, &consumer.block)),
+    );
+
+  let mut patches = vec![];
+  let mut rename = |file: &PathBuf, range: Range<usize>| {
+    patches.push(Patch {
+      file: file.clone(),
+      name: old_name.to_string(),
+      range,
+      replacement: new_name.to_string(),
+    });
+  };
+
+  for (file, block) in blocks {
+    let content = project
+      .files
+      .get(file)
+      .map(String::as_str)
+      .unwrap_or_default();
+
+    for position in [block.opening_name, block.closing_name] {
+      for range in name_segments(
+        content,
+        position.start.offset..position.end.offset,
+        old_name,
+      ) {
+        rename(file, range);
+      }
+    }
+  }
+
+  let mut provider_files = project
+    .providers
+    .values()
+    .map(|provider| &provider.file)
+    .collect::<Vec<_>>();
+  provider_files.sort();
+  provider_files.dedup();
+
+  for file in provider_files {
+    let content = project
+      .files
+      .get(file)
+      .map(String::as_str)
+      .unwrap_or_default();
+
+    for include in find_includes(content, &project.config)? {
+      if include.name == old_name {
+        rename(
+          file,
+          include.name_position.start.offset..include.name_position.end.offset,
+        );
+      }
+    }
+  }
+
+  patches.sort_by(|a, b| a.file.cmp(&b.file).then(a.range.start.cmp(&b.range.start)));
+
+  Ok(patches)
+}
+
+/// The ranges of the segments of the concatenated name within `range` which
+/// equal `name`. A name without `+` is a single segment.
+fn name_segments(content: &str, range: Range<usize>, name: &str) -> Vec<Range<usize>> {
+  let Some(text) = content.get(range.clone()) else {
+    return vec![];
+  };
+  let mut start = range.start;
+  let mut segments = vec![];
+
+  for segment in concatenated_names(text) {
+    if segment == name {
+      segments.push(start..start + segment.len());
+    }
+
+    start += segment.len() + CONCAT_SEPARATOR.len_utf8();
+  }
+
+  segments
+}
+
+/// The name of the block whose tag contains the byte offset of the file, e.g.
+/// the block under the cursor of an editor which renames it. Within a
+/// concatenated consumer, e.g. `{=intro+install}`, it is the name of the
+/// segment at the offset.
+pub fn block_name_at(project: &Project, file: &Path, offset: usize) -> MdtResult<Option<String>> {
+  let Some(content) = project.files.get(file) else {
+    return Ok(None);
+  };
+
+  let blocks = project
+    .providers
+    .values()
+    .filter(|provider| provider.file == file)
+    .map(|provider| &provider.block)
+    .chain(
+      project
+        .consumers
+        .iter()
+        .filter(|consumer| consumer.file == file)
+        .map(|consumer| &consumer.block),
+    );
+  let mut names = blocks
+    .flat_map(|block| [block.opening_name, block.closing_name])
+    .collect::<Vec<_>>();
+  names.extend(
+    find_includes(content, &project.config)?
+      .into_iter()
+      .map(|include| include.name_position),
+  );
+
+  for position in names {
+    let range = position.start.offset..position.end.offset;
+
+    if !(range.start..=range.end).contains(&offset) {
+      continue;
+    }
+
+    let Some(text) = content.get(range.clone()) else {
+      continue;
+    };
+    let mut start = range.start;
+
+    for segment in concatenated_names(text) {
+      if offset <= start + segment.len() {
+        return Ok(Some(segment.to_string()));
+      }
+
+      start += segment.len() + CONCAT_SEPARATOR.len_utf8();
+    }
+  }
+
+  Ok(None)
+}
+
+/// Check that `old_name` exists and that `new_name` is a valid, unused block
+/// name.
+pub fn validate_rename(project: &Project, old_name: &str, new_name: &str) -> MdtResult<()> {
+  let exists = project.providers.contains_key(old_name)
+    || project
+      .consumers
+      .iter()
+      .any(|consumer| concatenated_names(&consumer.block.name).any(|name| name == old_name));
+
+  if !exists {
+    return Err(MdtError::UnknownBlock(old_name.to_string()));
+  }
+
+  if old_name == new_name {
+    return check_block_name(project, new_name);
+  }
+
+  validate_new_block_name(project, new_name)
+}
+
+/// Check that `name` is a valid block name which no provider uses yet. A
+/// collision suggests the first free name with a numeric suffix, e.g.
+/// `install2`.
+pub fn validate_new_block_name(project: &Project, name: &str) -> MdtResult<()> {
+  check_block_name(project, name)?;
+
+  if let Some(existing) = project.providers.get(name) {
+    let suggestion = (2..)
+      .map(|index| format!("{name}{index}"))
+      .find(|candidate| !project.providers.contains_key(candidate))
+      .unwrap_or_default();
+
+    return Err(MdtError::BlockNameCollision {
+      name: name.to_string(),
+      file: existing.file.clone(),
+      suggestion,
+    });
+  }
+
+  Ok(())
+}
+
+/// Check that the name is valid and follows the naming policy.
+fn check_block_name(project: &Project, name: &str) -> MdtResult<()> {
+  if !is_valid_block_name(name) {
+    return Err(MdtError::InvalidBlockName {
+      name: name.to_string(),
+      suggestion: suggest_block_name(name)
+        .filter(|suggestion| !project.providers.contains_key(suggestion)),
+    });
+  }
+
+  project.config.naming.check(name)
+}
+
+/// Group the patches by file and apply them to the project's file contents.
+pub fn apply_project_patches(project: &Project, patches: &[Patch]) -> UpdateResult {
+  let mut result = UpdateResult::default();
+  let mut patches_by_file = BTreeMap::<&PathBuf, Vec<&Patch>>::new();
+
+  for patch in patches {
+    patches_by_file.entry(&patch.file).or_default().push(patch);
+  }
+
+  for (file, file_patches) in patches_by_file {
+    let Some(original) = project.files.get(file) else {
+      continue;
+    };
+
+    result.updated_count += file_patches.len();
+    result
+      .updated_files
+      .insert(file.clone(), apply_patches(original, file_patches));
+  }
+
+  result
+}
+
+/// Apply the patches to the content. The patches must belong to the same file
+/// and must not overlap. Bytes outside the patched ranges are copied as is.
+pub fn apply_patches<'a>(content: &str, patches: impl IntoIterator<Item = &'a Patch>) -> String {
+  let mut patches = patches.into_iter().collect::<Vec<_>>();
+  patches.sort_by_key(|patch| patch.range.start);
+
+  let mut updated = String::with_capacity(content.len());
+  let mut cursor = 0;
+
+  for patch in patches {
+    updated.push_str(content.get(cursor..patch.range.start).unwrap_or_default());
+    updated.push_str(&patch.replacement);
+    cursor = patch.range.end;
+  }
+
+  updated.push_str(content.get(cursor..).unwrap_or_default());
+  updated
+}
+
+/// Compute the new content for every file with stale consumers. Only the
+/// regions between the tags of stale consumers are replaced, the rest of the
+/// file is copied byte for byte.
+pub fn compute_updates(project: &Project) -> MdtResult<UpdateResult> {
+  compute_updates_for(project, |_| true)
+}
+
+/// Compute the new content for the stale consumers of one block, e.g. to
+/// accept a single change while other blocks are stale. Consumers which
+/// concatenate the block, e.g. `usage+install`, are included. When a file is
+/// given only its consumers are updated.
+pub fn compute_block_updates(
+  project: &Project,
+  name: &str,
+  file: Option<&Path>,
+) -> MdtResult<UpdateResult> {
+  let consumes = |consumer: &ConsumerEntry| {
+    concatenated_names(&consumer.block.name).any(|candidate| candidate == name)
+  };
+
+  if !project.providers.contains_key(name) && !project.consumers.iter().any(consumes) {
+    return Err(MdtError::UnknownBlock(name.to_string()));
+  }
+
+  compute_updates_for(project, |consumer| {
+    consumes(consumer) && file.map_or(true, |file| consumer.file == file)
+  })
+}
+
+fn compute_updates_for(
+  project: &Project,
+  filter: impl Fn(&ConsumerEntry) -> bool,
+) -> MdtResult<UpdateResult> {
+  let patches = compute_patches_for(project, &filter)?;
+  let mut result = apply_project_patches(project, &patches);
+
+  if project.config.update.content_hash {
+    result.conflicts = project
+      .consumers
+      .iter()
+      .filter(|consumer| filter(consumer) && !consumer.is_frozen() && consumer.is_edited())
+      .map(|consumer| (consumer.file.clone(), consumer.block.name.clone()))
+      .collect();
+  }
+
+  Ok(result)
+}
+
+/// A short hash of the content which is recorded in closing tags to detect
+/// manual edits. It uses 64 bit FNV-1a, folded to 32 bits, so the hash is
+/// stable across platforms and releases.
+pub fn content_hash(content: &str) -> String {
+  let hash = content
+    .bytes()
+    .fold(0xcbf2_9ce4_8422_2325_u64, |hash, byte| {
+      (hash ^ u64::from(byte)).wrapping_mul(0x0100_0000_01b3)
+    });
+
+  format!("{:08x}", (hash ^ (hash >> 32)) as u32)
+}
+
+/// Write the updated files to disk in parallel. Files whose content on disk
+/// already matches are left untouched so their modification time is
+/// preserved. When several writes fail the error for the first file (by path)
+/// is returned so the outcome doesn't depend on thread scheduling.
+pub fn write_updates(result: &UpdateResult) -> MdtResult<()> {
+  let files = result.updated_files.iter().collect::<Vec<_>>();
+
+  if files.is_empty() {
+    return Ok(());
+  }
+
+  let threads = thread::available_parallelism()
+    .map_or(1, |threads| threads.get())
+    .min(files.len());
+  let chunk_size = (files.len() + threads - 1) / threads;
+
+  let results = thread::scope(|scope| {
+    let handles = files
+      .chunks(chunk_size)
+      .map(|chunk| {
+        scope.spawn(move || {
+          chunk
+            .iter()
+            .map(|(file, content)| write_if_changed(file, content))
+            .collect::<Vec<_>>()
+        })
+      })
+      .collect::<Vec<_>>();
+
+    handles
+      .into_iter()
+      .flat_map(|handle| handle.join().expect("update writer thread panicked"))
+      .collect::<Vec<_>>()
+  });
+
+  results.into_iter().collect()
+}
+
+fn write_if_changed(file: &PathBuf, content: &str) -> MdtResult<()> {
+  if let Ok(existing) = fs::read_to_string(file) {
+    if existing == content {
+      return Ok(());
+    }
+  }
+
+  fs::write(file, content)?;
+
+  Ok(())
+}
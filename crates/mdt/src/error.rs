@@ -1,3 +1,5 @@
+use std::path::PathBuf;
+
 use miette::Diagnostic;
 use thiserror::Error;
 
@@ -16,6 +18,134 @@ pub enum MdtError {
   #[error("invalid token sequence")]
   #[diagnostic(code(mdt::invalid_token_sequence))]
   InvalidTokenSequence(usize),
+  #[error("unknown transformer: `{0}`")]
+  #[diagnostic(code(mdt::unknown_transformer))]
+  UnknownTransformer(String),
+  #[error("unknown transformer alias: `@{0}`")]
+  #[diagnostic(
+    code(mdt::unknown_transformer_alias),
+    help("aliases are defined in the `[transformer_aliases]` section of `mdt.toml`")
+  )]
+  UnknownTransformerAlias(String),
+  #[error("the transformer `{0}` isn't allowed in this project")]
+  #[diagnostic(
+    code(mdt::denied_transformer),
+    help(
+      "transformers are allowed with `allow` and denied with `deny` in the `[transformers]` \
+       section of `mdt.toml`"
+    )
+  )]
+  DeniedTransformer(String),
+  #[error("invalid arguments for transformer `{name}`: {message}")]
+  #[diagnostic(code(mdt::invalid_transformer_arguments))]
+  InvalidTransformerArguments { name: String, message: String },
+  #[error(
+    "invalid block name `{name}`{}",
+    suggestion.as_ref().map(|suggestion| format!(", try `{suggestion}`")).unwrap_or_default()
+  )]
+  #[diagnostic(
+    code(mdt::invalid_block_name),
+    help(
+      "block names must start with a letter or `_` followed by letters, digits or `_`, and can be \
+       namespaced with `/` or `:`"
+    )
+  )]
+  InvalidBlockName {
+    name: String,
+    suggestion: Option<String>,
+  },
+  #[error("a provider named `{name}` already exists in `{}`, try `{suggestion}`", file.display())]
+  #[diagnostic(code(mdt::block_name_collision))]
+  BlockNameCollision {
+    name: String,
+    file: PathBuf,
+    suggestion: String,
+  },
+  #[error("no block named `{0}` exists")]
+  #[diagnostic(code(mdt::unknown_block))]
+  UnknownBlock(String),
+  #[error("no package named `{0}` exists")]
+  #[diagnostic(
+    code(mdt::unknown_package),
+    help("packages are directories with a `Cargo.toml` or `package.json` which has a name")
+  )]
+  UnknownPackage(String),
+  #[error("`{}` isn't a template file", .0.display())]
+  #[diagnostic(
+    code(mdt::not_a_template_file),
+    help("providers are defined in files ending with `.t.md`")
+  )]
+  NotATemplateFile(PathBuf),
+  #[error("the glob `{glob}` is invalid: {message}")]
+  #[diagnostic(code(mdt::invalid_glob))]
+  InvalidGlob { glob: String, message: String },
+  #[error("duplicate provider `{name}` found in `{}` and `{}`", first.display(), second.display())]
+  #[diagnostic(code(mdt::duplicate_provider))]
+  DuplicateProvider {
+    name: String,
+    first: PathBuf,
+    second: PathBuf,
+  },
+  #[error("invalid configuration: {0}")]
+  #[diagnostic(code(mdt::invalid_config))]
+  InvalidConfig(String),
+  #[error("block name `{name}` violates the naming policy: {message}")]
+  #[diagnostic(
+    code(mdt::block_name_policy),
+    help("the naming policy is configured in the `[naming]` section of `mdt.toml`")
+  )]
+  BlockNamePolicy { name: String, message: String },
+  #[error("the provider for `{name}` has no section with the heading `{section}`")]
+  #[diagnostic(code(mdt::missing_section))]
+  MissingSection { name: String, section: String },
+  #[error("the provider for `{name}` has no region named `{region}`")]
+  #[diagnostic(
+    code(mdt::missing_region),
+    help("regions are marked with `<!-- {{#region: name}} -->` and `<!-- {{/region}} -->`")
+  )]
+  MissingRegion { name: String, region: String },
+  #[error("failed to load data file `{}`: {message}", path.display())]
+  #[diagnostic(code(mdt::data_file))]
+  DataFile { path: PathBuf, message: String },
+  #[error("failed to read the ignore file `{}`: {message}", path.display())]
+  #[diagnostic(code(mdt::ignore_file))]
+  IgnoreFile { path: PathBuf, message: String },
+  #[error("invalid data for block `{name}`: {message}")]
+  #[diagnostic(
+    code(mdt::invalid_block_data),
+    help("data overrides are a json object, e.g. `{{=install data:{{\"pm\":\"pnpm\"}}}}`")
+  )]
+  InvalidBlockData { name: String, message: String },
+  #[error("failed to render the template for `{name}`: {message}")]
+  #[diagnostic(code(mdt::template))]
+  Template { name: String, message: String },
+  #[error("the block `{0}` contains itself through its nested blocks")]
+  #[diagnostic(code(mdt::circular_block))]
+  CircularBlock(String),
+  #[error("circular include: {0}")]
+  #[diagnostic(
+    code(mdt::circular_include),
+    help("a provider can't include itself, directly or through other providers")
+  )]
+  CircularInclude(String),
+  #[error("invalid expression `{expression}`: {message}")]
+  #[diagnostic(code(mdt::invalid_expression))]
+  InvalidExpression { expression: String, message: String },
+  #[error("the transformer `{name}` failed: {message}")]
+  #[diagnostic(code(mdt::transformer_failed))]
+  TransformerFailed { name: String, message: String },
+  #[error("failed to decrypt the provider `{name}`: {message}")]
+  #[diagnostic(
+    code(mdt::decryption),
+    help("the key command is configured in the `[encryption]` section of `mdt.toml`")
+  )]
+  Decryption { name: String, message: String },
+  #[error("the command `{command}` failed: {message}")]
+  #[diagnostic(code(mdt::vcs))]
+  Vcs { command: String, message: String },
+  #[error("the command `{command}` failed: {message}")]
+  #[diagnostic(code(mdt::command_failed))]
+  CommandFailed { command: String, message: String },
 }
 
 pub type MdtResult<T> = std::result::Result<T, MdtError>;
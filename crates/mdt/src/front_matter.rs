@@ -0,0 +1,115 @@
+use serde::Deserialize;
+
+use crate::ProviderScope;
+
+/// The metadata at the start of a definition file which applies to all of its
+/// providers. Both YAML (`---`) and TOML (`+++`) front matter are supported.
+///
+/// ```md
+/// ---
+/// description: Snippets shared by every readme
+/// tags: [readme, rust]
+/// scope: local
+/// ---
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(default)]
+pub struct FrontMatter {
+  pub description: Option<String>,
+  pub tags: Vec<String>,
+  /// Overrides the `[workspace] scope` of the project for the providers.
+  pub scope: Option<ProviderScope>,
+}
+
+impl FrontMatter {
+  /// Parse the front matter from the start of the content. The default is
+  /// returned when the content has no front matter or it can't be parsed.
+  pub fn parse(content: &str) -> Self {
+    let mut lines = content.lines();
+    let Some(delimiter) = lines.next().map(str::trim_end) else {
+      return Self::default();
+    };
+
+    if delimiter != "---" && delimiter != "+++" {
+      return Self::default();
+    }
+
+    let body = lines
+      .take_while(|line| line.trim_end() != delimiter)
+      .collect::<Vec<_>>();
+
+    if delimiter == "+++" {
+      return toml::from_str(&body.join("\n")).unwrap_or_default();
+    }
+
+    parse_yaml(&body)
+  }
+}
+
+/// Read the supported keys from simple YAML front matter. Values can be plain
+/// or quoted strings and tags can be an inline list or a list of `- item`
+/// lines.
+fn parse_yaml(lines: &[&str]) -> FrontMatter {
+  let mut front_matter = FrontMatter::default();
+  let mut in_tags = false;
+
+  for line in lines {
+    if in_tags {
+      if let Some(item) = line.trim_start().strip_prefix("- ") {
+        front_matter.tags.push(unquote(item).to_string());
+        continue;
+      }
+
+      in_tags = false;
+    }
+
+    let Some((key, value)) = line.split_once(':') else {
+      continue;
+    };
+
+    let value = value.trim();
+
+    match key.trim_end() {
+      "description" => {
+        front_matter.description =
+          Some(unquote(value).to_string()).filter(|value| !value.is_empty());
+      }
+      "scope" => {
+        front_matter.scope = match unquote(value) {
+          "local" => Some(ProviderScope::Local),
+          "workspace" => Some(ProviderScope::Workspace),
+          _ => None,
+        };
+      }
+      "tags" if value.is_empty() => in_tags = true,
+      "tags" => {
+        front_matter.tags = value
+          .trim_start_matches('[')
+          .trim_end_matches(']')
+          .split(',')
+          .map(unquote)
+          .filter(|tag| !tag.is_empty())
+          .map(String::from)
+          .collect();
+      }
+      _ => {}
+    }
+  }
+
+  front_matter
+}
+
+fn unquote(value: &str) -> &str {
+  let value = value.trim();
+
+  for quote in ['"', '\''] {
+    if let Some(inner) = value
+      .strip_prefix(quote)
+      .and_then(|value| value.strip_suffix(quote))
+    {
+      return inner;
+    }
+  }
+
+  value
+}
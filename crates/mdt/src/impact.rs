@@ -0,0 +1,159 @@
+use std::path::Path;
+use std::path::PathBuf;
+
+use crate::concatenated_names;
+use crate::is_definition_file;
+use crate::render_consumer;
+use crate::MdtResult;
+use crate::Position;
+use crate::Project;
+use crate::ProviderEntry;
+
+/// The consumers which an unsaved edit of a provider would make stale.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ProviderImpact {
+  pub name: String,
+  /// The opening tag of the provider within the edited content.
+  pub opening: Position,
+  /// The consumers whose rendered content changes, as `(file, name)` pairs.
+  pub consumers: Vec<(PathBuf, String)>,
+}
+
+impl ProviderImpact {
+  /// The number of files which contain the affected consumers.
+  pub fn file_count(&self) -> usize {
+    let mut files = self
+      .consumers
+      .iter()
+      .map(|(file, _)| file)
+      .collect::<Vec<_>>();
+    files.sort();
+    files.dedup();
+    files.len()
+  }
+}
+
+/// The impact of replacing the content of a definition file with an unsaved
+/// edit, for each provider in the edited content. Only the edited file is
+/// parsed again and its providers are swapped into the project while they are
+/// rendered, so the rest of the project isn't copied. Only the consumers of
+/// the providers whose rendered content changed are rendered. Frozen consumers
+/// and consumers within the file itself are skipped. The project is left as it
+/// was.
+pub fn provider_impact(
+  project: &mut Project,
+  file: &Path,
+  content: &str,
+) -> MdtResult<Vec<ProviderImpact>> {
+  if !is_definition_file(file) {
+    return Ok(vec![]);
+  }
+
+  let parsed = Project::from_files_with_config(
+    [(file.to_path_buf(), content.to_string())],
+    project.config.clone(),
+  )?;
+  let mut edited = parsed.providers.into_values().collect::<Vec<_>>();
+  edited.sort_by_key(|provider| provider.block.opening.start.offset);
+
+  let saved = edited
+    .iter()
+    .map(|provider| {
+      project
+        .providers
+        .get(&provider.block.name)
+        .map(|saved| project.render_provider(saved))
+        .transpose()
+    })
+    .collect::<MdtResult<Vec<_>>>()?;
+
+  // Swap the edited providers in, along with any provider they replace, and
+  // restore the saved ones even when rendering fails.
+  let swapped = project
+    .providers
+    .iter()
+    .filter(|(name, provider)| {
+      provider.file == file || edited.iter().any(|edited| &&edited.block.name == name)
+    })
+    .map(|(name, _)| name.clone())
+    .collect::<Vec<_>>()
+    .into_iter()
+    .filter_map(|name| project.providers.remove_entry(&name))
+    .collect::<Vec<_>>();
+  project.providers.extend(
+    edited
+      .iter()
+      .map(|provider| (provider.block.name.clone(), provider.clone())),
+  );
+
+  let rendered = render_edited(project, file, &edited, &saved);
+
+  for provider in &edited {
+    project.providers.remove(&provider.block.name);
+  }
+
+  project.providers.extend(swapped);
+
+  let mut impacts = vec![];
+
+  for (provider, consumers) in edited.iter().zip(rendered?) {
+    let mut stale = vec![];
+
+    for (index, content) in consumers {
+      let Some(consumer) = project.consumers.get(index) else {
+        continue;
+      };
+
+      if render_consumer(project, consumer)? != content {
+        stale.push((consumer.file.clone(), consumer.block.name.clone()));
+      }
+    }
+
+    impacts.push(ProviderImpact {
+      name: provider.block.name.clone(),
+      opening: provider.block.opening,
+      consumers: stale,
+    });
+  }
+
+  Ok(impacts)
+}
+
+/// The index and rendered content of each consumer of a provider.
+type RenderedConsumers = Vec<(usize, Option<String>)>;
+
+/// Render the consumers of each edited provider whose rendered content
+/// differs from the saved one, while the edited providers are swapped in. The
+/// consumers are returned by their index in the project.
+fn render_edited(
+  project: &Project,
+  file: &Path,
+  edited: &[ProviderEntry],
+  saved: &[Option<String>],
+) -> MdtResult<Vec<RenderedConsumers>> {
+  let mut rendered = vec![];
+
+  for (provider, saved) in edited.iter().zip(saved) {
+    let mut consumers = vec![];
+
+    if saved.as_ref() != Some(&project.render_provider(provider)?) {
+      for (index, consumer) in project
+        .consumers
+        .iter()
+        .enumerate()
+        .filter(|(_, consumer)| {
+          consumer.file != file
+            && !consumer.is_frozen()
+            && !consumer.block.is_inline()
+            && concatenated_names(&consumer.block.name).any(|name| name == provider.block.name)
+        })
+      {
+        consumers.push((index, render_consumer(project, consumer)?));
+      }
+    }
+
+    rendered.push(consumers);
+  }
+
+  Ok(rendered)
+}
@@ -0,0 +1,111 @@
+use std::ops::Range;
+use std::path::Path;
+
+use regex::Regex;
+
+use crate::comment_nodes;
+use crate::MdtConfig;
+
+/// Content rendered into a consumer after the tags which would change the
+/// blocks of the file have been escaped.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct GuardedContent {
+  pub content: String,
+  /// The tags which were escaped, e.g. `{/readme}`.
+  pub escaped: Vec<String>,
+}
+
+/// Escape the tags within content rendered into a consumer of the file which
+/// would otherwise end the consumer early or define blocks once the file is
+/// parsed again. Consumer tags which are paired within the content, e.g. a
+/// nested consumer, are kept. Escaped tags are written as `{{/name}}`, which
+/// the `unescape` transformer turns back into tags.
+pub fn guard_injected_tags(file: &Path, content: &str, config: &MdtConfig) -> GuardedContent {
+  let tags = injected_tags(file, content, config);
+  let mut open: Vec<usize> = vec![];
+  let mut unbalanced = vec![];
+
+  for (index, tag) in tags.iter().enumerate() {
+    match tag.kind {
+      '=' => open.push(index),
+      '/' => {
+        match open.last() {
+          Some(&last) if tags.get(last).map(|tag| tag.name.as_str()) == Some(&tag.name) => {
+            open.pop();
+          }
+          _ => unbalanced.push(index),
+        }
+      }
+      _ => unbalanced.push(index),
+    }
+  }
+
+  unbalanced.extend(open);
+  unbalanced.sort_unstable();
+
+  let mut guarded = GuardedContent::default();
+  let mut offset = 0;
+
+  for tag in unbalanced.into_iter().filter_map(|index| tags.get(index)) {
+    let text = content.get(tag.range.clone()).unwrap_or_default();
+    guarded
+      .content
+      .push_str(content.get(offset..tag.range.start).unwrap_or_default());
+    guarded.content.push_str(&format!("{{{text}}}"));
+    guarded.escaped.push(text.to_string());
+    offset = tag.range.end;
+  }
+
+  guarded
+    .content
+    .push_str(content.get(offset..).unwrap_or_default());
+  guarded
+}
+
+/// A tag within a comment of rendered content.
+struct InjectedTag {
+  /// The `=`, `@` or `/` which follows the opening brace.
+  kind: char,
+  name: String,
+  /// The byte range of the tag, including its braces.
+  range: Range<usize>,
+}
+
+/// The consumer, provider and closing tags within the comments the parser
+/// would read from the content, in order. Escaped tags are skipped.
+fn injected_tags(file: &Path, content: &str, config: &MdtConfig) -> Vec<InjectedTag> {
+  let Ok(pattern) = Regex::new(r"\{([=@/])([A-Za-z_][A-Za-z0-9_/:]*)(?:[^{}\n]|\{[^{}\n]*\})*\}")
+  else {
+    return vec![];
+  };
+
+  let mut tags = vec![];
+
+  for node in comment_nodes(file, content, config) {
+    let Some(start) = node.position.map(|position| position.start.offset) else {
+      continue;
+    };
+
+    for captures in pattern.captures_iter(&node.value) {
+      let (Some(tag), Some(kind), Some(name)) = (captures.get(0), captures.get(1), captures.get(2))
+      else {
+        continue;
+      };
+
+      let range = start + tag.start()..start + tag.end();
+      let escaped = range.start > 0 && content.get(range.start - 1..range.start) == Some("{");
+
+      if escaped {
+        continue;
+      }
+
+      tags.push(InjectedTag {
+        kind: kind.as_str().chars().next().unwrap_or_default(),
+        name: name.as_str().to_string(),
+        range,
+      });
+    }
+  }
+
+  tags
+}
@@ -1,11 +1,16 @@
 use markdown::mdast::Html;
 use snailquote::unescape;
 
+use crate::CommentStyle;
 use crate::MdtResult;
 use crate::Position;
 use crate::Token;
 use crate::TokenGroup;
 
+/// The prefix of the json object which overrides the data for a consumer,
+/// e.g. `{=install data:{"pm":"pnpm"}}`.
+const DATA_PREFIX: &str = "data:";
+
 struct TokenizerState {
   /// The remaining html nodes
   nodes: Vec<Html>,
@@ -21,6 +26,8 @@ struct TokenizerState {
   content: Option<String>,
   /// Whether we are currently inside an html comment.
   stack: Vec<LexerContext>,
+  /// The delimiters of the comments which contain tags.
+  style: CommentStyle,
 }
 
 impl TokenizerState {
@@ -61,6 +68,16 @@ impl TokenizerState {
     }
   }
 
+  /// Update the token group using the raw source text to advance the
+  /// position. This is needed when the token no longer matches the source,
+  /// e.g. a string which contained escape sequences.
+  fn update_token_group_with_raw(&mut self, token: Token, raw: &str) {
+    if let Some(group) = self.token_group.as_mut() {
+      group.position.advance_end(raw);
+      group.tokens.push(token);
+    }
+  }
+
   fn whitespace(&mut self, byte: u8) {
     let token = Token::Whitespace(byte);
     self.update_token_group(token, false);
@@ -76,11 +93,21 @@ impl TokenizerState {
   /// Collect the next identifier starting from the current character. Returns
   /// false if no identifier is found.
   fn collect_identifier(&mut self) -> bool {
+    self.collect_ident_with(|content| lex_identifier(content))
+  }
+
+  /// Collect the next block name, which may be namespaced, e.g.
+  /// `docs/overview`. Returns false if no name is found.
+  fn collect_block_name(&mut self) -> bool {
+    self.collect_ident_with(lex_tag_name)
+  }
+
+  fn collect_ident_with(&mut self, lex: fn(&str) -> usize) -> bool {
     let Some(content) = self.content.as_ref() else {
       return false;
     };
 
-    let ident_length = lex_identifier(content);
+    let ident_length = lex(content);
 
     if ident_length == 0 {
       return false;
@@ -130,24 +157,75 @@ impl TokenizerState {
       return false;
     }
 
-    let Some(string) = self
+    let Some(raw) = self
     .advance(length + 2) else {
       return false;
     };
 
-    let Some (mut string) = string.get(1..string.len() - 1).map(|string| string.to_string()) else {
+    let Some (mut string) = raw.get(1..raw.len() - 1).map(|string| string.to_string()) else {
       return false;
     };
 
     if has_escapes {
-      string = match unescape(&string).ok() {
+      // `snailquote` only processes escapes within a quoted string.
+      string = match unescape(&format!("\"{string}\"")).ok() {
         Some(unescaped) => unescaped,
         None => return false,
       };
     }
 
     let token = Token::String(string, delimiter);
-    self.update_token_group(token, false);
+    self.update_token_group_with_raw(token, &raw);
+    true
+  }
+
+  /// Collect a json object of data overrides, e.g. `data:{"pm":"pnpm"}`.
+  /// Braces within strings are ignored when finding the end of the object.
+  fn collect_data(&mut self) -> bool {
+    let Some(object) = self.content.as_ref().and_then(|content| content.get(DATA_PREFIX.len()..))
+    else {
+      return false;
+    };
+
+    let mut depth = 0_usize;
+    let mut in_string = false;
+    let mut escaped = false;
+    let mut length = None;
+
+    for (index, byte) in object.bytes().enumerate() {
+      match (in_string, escaped, byte) {
+        (true, true, _) => escaped = false,
+        (true, false, b'\\') => escaped = true,
+        (true, false, b'"') => in_string = false,
+        (true, false, _) => {}
+        (false, _, b'"') => in_string = true,
+        (false, _, b'{') => depth += 1,
+        (false, _, b'}') => {
+          depth = depth.saturating_sub(1);
+
+          if depth == 0 {
+            length = Some(index + 1);
+            break;
+          }
+        }
+        _ => {}
+      }
+    }
+
+    let Some(length) = length else {
+      return false;
+    };
+
+    let Some(raw) = self.advance(DATA_PREFIX.len() + length) else {
+      return false;
+    };
+
+    let Some(object) = raw.get(DATA_PREFIX.len()..) else {
+      return false;
+    };
+
+    let token = Token::Data(object.to_string());
+    self.update_token_group_with_raw(token, &raw);
     true
   }
 
@@ -165,7 +243,13 @@ impl TokenizerState {
     }
 
     let mut state = State::Integer;
-    let mut length = content.chars().take_while(|&c| c.is_ascii_digit()).count();
+    let sign = usize::from(content.starts_with('-'));
+    let mut length = sign
+      + content
+        .chars()
+        .skip(sign)
+        .take_while(|&c| c.is_ascii_digit())
+        .count();
     for ch in content.chars().skip(length) {
       state = match (ch, state) {
         ('.', State::Integer) => State::Fraction,
@@ -199,14 +283,36 @@ impl TokenizerState {
     }
   }
 
+  /// Whether the current token group is an inline tag, e.g. `{~version:"…"}`.
+  fn is_inline(&self) -> bool {
+    self
+      .token_group
+      .as_ref()
+      .map_or(false, |group| group.tokens.contains(&Token::InlineTag))
+  }
+
+  fn open_token(&self) -> Token {
+    if self.style == CommentStyle::html() {
+      Token::HtmlCommentOpen
+    } else {
+      Token::CommentOpen(self.style.open.clone())
+    }
+  }
+
+  fn close_token(&self) -> Token {
+    if self.style == CommentStyle::html() {
+      Token::HtmlCommentClose
+    } else {
+      Token::CommentClose(self.style.close_delimiter().to_string())
+    }
+  }
+
   /// Push the token group to the groups `Vec` and reset the token group.
   fn push_token_group(&mut self) {
     let Some(group) = self.token_group.take() else {
       return;
     };
 
-    println!("Pushing token group: {:?}", group.tokens);
-
     if group.is_valid() {
       self.groups.push(group);
     }
@@ -245,7 +351,7 @@ impl TokenizerState {
       return;
     };
 
-    let token = Token::HtmlCommentClose;
+    let token = self.close_token();
 
     let steps = if let Some(steps) = memstr(content.as_bytes(), token.to_string().as_bytes()) {
       steps + token.increment()
@@ -258,6 +364,12 @@ impl TokenizerState {
 }
 
 pub fn tokenize(nodes: Vec<Html>) -> MdtResult<Vec<TokenGroup>> {
+  tokenize_with_style(nodes, &CommentStyle::html())
+}
+
+/// Tokenize comments which use the delimiters of the comment style, e.g.
+/// `{# {=block} #}`.
+pub fn tokenize_with_style(nodes: Vec<Html>, style: &CommentStyle) -> MdtResult<Vec<TokenGroup>> {
   let mut state = TokenizerState {
     nodes,
     groups: vec![],
@@ -266,6 +378,7 @@ pub fn tokenize(nodes: Vec<Html>) -> MdtResult<Vec<TokenGroup>> {
     content: None,
     token_group: None,
     stack: vec![LexerContext::Outside],
+    style: style.clone(),
   };
 
   loop {
@@ -289,12 +402,12 @@ fn tokenize_node(state: &mut TokenizerState) -> MdtResult<()> {
 
     match state.stack.last() {
       Some(LexerContext::Outside) => {
-        if let Some("<!--") = content.get(0..4) {
-          let token = Token::HtmlCommentOpen;
+        if content.starts_with(&state.style.open) {
+          let token = state.open_token();
           // Entering an html comment
           state.stack.push(LexerContext::HtmlComment);
           state.update_token_group(token, true);
-          state.advance(4);
+          state.advance(state.style.open.len());
 
           continue;
         }
@@ -302,11 +415,11 @@ fn tokenize_node(state: &mut TokenizerState) -> MdtResult<()> {
         state.advance(1);
       }
       Some(LexerContext::HtmlComment) => {
-        if let Some("-->") = content.get(0..3) {
-          let token = Token::HtmlCommentClose;
+        if content.starts_with(state.style.close_delimiter()) {
+          let token = state.close_token();
           state.stack.pop();
           state.update_token_group(token, false);
-          state.advance(3);
+          state.advance(state.style.close_delimiter().len());
           state.push_token_group();
           continue;
         }
@@ -333,6 +446,26 @@ fn tokenize_node(state: &mut TokenizerState) -> MdtResult<()> {
             state.advance(2);
             continue;
           }
+          Some("{>") => {
+            let token = Token::IncludeTag;
+            state.stack.push(LexerContext::Tag);
+            state.update_token_group(token, false);
+            state.advance(2);
+            continue;
+          }
+          Some("{~") => {
+            let token = Token::InlineTag;
+            state.stack.push(LexerContext::Tag);
+            state.update_token_group(token, false);
+            state.advance(2);
+            continue;
+          }
+          // An escaped tag, e.g. `{{=example}}`, is left as text. The `unescape`
+          // transformer turns it back into a tag.
+          Some("{{") => {
+            state.exit_comment_block();
+            continue;
+          }
           _ => {}
         }
 
@@ -352,6 +485,17 @@ fn tokenize_node(state: &mut TokenizerState) -> MdtResult<()> {
         }
       }
       Some(LexerContext::Tag) => {
+        if content
+          .strip_prefix(DATA_PREFIX)
+          .map_or(false, |object| object.starts_with('{'))
+        {
+          if !state.collect_data() {
+            state.exit_comment_block();
+          }
+
+          continue;
+        }
+
         match content.bytes().next() {
           Some(b'\n') => {
             state.newline();
@@ -368,6 +512,34 @@ fn tokenize_node(state: &mut TokenizerState) -> MdtResult<()> {
             state.stack.pop();
             continue;
           }
+          Some(b'?') => {
+            let token = Token::OptionalMarker;
+            state.update_token_group(token, false);
+            state.advance(1);
+            continue;
+          }
+          Some(b'#') => {
+            let token = Token::RegionMarker;
+            state.update_token_group(token, false);
+            state.advance(1);
+            continue;
+          }
+          // The template of an inline block, e.g. `{~version:"{{ pkg.version }}"}`.
+          Some(b':') if state.is_inline() => {
+            let token = Token::ArgumentDelimiter;
+            state.update_token_group(token, false);
+            state.advance(1);
+            continue;
+          }
+          Some(symbol @ (b'\'' | b'"')) if state.is_inline() => {
+            let collected = state.collect_string(symbol);
+
+            if !collected {
+              state.exit_comment_block();
+            }
+
+            continue;
+          }
           Some(b'|') => {
             let token = Token::Pipe;
             state.update_token_group(token, false);
@@ -376,7 +548,7 @@ fn tokenize_node(state: &mut TokenizerState) -> MdtResult<()> {
             continue;
           }
           Some(ch) if ch.is_ascii_alphabetic() => {
-            let collected = state.collect_identifier();
+            let collected = state.collect_block_name();
 
             if !collected {
               state.exit_comment_block();
@@ -408,6 +580,12 @@ fn tokenize_node(state: &mut TokenizerState) -> MdtResult<()> {
             state.advance(1);
             continue;
           }
+          Some(b'@') => {
+            let token = Token::AliasMarker;
+            state.update_token_group(token, false);
+            state.advance(1);
+            continue;
+          }
           Some(b'|') => {
             let token = Token::Pipe;
             state.update_token_group(token, false);
@@ -431,7 +609,7 @@ fn tokenize_node(state: &mut TokenizerState) -> MdtResult<()> {
 
             continue;
           }
-          Some(ch) if ch.is_ascii_digit() => {
+          Some(ch) if ch.is_ascii_digit() || is_negative_number(content) => {
             let collected = state.collect_number();
 
             if !collected {
@@ -479,6 +657,153 @@ pub fn memstr(haystack: &[u8], needle: &[u8]) -> Option<usize> {
     .position(|window| window == needle)
 }
 
+fn is_negative_number(content: &str) -> bool {
+  let mut bytes = content.bytes();
+  bytes.next() == Some(b'-') && bytes.next().map_or(false, |byte| byte.is_ascii_digit())
+}
+
+/// The characters which separate the namespaces of a block name, e.g.
+/// `docs/overview` or `pkg:install`.
+pub const NAMESPACE_SEPARATORS: &[char] = &['/', ':'];
+
+/// The separator between the providers which a consumer concatenates, e.g.
+/// `{=intro+install}`.
+pub const CONCAT_SEPARATOR: char = '+';
+
+/// The names of the providers which a consumer renders, e.g. `intro` and
+/// `install` for `intro+install`.
+pub fn concatenated_names(name: &str) -> impl Iterator<Item = &str> {
+  name.split(CONCAT_SEPARATOR)
+}
+
+/// Whether the name can be used for a block, e.g. `exampleName`. Names start
+/// with a letter or an underscore followed by letters, digits or underscores.
+/// They can be namespaced with `/` or `:`, e.g. `docs/overview`.
+pub fn is_valid_block_name(name: &str) -> bool {
+  !name.is_empty() && lex_block_name(name) == name.len()
+}
+
+/// The namespace of a block name, e.g. `docs` for `docs/overview`. Nested
+/// namespaces are kept together, e.g. `pkg/docs` for `pkg/docs/overview`.
+pub fn block_namespace(name: &str) -> Option<&str> {
+  name
+    .rfind(NAMESPACE_SEPARATORS)
+    .and_then(|index| name.get(..index))
+}
+
+/// Whether the block name is within the namespace or one of its nested
+/// namespaces, e.g. `pkg/docs/overview` is within `pkg` and `pkg/docs`.
+pub fn is_in_namespace(name: &str, namespace: &str) -> bool {
+  name
+    .strip_prefix(namespace)
+    .map_or(false, |rest| rest.starts_with(NAMESPACE_SEPARATORS))
+}
+
+/// Convert an invalid name into a valid camel case block name, e.g.
+/// `my-block` becomes `myBlock`. Each namespace is converted separately, so
+/// `my-pkg/install-steps` becomes `myPkg/installSteps`. Returns `None` when no
+/// valid name can be derived.
+pub fn suggest_block_name(name: &str) -> Option<String> {
+  let mut suggestion = String::new();
+  let mut rest = name;
+
+  loop {
+    let end = rest.find(NAMESPACE_SEPARATORS).unwrap_or(rest.len());
+    let (segment, remaining) = rest.split_at(end);
+    suggestion.push_str(&suggest_identifier(segment)?);
+
+    let mut chars = remaining.chars();
+    let Some(separator) = chars.next() else {
+      break;
+    };
+
+    suggestion.push(separator);
+    rest = chars.as_str();
+  }
+
+  Some(suggestion)
+}
+
+fn suggest_identifier(name: &str) -> Option<String> {
+  let mut suggestion = String::new();
+
+  for (index, word) in name
+    .split(|ch: char| !(ch.is_ascii_alphanumeric() || ch == '_'))
+    .filter(|word| !word.is_empty())
+    .enumerate()
+  {
+    let mut chars = word.chars();
+    let Some(first) = chars.next() else {
+      continue;
+    };
+
+    if index == 0 {
+      suggestion.push(first);
+    } else {
+      suggestion.push(first.to_ascii_uppercase());
+    }
+
+    suggestion.extend(chars);
+  }
+
+  let suggestion = suggestion.trim_start_matches(|ch: char| ch.is_ascii_digit());
+  let is_valid = !suggestion.is_empty() && lex_identifier(suggestion) == suggestion.len();
+  is_valid.then(|| suggestion.to_string())
+}
+
+/// The length of the block name at the start of the content. Namespaces are
+/// identifiers joined by a separator, e.g. `docs/overview`.
+fn lex_block_name(content: &str) -> usize {
+  let mut length = lex_identifier(content);
+
+  if length == 0 {
+    return 0;
+  }
+
+  loop {
+    let rest = content.get(length..).unwrap_or_default();
+    let has_separator = rest
+      .chars()
+      .next()
+      .map_or(false, |ch| NAMESPACE_SEPARATORS.contains(&ch));
+    let segment = if has_separator {
+      lex_identifier(rest.get(1..).unwrap_or_default())
+    } else {
+      0
+    };
+
+    if segment == 0 {
+      return length;
+    }
+
+    length += 1 + segment;
+  }
+}
+
+/// The length of the name in a tag, which can join several block names with
+/// the concat separator, e.g. `intro+install`.
+fn lex_tag_name(content: &str) -> usize {
+  let mut length = lex_block_name(content);
+
+  if length == 0 {
+    return 0;
+  }
+
+  loop {
+    let rest = content.get(length..).unwrap_or_default();
+    let segment = match rest.strip_prefix(CONCAT_SEPARATOR) {
+      Some(rest) => lex_block_name(rest),
+      None => 0,
+    };
+
+    if segment == 0 {
+      return length;
+    }
+
+    length += 1 + segment;
+  }
+}
+
 fn lex_identifier(content: impl AsRef<str>) -> usize {
   content
     .as_ref()
@@ -502,7 +827,8 @@ enum LexerContext {
   Outside,
   /// The lexer is currently inside an html comment.
   HtmlComment,
-  /// The lexer is currently inside a consumer, provider or closing tag.
+  /// The lexer is currently inside a consumer, provider, closing, include or
+  /// inline tag.
   Tag,
   /// The lexer is currently inside a filters.
   Filter,
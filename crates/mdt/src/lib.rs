@@ -4,19 +4,77 @@
 //! <!-- {=mdtPackageDocumentation|prefix:"\n"|indent:"//! "} -->
 //! <!-- {/mdtPackageDocumentation} -->
 
+pub use adopt::*;
+pub use assets::*;
+pub use attach::*;
+pub use cargo_metadata::*;
+pub use chain::*;
+pub use changelog::*;
+pub use check::*;
+pub use config::*;
+pub use data::*;
+pub use dates::*;
+pub use deprecations::*;
+pub use docs::*;
+pub use encryption::*;
+pub use engine::*;
 pub use error::*;
+pub use front_matter::*;
+pub use impact::*;
+pub use injection::*;
 pub use lexer::*;
+pub use packages::*;
 pub use parser::*;
+pub use path_filter::*;
 pub use patterns::PatternMatcher;
 pub use position::*;
+pub use project::*;
+pub use regions::*;
+pub use removal::*;
+pub use scaffold::*;
+pub use search::*;
+pub use text_format::*;
 pub use tokens::*;
+pub use transformers::*;
+pub use variables::*;
+pub use vcs::*;
+pub use workspace::*;
 
+mod adopt;
+mod assets;
+mod attach;
+mod cargo_metadata;
+mod chain;
+mod changelog;
+mod check;
+mod config;
+mod data;
+mod dates;
+mod deprecations;
+mod docs;
+mod encryption;
+mod engine;
 mod error;
+mod front_matter;
+mod impact;
+mod injection;
 mod lexer;
+mod packages;
 mod parser;
+mod path_filter;
 pub mod patterns;
 mod position;
+mod project;
+mod regions;
+mod removal;
+mod scaffold;
+mod search;
+mod text_format;
 mod tokens;
+mod transformers;
+mod variables;
+mod vcs;
+mod workspace;
 
 #[cfg(test)]
 mod __fixtures;
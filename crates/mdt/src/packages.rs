@@ -0,0 +1,82 @@
+use std::fs;
+use std::path::Path;
+use std::path::PathBuf;
+
+use crate::MdtResult;
+use crate::IGNORED_DIRECTORIES;
+
+/// A package within a monorepo, e.g. a Cargo workspace member or a pnpm
+/// workspace package.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Package {
+  pub name: String,
+  /// The directory which contains the manifest.
+  pub directory: PathBuf,
+}
+
+/// Find the packages below the root. A directory is a package when it has a
+/// `Cargo.toml` with a `[package]` name or a `package.json` with a name. The
+/// root itself is never a package, since it's the whole project.
+pub fn detect_packages(root: &Path) -> MdtResult<Vec<Package>> {
+  let mut packages = vec![];
+  collect_packages(root, root, &mut packages)?;
+  packages.sort_by(|a, b| a.directory.cmp(&b.directory));
+
+  Ok(packages)
+}
+
+/// The innermost package which contains the file.
+pub fn package_for<'a>(packages: &'a [Package], file: &Path) -> Option<&'a Package> {
+  packages
+    .iter()
+    .filter(|package| file.starts_with(&package.directory))
+    .max_by_key(|package| package.directory.components().count())
+}
+
+fn collect_packages(root: &Path, directory: &Path, packages: &mut Vec<Package>) -> MdtResult<()> {
+  if directory != root {
+    if let Some(name) = package_name(directory) {
+      packages.push(Package {
+        name,
+        directory: directory.to_path_buf(),
+      });
+    }
+  }
+
+  for entry in fs::read_dir(directory)? {
+    let entry = entry?;
+    let name = entry.file_name();
+    let name = name.to_string_lossy();
+
+    if entry.file_type()?.is_dir()
+      && !name.starts_with('.')
+      && !IGNORED_DIRECTORIES.contains(&name.as_ref())
+    {
+      collect_packages(root, &entry.path(), packages)?;
+    }
+  }
+
+  Ok(())
+}
+
+/// The name from the manifest in the directory. Manifests which can't be
+/// read are ignored.
+fn package_name(directory: &Path) -> Option<String> {
+  let cargo = fs::read_to_string(directory.join("Cargo.toml"))
+    .ok()
+    .and_then(|content| content.parse::<toml::Value>().ok())
+    .and_then(|manifest| {
+      manifest
+        .get("package")?
+        .get("name")?
+        .as_str()
+        .map(String::from)
+    });
+
+  cargo.or_else(|| {
+    fs::read_to_string(directory.join("package.json"))
+      .ok()
+      .and_then(|content| serde_json::from_str::<serde_json::Value>(&content).ok())
+      .and_then(|manifest| manifest.get("name")?.as_str().map(String::from))
+  })
+}
@@ -1,29 +1,331 @@
+use std::fmt::Display;
+use std::str::FromStr;
+
 use derive_more::Deref;
 use derive_more::DerefMut;
 use markdown::mdast::Html;
 use markdown::mdast::Node;
 use markdown::to_mdast;
+use markdown::unist::Position as UnistPosition;
 use markdown::ParseOptions;
+use serde_json::Value;
 
 use super::MdtError;
 use super::MdtResult;
+use crate::tokenize;
+use crate::tokenize_with_style;
+use crate::validate_transformer;
+use crate::CommentStyle;
+use crate::MdtConfig;
+use crate::Point;
 use crate::Position;
+use crate::Token;
+use crate::TokenGroup;
 
 pub fn parse(content: impl AsRef<str>) -> MdtResult<Vec<Block>> {
+  parse_with_config(content, &MdtConfig::default())
+}
+
+/// Parse the markdown content, allowing the custom transformers and aliases
+/// from the configuration to be used in tags.
+pub fn parse_with_config(content: impl AsRef<str>, config: &MdtConfig) -> MdtResult<Vec<Block>> {
   let content = content.as_ref();
   let html_nodes = get_html_nodes(content)?;
-  let blocks = vec![];
-  let _block_creators = Vec::<BlockCreator>::new();
+  let token_groups = tokenize(html_nodes)?;
+
+  build_blocks_from_groups(&token_groups, config)
+}
+
+/// Parse the markdown content and report the problems which the parser
+/// recovered from.
+pub fn parse_with_diagnostics(
+  content: impl AsRef<str>,
+  config: &MdtConfig,
+) -> MdtResult<(Vec<Block>, Vec<ParseDiagnostic>)> {
+  let html_nodes = get_html_nodes(content.as_ref())?;
+  let token_groups = tokenize(html_nodes)?;
 
-  for node in html_nodes {
-    let Some(ref _position) = node.position else {
+  build_blocks_with_diagnostics(&token_groups, config)
+}
+
+/// A problem in the tags which the parser recovered from.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ParseDiagnostic {
+  /// A block was closed by a tag with a different name, e.g. `{@a}` closed by
+  /// `{/b}`. The block is still created using the mismatched closing tag.
+  MismatchedClosingTag {
+    /// The name of the block.
+    expected: String,
+    /// The name in the closing tag.
+    found: String,
+    /// The position of the name within the closing tag.
+    position: Position,
+  },
+}
+
+impl Display for ParseDiagnostic {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    match self {
+      Self::MismatchedClosingTag {
+        expected,
+        found,
+        position,
+      } => {
+        write!(
+          f,
+          "the closing tag `{found}` at {}:{} doesn't match the block `{expected}`",
+          position.start.line, position.start.column
+        )
+      }
+    }
+  }
+}
+
+/// Build the blocks from the token groups. Blocks can be nested, so each
+/// closing tag is paired with the innermost open tag that shares its name.
+/// Closing tags with the wrong name are recovered from, see
+/// [`build_blocks_with_diagnostics`]. The blocks are ordered by the position of
+/// their opening tag.
+pub fn build_blocks_from_groups(
+  groups: &[TokenGroup],
+  config: &MdtConfig,
+) -> MdtResult<Vec<Block>> {
+  build_blocks_with_diagnostics(groups, config).map(|(blocks, _)| blocks)
+}
+
+/// Build the blocks from the token groups, recovering from closing tags with
+/// the wrong name. A block which is never closed by its own name is closed by
+/// the first unmatched closing tag which followed it while it was the
+/// innermost open block.
+pub fn build_blocks_with_diagnostics(
+  groups: &[TokenGroup],
+  config: &MdtConfig,
+) -> MdtResult<(Vec<Block>, Vec<ParseDiagnostic>)> {
+  let mut blocks = vec![];
+  let mut diagnostics = vec![];
+  let mut open: Vec<BlockCreator> = vec![];
+
+  for group in groups {
+    let Some(tag) = group.tag() else {
       continue;
     };
 
-    for _ch in content.chars() {}
+    let Some(name) = group.name() else {
+      continue;
+    };
+
+    match tag {
+      Token::ConsumerTag | Token::ProviderTag => {
+        let r#type = if tag == &Token::ConsumerTag {
+          BlockType::Consumer
+        } else {
+          BlockType::Provider
+        };
+
+        let mut creator = BlockCreator::new(name.to_string(), r#type, group.position);
+        creator.opening_name = group.name_position();
+        creator.transformers = group.transformers(config)?;
+        creator.optional = group.is_optional();
+        creator.region = group.region().map(String::from);
+        creator.data = group.data()?;
+        open.push(creator);
+      }
+      Token::InlineTag => {
+        let mut creator = BlockCreator::new(name.to_string(), BlockType::Inline, group.position);
+        creator.opening_name = group.name_position();
+        creator.transformers = group.transformers(config)?;
+        creator.template = group.template().map(String::from);
+        open.push(creator);
+      }
+      Token::CloseTag => {
+        let Some(index) = open.iter().rposition(|creator| creator.name == name) else {
+          if let Some(creator) = open.last_mut() {
+            creator.mismatched.get_or_insert_with(|| group.clone());
+          }
+
+          continue;
+        };
+
+        while open.len() > index + 1 {
+          let Some(unclosed) = open.pop() else {
+            break;
+          };
+
+          blocks.push(unclosed.into_mismatched_block(&mut diagnostics)?);
+        }
+
+        let Some(mut creator) = open.pop() else {
+          continue;
+        };
+
+        creator.closing = Some(group.position);
+        creator.closing_name = group.name_position();
+        creator.hash = group.hash();
+        blocks.push(creator.into_block()?);
+      }
+      _ => {}
+    }
   }
 
-  Ok(blocks)
+  while let Some(creator) = open.pop() {
+    blocks.push(creator.into_mismatched_block(&mut diagnostics)?);
+  }
+
+  blocks.sort_by_key(|block| block.opening.start.offset);
+
+  Ok((blocks, diagnostics))
+}
+
+/// Parse blocks from a source file which isn't markdown. The html comments
+/// are located directly in the raw text since code comments aren't valid
+/// markdown.
+pub fn parse_source(content: impl AsRef<str>) -> MdtResult<Vec<Block>> {
+  parse_source_with_config(content, &MdtConfig::default())
+}
+
+/// Parse blocks from a source file, allowing the custom transformers and
+/// aliases from the configuration to be used in tags.
+pub fn parse_source_with_config(
+  content: impl AsRef<str>,
+  config: &MdtConfig,
+) -> MdtResult<Vec<Block>> {
+  let html_nodes = get_source_html_nodes(content);
+  let token_groups = tokenize(html_nodes)?;
+
+  build_blocks_from_groups(&token_groups, config)
+}
+
+/// Parse blocks from a source file and report the problems which the parser
+/// recovered from.
+pub fn parse_source_with_diagnostics(
+  content: impl AsRef<str>,
+  config: &MdtConfig,
+) -> MdtResult<(Vec<Block>, Vec<ParseDiagnostic>)> {
+  let html_nodes = get_source_html_nodes(content);
+  let token_groups = tokenize(html_nodes)?;
+
+  build_blocks_with_diagnostics(&token_groups, config)
+}
+
+/// Parse blocks from a file whose tags are within comments of the style, e.g.
+/// `{# {=block} #}`, and report the problems which the parser recovered from.
+pub fn parse_comments_with_diagnostics(
+  content: impl AsRef<str>,
+  style: &CommentStyle,
+  config: &MdtConfig,
+) -> MdtResult<(Vec<Block>, Vec<ParseDiagnostic>)> {
+  let nodes = get_comment_nodes(content, style);
+  let token_groups = tokenize_with_style(nodes, style)?;
+
+  build_blocks_with_diagnostics(&token_groups, config)
+}
+
+/// Parse a transformer chain written the way it appears in a tag, e.g.
+/// `trim|indent:"  "`. The leading `|` is optional.
+pub fn parse_transformers(chain: &str, config: &MdtConfig) -> MdtResult<Vec<Transformer>> {
+  let chain = chain.trim().trim_start_matches('|');
+
+  if chain.is_empty() {
+    return Ok(vec![]);
+  }
+
+  let blocks = parse_source_with_config(
+    format!("<!-- {{=chain|{chain}}} --><!-- {{/chain}} -->"),
+    config,
+  )?;
+
+  Ok(
+    blocks
+      .into_iter()
+      .next()
+      .map(|block| block.transformers)
+      .unwrap_or_default(),
+  )
+}
+
+/// An include tag, e.g. `<!-- {> intro} -->`, which is replaced by the
+/// rendered content of another provider.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Include {
+  /// The name of the included provider.
+  pub name: String,
+  /// The position of the html comment which contains the tag.
+  pub position: Position,
+  /// The position of the name within the tag.
+  pub name_position: Position,
+  pub transformers: Vec<Transformer>,
+}
+
+/// Find the include tags within provider content.
+pub fn find_includes(content: &str, config: &MdtConfig) -> MdtResult<Vec<Include>> {
+  let groups = tokenize(get_source_html_nodes(content))?;
+  let mut includes = vec![];
+
+  for group in groups {
+    let (Some(Token::IncludeTag), Some(name)) = (group.tag(), group.name()) else {
+      continue;
+    };
+
+    includes.push(Include {
+      name: name.to_string(),
+      position: group.position,
+      name_position: group.name_position().unwrap_or(group.position),
+      transformers: group.transformers(config)?,
+    });
+  }
+
+  Ok(includes)
+}
+
+/// Find every html comment in the raw text of the content.
+pub fn get_source_html_nodes(content: impl AsRef<str>) -> Vec<Html> {
+  get_comment_nodes(content, &CommentStyle::html())
+}
+
+/// Find every comment with the delimiters of the style in the raw text of the
+/// content. The nodes are named after html since the lexer treats every
+/// comment the same way.
+pub fn get_comment_nodes(content: impl AsRef<str>, style: &CommentStyle) -> Vec<Html> {
+  let content = content.as_ref();
+  let open = style.open.as_str();
+  let close = style.close_delimiter();
+  let mut nodes = vec![];
+  let mut point = Point::new(1, 1, 0);
+  let mut offset = 0;
+
+  while let Some(start) = content
+    .get(offset..)
+    .and_then(|rest| rest.find(open))
+    .map(|index| index + offset)
+  {
+    let Some(end) = content
+      .get(start + open.len()..)
+      .and_then(|rest| rest.find(close))
+      .map(|index| index + start + open.len() + close.len())
+    else {
+      break;
+    };
+
+    point.advance(content.get(offset..start).unwrap_or_default());
+    let start_point = point;
+    let value = content.get(start..end).unwrap_or_default();
+    point.advance(value);
+    offset = end;
+
+    nodes.push(Html {
+      value: value.to_string(),
+      position: Some(UnistPosition::new(
+        start_point.line,
+        start_point.column,
+        start_point.offset,
+        point.line,
+        point.column,
+        point.offset,
+      )),
+    });
+  }
+
+  nodes
 }
 
 pub fn get_html_nodes(content: impl AsRef<str>) -> MdtResult<Vec<Html>> {
@@ -61,8 +363,18 @@ struct BlockCreator {
   name: String,
   r#type: BlockType,
   opening: Position,
+  opening_name: Option<Position>,
   closing: Option<Position>,
+  closing_name: Option<Position>,
   transformers: Vec<Transformer>,
+  optional: bool,
+  region: Option<String>,
+  hash: Option<String>,
+  data: Option<Value>,
+  template: Option<String>,
+  /// The first closing tag with a different name which followed the opening
+  /// tag while this was the innermost open block.
+  mismatched: Option<TokenGroup>,
 }
 
 impl BlockCreator {
@@ -71,11 +383,39 @@ impl BlockCreator {
       name,
       r#type,
       opening,
+      opening_name: None,
       closing: None,
+      closing_name: None,
       transformers: vec![],
+      optional: false,
+      region: None,
+      hash: None,
+      data: None,
+      template: None,
+      mismatched: None,
     }
   }
 
+  /// Close the block with its mismatched closing tag and report the
+  /// mismatch. Without one the block is missing its closing tag.
+  fn into_mismatched_block(mut self, diagnostics: &mut Vec<ParseDiagnostic>) -> MdtResult<Block> {
+    let Some(group) = self.mismatched.take() else {
+      return Err(MdtError::MissingClosingTag(self.name));
+    };
+
+    let position = group.name_position().unwrap_or(group.position);
+    diagnostics.push(ParseDiagnostic::MismatchedClosingTag {
+      expected: self.name.clone(),
+      found: group.name().unwrap_or_default().to_string(),
+      position,
+    });
+    self.closing = Some(group.position);
+    self.closing_name = Some(position);
+    self.hash = group.hash();
+
+    self.into_block()
+  }
+
   pub fn into_block(self) -> MdtResult<Block> {
     let Some(closing) = self.closing else {
       return Err(MdtError::MissingClosingTag(self.name));
@@ -85,38 +425,308 @@ impl BlockCreator {
       name: self.name,
       r#type: self.r#type,
       opening: self.opening,
+      opening_name: self.opening_name.unwrap_or(self.opening),
       closing,
+      closing_name: self.closing_name.unwrap_or(closing),
       transformers: self.transformers,
+      optional: self.optional,
+      region: self.region,
+      hash: self.hash,
+      data: self.data,
+      template: self.template,
     };
 
     Ok(block)
   }
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct Block {
   /// The name of the block. This is used to
   pub name: String,
   pub r#type: BlockType,
   pub opening: Position,
+  /// The position of the name within the opening tag.
+  pub opening_name: Position,
   pub closing: Position,
+  /// The position of the name within the closing tag.
+  pub closing_name: Position,
   pub transformers: Vec<Transformer>,
+  /// Whether the consumer is marked optional with `?`, e.g. `{=badge?}`. It
+  /// renders as empty when the provider doesn't exist.
+  pub optional: bool,
+  /// The region of the provider which the consumer renders, e.g.
+  /// `quickstart` for `{=docs#quickstart}`.
+  pub region: Option<String>,
+  /// The hash of the content when it was last written, recorded in the
+  /// closing tag, e.g. `{/block|hash:"1a2b3c4d"}`.
+  pub hash: Option<String>,
+  /// The data which overrides the project data when rendering the provider
+  /// for this consumer, e.g. `{=install data:{"pm":"pnpm"}}`.
+  pub data: Option<Value>,
+  /// The template an inline block renders with the project data, e.g.
+  /// `{{ pkg.version }}` for `{~version:"{{ pkg.version }}"}`.
+  pub template: Option<String>,
 }
 
-#[derive(Debug, Clone)]
+impl Block {
+  /// The byte range of the content between the opening and closing tags.
+  pub fn content_range(&self) -> std::ops::Range<usize> {
+    self.opening.end.offset..self.closing.start.offset
+  }
+
+  /// Whether the block renders its own template instead of a provider.
+  pub fn is_inline(&self) -> bool {
+    self.r#type == BlockType::Inline
+  }
+
+  /// Whether the other block is nested within the content of this block.
+  pub fn contains(&self, other: &Block) -> bool {
+    let range = self.content_range();
+    range.start <= other.opening.start.offset && other.closing.end.offset <= range.end
+  }
+}
+
+#[derive(Debug, Clone, PartialEq)]
 pub struct Transformer {
   pub r#type: TransformerType,
   pub args: Vec<Argument>,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub enum Argument {
   String(String),
   Number(f64),
   Boolean(bool),
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+impl Argument {
+  pub fn as_str(&self) -> Option<&str> {
+    match self {
+      Argument::String(value) => Some(value),
+      _ => None,
+    }
+  }
+
+  pub fn as_number(&self) -> Option<f64> {
+    match self {
+      Argument::Number(value) => Some(*value),
+      _ => None,
+    }
+  }
+
+  pub fn as_bool(&self) -> Option<bool> {
+    match self {
+      Argument::Boolean(value) => Some(*value),
+      _ => None,
+    }
+  }
+}
+
+impl TokenGroup {
+  /// The tag token which determines the kind of block this group represents.
+  pub fn tag(&self) -> Option<&Token> {
+    self.tokens.iter().find(|token| token.is_tag())
+  }
+
+  /// The content hash recorded in a closing tag, e.g. `|hash:"1a2b3c4d"`.
+  pub fn hash(&self) -> Option<String> {
+    let mut tokens = self
+      .tokens
+      .iter()
+      .filter(|token| !matches!(token, Token::Whitespace(_) | Token::Newline))
+      .skip_while(|token| token != &&Token::Pipe)
+      .skip(1);
+
+    match (tokens.next(), tokens.next(), tokens.next()) {
+      (Some(Token::Ident(name)), Some(Token::ArgumentDelimiter), Some(Token::String(hash, _)))
+        if name == "hash" =>
+      {
+        Some(hash.clone())
+      }
+      _ => None,
+    }
+  }
+
+  /// The provider region selected after the name, e.g. `{=docs#quickstart}`.
+  pub fn region(&self) -> Option<&str> {
+    self
+      .tokens
+      .iter()
+      .skip_while(|token| token != &&Token::RegionMarker)
+      .nth(1)
+      .and_then(|token| {
+        match token {
+          Token::Ident(region) => Some(region.as_str()),
+          _ => None,
+        }
+      })
+  }
+
+  /// The data overrides after the name, e.g. `{=install data:{"pm":"pnpm"}}`,
+  /// which must be a json object.
+  pub fn data(&self) -> MdtResult<Option<Value>> {
+    let Some(object) = self.tokens.iter().find_map(|token| {
+      match token {
+        Token::Data(object) => Some(object),
+        _ => None,
+      }
+    }) else {
+      return Ok(None);
+    };
+
+    let invalid = |message: String| {
+      MdtError::InvalidBlockData {
+        name: self.name().unwrap_or_default().to_string(),
+        message,
+      }
+    };
+
+    match serde_json::from_str(object).map_err(|error| invalid(error.to_string()))? {
+      value @ Value::Object(_) => Ok(Some(value)),
+      _ => Err(invalid("the data must be a json object".into())),
+    }
+  }
+
+  /// The template of an inline tag which follows the name, e.g.
+  /// `{{ pkg.version }}` for `{~version:"{{ pkg.version }}"|trim}`.
+  pub fn template(&self) -> Option<&str> {
+    if self.tag() != Some(&Token::InlineTag) {
+      return None;
+    }
+
+    self
+      .tokens
+      .iter()
+      .take_while(|token| token != &&Token::Pipe)
+      .skip_while(|token| token != &&Token::ArgumentDelimiter)
+      .find_map(|token| {
+        match token {
+          Token::String(template, _) => Some(template.as_str()),
+          _ => None,
+        }
+      })
+  }
+
+  /// Whether the name is followed by `?` to mark the consumer optional.
+  pub fn is_optional(&self) -> bool {
+    self.tokens.contains(&Token::OptionalMarker)
+  }
+
+  /// The name of the block which directly follows the tag token.
+  pub fn name(&self) -> Option<&str> {
+    self
+      .tokens
+      .iter()
+      .skip_while(|token| !token.is_tag())
+      .find_map(|token| {
+        match token {
+          Token::Ident(name) => Some(name.as_str()),
+          _ => None,
+        }
+      })
+  }
+
+  /// The position of the block name within the token group.
+  pub fn name_position(&self) -> Option<Position> {
+    let tag_index = self.tokens.iter().position(Token::is_tag)?;
+    let name_index = self
+      .tokens
+      .iter()
+      .enumerate()
+      .skip(tag_index)
+      .find_map(|(index, token)| matches!(token, Token::Ident(_)).then_some(index))?;
+
+    Some(self.position_of_range(name_index))
+  }
+
+  /// Collect the transformers declared after the block name, e.g.
+  /// `|trim|indent:"/// "`. Names which aren't builtin must be defined in the
+  /// `[transformers]` table of the configuration and aliases, e.g. `|@rustdoc`,
+  /// are expanded from the `[transformer_aliases]` table.
+  pub fn transformers(&self, config: &MdtConfig) -> MdtResult<Vec<Transformer>> {
+    let mut transformers: Vec<Transformer> = vec![];
+    let mut expecting_name = false;
+    let mut expecting_alias = false;
+    let mut alias: Option<&str> = None;
+
+    for token in &self.tokens {
+      let argument = match token {
+        Token::Pipe => {
+          expecting_name = true;
+          alias = None;
+          continue;
+        }
+        Token::AliasMarker if expecting_name => {
+          expecting_alias = true;
+          continue;
+        }
+        Token::Ident(name) if expecting_alias => {
+          expecting_name = false;
+          expecting_alias = false;
+          alias = Some(name);
+          let Some(chain) = config.transformer_aliases.get(name) else {
+            return Err(MdtError::UnknownTransformerAlias(name.clone()));
+          };
+
+          transformers.extend(parse_transformers(chain, config)?);
+          continue;
+        }
+        Token::Ident(name) if expecting_name => {
+          expecting_name = false;
+
+          if !config.transformers.is_allowed(name) {
+            return Err(MdtError::DeniedTransformer(name.clone()));
+          }
+
+          let r#type = match name.parse() {
+            Ok(r#type) => r#type,
+            Err(_) if config.transformers.custom.contains_key(name) => {
+              TransformerType::Custom(name.clone())
+            }
+            Err(error) => return Err(error),
+          };
+
+          transformers.push(Transformer {
+            r#type,
+            args: vec![],
+          });
+          continue;
+        }
+        Token::String(value, _) => Argument::String(value.clone()),
+        Token::Int(value) => Argument::Number(*value as f64),
+        Token::Float(value) => Argument::Number(*value),
+        Token::Ident(value) if value == "true" || value == "false" => {
+          Argument::Boolean(value == "true")
+        }
+        _ => continue,
+      };
+
+      if let Some(alias) = alias {
+        return Err(MdtError::InvalidTransformerArguments {
+          name: format!("@{alias}"),
+          message: "aliases don't accept arguments".into(),
+        });
+      }
+
+      push_argument(&mut transformers, argument);
+    }
+
+    for transformer in &transformers {
+      validate_transformer(transformer, &config.transformers.custom)?;
+    }
+
+    Ok(transformers)
+  }
+}
+
+fn push_argument(transformers: &mut [Transformer], argument: Argument) {
+  if let Some(transformer) = transformers.last_mut() {
+    transformer.args.push(argument);
+  }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub enum TransformerType {
   /// Trim all whitespace from the start and end of the content.
   Trim,
@@ -134,6 +744,248 @@ pub enum TransformerType {
   Code,
   /// Replace all instances of the given string with the replacement string.
   Replace,
+  /// Shift the level of every markdown heading by the given number, e.g.
+  /// `headingShift:1` turns `## Usage` into `### Usage`. Levels are clamped
+  /// between 1 and 6.
+  HeadingShift,
+  /// Truncate the content to a maximum number of characters, e.g.
+  /// `truncate:200:"…"`. An optional third argument selects the unit:
+  /// `"chars"` (default), `"words"` to keep that many words or `"lines"`. A
+  /// fourth argument of `true` keeps character truncation from splitting a
+  /// word, e.g. `truncate:200:"…":"chars":true`.
+  Truncate,
+  /// Reflow paragraphs so that no line is longer than the given width, e.g.
+  /// `wrapWidth:80`. Fenced code blocks, headings, tables and html are left
+  /// untouched. Place it before `indent` so the width excludes the prefix.
+  WrapWidth,
+  /// Remove the leading whitespace shared by every line. This is the inverse
+  /// of `indent`.
+  Dedent,
+  /// Replace the content with a nested list of links to its headings. The
+  /// anchors match the ones generated by GitHub.
+  Toc,
+  /// Extract a single section, e.g. `section:"Installation"`, from its heading
+  /// up to the next heading of the same or a higher level.
+  Section,
+  /// Remove html tags and comments, keeping the text inside the tags.
+  StripHtml,
+  /// Render the markdown content to html.
+  MarkdownToHtml,
+  /// Escape the content for html text or attribute values.
+  EscapeHtml,
+  /// Escape the content for use inside a JSON string.
+  EscapeJson,
+  /// Escape the content for use inside a TOML basic string.
+  EscapeToml,
+  /// Rewrite relative markdown links so that they resolve from the consumer's
+  /// file instead of the provider's file.
+  RelativeLinks,
+  /// Collapse runs of blank lines into a single blank line.
+  SquashBlankLines,
+  /// Turn escaped tags like `<!-- {{=example}} -->` into the literal tags they
+  /// show, e.g. to render examples of mdt syntax into documentation.
+  Unescape,
+  /// Convert the content into the anchor GitHub generates for a heading, e.g.
+  /// `Getting Started` becomes `getting-started`.
+  Slug,
+  /// The same as `slug` with a leading `#`, e.g. `#getting-started`.
+  Anchor,
+  /// Reformat a date or timestamp, e.g. `date:"%B %e, %Y"`. ISO 8601 dates,
+  /// RFC 3339 timestamps and unix timestamps are understood. The default
+  /// format is `%Y-%m-%d` and content which isn't a date is left unchanged.
+  Date,
+  /// Convert markdown to plain text, e.g. for emails.
+  PlainText,
+  /// Convert markdown to Slack's `mrkdwn` format.
+  SlackFormat,
+  /// Keep a range of lines, e.g. `lines:3:10`. Line numbers start at 1, the
+  /// range is inclusive and the end is optional.
+  Lines,
+  /// Keep the first lines of the content, e.g. `firstLines:5`.
+  FirstLines,
+  /// Keep the last lines of the content, e.g. `lastLines:5`.
+  LastLines,
+  /// Embed the relative images as `data:` uris, resolved from the provider
+  /// file. An optional maximum size in bytes, e.g. `inlineImages:4096`, keeps
+  /// larger images as links.
+  InlineImages,
+  /// Keep the content only when the expression is truthy, e.g.
+  /// `if:"pkg.version is since('2.0')"`. Expressions are evaluated against the
+  /// project data and support comparisons and `and`, `or` and `not`.
+  If,
+  /// Keep the content only when the expression is falsy, e.g.
+  /// `unless:"pkg.private"`.
+  Unless,
+  /// Keep the current content of the consumer, which intentionally diverges
+  /// from its provider. `mdt update` skips it and `mdt check` reports it as
+  /// held rather than stale.
+  Frozen,
+  /// A transformer defined in the `[transformers]` table of `mdt.toml`.
+  Custom(String),
+}
+
+impl TransformerType {
+  /// The name used to reference the transformer within a tag.
+  pub fn name(&self) -> &str {
+    match self {
+      TransformerType::Trim => "trim",
+      TransformerType::TrimStart => "trimStart",
+      TransformerType::TrimEnd => "trimEnd",
+      TransformerType::Wrap => "wrap",
+      TransformerType::Indent => "indent",
+      TransformerType::CodeBlock => "codeBlock",
+      TransformerType::Code => "code",
+      TransformerType::Replace => "replace",
+      TransformerType::HeadingShift => "headingShift",
+      TransformerType::Truncate => "truncate",
+      TransformerType::WrapWidth => "wrapWidth",
+      TransformerType::Dedent => "dedent",
+      TransformerType::Toc => "toc",
+      TransformerType::Section => "section",
+      TransformerType::StripHtml => "stripHtml",
+      TransformerType::MarkdownToHtml => "markdownToHtml",
+      TransformerType::EscapeHtml => "escapeHtml",
+      TransformerType::EscapeJson => "escapeJson",
+      TransformerType::EscapeToml => "escapeToml",
+      TransformerType::RelativeLinks => "relativeLinks",
+      TransformerType::SquashBlankLines => "squashBlankLines",
+      TransformerType::Unescape => "unescape",
+      TransformerType::Slug => "slug",
+      TransformerType::Anchor => "anchor",
+      TransformerType::Date => "date",
+      TransformerType::PlainText => "plainText",
+      TransformerType::SlackFormat => "slackFormat",
+      TransformerType::Lines => "lines",
+      TransformerType::FirstLines => "firstLines",
+      TransformerType::LastLines => "lastLines",
+      TransformerType::InlineImages => "inlineImages",
+      TransformerType::If => "if",
+      TransformerType::Unless => "unless",
+      TransformerType::Frozen => "frozen",
+      TransformerType::Custom(name) => name,
+    }
+  }
+}
+
+/// The names of the built-in transformers, in the order they are documented.
+pub const BUILT_IN_TRANSFORMERS: &[&str] = &[
+  "trim",
+  "trimStart",
+  "trimEnd",
+  "wrap",
+  "indent",
+  "codeBlock",
+  "code",
+  "replace",
+  "headingShift",
+  "truncate",
+  "wrapWidth",
+  "dedent",
+  "toc",
+  "section",
+  "stripHtml",
+  "markdownToHtml",
+  "escapeHtml",
+  "escapeJson",
+  "escapeToml",
+  "relativeLinks",
+  "squashBlankLines",
+  "unescape",
+  "slug",
+  "anchor",
+  "date",
+  "plainText",
+  "slackFormat",
+  "lines",
+  "firstLines",
+  "lastLines",
+  "inlineImages",
+  "if",
+  "unless",
+  "frozen",
+];
+
+impl FromStr for TransformerType {
+  type Err = MdtError;
+
+  fn from_str(name: &str) -> Result<Self, Self::Err> {
+    let transformer_type = match name {
+      "trim" => TransformerType::Trim,
+      "trimStart" => TransformerType::TrimStart,
+      "trimEnd" => TransformerType::TrimEnd,
+      "wrap" => TransformerType::Wrap,
+      "indent" => TransformerType::Indent,
+      "codeBlock" => TransformerType::CodeBlock,
+      "code" => TransformerType::Code,
+      "replace" => TransformerType::Replace,
+      "headingShift" => TransformerType::HeadingShift,
+      "truncate" => TransformerType::Truncate,
+      "wrapWidth" => TransformerType::WrapWidth,
+      "dedent" => TransformerType::Dedent,
+      "toc" => TransformerType::Toc,
+      "section" => TransformerType::Section,
+      "stripHtml" => TransformerType::StripHtml,
+      "markdownToHtml" => TransformerType::MarkdownToHtml,
+      "escapeHtml" => TransformerType::EscapeHtml,
+      "escapeJson" => TransformerType::EscapeJson,
+      "escapeToml" => TransformerType::EscapeToml,
+      "relativeLinks" => TransformerType::RelativeLinks,
+      "squashBlankLines" => TransformerType::SquashBlankLines,
+      "unescape" => TransformerType::Unescape,
+      "slug" => TransformerType::Slug,
+      "anchor" => TransformerType::Anchor,
+      "date" => TransformerType::Date,
+      "plainText" => TransformerType::PlainText,
+      "slackFormat" => TransformerType::SlackFormat,
+      "lines" => TransformerType::Lines,
+      "firstLines" => TransformerType::FirstLines,
+      "lastLines" => TransformerType::LastLines,
+      "inlineImages" => TransformerType::InlineImages,
+      "if" => TransformerType::If,
+      "unless" => TransformerType::Unless,
+      "frozen" => TransformerType::Frozen,
+      _ => return Err(MdtError::UnknownTransformer(name.to_string())),
+    };
+
+    Ok(transformer_type)
+  }
+}
+
+impl Display for Transformer {
+  /// Format the transformer the way it is written in a tag, e.g.
+  /// `indent:"  "`.
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    write!(f, "{}", self.r#type)?;
+
+    for arg in &self.args {
+      write!(f, ":{arg}")?;
+    }
+
+    Ok(())
+  }
+}
+
+impl Display for Argument {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    match self {
+      Argument::String(value) => {
+        let escaped = value
+          .replace('\\', "\\\\")
+          .replace('"', "\\\"")
+          .replace('\n', "\\n")
+          .replace('\t', "\\t");
+        write!(f, "\"{escaped}\"")
+      }
+      Argument::Number(value) => write!(f, "{value}"),
+      Argument::Boolean(value) => write!(f, "{value}"),
+    }
+  }
+}
+
+impl Display for TransformerType {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    write!(f, "{}", self.name())
+  }
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -156,4 +1008,11 @@ pub enum BlockType {
   /// <!-- {/exampleConsumer} -->
   /// ```
   Consumer,
+  /// Inline blocks render their own template with the project data instead
+  /// of the content of a provider, and can decorate it with transformers.
+  ///
+  /// ```md
+  /// <!-- {~version:"{{ pkg.version }}"|code} -->`1.0.0`<!-- {/version} -->
+  /// ```
+  Inline,
 }
@@ -0,0 +1,72 @@
+use std::path::Path;
+use std::path::PathBuf;
+
+use globset::GlobBuilder;
+use globset::GlobSet;
+use globset::GlobSetBuilder;
+
+use crate::MdtError;
+use crate::MdtResult;
+
+/// Restricts a scan to the files which match one of the `only` globs and
+/// none of the `skip` globs, e.g. `mdt update --only "docs/**"`. The globs are
+/// relative to the root of the filter. Definition files are always scanned so
+/// every provider is available.
+#[derive(Debug, Clone, Default)]
+pub struct PathFilter {
+  root: PathBuf,
+  only: Option<GlobSet>,
+  skip: Option<GlobSet>,
+}
+
+impl PathFilter {
+  pub fn new(root: impl Into<PathBuf>, only: &[String], skip: &[String]) -> MdtResult<Self> {
+    Ok(Self {
+      root: root.into(),
+      only: build_globs(only)?,
+      skip: build_globs(skip)?,
+    })
+  }
+
+  /// Whether the file passes the filter.
+  pub fn matches(&self, file: &Path) -> bool {
+    let relative = file.strip_prefix(&self.root).unwrap_or(file);
+
+    self
+      .only
+      .as_ref()
+      .map_or(true, |only| only.is_match(relative))
+      && !self
+        .skip
+        .as_ref()
+        .map_or(false, |skip| skip.is_match(relative))
+  }
+}
+
+fn build_globs(globs: &[String]) -> MdtResult<Option<GlobSet>> {
+  if globs.is_empty() {
+    return Ok(None);
+  }
+
+  let invalid = |glob: &str, error: globset::Error| {
+    MdtError::InvalidGlob {
+      glob: glob.to_string(),
+      message: error.to_string(),
+    }
+  };
+  let mut set = GlobSetBuilder::new();
+
+  for glob in globs {
+    set.add(
+      GlobBuilder::new(glob)
+        .literal_separator(true)
+        .build()
+        .map_err(|error| invalid(glob, error))?,
+    );
+  }
+
+  set
+    .build()
+    .map(Some)
+    .map_err(|error| invalid(&globs.join(", "), error))
+}
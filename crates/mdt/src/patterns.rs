@@ -7,29 +7,44 @@ pub type PatternMatcher = Box<dyn Fn(&TokenGroup, usize) -> MdtResult<usize> + '
 
 pub fn closing_pattern() -> Vec<PatternMatcher> {
   vec![
-    one(vec![Token::HtmlCommentOpen]),
+    one(vec![Token::HtmlCommentOpen, Token::comment_open()]),
     optional_many(vec![Token::whitespace(), Token::Newline]),
     one(vec![Token::CloseTag]),
     optional_many(vec![Token::whitespace()]),
     one(vec![Token::any()]),
     optional_many(vec![Token::whitespace()]),
+    optional_group(vec![
+      one(vec![Token::Pipe]),
+      optional_many(vec![Token::whitespace()]),
+      one(vec![Token::Ident("hash".into())]),
+      optional_many(vec![Token::whitespace()]),
+      one(vec![Token::ArgumentDelimiter]),
+      optional_many(vec![Token::whitespace()]),
+      one(vec![Token::string()]),
+      optional_many(vec![Token::whitespace()]),
+    ]),
     one(vec![Token::BraceClose]),
     optional_many(vec![Token::whitespace(), Token::Newline]),
-    one(vec![Token::HtmlCommentClose]),
+    one(vec![Token::HtmlCommentClose, Token::comment_close()]),
   ]
 }
 
 pub fn consumer_pattern() -> Vec<PatternMatcher> {
   vec![
-    one(vec![Token::HtmlCommentOpen]),
+    one(vec![Token::HtmlCommentOpen, Token::comment_open()]),
     optional_many(vec![Token::whitespace(), Token::Newline]),
     one(vec![Token::ConsumerTag]),
     optional_many(vec![Token::whitespace()]),
     one(vec![Token::any()]),
+    optional_group(vec![one(vec![Token::RegionMarker]), one(vec![Token::any()])]),
+    optional_many(vec![Token::OptionalMarker]),
+    optional_many(vec![Token::whitespace()]),
+    optional_many(vec![Token::data()]),
     optional_many(vec![Token::whitespace()]),
     optional_many_group(vec![
       one(vec![Token::Pipe]),
       optional_many(vec![Token::whitespace()]),
+      optional_many(vec![Token::AliasMarker]),
       one(vec![Token::any()]),
       optional_many(vec![Token::whitespace()]),
       optional_many_group(vec![
@@ -47,13 +62,13 @@ pub fn consumer_pattern() -> Vec<PatternMatcher> {
     ]),
     one(vec![Token::BraceClose]),
     optional_many(vec![Token::whitespace(), Token::Newline]),
-    one(vec![Token::HtmlCommentClose]),
+    one(vec![Token::HtmlCommentClose, Token::comment_close()]),
   ]
 }
 
 pub fn provider_pattern() -> Vec<PatternMatcher> {
   vec![
-    one(vec![Token::HtmlCommentOpen]),
+    one(vec![Token::HtmlCommentOpen, Token::comment_open()]),
     optional_many(vec![Token::whitespace(), Token::Newline]),
     one(vec![Token::ProviderTag]),
     optional_many(vec![Token::whitespace()]),
@@ -62,6 +77,7 @@ pub fn provider_pattern() -> Vec<PatternMatcher> {
     optional_many_group(vec![
       one(vec![Token::Pipe]),
       optional_many(vec![Token::whitespace()]),
+      optional_many(vec![Token::AliasMarker]),
       one(vec![Token::any()]),
       optional_many(vec![Token::whitespace()]),
       optional_many_group(vec![
@@ -79,7 +95,77 @@ pub fn provider_pattern() -> Vec<PatternMatcher> {
     ]),
     one(vec![Token::BraceClose]),
     optional_many(vec![Token::whitespace(), Token::Newline]),
-    one(vec![Token::HtmlCommentClose]),
+    one(vec![Token::HtmlCommentClose, Token::comment_close()]),
+  ]
+}
+
+pub fn inline_pattern() -> Vec<PatternMatcher> {
+  vec![
+    one(vec![Token::HtmlCommentOpen, Token::comment_open()]),
+    optional_many(vec![Token::whitespace(), Token::Newline]),
+    one(vec![Token::InlineTag]),
+    optional_many(vec![Token::whitespace()]),
+    one(vec![Token::any()]),
+    optional_many(vec![Token::whitespace()]),
+    one(vec![Token::ArgumentDelimiter]),
+    optional_many(vec![Token::whitespace()]),
+    one(vec![Token::string()]),
+    optional_many(vec![Token::whitespace()]),
+    optional_many_group(vec![
+      one(vec![Token::Pipe]),
+      optional_many(vec![Token::whitespace()]),
+      optional_many(vec![Token::AliasMarker]),
+      one(vec![Token::any()]),
+      optional_many(vec![Token::whitespace()]),
+      optional_many_group(vec![
+        one(vec![Token::ArgumentDelimiter]),
+        optional_many(vec![Token::whitespace()]),
+        one(vec![
+          Token::string(),
+          Token::r#true(),
+          Token::r#false(),
+          Token::int(),
+          Token::float(),
+        ]),
+        optional_many(vec![Token::whitespace()]),
+      ]),
+    ]),
+    one(vec![Token::BraceClose]),
+    optional_many(vec![Token::whitespace(), Token::Newline]),
+    one(vec![Token::HtmlCommentClose, Token::comment_close()]),
+  ]
+}
+
+pub fn include_pattern() -> Vec<PatternMatcher> {
+  vec![
+    one(vec![Token::HtmlCommentOpen, Token::comment_open()]),
+    optional_many(vec![Token::whitespace(), Token::Newline]),
+    one(vec![Token::IncludeTag]),
+    optional_many(vec![Token::whitespace()]),
+    one(vec![Token::any()]),
+    optional_many(vec![Token::whitespace()]),
+    optional_many_group(vec![
+      one(vec![Token::Pipe]),
+      optional_many(vec![Token::whitespace()]),
+      optional_many(vec![Token::AliasMarker]),
+      one(vec![Token::any()]),
+      optional_many(vec![Token::whitespace()]),
+      optional_many_group(vec![
+        one(vec![Token::ArgumentDelimiter]),
+        optional_many(vec![Token::whitespace()]),
+        one(vec![
+          Token::string(),
+          Token::r#true(),
+          Token::r#false(),
+          Token::int(),
+          Token::float(),
+        ]),
+        optional_many(vec![Token::whitespace()]),
+      ]),
+    ]),
+    one(vec![Token::BraceClose]),
+    optional_many(vec![Token::whitespace(), Token::Newline]),
+    one(vec![Token::HtmlCommentClose, Token::comment_close()]),
   ]
 }
 
@@ -201,7 +287,13 @@ impl TokenGroup {
   }
 
   pub fn is_valid(&self) -> bool {
-    let patterns = vec![closing_pattern(), provider_pattern(), consumer_pattern()];
+    let patterns = vec![
+      closing_pattern(),
+      provider_pattern(),
+      consumer_pattern(),
+      include_pattern(),
+      inline_pattern(),
+    ];
 
     for pattern in patterns {
       let Some(result) = self.matches_pattern(pattern).ok() else {
@@ -245,4 +337,16 @@ impl Token {
   fn float() -> Self {
     Self::Float(0.0)
   }
+
+  fn data() -> Self {
+    Self::Data(String::new())
+  }
+
+  fn comment_open() -> Self {
+    Self::CommentOpen(String::new())
+  }
+
+  fn comment_close() -> Self {
+    Self::CommentClose(String::new())
+  }
 }
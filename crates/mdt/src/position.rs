@@ -12,7 +12,7 @@ pub struct Point {
   pub line: usize,
   /// 1-indexed integer representing a column in a source file.
   pub column: usize,
-  /// 0-indexed integer representing a character in a source file.
+  /// 0-indexed integer representing the byte offset in a source file.
   pub offset: usize,
 }
 
@@ -31,11 +31,11 @@ impl Point {
       if char == '\n' {
         self.line += 1;
         self.column = 0;
-        self.offset += 1;
       } else {
         self.column += 1;
-        self.offset += 1;
       }
+
+      self.offset += char.len_utf8();
     }
   }
 }
@@ -0,0 +1,913 @@
+use std::borrow::Cow;
+use std::collections::BTreeMap;
+use std::collections::BTreeSet;
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::sync::Mutex;
+
+use globset::GlobSet;
+use ignore::gitignore::Gitignore;
+use ignore::gitignore::GitignoreBuilder;
+use markdown::mdast::Html;
+use serde_json::Value;
+
+use crate::apply_transformers_with_context;
+use crate::check_sections;
+use crate::concatenated_names;
+use crate::content_hash;
+use crate::decrypt_content;
+use crate::detect_packages;
+use crate::env_profile;
+use crate::find_config_deprecations;
+use crate::find_includes;
+use crate::get_comment_nodes;
+use crate::get_html_nodes;
+use crate::get_source_html_nodes;
+use crate::is_encrypted;
+use crate::is_project_root;
+use crate::load_data;
+use crate::package_for;
+use crate::parse_comments_with_diagnostics;
+use crate::parse_source_with_diagnostics;
+use crate::parse_transformers;
+use crate::parse_with_diagnostics;
+use crate::render_template_with_filters;
+use crate::rewrite_image_urls;
+use crate::select_region;
+use crate::strip_region_markers;
+use crate::Block;
+use crate::BlockType;
+use crate::CommentStyle;
+use crate::DataMap;
+use crate::Deprecation;
+use crate::FrontMatter;
+use crate::MdtConfig;
+use crate::MdtError;
+use crate::MdtResult;
+use crate::Package;
+use crate::ParseDiagnostic;
+use crate::PathFilter;
+use crate::ProviderScope;
+use crate::TransformContext;
+use crate::TransformerType;
+use crate::CONCAT_SEPARATOR;
+use crate::CONFIG_FILE_NAME;
+
+/// The suffix used by definition files which contain the provider blocks.
+pub const DEFINITION_FILE_SUFFIX: &str = ".t.md";
+
+/// The directory for definition files. It is scanned although other hidden
+/// directories are skipped.
+pub const TEMPLATES_DIRECTORY: &str = ".templates";
+
+/// Files with these extensions are parsed as markdown.
+pub const MARKDOWN_EXTENSIONS: &[&str] = &["md", "mdx", "markdown"];
+
+/// Files with these extensions are scanned for html comments containing tags,
+/// unless `[scan] extensions` replaces them.
+pub const SOURCE_EXTENSIONS: &[&str] = &[
+  "rs", "ts", "tsx", "js", "jsx", "mjs", "cjs", "mts", "cts", "py", "go", "java", "kt", "swift",
+  "dart", "rb", "c", "h", "cpp", "hpp", "cs", "php", "toml", "yaml", "yml", "html",
+];
+
+/// The file which lists the paths below its directory that aren't scanned,
+/// with the syntax of `.gitignore`. It can be placed in any directory.
+pub const IGNORE_FILE_NAME: &str = ".mdtignore";
+
+/// Directories which are never scanned.
+pub(crate) const IGNORED_DIRECTORIES: &[&str] = &["node_modules", "target"];
+
+/// A provider block along with the file it was defined in.
+#[derive(Debug, Clone)]
+pub struct ProviderEntry {
+  pub block: Block,
+  pub file: PathBuf,
+  /// The content between the opening and closing tags with the doc comment
+  /// removed.
+  pub content: String,
+  /// A short description of the provider. It is read from a leading
+  /// `{#- doc: ... -#}` comment or else from the `description` in the front
+  /// matter of the definition file.
+  pub description: Option<String>,
+  /// The tags from the front matter of the definition file.
+  pub tags: Vec<String>,
+  /// Whether other projects of a workspace can consume the provider.
+  pub scope: ProviderScope,
+  /// The directory of the workspace project the provider was imported from,
+  /// relative to the workspace root. It is `None` for the project's own
+  /// providers.
+  pub imported_from: Option<PathBuf>,
+}
+
+impl ProviderEntry {
+  /// Whether the provider has every one of the tags.
+  pub fn has_tags(&self, tags: &[String]) -> bool {
+    tags.iter().all(|tag| self.tags.contains(tag))
+  }
+}
+
+/// A consumer block along with the file it was found in.
+#[derive(Debug, Clone)]
+pub struct ConsumerEntry {
+  pub block: Block,
+  pub file: PathBuf,
+  /// The current content between the opening and closing tags.
+  pub content: String,
+}
+
+impl ConsumerEntry {
+  /// Whether the content no longer matches the hash recorded in the closing
+  /// tag, because it was edited by hand since it was last written.
+  pub fn is_edited(&self) -> bool {
+    self
+      .block
+      .hash
+      .as_ref()
+      .map_or(false, |hash| hash != &content_hash(&self.content))
+  }
+
+  /// Whether the consumer is held at its current content with `|frozen`.
+  pub fn is_frozen(&self) -> bool {
+    self
+      .block
+      .transformers
+      .iter()
+      .any(|transformer| transformer.r#type == TransformerType::Frozen)
+  }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Project {
+  /// The providers found in the definition files, indexed by name.
+  pub providers: HashMap<String, ProviderEntry>,
+  /// All consumers in the order they were found.
+  pub consumers: Vec<ConsumerEntry>,
+  /// The content of every file which contains at least one block.
+  pub files: BTreeMap<PathBuf, String>,
+  /// The configuration loaded from the project root.
+  pub config: MdtConfig,
+  /// The data loaded from the files in the `[data]` section of the
+  /// configuration.
+  pub data: DataMap,
+  /// The directory the project was scanned from. It is empty for projects
+  /// built from files in memory.
+  pub root: PathBuf,
+  /// The problems the parser recovered from, with the file they were found in.
+  pub parse_diagnostics: Vec<(PathBuf, ParseDiagnostic)>,
+  /// The packages of a monorepo. It is empty for projects built from files in
+  /// memory.
+  pub packages: Vec<Package>,
+  /// The deprecated features which the project uses.
+  pub deprecations: Vec<Deprecation>,
+  /// The providers which other projects of the workspace consume.
+  pub workspace_consumed: BTreeSet<String>,
+  /// The decrypted content of encrypted providers, keyed by the encrypted
+  /// content, so the key command runs once per provider within a scan.
+  decrypted: Arc<Mutex<HashMap<String, String>>>,
+}
+
+impl Project {
+  /// Build a project from files which have already been read into memory.
+  pub fn from_files(files: impl IntoIterator<Item = (PathBuf, String)>) -> MdtResult<Self> {
+    Self::from_files_with_config(files, MdtConfig::default())
+  }
+
+  /// Build a project from files in memory using the provided configuration.
+  pub fn from_files_with_config(
+    files: impl IntoIterator<Item = (PathBuf, String)>,
+    config: MdtConfig,
+  ) -> MdtResult<Self> {
+    let mut project = Project {
+      config,
+      ..Project::default()
+    };
+    let mut files = files.into_iter().collect::<Vec<_>>();
+    files.sort_by(|a, b| a.0.cmp(&b.0));
+
+    for (file, content) in files {
+      project.add_file(file, content)?;
+    }
+
+    Ok(project)
+  }
+
+  /// Render the provider content. When data or filters have been configured
+  /// the content is first rendered as a template, e.g. `{{ pkg.version }}`,
+  /// so projects without either can use `{{` freely.
+  pub fn render_provider(&self, provider: &ProviderEntry) -> MdtResult<String> {
+    self.render_nested_provider(provider, None, None, &mut vec![])
+  }
+
+  /// Render the provider after replacing the content of the consumers nested
+  /// within it, so blocks are resolved inside-out. Only the named region is
+  /// rendered when one is given and the data overrides of the consumer are
+  /// merged over the project data. `parents` holds the names of the providers
+  /// currently being rendered to detect cycles.
+  fn render_nested_provider(
+    &self,
+    provider: &ProviderEntry,
+    region: Option<&str>,
+    data: Option<&Value>,
+    parents: &mut Vec<String>,
+  ) -> MdtResult<String> {
+    let name = &provider.block.name;
+
+    if parents.contains(name) {
+      return Err(MdtError::CircularBlock(name.clone()));
+    }
+
+    parents.push(name.clone());
+    let content = self.render_nested_content(provider, region, data, parents);
+    parents.pop();
+
+    let content = apply_transformers_with_context(
+      content?,
+      &provider.block.transformers,
+      &self.transform_context(provider, None),
+    )?;
+
+    let Some(base_url) = self.config.assets.base_url.as_deref() else {
+      return Ok(content);
+    };
+
+    let file = provider
+      .file
+      .strip_prefix(&self.root)
+      .unwrap_or(&provider.file);
+
+    Ok(rewrite_image_urls(&content, file, base_url))
+  }
+
+  /// The providers rendered by a consumer with the name, in order. A
+  /// consumer like `{=intro+install}` concatenates several providers. Returns
+  /// `None` when any of them doesn't exist.
+  pub fn providers_for(&self, name: &str) -> Option<Vec<&ProviderEntry>> {
+    concatenated_names(name)
+      .map(|name| self.providers.get(name))
+      .collect()
+  }
+
+  /// The name of the innermost package which contains the file.
+  pub fn package_name(&self, file: &Path) -> Option<&str> {
+    package_for(&self.packages, file).map(|package| package.name.as_str())
+  }
+
+  /// The path of the file relative to the root, for output. Files outside of
+  /// the root are returned as they are.
+  pub fn relative_path<'a>(&self, file: &'a Path) -> &'a Path {
+    file.strip_prefix(&self.root).unwrap_or(file)
+  }
+
+  /// Render the content a consumer should contain, resolving nested blocks.
+  /// The providers of a concatenating consumer are joined before the
+  /// consumer's transformers are applied, using the context of the first one.
+  /// Returns `None` when there is no provider with a matching name, unless
+  /// the consumer is optional, which renders as empty. Inline blocks render
+  /// their own template instead.
+  pub(crate) fn render_nested_consumer(
+    &self,
+    consumer: &ConsumerEntry,
+    parents: &mut Vec<String>,
+  ) -> MdtResult<Option<String>> {
+    if let Some(template) = consumer.block.template.as_deref() {
+      return self.render_inline(consumer, template).map(Some);
+    }
+
+    let Some(providers) = self.providers_for(&consumer.block.name) else {
+      return Ok(consumer.block.optional.then(String::new));
+    };
+
+    let mut content = String::new();
+
+    for provider in &providers {
+      let block = &consumer.block;
+      content.push_str(&self.render_nested_provider(
+        provider,
+        block.region.as_deref(),
+        block.data.as_ref(),
+        parents,
+      )?);
+    }
+
+    check_sections(&consumer.block.name, &content, &consumer.block.transformers)?;
+    let Some(provider) = providers.first() else {
+      return Ok(None);
+    };
+    let context = self.transform_context(provider, Some(consumer));
+
+    apply_transformers_with_context(content, &consumer.block.transformers, &context).map(Some)
+  }
+
+  /// Render the template of an inline block with the project data, e.g.
+  /// `{{ pkg.version }}`, and then apply its transformers.
+  fn render_inline(&self, consumer: &ConsumerEntry, template: &str) -> MdtResult<String> {
+    let content = render_template_with_filters(
+      &consumer.block.name,
+      template,
+      &self.data,
+      &self.config.filters,
+    )?;
+    let context = TransformContext {
+      provider_file: None,
+      consumer_file: Some(&consumer.file),
+      custom_transformers: Some(&self.config.transformers.custom),
+      data: Some(&self.data),
+    };
+
+    apply_transformers_with_context(content, &consumer.block.transformers, &context)
+  }
+
+  /// The decrypted content of the provider. It is only decrypted the first
+  /// time it is rendered.
+  fn decrypt(&self, provider: &ProviderEntry) -> MdtResult<String> {
+    let cached = self
+      .decrypted
+      .lock()
+      .ok()
+      .and_then(|decrypted| decrypted.get(&provider.content).cloned());
+
+    if let Some(content) = cached {
+      return Ok(content);
+    }
+
+    let content = decrypt_content(
+      &provider.block.name,
+      &provider.content,
+      &self.config.encryption,
+      &self.root,
+    )?;
+
+    if let Ok(mut decrypted) = self.decrypted.lock() {
+      decrypted.insert(provider.content.clone(), content.clone());
+    }
+
+    Ok(content)
+  }
+
+  /// The provider content with nested consumers resolved, narrowed to the
+  /// region, rendered as a template and with includes replaced by the content
+  /// they include. Region markers are removed before the template is rendered.
+  fn render_nested_content(
+    &self,
+    provider: &ProviderEntry,
+    region: Option<&str>,
+    data: Option<&Value>,
+    parents: &mut Vec<String>,
+  ) -> MdtResult<String> {
+    let content = if is_encrypted(&provider.content) {
+      self.decrypt(provider)?
+    } else {
+      self.resolve_nested_consumers(provider, parents)?
+    };
+    let content = match region {
+      Some(region) => {
+        select_region(&content, region).ok_or_else(|| {
+          MdtError::MissingRegion {
+            name: provider.block.name.clone(),
+            region: region.to_string(),
+          }
+        })?
+      }
+      None => strip_region_markers(&content),
+    };
+    let context = self.build_render_context(data);
+    let content = if context.is_empty() && self.config.filters.is_empty() {
+      content
+    } else {
+      render_template_with_filters(
+        &provider.block.name,
+        &content,
+        &context,
+        &self.config.filters,
+      )?
+    };
+
+    self.resolve_includes(content, parents)
+  }
+
+  /// The data used to render provider templates, with the overrides of a
+  /// consumer merged over the project data. Nested objects are merged key by
+  /// key.
+  pub fn build_render_context(&self, overrides: Option<&Value>) -> Cow<'_, DataMap> {
+    let Some(Value::Object(overrides)) = overrides else {
+      return Cow::Borrowed(&self.data);
+    };
+
+    let mut context = self.data.clone();
+
+    for (key, value) in overrides {
+      match context.get_mut(key) {
+        Some(existing) => merge_values(existing, value),
+        None => {
+          context.insert(key.clone(), value.clone());
+        }
+      }
+    }
+
+    Cow::Owned(context)
+  }
+
+  /// Replace each `{> name}` include tag with the rendered content of the
+  /// provider it names.
+  fn resolve_includes(&self, content: String, parents: &mut Vec<String>) -> MdtResult<String> {
+    let includes = find_includes(&content, &self.config)?;
+    let mut resolved = content;
+
+    for include in includes.iter().rev() {
+      if parents.contains(&include.name) {
+        let mut chain = parents.clone();
+        chain.push(include.name.clone());
+        return Err(MdtError::CircularInclude(chain.join(" -> ")));
+      }
+
+      let Some(provider) = self.providers.get(&include.name) else {
+        return Err(MdtError::UnknownBlock(include.name.clone()));
+      };
+
+      let rendered = self.render_nested_provider(provider, None, None, parents)?;
+      let rendered = apply_transformers_with_context(
+        rendered,
+        &include.transformers,
+        &self.transform_context(provider, None),
+      )?;
+      let range = include.position.start.offset..include.position.end.offset;
+
+      if resolved.get(range.clone()).is_some() {
+        resolved.replace_range(range, &rendered);
+      }
+    }
+
+    Ok(resolved)
+  }
+
+  /// The provider content with the content of each directly nested consumer
+  /// replaced by its rendered content.
+  fn resolve_nested_consumers(
+    &self,
+    provider: &ProviderEntry,
+    parents: &mut Vec<String>,
+  ) -> MdtResult<String> {
+    let nested = self
+      .consumers
+      .iter()
+      .filter(|consumer| consumer.file == provider.file && provider.block.contains(&consumer.block))
+      .collect::<Vec<_>>();
+    let direct = nested.iter().filter(|consumer| {
+      !nested
+        .iter()
+        .any(|other| other.block != consumer.block && other.block.contains(&consumer.block))
+    });
+
+    // Offsets within the provider content, which doesn't include the doc
+    // comment.
+    let range = provider.block.content_range();
+    let removed = range.len().saturating_sub(provider.content.len());
+    let mut content = provider.content.clone();
+    let mut replacements = vec![];
+
+    for consumer in direct {
+      let Some(rendered) = self.render_nested_consumer(consumer, parents)? else {
+        continue;
+      };
+
+      let consumer_range = consumer.block.content_range();
+      let start = consumer_range.start.saturating_sub(range.start + removed);
+      let end = consumer_range.end.saturating_sub(range.start + removed);
+      replacements.push((start..end, rendered));
+    }
+
+    replacements.sort_by_key(|(range, _)| std::cmp::Reverse(range.start));
+
+    for (range, rendered) in replacements {
+      if content.get(range.clone()).is_some() {
+        content.replace_range(range, &rendered);
+      }
+    }
+
+    Ok(content)
+  }
+
+  /// The context for applying transformers to the content of the provider,
+  /// optionally on its way into the consumer.
+  pub fn transform_context<'a>(
+    &'a self,
+    provider: &'a ProviderEntry,
+    consumer: Option<&'a ConsumerEntry>,
+  ) -> TransformContext<'a> {
+    TransformContext {
+      provider_file: Some(&provider.file),
+      consumer_file: consumer.map(|consumer| consumer.file.as_path()),
+      custom_transformers: Some(&self.config.transformers.custom),
+      data: Some(&self.data),
+    }
+  }
+
+  /// Parse the file and add its blocks to the project.
+  pub fn add_file(&mut self, file: PathBuf, content: String) -> MdtResult<()> {
+    let (blocks, diagnostics) = parse_file_with_diagnostics(&file, &content, &self.config)?;
+    self.parse_diagnostics.extend(
+      diagnostics
+        .into_iter()
+        .map(|diagnostic| (file.clone(), diagnostic)),
+    );
+    let is_definition = is_definition_file(&file);
+    let has_blocks = !blocks.is_empty();
+    let front_matter = if is_definition {
+      FrontMatter::parse(&content)
+    } else {
+      FrontMatter::default()
+    };
+
+    for block in blocks {
+      let block_content = content
+        .get(block.content_range())
+        .unwrap_or_default()
+        .to_string();
+
+      match block.r#type {
+        BlockType::Provider if is_definition => {
+          if block.name.contains(CONCAT_SEPARATOR) {
+            return Err(MdtError::InvalidBlockName {
+              name: block.name,
+              suggestion: None,
+            });
+          }
+
+          self.config.naming.check(&block.name)?;
+          let (doc, block_content) = split_doc_comment(&block_content);
+          let description = doc.or_else(|| front_matter.description.clone());
+
+          if let Some(existing) = self.providers.get(&block.name) {
+            return Err(MdtError::DuplicateProvider {
+              name: block.name,
+              first: existing.file.clone(),
+              second: file,
+            });
+          }
+
+          self.providers.insert(
+            block.name.clone(),
+            ProviderEntry {
+              block,
+              file: file.clone(),
+              content: block_content,
+              description,
+              tags: front_matter.tags.clone(),
+              scope: front_matter.scope.unwrap_or(self.config.workspace.scope),
+              imported_from: None,
+            },
+          );
+        }
+        BlockType::Provider => {}
+        BlockType::Consumer => {
+          let mut block = block;
+
+          if block.transformers.is_empty() {
+            if let Some(chain) = self.config.defaults.transformers_for(&file) {
+              block.transformers = parse_transformers(chain, &self.config)?;
+            }
+          }
+
+          self.consumers.push(ConsumerEntry {
+            block,
+            file: file.clone(),
+            content: block_content,
+          });
+        }
+        BlockType::Inline => {
+          self.consumers.push(ConsumerEntry {
+            block,
+            file: file.clone(),
+            content: block_content,
+          });
+        }
+      }
+    }
+
+    if has_blocks {
+      self.files.insert(file, content);
+    }
+
+    Ok(())
+  }
+}
+
+/// Scan the directory for definition files and consumers.
+pub fn scan_project(root: impl AsRef<Path>) -> MdtResult<Project> {
+  scan_project_with_overlays(root, &BTreeMap::new())
+}
+
+/// Scan the directory, preferring the overlay content to the content on disk.
+/// Overlays are the unsaved contents of documents open in an editor so that
+/// edits computed from the project match what the user currently sees.
+pub fn scan_project_with_overlays(
+  root: impl AsRef<Path>,
+  overlays: &BTreeMap<PathBuf, String>,
+) -> MdtResult<Project> {
+  scan_project_filtered(
+    root,
+    overlays,
+    &PathFilter::default(),
+    env_profile().as_deref(),
+  )
+}
+
+/// Scan the directory with the overlays and the profile applied to the
+/// configuration, skipping the files which don't pass the filter.
+pub fn scan_project_filtered(
+  root: impl AsRef<Path>,
+  overlays: &BTreeMap<PathBuf, String>,
+  filter: &PathFilter,
+  profile: Option<&str>,
+) -> MdtResult<Project> {
+  let root = root.as_ref();
+  let config = MdtConfig::load_with_profile(root, profile)?;
+  let data = load_data(root, &config)?;
+  let include = config.include.matcher()?;
+  let included = |file: &Path| {
+    is_included(include.as_ref(), root, file) && (is_definition_file(file) || filter.matches(file))
+  };
+  let mut files = scanned_files(root, &config)?;
+  files.retain(|file| is_definition_file(file) || filter.matches(file));
+
+  let mut contents = BTreeMap::new();
+
+  for file in files {
+    let content = match overlays.get(&file) {
+      Some(content) => content.clone(),
+      None => fs::read_to_string(&file)?,
+    };
+
+    contents.insert(file, content);
+  }
+
+  for (file, content) in overlays {
+    if file.starts_with(root)
+      && is_scanned_file(file, &config)
+      && included(file)
+      && !contents.contains_key(file)
+      && !is_ignored_path(root, file)?
+    {
+      contents.insert(file.clone(), content.clone());
+    }
+  }
+
+  let mut project = Project::from_files_with_config(contents, config)?;
+  project.data = data;
+  project.packages = detect_packages(root)?;
+
+  let config_file = root.join(CONFIG_FILE_NAME);
+
+  if config_file.is_file() {
+    project.deprecations = find_config_deprecations(&fs::read_to_string(config_file)?);
+  }
+  project.root = root.to_path_buf();
+
+  Ok(project)
+}
+
+/// The files below the root which are scanned for blocks, in sorted order.
+/// Files ignored by a `.mdtignore`, outside of the `[include]` patterns or
+/// within a nested project are skipped.
+pub fn scanned_files(root: &Path, config: &MdtConfig) -> MdtResult<Vec<PathBuf>> {
+  let include = config.include.matcher()?;
+  let mut files = vec![];
+  collect_files(root, config, &mut vec![], &mut files)?;
+  files.retain(|file| is_included(include.as_ref(), root, file));
+  files.sort();
+
+  Ok(files)
+}
+
+/// Whether the `[include]` patterns match the file. Every file is included
+/// when there are no patterns.
+fn is_included(include: Option<&GlobSet>, root: &Path, file: &Path) -> bool {
+  include.map_or(true, |include| {
+    include.is_match(file.strip_prefix(root).unwrap_or(file))
+  })
+}
+
+/// Collect the scanned files below the directory. `ignores` holds the ignore
+/// files of the parent directories, innermost last.
+fn collect_files(
+  directory: &Path,
+  config: &MdtConfig,
+  ignores: &mut Vec<Gitignore>,
+  files: &mut Vec<PathBuf>,
+) -> MdtResult<()> {
+  let ignore = load_ignore_file(directory)?;
+  let has_ignore = ignore.is_some();
+  ignores.extend(ignore);
+
+  for entry in fs::read_dir(directory)? {
+    let entry = entry?;
+    let path = entry.path();
+    let file_type = entry.file_type()?;
+    let name = entry.file_name();
+    let name = name.to_string_lossy();
+
+    if is_ignored(ignores, &path, file_type.is_dir()) {
+      continue;
+    }
+
+    if file_type.is_dir() {
+      if (name.starts_with('.') && name != TEMPLATES_DIRECTORY)
+        || IGNORED_DIRECTORIES.contains(&name.as_ref())
+        || is_project_root(&path)
+      {
+        continue;
+      }
+
+      collect_files(&path, config, ignores, files)?;
+    } else if file_type.is_file() && is_scanned_file(&path, config) {
+      files.push(path);
+    }
+  }
+
+  if has_ignore {
+    ignores.pop();
+  }
+
+  Ok(())
+}
+
+/// The `.mdtignore` file of the directory, when there is one.
+fn load_ignore_file(directory: &Path) -> MdtResult<Option<Gitignore>> {
+  let path = directory.join(IGNORE_FILE_NAME);
+
+  if !path.is_file() {
+    return Ok(None);
+  }
+
+  let error = |error: ignore::Error| {
+    MdtError::IgnoreFile {
+      path: path.clone(),
+      message: error.to_string(),
+    }
+  };
+  let mut builder = GitignoreBuilder::new(directory);
+
+  if let Some(cause) = builder.add(&path) {
+    return Err(error(cause));
+  }
+
+  builder.build().map(Some).map_err(error)
+}
+
+/// Whether the innermost ignore file with a matching pattern ignores the
+/// path. A negated pattern, e.g. `!keep.md`, includes it again.
+fn is_ignored(ignores: &[Gitignore], path: &Path, is_dir: bool) -> bool {
+  ignores
+    .iter()
+    .rev()
+    .map(|ignore| ignore.matched(path, is_dir))
+    .find(|matched| !matched.is_none())
+    .map_or(false, |matched| matched.is_ignore())
+}
+
+/// Whether the file, or a directory containing it, is ignored by the ignore
+/// files between the root and the file.
+fn is_ignored_path(root: &Path, file: &Path) -> MdtResult<bool> {
+  let relative = file.strip_prefix(root).unwrap_or(file);
+  let mut components = relative.components().peekable();
+  let mut directory = root.to_path_buf();
+  let mut ignores = vec![];
+
+  while let Some(component) = components.next() {
+    ignores.extend(load_ignore_file(&directory)?);
+    let path = directory.join(component);
+
+    if is_ignored(&ignores, &path, components.peek().is_some()) {
+      return Ok(true);
+    }
+
+    directory = path;
+  }
+
+  Ok(false)
+}
+
+/// Whether the file is a definition file, e.g. `template.t.md`.
+pub fn is_definition_file(file: &Path) -> bool {
+  file.file_name().map_or(false, |name| {
+    name.to_string_lossy().ends_with(DEFINITION_FILE_SUFFIX)
+  })
+}
+
+fn is_markdown_file(file: &Path) -> bool {
+  has_extension(file, MARKDOWN_EXTENSIONS)
+}
+
+/// Whether the file is scanned for tags. Files with a configured comment
+/// style are scanned along with markdown and source files.
+fn is_scanned_file(file: &Path, config: &MdtConfig) -> bool {
+  has_extension(file, MARKDOWN_EXTENSIONS)
+    || file.extension().map_or(false, |extension| {
+      config
+        .scan
+        .is_source_extension(&extension.to_string_lossy())
+    })
+    || config.comment_style(file) != CommentStyle::html()
+}
+
+fn has_extension(file: &Path, extensions: &[&str]) -> bool {
+  file.extension().map_or(false, |extension| {
+    extensions
+      .iter()
+      .any(|candidate| extension.eq_ignore_ascii_case(candidate))
+  })
+}
+
+/// Parse the file as markdown or as source code depending on the extension.
+/// Files with a configured comment style are parsed using its delimiters.
+pub fn parse_file(file: &Path, content: &str, config: &MdtConfig) -> MdtResult<Vec<Block>> {
+  parse_file_with_diagnostics(file, content, config).map(|(blocks, _)| blocks)
+}
+
+/// Parse the file and report the problems which the parser recovered from.
+pub fn parse_file_with_diagnostics(
+  file: &Path,
+  content: &str,
+  config: &MdtConfig,
+) -> MdtResult<(Vec<Block>, Vec<ParseDiagnostic>)> {
+  let style = config.comment_style(file);
+
+  if style != CommentStyle::html() {
+    parse_comments_with_diagnostics(content, &style, config)
+  } else if is_markdown_file(file) {
+    parse_with_diagnostics(content, config)
+  } else {
+    parse_source_with_diagnostics(content, config)
+  }
+}
+
+/// The comments which the parser reads tags from in the file. Markdown which
+/// can't be parsed has no comments.
+pub(crate) fn comment_nodes(file: &Path, content: &str, config: &MdtConfig) -> Vec<Html> {
+  let style = config.comment_style(file);
+
+  if style != CommentStyle::html() {
+    get_comment_nodes(content, &style)
+  } else if is_markdown_file(file) {
+    get_html_nodes(content).unwrap_or_default()
+  } else {
+    get_source_html_nodes(content)
+  }
+}
+
+/// Merge the value over the target. Objects are merged key by key and every
+/// other value replaces the target.
+fn merge_values(target: &mut Value, value: &Value) {
+  match (target, value) {
+    (Value::Object(target), Value::Object(value)) => {
+      for (key, value) in value {
+        match target.get_mut(key) {
+          Some(existing) => merge_values(existing, value),
+          None => {
+            target.insert(key.clone(), value.clone());
+          }
+        }
+      }
+    }
+    (target, value) => *target = value.clone(),
+  }
+}
+
+/// Split a leading `{#- doc: ... -#}` comment from the provider content. The
+/// comment and the line break which follows it are removed. The dashes are
+/// optional.
+pub fn split_doc_comment(content: &str) -> (Option<String>, String) {
+  let trimmed = content.trim_start();
+  let leading = content.len() - trimmed.len();
+  let parsed = trimmed
+    .strip_prefix("{#")
+    .and_then(|rest| {
+      let end = rest.find("#}")?;
+      Some((rest.get(..end)?, end + 4))
+    })
+    .and_then(|(comment, length)| {
+      let comment = comment.strip_prefix('-').unwrap_or(comment);
+      let comment = comment.strip_suffix('-').unwrap_or(comment);
+      let doc = comment.trim().strip_prefix("doc:")?.trim();
+      Some((doc, length))
+    });
+
+  let Some((doc, length)) = parsed else {
+    return (None, content.to_string());
+  };
+
+  let rest = trimmed.get(length..).unwrap_or_default();
+  let rest = rest
+    .strip_prefix("\r\n")
+    .or_else(|| rest.strip_prefix('\n'))
+    .unwrap_or(rest);
+  let description = (!doc.is_empty()).then(|| doc.to_string());
+
+  (
+    description,
+    format!("{}{rest}", content.get(..leading).unwrap_or_default()),
+  )
+}
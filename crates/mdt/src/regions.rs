@@ -0,0 +1,95 @@
+use std::ops::Range;
+
+use regex::Regex;
+
+/// A named region within provider content, marked with
+/// `<!-- {#region: quickstart} -->` and `<!-- {/region} -->`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Region {
+  pub name: String,
+  /// The byte range of the content between the markers.
+  pub content: Range<usize>,
+}
+
+/// Find the regions within the content. Regions can be nested and markers
+/// which aren't paired are ignored.
+pub fn find_regions(content: &str) -> Vec<Region> {
+  let mut regions = vec![];
+  let mut open: Vec<(String, usize)> = vec![];
+
+  for (marker, name) in region_markers(content) {
+    match name {
+      Some(name) => open.push((name, marker.end)),
+      None => {
+        if let Some((name, start)) = open.pop() {
+          regions.push(Region {
+            name,
+            content: start..marker.start,
+          });
+        }
+      }
+    }
+  }
+
+  regions.sort_by_key(|region| region.content.start);
+  regions
+}
+
+/// The content of the named region without any nested markers. Returns `None`
+/// when the region doesn't exist.
+pub fn select_region(content: &str, name: &str) -> Option<String> {
+  let region = find_regions(content)
+    .into_iter()
+    .find(|region| region.name == name)?;
+
+  content.get(region.content).map(strip_region_markers)
+}
+
+/// Remove every region marker from the content. A marker on a line of its
+/// own is removed along with the line break which follows it.
+pub fn strip_region_markers(content: &str) -> String {
+  let mut stripped = String::with_capacity(content.len());
+  let mut offset = 0;
+
+  for (marker, _) in region_markers(content) {
+    let starts_line = marker.start == 0
+      || content
+        .get(..marker.start)
+        .map_or(false, |before| before.ends_with('\n'));
+    let end = if starts_line
+      && content
+        .get(marker.end..)
+        .map_or(false, |after| after.starts_with('\n'))
+    {
+      marker.end + 1
+    } else {
+      marker.end
+    };
+
+    stripped.push_str(content.get(offset..marker.start).unwrap_or_default());
+    offset = end;
+  }
+
+  stripped.push_str(content.get(offset..).unwrap_or_default());
+  stripped
+}
+
+/// The opening and closing region markers in order. Opening markers include
+/// the name of the region.
+fn region_markers(content: &str) -> Vec<(Range<usize>, Option<String>)> {
+  let Ok(pattern) =
+    Regex::new(r"<!--\s*\{(?:#region:\s*([A-Za-z_][A-Za-z0-9_/:]*)|/region)\s*\}\s*-->")
+  else {
+    return vec![];
+  };
+
+  pattern
+    .captures_iter(content)
+    .filter_map(|captures| {
+      let marker = captures.get(0)?;
+      let name = captures.get(1).map(|name| name.as_str().to_string());
+
+      Some((marker.range(), name))
+    })
+    .collect()
+}
@@ -0,0 +1,130 @@
+use std::ops::Range;
+use std::path::PathBuf;
+
+use crate::concatenated_names;
+use crate::MdtError;
+use crate::MdtResult;
+use crate::Patch;
+use crate::Position;
+use crate::Project;
+
+/// What `mdt rm` does with the consumers of the removed provider.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ConsumerRemoval {
+  /// Leave the consumers as they are. They become orphans.
+  #[default]
+  Keep,
+  /// Remove the tags of the consumers and keep their last synced content.
+  Strip,
+}
+
+/// The patches which remove a provider along with what happens to its
+/// consumers, as `(file, name)` pairs.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct BlockRemoval {
+  pub patches: Vec<Patch>,
+  /// The consumers whose tags are removed.
+  pub stripped: Vec<(PathBuf, String)>,
+  /// The consumers which are left without a provider.
+  pub orphans: Vec<(PathBuf, String)>,
+}
+
+/// Compute the patches which remove a provider. Tags which are alone on their
+/// line are removed along with the line. Consumers of a concatenation, e.g.
+/// `{=first+second}`, are never stripped.
+pub fn compute_removal(
+  project: &Project,
+  name: &str,
+  consumers: ConsumerRemoval,
+) -> MdtResult<BlockRemoval> {
+  let Some(provider) = project.providers.get(name) else {
+    return Err(MdtError::UnknownBlock(name.to_string()));
+  };
+
+  let mut removal = BlockRemoval::default();
+  let mut remove = |file: &PathBuf, start: &Position, end: &Position| {
+    let content = project
+      .files
+      .get(file)
+      .map(String::as_str)
+      .unwrap_or_default();
+
+    removal.patches.push(Patch {
+      file: file.clone(),
+      name: name.to_string(),
+      range: whole_line(content, start.start.offset..end.end.offset),
+      replacement: String::new(),
+    });
+  };
+
+  remove(
+    &provider.file,
+    &provider.block.opening,
+    &provider.block.closing,
+  );
+
+  for consumer in project
+    .consumers
+    .iter()
+    .filter(|consumer| concatenated_names(&consumer.block.name).any(|candidate| candidate == name))
+  {
+    let entry = (consumer.file.clone(), consumer.block.name.clone());
+
+    if consumers == ConsumerRemoval::Strip && consumer.block.name == name {
+      remove(
+        &consumer.file,
+        &consumer.block.opening,
+        &consumer.block.opening,
+      );
+      remove(
+        &consumer.file,
+        &consumer.block.closing,
+        &consumer.block.closing,
+      );
+      removal.stripped.push(entry);
+    } else {
+      removal.orphans.push(entry);
+    }
+  }
+
+  removal
+    .patches
+    .sort_by(|a, b| a.file.cmp(&b.file).then(a.range.start.cmp(&b.range.start)));
+
+  Ok(removal)
+}
+
+/// Extend the range to its whole line, including the line break, when nothing
+/// but whitespace surrounds it on the line. A blank line which follows is also
+/// removed when one precedes it, so that no double blank line is left.
+fn whole_line(content: &str, range: Range<usize>) -> Range<usize> {
+  let before = content.get(..range.start).unwrap_or_default();
+  let after = content.get(range.end..).unwrap_or_default();
+  let line_start = before.rfind('\n').map_or(0, |index| index + 1);
+  let line_end = after
+    .find('\n')
+    .map_or(content.len(), |index| range.end + index + 1);
+  let alone = before
+    .get(line_start..)
+    .map_or(false, |text| text.trim().is_empty())
+    && content
+      .get(range.end..line_end)
+      .map_or(false, |text| text.trim().is_empty());
+
+  if !alone {
+    return range;
+  }
+
+  let preceded = before
+    .get(..line_start)
+    .map_or(true, |text| text.is_empty() || text.ends_with("\n\n"));
+  let blank = content
+    .get(line_end..)
+    .and_then(|rest| rest.split_inclusive('\n').next())
+    .filter(|line| line.trim().is_empty() && line.ends_with('\n'));
+
+  match blank {
+    Some(blank) if preceded => line_start..line_end + blank.len(),
+    _ => line_start..line_end,
+  }
+}
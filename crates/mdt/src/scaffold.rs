@@ -0,0 +1,44 @@
+use std::path::Path;
+
+use crate::is_definition_file;
+use crate::validate_new_block_name;
+use crate::MdtError;
+use crate::MdtResult;
+use crate::Project;
+
+/// The template file within [`TEMPLATES_DIRECTORY`] which `mdt new` adds
+/// providers to by default.
+pub const DEFAULT_TEMPLATE_FILE: &str = "template.t.md";
+
+/// Append an empty provider for the block to the content of a template file.
+/// The name must be valid and not used by another provider of the project.
+pub fn scaffold_provider(
+  project: &Project,
+  file: &Path,
+  content: &str,
+  name: &str,
+) -> MdtResult<String> {
+  if !is_definition_file(file) {
+    return Err(MdtError::NotATemplateFile(file.to_path_buf()));
+  }
+
+  validate_new_block_name(project, name)?;
+
+  let style = project.config.comment_style(file);
+  let mut updated = content.to_string();
+
+  if !updated.is_empty() && !updated.ends_with('\n') {
+    updated.push('\n');
+  }
+
+  if !updated.is_empty() {
+    updated.push('\n');
+  }
+
+  updated.push_str(&style.wrap(&format!("{{@{name}}}")));
+  updated.push_str("\n\n");
+  updated.push_str(&style.wrap(&format!("{{/{name}}}")));
+  updated.push('\n');
+
+  Ok(updated)
+}
@@ -0,0 +1,143 @@
+use std::ops::Range;
+use std::path::PathBuf;
+
+use regex::Regex;
+use serde::Serialize;
+
+use crate::concatenated_names;
+use crate::Project;
+
+/// The maximum number of snippets returned for each provider.
+const MAX_SNIPPETS: usize = 3;
+
+/// A provider which matches a search query.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct SearchResult {
+  pub name: String,
+  /// The file of the provider, relative to the root of the project.
+  pub file: PathBuf,
+  pub description: Option<String>,
+  pub tags: Vec<String>,
+  /// Whether the query matches the name of the provider.
+  pub name_match: bool,
+  /// The lines of the provider content which match the query.
+  pub snippets: Vec<SearchSnippet>,
+  /// The number of consumers of the provider.
+  pub consumer_count: usize,
+}
+
+/// A matching line of provider content.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct SearchSnippet {
+  /// The line number within the provider content, starting at 1.
+  pub line: usize,
+  pub text: String,
+  /// The byte ranges within `text` which match the query.
+  pub highlights: Vec<Range<usize>>,
+}
+
+impl SearchSnippet {
+  /// The snippet text with every match wrapped in the marker, e.g. `**`.
+  pub fn highlighted(&self, marker: &str) -> String {
+    let mut result = String::with_capacity(self.text.len());
+    let mut cursor = 0;
+
+    for range in &self.highlights {
+      result.push_str(self.text.get(cursor..range.start).unwrap_or_default());
+      result.push_str(marker);
+      result.push_str(self.text.get(range.clone()).unwrap_or_default());
+      result.push_str(marker);
+      cursor = range.end;
+    }
+
+    result.push_str(self.text.get(cursor..).unwrap_or_default());
+    result
+  }
+}
+
+/// Search the names, descriptions and content of every provider with all of
+/// the `tags` for the query. The search is case insensitive. Providers whose
+/// name matches come first, followed by the providers with the most matching
+/// lines.
+pub fn search_providers(project: &Project, query: &str, tags: &[String]) -> Vec<SearchResult> {
+  let query = query.trim();
+
+  if query.is_empty() {
+    return vec![];
+  }
+
+  let Ok(pattern) = Regex::new(&format!("(?i){}", regex::escape(query))) else {
+    return vec![];
+  };
+
+  let mut results = vec![];
+
+  for provider in project.providers.values() {
+    if !provider.has_tags(tags) {
+      continue;
+    }
+
+    let name_match = pattern.is_match(&provider.block.name);
+    let description_match = provider
+      .description
+      .as_ref()
+      .map_or(false, |description| pattern.is_match(description));
+    let matching_lines = provider
+      .content
+      .lines()
+      .enumerate()
+      .filter(|(_, line)| pattern.is_match(line))
+      .collect::<Vec<_>>();
+
+    if !name_match && !description_match && matching_lines.is_empty() {
+      continue;
+    }
+
+    let snippets = matching_lines
+      .iter()
+      .take(MAX_SNIPPETS)
+      .map(|(index, line)| {
+        let text = line.trim();
+        let highlights = pattern.find_iter(text).map(|found| found.range()).collect();
+
+        SearchSnippet {
+          line: index + 1,
+          text: text.to_string(),
+          highlights,
+        }
+      })
+      .collect();
+
+    results.push((
+      matching_lines.len(),
+      SearchResult {
+        name: provider.block.name.clone(),
+        file: provider
+          .file
+          .strip_prefix(&project.root)
+          .unwrap_or(&provider.file)
+          .to_path_buf(),
+        description: provider.description.clone(),
+        tags: provider.tags.clone(),
+        name_match,
+        snippets,
+        consumer_count: project
+          .consumers
+          .iter()
+          .filter(|consumer| {
+            concatenated_names(&consumer.block.name).any(|name| name == provider.block.name)
+          })
+          .count(),
+      },
+    ));
+  }
+
+  results.sort_by(|(a_count, a), (b_count, b)| {
+    b.name_match
+      .cmp(&a.name_match)
+      .then(b_count.cmp(a_count))
+      .then(a.name.cmp(&b.name))
+  });
+
+  results.into_iter().map(|(_, result)| result).collect()
+}
@@ -0,0 +1,176 @@
+use markdown::mdast::Node;
+use markdown::ParseOptions;
+
+use crate::strip_html;
+
+/// The output of [`markdown_to_text`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TextStyle {
+  /// Plain text without any markup, e.g. for emails.
+  Plain,
+  /// Slack's `mrkdwn` format.
+  Slack,
+}
+
+/// Convert markdown to plain text or Slack `mrkdwn`. Headings, emphasis and
+/// code markers are removed or translated, links keep their url and html is
+/// stripped.
+pub fn markdown_to_text(content: &str, style: TextStyle) -> String {
+  let Ok(root) = markdown::to_mdast(content, &ParseOptions::gfm()) else {
+    return content.to_string();
+  };
+
+  render_blocks(
+    root.children().map(Vec::as_slice).unwrap_or_default(),
+    style,
+  )
+}
+
+fn render_blocks(nodes: &[Node], style: TextStyle) -> String {
+  nodes
+    .iter()
+    .filter_map(|node| render_block(node, style))
+    .filter(|block| !block.is_empty())
+    .collect::<Vec<_>>()
+    .join("\n\n")
+}
+
+fn render_block(node: &Node, style: TextStyle) -> Option<String> {
+  let text = match node {
+    Node::Paragraph(paragraph) => render_inline(&paragraph.children, style),
+    Node::Heading(heading) => {
+      let text = render_inline(&heading.children, style);
+
+      match style {
+        TextStyle::Plain => text,
+        TextStyle::Slack => format!("*{text}*"),
+      }
+    }
+    Node::BlockQuote(quote) => {
+      let text = render_blocks(&quote.children, style);
+
+      match style {
+        TextStyle::Plain => text,
+        TextStyle::Slack => {
+          text
+            .lines()
+            .map(|line| format!("> {line}"))
+            .collect::<Vec<_>>()
+            .join("\n")
+        }
+      }
+    }
+    Node::List(list) => {
+      let start = list.start.unwrap_or(1) as usize;
+
+      list
+        .children
+        .iter()
+        .enumerate()
+        .map(|(index, item)| {
+          let marker = match (list.ordered, style) {
+            (true, _) => format!("{}.", start + index),
+            (false, TextStyle::Plain) => "-".to_string(),
+            (false, TextStyle::Slack) => "•".to_string(),
+          };
+          let children = item.children().map(Vec::as_slice).unwrap_or_default();
+          let content = children
+            .iter()
+            .filter_map(|child| render_block(child, style))
+            .collect::<Vec<_>>()
+            .join("\n")
+            .replace('\n', "\n  ");
+
+          format!("{marker} {content}")
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+    }
+    Node::Code(code) => {
+      match style {
+        TextStyle::Plain => code.value.clone(),
+        TextStyle::Slack => format!("```\n{}\n```", code.value),
+      }
+    }
+    Node::Html(html) => strip_html(&html.value).trim().to_string(),
+    Node::Table(table) => {
+      table
+        .children
+        .iter()
+        .map(|row| {
+          row
+            .children()
+            .map(Vec::as_slice)
+            .unwrap_or_default()
+            .iter()
+            .map(|cell| {
+              render_inline(
+                cell.children().map(Vec::as_slice).unwrap_or_default(),
+                style,
+              )
+            })
+            .collect::<Vec<_>>()
+            .join(" | ")
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+    }
+    Node::ThematicBreak(_) | Node::Definition(_) | Node::Yaml(_) | Node::Toml(_) => {
+      return None;
+    }
+    node => node.to_string(),
+  };
+
+  Some(text)
+}
+
+fn render_inline(nodes: &[Node], style: TextStyle) -> String {
+  nodes
+    .iter()
+    .map(|node| {
+      match node {
+        Node::Text(text) => {
+          match style {
+            TextStyle::Plain => text.value.clone(),
+            TextStyle::Slack => escape_slack(&text.value),
+          }
+        }
+        Node::Emphasis(node) => wrap(render_inline(&node.children, style), "_", style),
+        Node::Strong(node) => wrap(render_inline(&node.children, style), "*", style),
+        Node::Delete(node) => wrap(render_inline(&node.children, style), "~", style),
+        Node::InlineCode(code) => wrap(code.value.clone(), "`", style),
+        Node::Link(link) => {
+          let text = render_inline(&link.children, style);
+
+          match style {
+            TextStyle::Plain if text == link.url || text.is_empty() => link.url.clone(),
+            TextStyle::Plain => format!("{text} ({})", link.url),
+            TextStyle::Slack => format!("<{}|{text}>", link.url),
+          }
+        }
+        Node::LinkReference(reference) => render_inline(&reference.children, style),
+        Node::Image(image) => image.alt.clone(),
+        Node::ImageReference(image) => image.alt.clone(),
+        Node::Break(_) => "\n".to_string(),
+        Node::Html(html) => strip_html(&html.value),
+        node => node.to_string(),
+      }
+    })
+    .collect()
+}
+
+/// Wrap the text in the Slack marker. Plain text is returned unchanged.
+fn wrap(text: String, marker: &str, style: TextStyle) -> String {
+  match style {
+    TextStyle::Plain => text,
+    TextStyle::Slack => format!("{marker}{text}{marker}"),
+  }
+}
+
+/// Escape the characters which Slack uses for its own markup.
+fn escape_slack(text: &str) -> String {
+  text
+    .replace('&', "&amp;")
+    .replace('<', "&lt;")
+    .replace('>', "&gt;")
+}
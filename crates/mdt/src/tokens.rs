@@ -22,18 +22,34 @@ pub enum Token {
   HtmlCommentOpen,
   /// `-->`
   HtmlCommentClose,
+  /// The opening delimiter of a configured comment style, e.g. `{#`
+  CommentOpen(String),
+  /// The closing delimiter of a configured comment style, e.g. `#}`, or a
+  /// line break for line comments
+  CommentClose(String),
   /// `{=`
   ConsumerTag,
   /// `{@`
   ProviderTag,
   /// `{/`
   CloseTag,
+  /// `{>`
+  IncludeTag,
+  /// `{~`
+  InlineTag,
   /// `}`
   BraceClose,
   /// `|`
   Pipe,
   /// `:`
   ArgumentDelimiter,
+  /// `@` before the name of a transformer alias, e.g. `|@rustdoc`
+  AliasMarker,
+  /// `?` after the name of an optional consumer, e.g. `{=badge?}`
+  OptionalMarker,
+  /// `#` before the provider region a consumer selects, e.g.
+  /// `{=docs#quickstart}`
+  RegionMarker,
   /// ` ` | `\t` | `\r`
   Whitespace(u8),
   /// String content passed into a filter function e.g. `"my content"`
@@ -44,6 +60,9 @@ pub enum Token {
   Int(i64),
   /// A floating point number, e.g. `123.456`
   Float(f64),
+  /// A json object which overrides the data for a consumer, e.g.
+  /// `data:{"pm":"pnpm"}`. Only the object is kept.
+  Data(String),
 }
 
 impl Eq for Token {}
@@ -53,18 +72,26 @@ impl PartialEq for Token {
       (Token::Newline, Token::Newline) => true,
       (Token::HtmlCommentOpen, Token::HtmlCommentOpen) => true,
       (Token::HtmlCommentClose, Token::HtmlCommentClose) => true,
+      (Token::CommentOpen(value), Token::CommentOpen(other_value)) => value == other_value,
+      (Token::CommentClose(value), Token::CommentClose(other_value)) => value == other_value,
       (Token::ConsumerTag, Token::ConsumerTag) => true,
       (Token::ProviderTag, Token::ProviderTag) => true,
       (Token::CloseTag, Token::CloseTag) => true,
+      (Token::IncludeTag, Token::IncludeTag) => true,
+      (Token::InlineTag, Token::InlineTag) => true,
       (Token::BraceClose, Token::BraceClose) => true,
       (Token::Pipe, Token::Pipe) => true,
       (Token::ArgumentDelimiter, Token::ArgumentDelimiter) => true,
+      (Token::AliasMarker, Token::AliasMarker) => true,
+      (Token::OptionalMarker, Token::OptionalMarker) => true,
+      (Token::RegionMarker, Token::RegionMarker) => true,
       (Token::Whitespace(byte), Token::Whitespace(other_byte)) => byte == other_byte,
       (Token::String(value, delimiter), Token::String(other_value, other_delimiter)) => {
         value == other_value && delimiter == other_delimiter
       }
       (Token::Ident(value), Token::Ident(other_value)) => value == other_value,
       (Token::Int(value), Token::Int(other_value)) => value == other_value,
+      (Token::Data(value), Token::Data(other_value)) => value == other_value,
       (Token::Float(value), Token::Float(other_value)) => {
         approx_eq!(f64, *value, *other_value, ulps = 2)
       }
@@ -79,25 +106,49 @@ impl Token {
       Token::Newline => 1,
       Token::HtmlCommentOpen => 4,
       Token::HtmlCommentClose => 3,
+      Token::CommentOpen(delimiter) => delimiter.len(),
+      Token::CommentClose(delimiter) => delimiter.len(),
       Token::ProviderTag => 2,
       Token::ConsumerTag => 2,
       Token::CloseTag => 2,
+      Token::IncludeTag => 2,
+      Token::InlineTag => 2,
       Token::BraceClose => 1,
       Token::Pipe => 1,
       Token::ArgumentDelimiter => 1,
+      Token::AliasMarker => 1,
+      Token::OptionalMarker => 1,
+      Token::RegionMarker => 1,
       Token::Whitespace(_) => 1,
       Token::String(string, _) => string.len() + 2,
       Token::Ident(ident) => ident.len(),
       Token::Int(number) => number.to_string().len(),
       Token::Float(number) => number.to_string().len(),
+      Token::Data(object) => "data:".len() + object.len(),
     }
   }
 
+  /// Whether this token opens a consumer, provider, closing, include or
+  /// inline tag.
+  pub fn is_tag(&self) -> bool {
+    matches!(
+      self,
+      Token::ConsumerTag
+        | Token::ProviderTag
+        | Token::CloseTag
+        | Token::IncludeTag
+        | Token::InlineTag
+    )
+  }
+
   pub fn same_type(&self, other: &Token) -> bool {
     match (self, other) {
       (Token::String(..), Token::String(..)) => true,
       (Token::Int(_), Token::Int(_)) => true,
       (Token::Float(_), Token::Float(_)) => true,
+      (Token::Data(_), Token::Data(_)) => true,
+      (Token::CommentOpen(_), Token::CommentOpen(_)) => true,
+      (Token::CommentClose(_), Token::CommentClose(_)) => true,
       // Ident's can be a wildcard or specific name like `true` false`
       (Token::Ident(value), Token::Ident(other_value)) => {
         value == "*" || other_value == "*" || value == other_value
@@ -117,16 +168,27 @@ impl Display for Token {
       Token::Whitespace(byte) => write!(f, "{}", *byte as char),
       Token::HtmlCommentOpen => write!(f, "<!--"),
       Token::HtmlCommentClose => write!(f, "-->"),
+      Token::CommentOpen(delimiter) => write!(f, "{delimiter}"),
+      Token::CommentClose(delimiter) => write!(f, "{delimiter}"),
       Token::ConsumerTag => write!(f, "{{="),
       Token::ProviderTag => write!(f, "{{@"),
       Token::CloseTag => write!(f, "{{/"),
+      Token::IncludeTag => write!(f, "{{>"),
+      Token::InlineTag => write!(f, "{{~"),
       Token::BraceClose => write!(f, "}}"),
       Token::Pipe => write!(f, "|"),
       Token::ArgumentDelimiter => write!(f, ":"),
-      Token::String(string, ch) => write!(f, "{ch}{string}{ch}"),
+      Token::AliasMarker => write!(f, "@"),
+      Token::OptionalMarker => write!(f, "?"),
+      Token::RegionMarker => write!(f, "#"),
+      Token::String(string, ch) => {
+        let ch = *ch as char;
+        write!(f, "{ch}{string}{ch}")
+      }
       Token::Ident(ident) => write!(f, "{ident}"),
       Token::Int(number) => write!(f, "{number}"),
       Token::Float(number) => write!(f, "{number}"),
+      Token::Data(object) => write!(f, "data:{object}"),
     }
   }
 }
@@ -0,0 +1,1370 @@
+use std::collections::HashMap;
+use std::io;
+use std::io::Write;
+use std::path::Component;
+use std::path::Path;
+use std::path::PathBuf;
+use std::process::Command;
+use std::process::Output;
+use std::process::Stdio;
+use std::sync::OnceLock;
+use std::thread;
+
+use regex::Regex;
+use serde_json::Value;
+
+use crate::evaluate_condition;
+use crate::format_date;
+use crate::inline_images;
+use crate::markdown_to_text;
+use crate::render_template;
+use crate::validate_expression;
+use crate::Argument;
+use crate::CustomTransformer;
+use crate::CustomTransformers;
+use crate::DataMap;
+use crate::MdtError;
+use crate::MdtResult;
+use crate::TextStyle;
+use crate::Transformer;
+use crate::TransformerType;
+
+/// The units supported by the `truncate` transformer.
+const TRUNCATE_UNITS: &[&str] = &["chars", "words", "lines"];
+
+/// Apply the transformers in order to the provided content.
+pub fn apply_transformers(content: impl AsRef<str>, transformers: &[Transformer]) -> String {
+  transformers
+    .iter()
+    .fold(content.as_ref().to_string(), |content, transformer| {
+      apply_transformer(&content, transformer)
+    })
+}
+
+/// The files involved in rendering a consumer. Transformers like
+/// `relativeLinks` depend on where the content comes from and where it is
+/// going, while custom transformers depend on the configuration.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct TransformContext<'a> {
+  /// The file which contains the provider.
+  pub provider_file: Option<&'a Path>,
+  /// The file which contains the consumer.
+  pub consumer_file: Option<&'a Path>,
+  /// The transformers defined in `mdt.toml`.
+  pub custom_transformers: Option<&'a CustomTransformers>,
+  /// The data which `if` and `unless` expressions are evaluated against.
+  pub data: Option<&'a DataMap>,
+}
+
+/// Apply the transformers in order, using the context for the transformers
+/// which depend on the location of the provider and consumer or on the
+/// configuration.
+pub fn apply_transformers_with_context(
+  content: impl AsRef<str>,
+  transformers: &[Transformer],
+  context: &TransformContext,
+) -> MdtResult<String> {
+  let mut content = content.as_ref().to_string();
+
+  for transformer in transformers {
+    content = match (
+      &transformer.r#type,
+      context.provider_file,
+      context.consumer_file,
+    ) {
+      (TransformerType::RelativeLinks, Some(provider_file), Some(consumer_file)) => {
+        rewrite_relative_links(&content, provider_file, consumer_file)
+      }
+      (TransformerType::InlineImages, Some(provider_file), _) => {
+        let max_bytes = number_arg(&transformer.args, 0).map(|max_bytes| max_bytes as usize);
+        inline_images(&content, provider_file, max_bytes)
+      }
+      (TransformerType::If | TransformerType::Unless, ..) => {
+        let expression = string_arg(&transformer.args, 0).unwrap_or_default();
+        let empty = DataMap::new();
+        let is_true = evaluate_condition(expression, context.data.unwrap_or(&empty))?;
+
+        if is_true == (transformer.r#type == TransformerType::If) {
+          content
+        } else {
+          String::new()
+        }
+      }
+      (TransformerType::Custom(name), ..) => {
+        match context
+          .custom_transformers
+          .and_then(|custom| custom.get(name))
+        {
+          Some(custom) => run_custom_transformer(name, custom, &content, &transformer.args)?,
+          None => return Err(MdtError::UnknownTransformer(name.clone())),
+        }
+      }
+      _ => apply_transformer(&content, transformer),
+    };
+  }
+
+  Ok(content)
+}
+
+/// Run a transformer defined in `mdt.toml`. Commands receive the content on
+/// stdin and the arguments as positional parameters, e.g. `$1`. Templates
+/// can reference `content` and `args`.
+pub fn run_custom_transformer(
+  name: &str,
+  transformer: &CustomTransformer,
+  content: &str,
+  args: &[Argument],
+) -> MdtResult<String> {
+  let error = |message: String| {
+    MdtError::TransformerFailed {
+      name: name.to_string(),
+      message,
+    }
+  };
+
+  if let Some(template) = transformer.template.as_deref() {
+    let args = args.iter().map(argument_value).collect::<Vec<_>>();
+    let mut context = DataMap::new();
+    context.insert("content".into(), Value::String(content.to_string()));
+    context.insert("args".into(), Value::Array(args));
+
+    return render_template(name, template, &context);
+  }
+
+  let command = transformer.command.as_deref().unwrap_or_default();
+  let (output, written) = output_with_input(
+    Command::new("sh")
+      .arg("-c")
+      .arg(command)
+      .arg(name)
+      .args(args.iter().map(argument_string)),
+    content,
+  )
+  .map_err(|e| error(e.to_string()))?;
+
+  if !output.status.success() {
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    return Err(error(format!("{}: {}", output.status, stderr.trim())));
+  }
+
+  written.map_err(|e| error(e.to_string()))?;
+
+  String::from_utf8(output.stdout).map_err(|e| error(e.to_string()))
+}
+
+/// Run the command with the input written to its stdin from another thread.
+/// Writing all of the input first would block forever once a command which
+/// streams its output, e.g. `tr a-z A-Z`, fills the stdout pipe. The result of
+/// writing the input is returned separately, since a command can exit before
+/// reading all of it.
+pub(crate) fn output_with_input(
+  command: &mut Command,
+  input: &str,
+) -> io::Result<(Output, io::Result<()>)> {
+  let mut child = command
+    .stdin(Stdio::piped())
+    .stdout(Stdio::piped())
+    .stderr(Stdio::piped())
+    .spawn()?;
+  let stdin = child.stdin.take();
+
+  thread::scope(|scope| {
+    let writer = scope.spawn(move || {
+      match stdin {
+        Some(mut stdin) => stdin.write_all(input.as_bytes()),
+        None => Ok(()),
+      }
+    });
+    let output = child.wait_with_output()?;
+    let written = writer
+      .join()
+      .unwrap_or_else(|_| Err(io::Error::new(io::ErrorKind::Other, "writing the input panicked")));
+
+    Ok((output, written))
+  })
+}
+
+/// Run the command with the input written to its stdin and return its
+/// output. The error includes the stderr of a command which exits with an
+/// error.
+pub fn run_with_input(command: &mut Command, input: &str) -> MdtResult<Output> {
+  let error = |command: &Command, message: String| {
+    let args = command
+      .get_args()
+      .map(|arg| arg.to_string_lossy())
+      .collect::<Vec<_>>();
+
+    MdtError::CommandFailed {
+      command: format!(
+        "{} {}",
+        command.get_program().to_string_lossy(),
+        args.join(" ")
+      ),
+      message,
+    }
+  };
+  let (output, written) =
+    output_with_input(command, input).map_err(|e| error(command, e.to_string()))?;
+
+  if !output.status.success() {
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    return Err(error(
+      command,
+      format!("{}: {}", output.status, stderr.trim()),
+    ));
+  }
+
+  written.map_err(|e| error(command, format!("the input couldn't be written: {e}")))?;
+
+  Ok(output)
+}
+
+fn argument_value(argument: &Argument) -> Value {
+  match argument {
+    Argument::String(value) => Value::String(value.clone()),
+    Argument::Number(value) => serde_json::json!(value),
+    Argument::Boolean(value) => Value::Bool(*value),
+  }
+}
+
+fn argument_string(argument: &Argument) -> String {
+  match argument {
+    Argument::String(value) => value.clone(),
+    Argument::Number(value) => value.to_string(),
+    Argument::Boolean(value) => value.to_string(),
+  }
+}
+
+/// Apply a single transformer to the content.
+pub fn apply_transformer(content: &str, transformer: &Transformer) -> String {
+  let args = &transformer.args;
+
+  match &transformer.r#type {
+    TransformerType::Trim => content.trim().to_string(),
+    TransformerType::TrimStart => content.trim_start().to_string(),
+    TransformerType::TrimEnd => content.trim_end().to_string(),
+    TransformerType::Wrap => {
+      let wrapper = string_arg(args, 0).unwrap_or_default();
+      format!("{wrapper}{content}{wrapper}")
+    }
+    TransformerType::Indent => {
+      let indent = string_arg(args, 0).unwrap_or_default();
+      map_lines(content, |line| {
+        if line.is_empty() {
+          indent.trim_end().to_string()
+        } else {
+          format!("{indent}{line}")
+        }
+      })
+    }
+    TransformerType::CodeBlock => {
+      let language = string_arg(args, 0).unwrap_or_default();
+      format!("```{language}\n{content}\n```")
+    }
+    TransformerType::Code => format!("`{content}`"),
+    TransformerType::Replace => {
+      let (Some(search), Some(replacement)) = (string_arg(args, 0), string_arg(args, 1)) else {
+        return content.to_string();
+      };
+
+      content.replace(search, replacement)
+    }
+    TransformerType::HeadingShift => {
+      let shift = number_arg(args, 0).unwrap_or_default() as i64;
+      shift_headings(content, shift)
+    }
+    TransformerType::Truncate => {
+      let limit = number_arg(args, 0).unwrap_or_default() as usize;
+      let ellipsis = string_arg(args, 1).unwrap_or_default();
+      let unit = string_arg(args, 2).unwrap_or("chars");
+      let word_boundary = bool_arg(args, 3).unwrap_or_default();
+      truncate(content, limit, ellipsis, unit, word_boundary)
+    }
+    TransformerType::WrapWidth => {
+      let width = number_arg(args, 0).unwrap_or_default() as usize;
+      wrap_width(content, width)
+    }
+    TransformerType::Dedent => dedent(content),
+    TransformerType::Toc => table_of_contents(content),
+    TransformerType::Section => {
+      let title = string_arg(args, 0).unwrap_or_default();
+      find_section(content, title).unwrap_or_default()
+    }
+    TransformerType::StripHtml => strip_html(content),
+    TransformerType::MarkdownToHtml => markdown_to_html(content),
+    TransformerType::EscapeHtml => escape_html(content),
+    TransformerType::EscapeJson => escape_json(content),
+    TransformerType::EscapeToml => escape_toml(content),
+    TransformerType::SquashBlankLines => squash_blank_lines(content),
+    TransformerType::Unescape => unescape_tags(content),
+    TransformerType::Slug => slugify(content.trim()),
+    TransformerType::Anchor => format!("#{}", slugify(content.trim())),
+    TransformerType::PlainText => markdown_to_text(content, TextStyle::Plain),
+    TransformerType::SlackFormat => markdown_to_text(content, TextStyle::Slack),
+    // Frozen consumers are never updated, so the transformer has nothing to do.
+    TransformerType::Frozen => content.to_string(),
+    TransformerType::Date => {
+      let format = string_arg(args, 0).unwrap_or("%Y-%m-%d");
+      format_date(content, format).unwrap_or_else(|| content.to_string())
+    }
+    // Rewriting links, embedding images, evaluating conditions and running custom
+    // transformers requires a context, see `apply_transformers_with_context`.
+    TransformerType::RelativeLinks
+    | TransformerType::InlineImages
+    | TransformerType::If
+    | TransformerType::Unless
+    | TransformerType::Custom(_) => content.to_string(),
+    TransformerType::Lines => {
+      let start = number_arg(args, 0).unwrap_or(1.0) as usize;
+      let end = number_arg(args, 1).map(|end| end as usize);
+      select_lines(content, start, end)
+    }
+    TransformerType::FirstLines => {
+      let count = number_arg(args, 0).unwrap_or_default() as usize;
+      content
+        .split('\n')
+        .take(count)
+        .collect::<Vec<_>>()
+        .join("\n")
+    }
+    TransformerType::LastLines => {
+      let count = number_arg(args, 0).unwrap_or_default() as usize;
+      let lines = content.split('\n').collect::<Vec<_>>();
+      let skip = lines.len().saturating_sub(count);
+      lines.get(skip..).unwrap_or_default().join("\n")
+    }
+  }
+}
+
+/// Check that the transformer has been given the arguments it expects.
+pub fn validate_transformer(
+  transformer: &Transformer,
+  custom: &CustomTransformers,
+) -> MdtResult<()> {
+  let (required, expected): (usize, &[ArgumentKind]) = match &transformer.r#type {
+    TransformerType::Trim
+    | TransformerType::TrimStart
+    | TransformerType::TrimEnd
+    | TransformerType::Code
+    | TransformerType::Dedent
+    | TransformerType::Toc
+    | TransformerType::StripHtml
+    | TransformerType::MarkdownToHtml
+    | TransformerType::EscapeHtml
+    | TransformerType::EscapeJson
+    | TransformerType::EscapeToml
+    | TransformerType::RelativeLinks
+    | TransformerType::SquashBlankLines
+    | TransformerType::Unescape
+    | TransformerType::Slug
+    | TransformerType::Anchor
+    | TransformerType::PlainText
+    | TransformerType::SlackFormat
+    | TransformerType::Frozen => (0, &[]),
+    TransformerType::Wrap | TransformerType::Indent | TransformerType::Section => {
+      (1, &[ArgumentKind::String])
+    }
+    TransformerType::If | TransformerType::Unless => {
+      if let Some(expression) = string_arg(&transformer.args, 0) {
+        validate_expression(expression)?;
+      }
+
+      (1, &[ArgumentKind::String])
+    }
+    TransformerType::CodeBlock | TransformerType::Date => (0, &[ArgumentKind::String]),
+    TransformerType::Replace => (2, &[ArgumentKind::String, ArgumentKind::String]),
+    TransformerType::InlineImages => (0, &[ArgumentKind::Integer]),
+    TransformerType::HeadingShift
+    | TransformerType::WrapWidth
+    | TransformerType::FirstLines
+    | TransformerType::LastLines => (1, &[ArgumentKind::Integer]),
+    TransformerType::Lines => (1, &[ArgumentKind::Integer, ArgumentKind::Integer]),
+    TransformerType::Truncate => {
+      (
+        1,
+        &[
+          ArgumentKind::Integer,
+          ArgumentKind::String,
+          ArgumentKind::String,
+          ArgumentKind::Boolean,
+        ],
+      )
+    }
+    TransformerType::Custom(name) => {
+      let expected = custom.get(name).map_or(0, |custom| custom.args);
+
+      if transformer.args.len() != expected {
+        return Err(MdtError::InvalidTransformerArguments {
+          name: name.clone(),
+          message: format!(
+            "expected {expected} argument(s) but received {}",
+            transformer.args.len()
+          ),
+        });
+      }
+
+      return Ok(());
+    }
+  };
+
+  let args = &transformer.args;
+  let error = |message: String| {
+    Err(MdtError::InvalidTransformerArguments {
+      name: transformer.r#type.to_string(),
+      message,
+    })
+  };
+
+  if args.len() < required || args.len() > expected.len() {
+    let count = if required == expected.len() {
+      required.to_string()
+    } else {
+      format!("{required} to {}", expected.len())
+    };
+
+    return error(format!(
+      "expected {count} argument(s) but received {}",
+      args.len()
+    ));
+  }
+
+  for (index, (arg, kind)) in args.iter().zip(expected).enumerate() {
+    if !kind.matches(arg) {
+      return error(format!(
+        "argument {} should be {}",
+        index + 1,
+        kind.describe()
+      ));
+    }
+  }
+
+  if transformer.r#type == TransformerType::WrapWidth
+    && number_arg(args, 0).map_or(false, |width| width < 1.0)
+  {
+    return error("the width must be at least 1".into());
+  }
+
+  if matches!(
+    transformer.r#type,
+    TransformerType::FirstLines | TransformerType::LastLines
+  ) && number_arg(args, 0).map_or(false, |count| count < 0.0)
+  {
+    return error("the line count must not be negative".into());
+  }
+
+  if transformer.r#type == TransformerType::Lines {
+    let start = number_arg(args, 0).unwrap_or(1.0);
+
+    if start < 1.0 {
+      return error("line numbers start at 1".into());
+    }
+
+    if number_arg(args, 1).map_or(false, |end| end < start) {
+      return error("the end line must not be before the start line".into());
+    }
+  }
+
+  if transformer.r#type == TransformerType::Truncate {
+    if number_arg(args, 0).map_or(false, |limit| limit < 0.0) {
+      return error("the limit must not be negative".into());
+    }
+
+    if let Some(unit) = string_arg(args, 2) {
+      if !TRUNCATE_UNITS.contains(&unit) {
+        return error(format!(
+          "unknown unit `{unit}`, expected one of {}",
+          TRUNCATE_UNITS.join(", ")
+        ));
+      }
+    }
+  }
+
+  Ok(())
+}
+
+#[derive(Debug, Clone, Copy)]
+enum ArgumentKind {
+  String,
+  Integer,
+  Boolean,
+}
+
+impl ArgumentKind {
+  fn matches(&self, argument: &Argument) -> bool {
+    match self {
+      ArgumentKind::String => argument.as_str().is_some(),
+      ArgumentKind::Boolean => argument.as_bool().is_some(),
+      ArgumentKind::Integer => {
+        argument
+          .as_number()
+          .map_or(false, |number| number.fract() == 0.0)
+      }
+    }
+  }
+
+  fn describe(&self) -> &'static str {
+    match self {
+      ArgumentKind::String => "a string",
+      ArgumentKind::Integer => "an integer",
+      ArgumentKind::Boolean => "a boolean",
+    }
+  }
+}
+
+/// Truncate the content to `limit` units. The ellipsis is only appended when
+/// content was removed. Words are separated by whitespace, which is kept as
+/// it is between the words that remain. With `word_boundary`, a character
+/// limit which falls within a word drops the rest of that word as well, unless
+/// it is the first word.
+pub fn truncate(
+  content: &str,
+  limit: usize,
+  ellipsis: &str,
+  unit: &str,
+  word_boundary: bool,
+) -> String {
+  let truncated = match unit {
+    "lines" => {
+      if content.split('\n').count() <= limit {
+        return content.to_string();
+      }
+
+      content
+        .split('\n')
+        .take(limit)
+        .collect::<Vec<_>>()
+        .join("\n")
+    }
+    _ => {
+      let end = if unit == "words" {
+        content
+          .char_indices()
+          .filter(|&(index, ch)| {
+            let follows_whitespace = content
+              .get(..index)
+              .and_then(|before| before.chars().next_back())
+              .map_or(true, char::is_whitespace);
+
+            !ch.is_whitespace() && follows_whitespace
+          })
+          .nth(limit)
+      } else {
+        content.char_indices().nth(limit)
+      };
+
+      let Some((end, ch)) = end else {
+        return content.to_string();
+      };
+      let kept = content.get(..end).unwrap_or_default();
+      let within_word = !ch.is_whitespace()
+        && kept
+          .chars()
+          .next_back()
+          .map_or(false, |previous| !previous.is_whitespace());
+
+      let kept = if unit == "chars" && word_boundary && within_word {
+        kept
+          .rfind(char::is_whitespace)
+          .and_then(|boundary| kept.get(..boundary))
+          .unwrap_or(kept)
+      } else {
+        kept
+      };
+
+      kept.trim_end().to_string()
+    }
+  };
+
+  format!("{truncated}{ellipsis}")
+}
+
+/// Remove html tags and comments while keeping the text between the tags,
+/// e.g. `<b>bold</b>` becomes `bold`. A `<` which doesn't start a tag is kept.
+pub fn strip_html(content: &str) -> String {
+  let mut result = String::with_capacity(content.len());
+  let mut rest = content;
+
+  while let Some(start) = rest.find('<') {
+    result.push_str(rest.get(..start).unwrap_or_default());
+    let tag = rest.get(start..).unwrap_or_default();
+    let is_tag = tag.chars().nth(1).map_or(false, |ch| {
+      ch.is_ascii_alphabetic() || ch == '/' || ch == '!'
+    });
+    let end = if tag.starts_with("<!--") {
+      tag.find("-->").map(|end| end + 3)
+    } else {
+      tag.find('>').map(|end| end + 1)
+    };
+
+    match end.filter(|_| is_tag) {
+      Some(end) => rest = tag.get(end..).unwrap_or_default(),
+      None => {
+        result.push('<');
+        rest = tag.get(1..).unwrap_or_default();
+      }
+    }
+  }
+
+  result.push_str(rest);
+  result
+}
+
+/// Render the markdown to html with GitHub flavored markdown enabled. Raw html
+/// in the markdown is kept.
+pub fn markdown_to_html(content: &str) -> String {
+  let options = markdown::Options {
+    compile: markdown::CompileOptions {
+      allow_dangerous_html: true,
+      ..markdown::CompileOptions::gfm()
+    },
+    ..markdown::Options::gfm()
+  };
+
+  markdown::to_html_with_options(content, &options).unwrap_or_else(|_| markdown::to_html(content))
+}
+
+/// Escape the characters with a special meaning in html text and attribute
+/// values.
+pub fn escape_html(content: &str) -> String {
+  let mut escaped = String::with_capacity(content.len());
+
+  for ch in content.chars() {
+    match ch {
+      '&' => escaped.push_str("&amp;"),
+      '<' => escaped.push_str("&lt;"),
+      '>' => escaped.push_str("&gt;"),
+      '"' => escaped.push_str("&quot;"),
+      '\'' => escaped.push_str("&#39;"),
+      _ => escaped.push(ch),
+    }
+  }
+
+  escaped
+}
+
+/// Escape the content for use inside a JSON string. The surrounding quotes
+/// are not added.
+pub fn escape_json(content: &str) -> String {
+  let quoted = serde_json::Value::String(content.to_string()).to_string();
+
+  quoted
+    .get(1..quoted.len() - 1)
+    .unwrap_or_default()
+    .to_string()
+}
+
+/// Escape the content for use inside a TOML basic string. The surrounding
+/// quotes are not added.
+pub fn escape_toml(content: &str) -> String {
+  let mut escaped = String::with_capacity(content.len());
+
+  for ch in content.chars() {
+    match ch {
+      '"' => escaped.push_str("\\\""),
+      '\\' => escaped.push_str("\\\\"),
+      '\n' => escaped.push_str("\\n"),
+      '\r' => escaped.push_str("\\r"),
+      '\t' => escaped.push_str("\\t"),
+      '\u{8}' => escaped.push_str("\\b"),
+      '\u{c}' => escaped.push_str("\\f"),
+      ch if ch.is_control() => escaped.push_str(&format!("\\u{:04X}", ch as u32)),
+      _ => escaped.push(ch),
+    }
+  }
+
+  escaped
+}
+
+/// Rewrite the relative links and images in the markdown so they resolve from
+/// the consumer file rather than the provider file. Inline links, e.g.
+/// `[guide](../docs/guide.md)`, and reference definitions are rewritten, along
+/// with destinations in angle brackets, e.g. `[guide](<my guide.md>)`.
+/// Absolute paths, anchors and urls are left as they are, as is everything
+/// within code blocks and inline code.
+pub fn rewrite_relative_links(content: &str, provider_file: &Path, consumer_file: &Path) -> String {
+  let provider_directory = provider_file.parent().unwrap_or_else(|| Path::new(""));
+  let consumer_directory = consumer_file.parent().unwrap_or_else(|| Path::new(""));
+  let rewrite = |link: &str| -> Option<String> {
+    let is_relative = !link.is_empty()
+      && !link.starts_with(['/', '#', '?'])
+      && !link.contains("://")
+      && !link.starts_with("mailto:");
+
+    if !is_relative {
+      return None;
+    }
+
+    let split = link.find(['#', '?']).unwrap_or(link.len());
+    let (path, suffix) = link.split_at(split);
+    let target = normalize_path(&provider_directory.join(path));
+    let relative = relative_path(&normalize_path(consumer_directory), &target);
+    let relative = relative.to_string_lossy().replace('\\', "/");
+
+    Some(format!("{relative}{suffix}"))
+  };
+  let mut fence: Option<String> = None;
+
+  map_lines(content, |line| {
+    if fence_state(line, &mut fence) || fence.is_some() {
+      return line.to_string();
+    }
+
+    rewrite_definition(line, rewrite).unwrap_or_else(|| {
+      code_spans(line)
+        .into_iter()
+        .map(|(is_code, text)| {
+          if is_code {
+            text.to_string()
+          } else {
+            rewrite_inline_links(text, rewrite)
+          }
+        })
+        .collect()
+    })
+  })
+}
+
+/// Rewrite the destinations of the inline links and images in the text.
+fn rewrite_inline_links(text: &str, rewrite: impl Fn(&str) -> Option<String>) -> String {
+  let mut result = String::with_capacity(text.len());
+  let mut rest = text;
+
+  while let Some(start) = rest.find("](") {
+    let (before, after) = rest.split_at(start + 2);
+    result.push_str(before);
+
+    let (link, remaining) = split_destination(after);
+    result.push_str(&rewrite_destination(link, &rewrite));
+    rest = remaining;
+  }
+
+  result.push_str(rest);
+  result
+}
+
+/// Rewrite the destination of the line when it is a reference definition,
+/// e.g. `[guide]: ../docs/guide.md "Guide"`.
+fn rewrite_definition(line: &str, rewrite: impl Fn(&str) -> Option<String>) -> Option<String> {
+  let trimmed = line.trim_start();
+  let label_length = trimmed
+    .strip_prefix('[')
+    .and_then(|value| value.find("]:").map(|end| end + 3))
+    .filter(|_| !trimmed.starts_with("[^"))?;
+
+  let indent = line.len() - trimmed.len();
+  let (label, target) = trimmed.split_at(label_length);
+  let target_trimmed = target.trim_start();
+  let spacing = target.len() - target_trimmed.len();
+  let (link, title) = split_destination(target_trimmed);
+
+  Some(format!(
+    "{}{label}{}{}{title}",
+    line.get(..indent).unwrap_or_default(),
+    target.get(..spacing).unwrap_or_default(),
+    rewrite_destination(link, &rewrite)
+  ))
+}
+
+/// Split the link destination at the start of the text from what follows.
+/// A destination in angle brackets, which can contain spaces, ends after the
+/// `>`, and any other ends before the first space or `)`.
+fn split_destination(text: &str) -> (&str, &str) {
+  let length = if text.starts_with('<') {
+    text.find('>').map_or(text.len(), |end| end + 1)
+  } else {
+    text
+      .find(|ch: char| ch == ')' || ch.is_whitespace())
+      .unwrap_or(text.len())
+  };
+
+  text.split_at(length)
+}
+
+/// Rewrite the link destination, keeping its angle brackets.
+fn rewrite_destination(link: &str, rewrite: impl Fn(&str) -> Option<String>) -> String {
+  let bracketed = link
+    .strip_prefix('<')
+    .and_then(|link| link.strip_suffix('>'));
+
+  match bracketed {
+    Some(inner) => rewrite(inner).map_or_else(|| link.to_string(), |inner| format!("<{inner}>")),
+    None => rewrite(link).unwrap_or_else(|| link.to_string()),
+  }
+}
+
+/// Split the line into inline code spans and the text between them, as
+/// `(is_code, text)` pairs. A span closes with a run of as many backticks as
+/// opened it, and an unclosed run is text.
+fn code_spans(line: &str) -> Vec<(bool, &str)> {
+  let mut spans = vec![];
+  let mut text_start = 0;
+  let mut index = 0;
+
+  while let Some(offset) = line.get(index..).and_then(|rest| rest.find('`')) {
+    let start = index + offset;
+    let rest = line.get(start..).unwrap_or_default();
+    let run = rest.len() - rest.trim_start_matches('`').len();
+    let marker = "`".repeat(run);
+    let after = start + run;
+    let closing = line.get(after..).and_then(|rest| {
+      let mut search = 0;
+
+      // Only a run of exactly the same length closes the span.
+      while let Some(found) = rest.get(search..).and_then(|tail| tail.find(&marker)) {
+        let found = search + found;
+        let tail = rest.get(found..).unwrap_or_default();
+        let length = tail.len() - tail.trim_start_matches('`').len();
+
+        if length == run {
+          return Some(after + found + run);
+        }
+
+        search = found + length;
+      }
+
+      None
+    });
+
+    let Some(end) = closing else {
+      index = after;
+      continue;
+    };
+
+    spans.push((false, line.get(text_start..start).unwrap_or_default()));
+    spans.push((true, line.get(start..end).unwrap_or_default()));
+    text_start = end;
+    index = end;
+  }
+
+  spans.push((false, line.get(text_start..).unwrap_or_default()));
+  spans
+}
+
+/// Resolve the `.` and `..` components of the path without touching the file
+/// system.
+pub(crate) fn normalize_path(path: &Path) -> PathBuf {
+  let mut normalized = PathBuf::new();
+
+  for component in path.components() {
+    match component {
+      Component::CurDir => {}
+      Component::ParentDir => {
+        let can_pop = matches!(
+          normalized.components().next_back(),
+          Some(Component::Normal(_))
+        );
+
+        if can_pop {
+          normalized.pop();
+        } else {
+          normalized.push("..");
+        }
+      }
+      component => normalized.push(component),
+    }
+  }
+
+  normalized
+}
+
+/// The path to `target` relative to the `base` directory. Both paths must be
+/// normalized.
+fn relative_path(base: &Path, target: &Path) -> PathBuf {
+  let base = base.components().collect::<Vec<_>>();
+  let target = target.components().collect::<Vec<_>>();
+  let common = base.iter().zip(&target).take_while(|(a, b)| a == b).count();
+
+  let mut relative = PathBuf::new();
+
+  for _ in common..base.len() {
+    relative.push("..");
+  }
+
+  for component in target.iter().skip(common) {
+    relative.push(component);
+  }
+
+  relative
+}
+
+/// Replace escaped tags, e.g. `{{=example}}` or `{{/example}}`, with the tags
+/// they show. The parser ignores escaped tags so they can be used to document
+/// mdt itself.
+pub fn unescape_tags(content: &str) -> String {
+  escaped_tag_pattern()
+    .replace_all(content, "{$1}")
+    .into_owned()
+}
+
+/// An escaped tag, e.g. `{{=name}}`, with the tag without its outer braces.
+fn escaped_tag_pattern() -> &'static Regex {
+  static PATTERN: OnceLock<Regex> = OnceLock::new();
+
+  PATTERN.get_or_init(|| {
+    Regex::new(r"\{\{([=@/>][^{}\n]*)\}\}").expect("the escaped tag pattern is valid")
+  })
+}
+
+/// Collapse runs of blank lines into a single empty line. Lines inside fenced
+/// code blocks are kept as they are.
+pub fn squash_blank_lines(content: &str) -> String {
+  let mut lines = vec![];
+  let mut fence: Option<String> = None;
+  let mut previous_blank = false;
+
+  for line in content.split('\n') {
+    fence_state(line, &mut fence);
+
+    let is_blank = fence.is_none() && line.trim().is_empty();
+
+    if is_blank && previous_blank {
+      continue;
+    }
+
+    previous_blank = is_blank;
+    lines.push(if is_blank { "" } else { line });
+  }
+
+  lines.join("\n")
+}
+
+/// Keep the lines from `start` up to and including `end`. Line numbers start
+/// at 1 and every remaining line is kept when `end` is `None`.
+pub fn select_lines(content: &str, start: usize, end: Option<usize>) -> String {
+  let skip = start.saturating_sub(1);
+  let take = end.map_or(usize::MAX, |end| (end + 1).saturating_sub(start));
+
+  content
+    .split('\n')
+    .skip(skip)
+    .take(take)
+    .collect::<Vec<_>>()
+    .join("\n")
+}
+
+/// Shift the level of each ATX heading by `shift`, clamping the level between
+/// 1 and 6. Headings inside fenced code blocks are left untouched.
+pub fn shift_headings(content: &str, shift: i64) -> String {
+  let mut fence: Option<String> = None;
+
+  map_lines(content, |line| {
+    if fence_state(line, &mut fence) || fence.is_some() {
+      return line.to_string();
+    }
+
+    let Some((indent, level, rest)) = split_heading(line) else {
+      return line.to_string();
+    };
+
+    let level = (level as i64 + shift).clamp(1, 6) as usize;
+    format!("{indent}{}{rest}", "#".repeat(level))
+  })
+}
+
+/// Remove the common leading whitespace from every line. Only the prefix which
+/// is identical on every line is removed, so a tab is never traded for spaces
+/// when lines mix them. Lines which only contain whitespace are ignored when
+/// finding the common indentation and are emptied.
+pub fn dedent(content: &str) -> String {
+  let common = content
+    .split('\n')
+    .filter(|line| !line.trim().is_empty())
+    .map(|line| {
+      let indent = line.len() - line.trim_start_matches([' ', '\t']).len();
+      line.get(..indent).unwrap_or_default()
+    })
+    .reduce(|common, indent| {
+      let shared = common
+        .bytes()
+        .zip(indent.bytes())
+        .take_while(|(a, b)| a == b)
+        .count();
+      common.get(..shared).unwrap_or_default()
+    })
+    .unwrap_or_default();
+
+  map_lines(content, |line| {
+    if line.trim().is_empty() {
+      String::new()
+    } else {
+      line.strip_prefix(common).unwrap_or(line).to_string()
+    }
+  })
+}
+
+/// Reflow the paragraphs within the content so that lines are no longer than
+/// `width` characters. List items keep a hanging indent for their wrapped
+/// lines. Words longer than the width are placed on their own line.
+pub fn wrap_width(content: &str, width: usize) -> String {
+  let mut lines: Vec<String> = vec![];
+  let mut paragraph: Option<(String, String, Vec<&str>)> = None;
+  let mut fence: Option<String> = None;
+
+  let flush = |paragraph: &mut Option<(String, String, Vec<&str>)>, lines: &mut Vec<String>| {
+    if let Some((first_indent, indent, words)) = paragraph.take() {
+      lines.extend(wrap_words(&words, width, &first_indent, &indent));
+    }
+  };
+
+  for line in content.split('\n') {
+    if fence_state(line, &mut fence) {
+      flush(&mut paragraph, &mut lines);
+      lines.push(line.to_string());
+      continue;
+    }
+
+    let trimmed = line.trim_start();
+
+    if fence.is_some() || is_unwrappable_line(line) {
+      flush(&mut paragraph, &mut lines);
+      lines.push(line.to_string());
+      continue;
+    }
+
+    let indent = line.get(..line.len() - trimmed.len()).unwrap_or_default();
+
+    if let Some(marker_length) = list_marker_length(trimmed) {
+      flush(&mut paragraph, &mut lines);
+      let marker = trimmed.get(..marker_length).unwrap_or_default();
+      let words = trimmed
+        .get(marker_length..)
+        .unwrap_or_default()
+        .split_whitespace()
+        .collect();
+
+      paragraph = Some((
+        format!("{indent}{marker}"),
+        format!("{indent}{}", " ".repeat(marker_length)),
+        words,
+      ));
+      continue;
+    }
+
+    match paragraph.as_mut() {
+      Some((_, _, words)) => words.extend(trimmed.split_whitespace()),
+      None => {
+        paragraph = Some((
+          indent.to_string(),
+          indent.to_string(),
+          trimmed.split_whitespace().collect(),
+        ));
+      }
+    }
+  }
+
+  flush(&mut paragraph, &mut lines);
+  lines.join("\n")
+}
+
+/// Greedily fill lines with the words. The first line is prefixed with
+/// `first_indent` and the following lines with `indent`.
+fn wrap_words(words: &[&str], width: usize, first_indent: &str, indent: &str) -> Vec<String> {
+  let mut lines = vec![];
+  let mut line = first_indent.to_string();
+  let mut line_is_empty = true;
+
+  for word in words {
+    let length = line.chars().count();
+    let word_length = word.chars().count();
+
+    if !line_is_empty && length + 1 + word_length > width {
+      lines.push(line);
+      line = indent.to_string();
+      line_is_empty = true;
+    }
+
+    if !line_is_empty {
+      line.push(' ');
+    }
+
+    line.push_str(word);
+    line_is_empty = false;
+  }
+
+  lines.push(line);
+  lines
+}
+
+/// Lines which should never be merged with their neighbours.
+fn is_unwrappable_line(line: &str) -> bool {
+  let trimmed = line.trim_start();
+
+  trimmed.is_empty()
+    || line.starts_with("    ")
+    || line.starts_with('\t')
+    || split_heading(line).is_some()
+    || trimmed.starts_with(['|', '<', '>'])
+}
+
+/// The length of the list marker including the following space, e.g. `- ` or
+/// `12. `.
+fn list_marker_length(line: &str) -> Option<usize> {
+  if line.starts_with("- ") || line.starts_with("* ") || line.starts_with("+ ") {
+    return Some(2);
+  }
+
+  let digits = line.bytes().take_while(u8::is_ascii_digit).count();
+
+  if digits == 0 || digits > 9 {
+    return None;
+  }
+
+  let rest = line.get(digits..)?;
+
+  if rest.starts_with(". ") || rest.starts_with(") ") {
+    Some(digits + 2)
+  } else {
+    None
+  }
+}
+
+/// Generate a nested markdown list linking to every heading in the content.
+/// The shallowest heading level is used as the top level of the list.
+pub fn table_of_contents(content: &str) -> String {
+  let headings = collect_headings(content);
+  let Some(min_level) = headings.iter().map(|(level, _)| *level).min() else {
+    return String::new();
+  };
+
+  let mut slugs = Slugger::default();
+
+  headings
+    .iter()
+    .map(|(level, text)| {
+      let indent = "  ".repeat(level - min_level);
+      let slug = slugs.slug(text);
+      format!("{indent}- [{text}](#{slug})")
+    })
+    .collect::<Vec<_>>()
+    .join("\n")
+}
+
+/// Collect the level and text of each ATX heading outside of fenced code.
+pub fn collect_headings(content: &str) -> Vec<(usize, String)> {
+  let mut headings = vec![];
+  let mut fence: Option<String> = None;
+
+  for line in content.split('\n') {
+    if fence_state(line, &mut fence) || fence.is_some() {
+      continue;
+    }
+
+    if let Some((level, text)) = heading_text(line) {
+      headings.push((level, text.to_string()));
+    }
+  }
+
+  headings
+}
+
+/// Extract the section which starts with the heading matching `title` and
+/// ends before the next heading of the same or a higher level. Returns `None`
+/// when no heading matches.
+pub fn find_section(content: &str, title: &str) -> Option<String> {
+  let title = title.trim();
+  let mut fence: Option<String> = None;
+  let mut section: Option<(usize, Vec<&str>)> = None;
+
+  for line in content.split('\n') {
+    if !fence_state(line, &mut fence) && fence.is_none() {
+      if let Some((level, text)) = heading_text(line) {
+        match section.as_ref() {
+          Some((section_level, _)) if level <= *section_level => break,
+          None if text == title => section = Some((level, vec![])),
+          _ => {}
+        }
+      }
+    }
+
+    if let Some((_, lines)) = section.as_mut() {
+      lines.push(line);
+    }
+  }
+
+  section.map(|(_, lines)| lines.join("\n").trim_end_matches('\n').to_string())
+}
+
+/// Check that every `section` transformer in the chain can find its heading
+/// in the content it receives.
+pub fn check_sections(name: &str, content: &str, transformers: &[Transformer]) -> MdtResult<()> {
+  let mut content = content.to_string();
+
+  for transformer in transformers {
+    if transformer.r#type == TransformerType::Section {
+      let title = string_arg(&transformer.args, 0).unwrap_or_default();
+
+      if find_section(&content, title).is_none() {
+        return Err(MdtError::MissingSection {
+          name: name.to_string(),
+          section: title.to_string(),
+        });
+      }
+    }
+
+    content = apply_transformer(&content, transformer);
+  }
+
+  Ok(())
+}
+
+/// Generates unique GitHub compatible anchors. Repeated headings receive a
+/// numeric suffix, e.g. `usage`, `usage-1`, `usage-2`.
+#[derive(Debug, Default)]
+pub struct Slugger {
+  seen: HashMap<String, usize>,
+}
+
+impl Slugger {
+  pub fn slug(&mut self, text: &str) -> String {
+    let base = slugify(text);
+    let count = self.seen.get(&base).copied().unwrap_or(0);
+    let slug = if count == 0 {
+      base.clone()
+    } else {
+      format!("{base}-{count}")
+    };
+
+    self.seen.insert(base, count + 1);
+    slug
+  }
+}
+
+/// Convert the heading text into the anchor GitHub generates for it. Inline
+/// markdown is reduced to its text, letters are lowercased, spaces become `-`
+/// and other punctuation is removed.
+pub fn slugify(text: &str) -> String {
+  strip_inline_markdown(text)
+    .chars()
+    .filter_map(|ch| {
+      if ch.is_alphanumeric() {
+        Some(ch.to_lowercase().collect::<String>())
+      } else if ch == ' ' {
+        Some("-".into())
+      } else if ch == '-' || ch == '_' {
+        Some(ch.to_string())
+      } else {
+        None
+      }
+    })
+    .collect()
+}
+
+/// Replace links with their text and remove code and emphasis markers.
+fn strip_inline_markdown(text: &str) -> String {
+  let mut result = String::with_capacity(text.len());
+  let mut rest = text;
+
+  while let Some(start) = rest.find('[') {
+    let link = rest.get(start..).unwrap_or_default();
+    let parsed = link.find("](").and_then(|middle| {
+      let end = link.get(middle..)?.find(')')? + middle;
+      Some((link.get(1..middle)?, end))
+    });
+
+    let Some((label, end)) = parsed else {
+      result.push_str(rest.get(..=start).unwrap_or_default());
+      rest = rest.get(start + 1..).unwrap_or_default();
+      continue;
+    };
+
+    result.push_str(rest.get(..start).unwrap_or_default());
+    result.push_str(label);
+    rest = link.get(end + 1..).unwrap_or_default();
+  }
+
+  result.push_str(rest);
+  result.replace(['`', '*', '~'], "")
+}
+
+/// Split an ATX heading line into its indentation, level and remaining text.
+fn split_heading(line: &str) -> Option<(&str, usize, &str)> {
+  let trimmed = line.trim_start_matches(' ');
+  let indent_length = line.len() - trimmed.len();
+
+  if indent_length > 3 {
+    return None;
+  }
+
+  let level = trimmed.bytes().take_while(|&byte| byte == b'#').count();
+
+  if level == 0 || level > 6 {
+    return None;
+  }
+
+  let rest = trimmed.get(level..)?;
+
+  if !rest.is_empty() && !rest.starts_with([' ', '\t']) {
+    return None;
+  }
+
+  Some((line.get(..indent_length)?, level, rest))
+}
+
+/// Get the level and text of an ATX heading without the optional closing
+/// sequence, e.g. `## Title ##`.
+fn heading_text(line: &str) -> Option<(usize, &str)> {
+  let (_, level, rest) = split_heading(line)?;
+  let text = rest.trim();
+  let text = match text.trim_end_matches('#') {
+    stripped if stripped.is_empty() || stripped.ends_with([' ', '\t']) => stripped.trim_end(),
+    _ => text,
+  };
+
+  (!text.is_empty()).then_some((level, text))
+}
+
+/// Get the fence marker (e.g. "```" or "~~~~") when the line opens or closes
+/// a fenced code block.
+fn fence_marker(line: &str) -> Option<String> {
+  let trimmed = line.trim_start_matches(' ');
+
+  if line.len() - trimmed.len() > 3 {
+    return None;
+  }
+
+  for fence_char in ['`', '~'] {
+    let length = trimmed.chars().take_while(|&ch| ch == fence_char).count();
+
+    if length >= 3 {
+      return Some(fence_char.to_string().repeat(length));
+    }
+  }
+
+  None
+}
+
+/// Track the fenced code block which is open after `line`. Returns `true` when
+/// the line opens or closes a fence, or is a shorter marker within one.
+fn fence_state(line: &str, fence: &mut Option<String>) -> bool {
+  let Some(marker) = fence_marker(line) else {
+    return false;
+  };
+
+  match fence.as_ref() {
+    Some(open) if marker.starts_with(open.as_str()) => *fence = None,
+    None => *fence = Some(marker),
+    _ => {}
+  }
+
+  true
+}
+
+fn map_lines(content: &str, mut map: impl FnMut(&str) -> String) -> String {
+  content
+    .split('\n')
+    .map(&mut map)
+    .collect::<Vec<_>>()
+    .join("\n")
+}
+
+fn string_arg(args: &[Argument], index: usize) -> Option<&str> {
+  args.get(index).and_then(Argument::as_str)
+}
+
+fn number_arg(args: &[Argument], index: usize) -> Option<f64> {
+  args.get(index).and_then(Argument::as_number)
+}
+
+fn bool_arg(args: &[Argument], index: usize) -> Option<bool> {
+  args.get(index).and_then(Argument::as_bool)
+}
@@ -0,0 +1,303 @@
+use std::fs;
+use std::ops::Range;
+use std::path::PathBuf;
+
+use crate::Project;
+
+/// An occurrence of a data variable, e.g. `pkg.version`, within a template
+/// expression in a definition file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VariableUsage {
+  /// The dotted path of the variable, e.g. `pkg.version`.
+  pub variable: String,
+  /// The name of the provider which contains the variable.
+  pub provider: String,
+  pub file: PathBuf,
+  /// The byte range of the variable within the file.
+  pub range: Range<usize>,
+}
+
+/// The location of a key within a data file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DataLocation {
+  pub file: PathBuf,
+  /// The byte range of the key within the data file.
+  pub range: Range<usize>,
+}
+
+/// Every data variable used by the provider templates. This powers go to
+/// definition from a variable into its data file and references from a data
+/// key back to the templates using it.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct VariableIndex {
+  /// The usages ordered by file and then by position.
+  pub usages: Vec<VariableUsage>,
+}
+
+impl VariableIndex {
+  /// Collect the variables within the `{{ }}` and `{% %}` expressions of
+  /// every provider. Only variables in a configured data namespace are
+  /// included.
+  pub fn build(project: &Project) -> Self {
+    let mut usages = vec![];
+
+    for provider in project.providers.values() {
+      let Some(content) = project.files.get(&provider.file) else {
+        continue;
+      };
+
+      let range = provider.block.content_range();
+      let Some(template) = content.get(range.clone()) else {
+        continue;
+      };
+
+      for (variable, variable_range) in find_template_variables(template) {
+        let namespace = variable.split('.').next().unwrap_or_default();
+
+        if !project.data.contains_key(namespace) {
+          continue;
+        }
+
+        usages.push(VariableUsage {
+          variable,
+          provider: provider.block.name.clone(),
+          file: provider.file.clone(),
+          range: variable_range.start + range.start..variable_range.end + range.start,
+        });
+      }
+    }
+
+    usages.sort_by(|a, b| a.file.cmp(&b.file).then(a.range.start.cmp(&b.range.start)));
+
+    Self { usages }
+  }
+
+  /// The variable at the byte offset of the file.
+  pub fn usage_at(&self, file: &PathBuf, offset: usize) -> Option<&VariableUsage> {
+    self
+      .usages
+      .iter()
+      .find(|usage| &usage.file == file && usage.range.contains(&offset))
+  }
+
+  /// Every usage of the variable or of a variable nested within it, e.g. the
+  /// references to `pkg` include `pkg.version`.
+  pub fn references<'a>(&'a self, variable: &'a str) -> impl Iterator<Item = &'a VariableUsage> {
+    self.usages.iter().filter(move |usage| {
+      usage.variable == variable
+        || usage
+          .variable
+          .strip_prefix(variable)
+          .map_or(false, |rest| rest.starts_with('.'))
+    })
+  }
+}
+
+/// Find the location of the variable's key within its data file. Returns
+/// `None` when the namespace isn't configured or the key can't be found.
+pub fn find_data_definition(project: &Project, variable: &str) -> Option<DataLocation> {
+  let mut segments = variable.split('.');
+  let namespace = segments.next()?;
+  let source = project.config.data.get(namespace)?;
+  let file = project.root.join(source.path()?);
+  let keys = source
+    .select()
+    .into_iter()
+    .flat_map(|select| select.split('.'))
+    .chain(segments)
+    .collect::<Vec<_>>();
+
+  if keys.is_empty() {
+    return Some(DataLocation { file, range: 0..0 });
+  }
+
+  let content = fs::read_to_string(&file).ok()?;
+  let range = find_key_range(&content, &keys)?;
+
+  Some(DataLocation { file, range })
+}
+
+/// Find the byte range of the last key in a JSON or TOML document. Each key is
+/// searched for after the previous one, which is accurate for the nested keys
+/// of typical manifests like `package.json` and `Cargo.toml`.
+pub fn find_key_range(content: &str, keys: &[&str]) -> Option<Range<usize>> {
+  let mut cursor = 0;
+  let mut range = None;
+
+  for key in keys {
+    let found = find_key_after(content, key, cursor)?;
+    cursor = found.end;
+    range = Some(found);
+  }
+
+  range
+}
+
+fn find_key_after(content: &str, key: &str, start: usize) -> Option<Range<usize>> {
+  let rest = content.get(start..)?;
+  let quoted = format!("\"{key}\"");
+
+  for (index, _) in rest.match_indices(key) {
+    let absolute = start + index;
+    let before = content.get(..absolute).unwrap_or_default();
+    let after = content.get(absolute + key.len()..).unwrap_or_default();
+
+    // A quoted key in JSON or TOML, e.g. `"version": "1.0.0"`.
+    if before.ends_with('"') && after.starts_with('"') {
+      let after_quote = after.get(1..).unwrap_or_default().trim_start();
+
+      if after_quote.starts_with(':') || after_quote.starts_with('=') {
+        return Some(absolute - 1..absolute + quoted.len() - 1);
+      }
+    }
+
+    let line_start = before.rsplit('\n').next().unwrap_or_default().trim();
+    let trimmed_after = after.trim_start_matches([' ', '\t']);
+
+    // A bare TOML key, e.g. `version = "1.0.0"`.
+    if line_start.is_empty() && trimmed_after.starts_with('=') {
+      return Some(absolute..absolute + key.len());
+    }
+
+    // A TOML table header, e.g. `[package]` or `[workspace.package]`.
+    let is_header = (line_start == "["
+      || (line_start.starts_with('[') && line_start.ends_with('.')))
+      && after.starts_with([']', '.']);
+
+    if is_header {
+      return Some(absolute..absolute + key.len());
+    }
+  }
+
+  None
+}
+
+/// Find the dotted variables in the template expressions along with their
+/// byte ranges. Filters and their arguments, e.g. `| default("x")`, are
+/// skipped.
+pub fn find_template_variables(template: &str) -> Vec<(String, Range<usize>)> {
+  let mut variables = vec![];
+  let mut cursor = 0;
+
+  while let Some(start) = template.get(cursor..).and_then(|rest| {
+    let expression = rest.find("{{");
+    let statement = rest.find("{%");
+
+    match (expression, statement) {
+      (Some(a), Some(b)) => Some(a.min(b)),
+      (a, b) => a.or(b),
+    }
+  }) {
+    let open = cursor + start;
+    let close_marker = if template.get(open..open + 2) == Some("{{") {
+      "}}"
+    } else {
+      "%}"
+    };
+    let Some(end) = template
+      .get(open + 2..)
+      .and_then(|rest| rest.find(close_marker))
+    else {
+      break;
+    };
+
+    let body_start = open + 2;
+    let body = template
+      .get(body_start..body_start + end)
+      .unwrap_or_default();
+    collect_expression_variables(body, body_start, &mut variables);
+    cursor = body_start + end + 2;
+  }
+
+  variables
+}
+
+fn collect_expression_variables(
+  body: &str,
+  offset: usize,
+  variables: &mut Vec<(String, Range<usize>)>,
+) {
+  let bytes = body.as_bytes();
+  let mut index = 0;
+  let mut after_pipe = false;
+
+  while let Some(&byte) = bytes.get(index) {
+    if byte == b'"' || byte == b'\'' {
+      let quote = byte;
+      index += 1;
+
+      while bytes.get(index).map_or(false, |&current| current != quote) {
+        index += 1;
+      }
+
+      index += 1;
+      continue;
+    }
+
+    if byte == b'|' {
+      after_pipe = true;
+      index += 1;
+      continue;
+    }
+
+    let is_start = byte.is_ascii_alphabetic() || byte == b'_';
+    let follows_dot = index > 0 && bytes.get(index - 1) == Some(&b'.');
+
+    if !is_start || follows_dot {
+      index += 1;
+      continue;
+    }
+
+    let start = index;
+
+    while bytes.get(index).map_or(false, |&current| {
+      current.is_ascii_alphanumeric() || current == b'_' || current == b'.'
+    }) {
+      index += 1;
+    }
+
+    let variable = body
+      .get(start..index)
+      .unwrap_or_default()
+      .trim_end_matches('.');
+
+    if after_pipe {
+      // The first identifier after a pipe is the filter name.
+      after_pipe = false;
+      continue;
+    }
+
+    let is_call = bytes.get(index) == Some(&b'(');
+
+    if !is_call && !is_keyword(variable) {
+      variables.push((
+        variable.to_string(),
+        offset + start..offset + start + variable.len(),
+      ));
+    }
+  }
+}
+
+fn is_keyword(word: &str) -> bool {
+  matches!(
+    word,
+    "if"
+      | "else"
+      | "elif"
+      | "endif"
+      | "for"
+      | "in"
+      | "endfor"
+      | "set"
+      | "not"
+      | "and"
+      | "or"
+      | "is"
+      | "true"
+      | "false"
+      | "none"
+      | "True"
+      | "False"
+      | "None"
+  )
+}
@@ -0,0 +1,206 @@
+use std::fmt::Debug;
+use std::path::Path;
+use std::path::PathBuf;
+
+use serde::Serialize;
+
+use crate::MdtResult;
+
+/// The version control operations which git-aware features depend on. Paths
+/// are relative to the root of the project.
+pub trait Vcs: Debug {
+  /// The files which changed since the revision, including uncommitted and
+  /// untracked files.
+  fn changed_files(&self, since: &str) -> MdtResult<Vec<PathBuf>>;
+  /// The date of the last commit which modified the file as `YYYY-MM-DD`.
+  /// Returns `None` when the file was never committed.
+  fn last_modified(&self, file: &Path) -> MdtResult<Option<String>>;
+  /// The staged content of the file. Returns `None` when the file isn't in
+  /// the index.
+  fn staged_content(&self, file: &Path) -> MdtResult<Option<String>>;
+  /// Commit the files with the message.
+  fn commit(&self, files: &[PathBuf], message: &str) -> MdtResult<()>;
+  /// Push the current branch to its upstream.
+  fn push(&self) -> MdtResult<()>;
+  /// The latest tag, matching the glob when one is given, and details of the
+  /// current commit.
+  fn metadata(&self, tags: Option<&str>) -> MdtResult<VcsMetadata>;
+}
+
+/// Details of the repository which templates can render. The values are
+/// `None` in a repository without commits or tags.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct VcsMetadata {
+  pub latest_tag: Option<String>,
+  /// The abbreviated hash of the current commit.
+  pub short_sha: Option<String>,
+  /// The date of the current commit as `YYYY-MM-DD`.
+  pub commit_date: Option<String>,
+  /// The number of distinct authors of the commits on the current branch.
+  pub contributor_count: usize,
+}
+
+/// The version control system for the project at the root. Returns `None`
+/// when the root isn't within a repository or the `git` feature is disabled.
+pub fn detect_vcs(root: &Path) -> Option<Box<dyn Vcs>> {
+  #[cfg(feature = "git")]
+  if let Some(git) = git::GitCli::open(root) {
+    return Some(Box::new(git));
+  }
+
+  #[cfg(not(feature = "git"))]
+  let _ = root;
+
+  None
+}
+
+#[cfg(feature = "git")]
+pub use git::GitCli;
+
+#[cfg(feature = "git")]
+mod git {
+  use std::path::Path;
+  use std::path::PathBuf;
+  use std::process::Command;
+
+  use super::Vcs;
+  use super::VcsMetadata;
+  use crate::MdtError;
+  use crate::MdtResult;
+
+  /// Git support by shelling out to the `git` executable.
+  #[derive(Debug, Clone)]
+  pub struct GitCli {
+    root: PathBuf,
+  }
+
+  impl GitCli {
+    /// Open the repository which contains the root. Returns `None` when the
+    /// root isn't within a work tree or git isn't installed.
+    pub fn open(root: &Path) -> Option<Self> {
+      let git = Self {
+        root: root.to_path_buf(),
+      };
+      let inside = git
+        .try_run(&["rev-parse", "--is-inside-work-tree"])
+        .ok()??;
+
+      (inside.trim() == "true").then_some(git)
+    }
+
+    /// Run the command and return its output, failing when it exits with an
+    /// error.
+    fn run(&self, args: &[&str]) -> MdtResult<String> {
+      self.try_run(args)?.ok_or_else(|| {
+        MdtError::Vcs {
+          command: format!("git {}", args.join(" ")),
+          message: "the command exited with an error".into(),
+        }
+      })
+    }
+
+    /// Run the command and return its output, or `None` when it exits with an
+    /// error.
+    fn try_run(&self, args: &[&str]) -> MdtResult<Option<String>> {
+      let output = Command::new("git")
+        .args(args)
+        .current_dir(&self.root)
+        .output()
+        .map_err(|error| {
+          MdtError::Vcs {
+            command: format!("git {}", args.join(" ")),
+            message: error.to_string(),
+          }
+        })?;
+
+      if !output.status.success() {
+        return Ok(None);
+      }
+
+      Ok(Some(String::from_utf8_lossy(&output.stdout).into_owned()))
+    }
+  }
+
+  impl Vcs for GitCli {
+    fn changed_files(&self, since: &str) -> MdtResult<Vec<PathBuf>> {
+      let changed = self.run(&["diff", "--name-only", "--relative", since, "--"])?;
+      let untracked = self.run(&["ls-files", "--others", "--exclude-standard"])?;
+      let mut files = changed
+        .lines()
+        .chain(untracked.lines())
+        .filter(|line| !line.is_empty())
+        .map(PathBuf::from)
+        .collect::<Vec<_>>();
+      files.sort();
+      files.dedup();
+
+      Ok(files)
+    }
+
+    fn last_modified(&self, file: &Path) -> MdtResult<Option<String>> {
+      let file = file.to_string_lossy();
+      // The log fails in a repository without commits.
+      let date = self
+        .try_run(&["log", "-1", "--format=%cs", "--", &file])?
+        .unwrap_or_default();
+      let date = date.trim();
+
+      Ok((!date.is_empty()).then(|| date.to_string()))
+    }
+
+    fn staged_content(&self, file: &Path) -> MdtResult<Option<String>> {
+      let object = format!(":{}", file.to_string_lossy());
+      self.try_run(&["show", &object])
+    }
+
+    fn commit(&self, files: &[PathBuf], message: &str) -> MdtResult<()> {
+      let files = files
+        .iter()
+        .map(|file| file.to_string_lossy().into_owned())
+        .collect::<Vec<_>>();
+      let mut add = vec!["add", "--"];
+      add.extend(files.iter().map(String::as_str));
+      self.run(&add)?;
+      self.run(&["commit", "--quiet", "-m", message])?;
+
+      Ok(())
+    }
+
+    fn push(&self) -> MdtResult<()> {
+      self.run(&["push", "--quiet"]).map(|_| ())
+    }
+
+    fn metadata(&self, tags: Option<&str>) -> MdtResult<VcsMetadata> {
+      let mut describe = vec!["describe", "--tags", "--abbrev=0"];
+
+      if let Some(pattern) = tags {
+        describe.extend(["--match", pattern]);
+      }
+
+      // Both commands fail in a repository without commits or tags.
+      let latest_tag = self.try_run(&describe)?.unwrap_or_default();
+      let head = self
+        .try_run(&["log", "-1", "--format=%h%n%cs"])?
+        .unwrap_or_default();
+      let mut head = head.lines();
+      let authors = self.try_run(&["log", "--format=%aE"])?.unwrap_or_default();
+      let mut authors = authors.lines().map(str::to_lowercase).collect::<Vec<_>>();
+      authors.sort();
+      authors.dedup();
+
+      let value = |value: Option<&str>| {
+        value
+          .map(str::trim)
+          .filter(|value| !value.is_empty())
+          .map(String::from)
+      };
+
+      Ok(VcsMetadata {
+        latest_tag: value(Some(&latest_tag)),
+        short_sha: value(head.next()),
+        commit_date: value(head.next()),
+        contributor_count: authors.len(),
+      })
+    }
+  }
+}
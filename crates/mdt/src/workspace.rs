@@ -0,0 +1,207 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+use std::path::PathBuf;
+
+use globset::GlobBuilder;
+use globset::GlobSetBuilder;
+
+use crate::concatenated_names;
+use crate::scan_project_filtered;
+use crate::MdtConfig;
+use crate::MdtError;
+use crate::MdtResult;
+use crate::PathFilter;
+use crate::Project;
+use crate::ProviderEntry;
+use crate::ProviderScope;
+use crate::CONFIG_FILE_NAME;
+use crate::IGNORED_DIRECTORIES;
+
+/// Whether the directory is the root of a project, i.e. it has an `mdt.toml`.
+/// Scans stop at nested projects, which are scanned on their own.
+pub fn is_project_root(directory: &Path) -> bool {
+  directory.join(CONFIG_FILE_NAME).is_file()
+}
+
+/// The projects nested below the root, relative to it and in sorted order.
+/// Projects within nested projects are included.
+pub fn find_nested_projects(root: &Path) -> MdtResult<Vec<PathBuf>> {
+  let mut projects = vec![];
+  collect_projects(root, root, &mut projects)?;
+  projects.sort();
+
+  Ok(projects)
+}
+
+/// The members of the workspace, relative to the root: the nested projects
+/// which match the `[workspace] members` globs. When `recursive` every nested
+/// project is a member.
+pub fn workspace_members(
+  root: &Path,
+  config: &MdtConfig,
+  recursive: bool,
+) -> MdtResult<Vec<PathBuf>> {
+  let mut projects = find_nested_projects(root)?;
+
+  if recursive {
+    return Ok(projects);
+  }
+
+  let mut globs = GlobSetBuilder::new();
+
+  for member in &config.workspace.members {
+    let glob = GlobBuilder::new(member.trim_end_matches('/'))
+      .literal_separator(true)
+      .build()
+      .map_err(|error| {
+        MdtError::InvalidConfig(format!(
+          "the workspace member `{member}` isn't a valid glob: {error}"
+        ))
+      })?;
+    globs.add(glob);
+  }
+
+  let globs = globs
+    .build()
+    .map_err(|error| MdtError::InvalidConfig(error.to_string()))?;
+  projects.retain(|project| globs.is_match(project));
+
+  Ok(projects)
+}
+
+/// Scan the root and the members of its workspace, each with its own
+/// configuration. The root comes first with an empty path and the members
+/// follow, relative to the root.
+///
+/// The providers with a `workspace` scope are imported by the other
+/// projects: those of the root keep their name and those of a member are
+/// namespaced by its path, e.g. `packages/cli/install`. A project's own
+/// providers take precedence over imported ones with the same name.
+/// Imported providers are rendered with the data of the project which
+/// consumes them. Every project skips the files which don't pass the filter
+/// and applies the profile to its configuration.
+pub fn scan_workspace(
+  root: &Path,
+  recursive: bool,
+  filter: &PathFilter,
+  profile: Option<&str>,
+) -> MdtResult<Vec<(PathBuf, Project)>> {
+  let project = scan_project_filtered(root, &BTreeMap::new(), filter, profile)?;
+  let members = workspace_members(root, &project.config, recursive)?;
+  let mut projects = vec![(PathBuf::new(), project)];
+
+  for member in members {
+    let project = scan_project_filtered(root.join(&member), &BTreeMap::new(), filter, profile)?;
+    projects.push((member, project));
+  }
+
+  let exports = projects
+    .iter()
+    .map(|(member, project)| {
+      let providers = project
+        .providers
+        .values()
+        .filter(|provider| provider.scope == ProviderScope::Workspace)
+        .cloned()
+        .collect::<Vec<_>>();
+
+      (member.clone(), providers)
+    })
+    .collect::<Vec<_>>();
+
+  for (member, project) in &mut projects {
+    for (source, providers) in &exports {
+      if source == member {
+        continue;
+      }
+
+      for provider in providers {
+        import_provider(project, source, provider);
+      }
+    }
+  }
+
+  let consumed = projects
+    .iter()
+    .flat_map(|(_, project)| {
+      let consumers = project
+        .consumers
+        .iter()
+        .filter(|consumer| !consumer.block.is_inline());
+
+      consumers.flat_map(|consumer| {
+        concatenated_names(&consumer.block.name)
+          .filter_map(|name| project.providers.get(name))
+          .filter_map(|provider| {
+            let source = provider.imported_from.clone()?;
+            let name = provider
+              .block
+              .name
+              .strip_prefix(&format!("{}/", slash_path(&source)))
+              .unwrap_or(&provider.block.name);
+            Some((source, name.to_string()))
+          })
+          .collect::<Vec<_>>()
+      })
+    })
+    .collect::<Vec<_>>();
+
+  for (source, name) in consumed {
+    if let Some((_, project)) = projects.iter_mut().find(|(member, _)| *member == source) {
+      project.workspace_consumed.insert(name);
+    }
+  }
+
+  Ok(projects)
+}
+
+fn import_provider(project: &mut Project, source: &Path, provider: &ProviderEntry) {
+  let name = if source.as_os_str().is_empty() {
+    provider.block.name.clone()
+  } else {
+    format!("{}/{}", slash_path(source), provider.block.name)
+  };
+
+  if project.providers.contains_key(&name) {
+    return;
+  }
+
+  let mut provider = provider.clone();
+  provider.block.name = name.clone();
+  provider.imported_from = Some(source.to_path_buf());
+  project.providers.insert(name, provider);
+}
+
+/// The path with `/` separators on every platform.
+fn slash_path(path: &Path) -> String {
+  path
+    .components()
+    .map(|component| component.as_os_str().to_string_lossy())
+    .collect::<Vec<_>>()
+    .join("/")
+}
+
+fn collect_projects(root: &Path, directory: &Path, projects: &mut Vec<PathBuf>) -> MdtResult<()> {
+  for entry in fs::read_dir(directory)? {
+    let entry = entry?;
+    let path = entry.path();
+    let name = entry.file_name();
+    let name = name.to_string_lossy();
+
+    if !entry.file_type()?.is_dir()
+      || name.starts_with('.')
+      || IGNORED_DIRECTORIES.contains(&name.as_ref())
+    {
+      continue;
+    }
+
+    if is_project_root(&path) {
+      projects.push(path.strip_prefix(root).unwrap_or(&path).to_path_buf());
+    }
+
+    collect_projects(root, &path, projects)?;
+  }
+
+  Ok(())
+}
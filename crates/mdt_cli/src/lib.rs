@@ -1,16 +1,364 @@
+use std::path::Path;
+use std::path::PathBuf;
+use std::time::Duration;
+
+use clap::ArgGroup;
+use clap::Args;
 use clap::Parser;
 use clap::Subcommand;
+use clap::ValueEnum;
+use mdt::ColorChoice;
+use mdt::MdtResult;
+use mdt::PathFilter;
+use mdt::UserSettings;
 
 #[derive(Parser)]
 #[command(author, version, about, long_about = None)]
 pub struct MdtCli {
   #[command(subcommand)]
   pub command: Option<Commands>,
+  /// The root directory of the project. Defaults to the current directory.
+  #[arg(long, global = true)]
+  pub path: Option<PathBuf>,
+  /// Apply the overrides of a `[profile.<name>]` section of `mdt.toml`. It
+  /// can also be set with the `MDT_PROFILE` environment variable.
+  #[arg(long, global = true, env = "MDT_PROFILE")]
+  pub profile: Option<String>,
+  /// When output is colored: `auto`, `always` or `never`. Defaults to the
+  /// `color` of the user configuration.
+  #[arg(long, global = true, value_parser = parse_color)]
+  pub color: Option<ColorChoice>,
+}
+
+impl MdtCli {
+  pub fn root(&self) -> PathBuf {
+    self.path.clone().unwrap_or_else(|| PathBuf::from("."))
+  }
 }
 
 #[derive(Subcommand)]
 pub enum Commands {
   Init,
-  Check,
-  Update,
+  /// Add an empty provider to a template file in `.templates`, which is
+  /// created when it doesn't exist.
+  New {
+    /// The name of the provider.
+    name: String,
+    /// The template file within `.templates`. Defaults to `template.t.md`.
+    #[arg(long)]
+    file: Option<PathBuf>,
+  },
+  /// Check that every consumer is up to date.
+  Check {
+    /// Enable the strict checks: undefined variables, orphan consumers and
+    /// unused providers are errors, whitespace must match and links must
+    /// resolve.
+    #[arg(long)]
+    strict: bool,
+    /// Report deprecated features as errors instead of warnings.
+    #[arg(long)]
+    deny_deprecated: bool,
+    /// Only report the files within this package of a monorepo.
+    #[arg(long)]
+    package: Option<String>,
+    /// Stop checking new files once the time is spent, e.g. `500ms`, `2s` or
+    /// `1m`. Changed and recently modified files are checked first.
+    #[arg(long, value_parser = parse_duration)]
+    budget: Option<Duration>,
+    /// Only check a random sample of the consumers, e.g. `10%`. The seed and
+    /// the sampled consumers are printed so failures can be reproduced.
+    #[arg(long, value_parser = parse_percent)]
+    sample: Option<f64>,
+    /// The seed for `--sample`. Defaults to a seed based on the current time.
+    #[arg(long, requires = "sample")]
+    seed: Option<u64>,
+    /// Also check every nested project with its own `mdt.toml`, not only the
+    /// `[workspace]` members.
+    #[arg(long)]
+    recursive: bool,
+    #[command(flatten)]
+    stdin: StdinArgs,
+    #[command(flatten)]
+    filter: FilterArgs,
+  },
+  /// Update every stale consumer.
+  Update {
+    #[command(flatten)]
+    stdin: StdinArgs,
+    #[command(flatten)]
+    filter: FilterArgs,
+  },
+  /// Update the stale consumers of a single block and leave the others.
+  Sync {
+    /// The name of the block.
+    name: String,
+    /// Only update the consumers in this file, relative to the root.
+    #[arg(long)]
+    file: Option<PathBuf>,
+  },
+  /// Rename a block in the tags of its provider and of every consumer.
+  Rename {
+    /// The current name of the block.
+    old: String,
+    /// The new name of the block.
+    new: String,
+    /// Print the files which would change without writing them.
+    #[arg(long)]
+    dry_run: bool,
+  },
+  /// Remove a provider. Its consumers are left and reported as orphans unless
+  /// `--strip` is given.
+  #[command(group(ArgGroup::new("consumers").args(["keep_content", "strip"])))]
+  Rm {
+    /// The name of the provider.
+    name: String,
+    /// Leave the consumers and their content as they are. This is the
+    /// default.
+    #[arg(long)]
+    keep_content: bool,
+    /// Remove the tags of the consumers and keep their last synced content
+    /// inline.
+    #[arg(long)]
+    strip: bool,
+  },
+  /// Summarize the project and report data which is unused or missing.
+  Info {
+    /// Only count the blocks within this package of a monorepo.
+    #[arg(long)]
+    package: Option<String>,
+  },
+  /// List the providers along with their descriptions.
+  List {
+    /// Only list providers with this tag. Can be repeated.
+    #[arg(long = "tag")]
+    tags: Vec<String>,
+    /// Only list providers within this namespace, e.g. `docs` for
+    /// `docs/overview`.
+    #[arg(long)]
+    namespace: Option<String>,
+    /// Only list providers within this package of a monorepo.
+    #[arg(long)]
+    package: Option<String>,
+  },
+  /// Render a provider, e.g. to reuse it in an announcement.
+  Render {
+    /// The name of the provider.
+    name: String,
+    /// A transformer chain to apply, e.g. `trim|slackFormat`.
+    #[arg(long = "with", default_value = "")]
+    transformers: String,
+    /// Post the rendered content as the `text` of a JSON payload to the url,
+    /// e.g. a Slack incoming webhook.
+    #[arg(long)]
+    post_webhook: Option<String>,
+  },
+  /// Render the release notes for a tag and optionally publish them to the
+  /// GitHub release with the `gh` cli.
+  ReleaseNotes {
+    /// The tag of the release, e.g. `v1.2.0`.
+    #[arg(long)]
+    tag: String,
+    /// The provider with the release notes. Defaults to `provider` in the
+    /// `[release]` section of `mdt.toml`.
+    #[arg(long)]
+    provider: Option<String>,
+    /// Create the GitHub release, or update its body when it exists.
+    #[arg(long)]
+    push: bool,
+  },
+  /// Search the names and content of the providers.
+  Search {
+    /// The text to search for. The search is case insensitive.
+    query: String,
+    /// Only search providers with this tag. Can be repeated.
+    #[arg(long = "tag")]
+    tags: Vec<String>,
+    #[arg(long, value_enum, default_value_t = OutputFormat::Text)]
+    format: OutputFormat,
+  },
+  /// Insert a consumer for a provider into every file matching a glob.
+  Attach {
+    /// The name of the provider.
+    name: String,
+    /// A glob of the files, relative to the root, e.g. `crates/*/readme.md`.
+    #[arg(long)]
+    to: String,
+    /// Insert the consumer after the first line which starts with this text,
+    /// e.g. `# ` for the first heading. Defaults to the end of the file.
+    #[arg(long)]
+    after: Option<String>,
+  },
+  /// Insert a consumer for a provider into a file and fill it in.
+  Add {
+    /// The name of the provider.
+    name: String,
+    /// The file, relative to the root.
+    file: PathBuf,
+    /// Insert the consumer before this line, starting at 1. Defaults to the
+    /// end of the file.
+    #[arg(long, conflicts_with = "heading")]
+    line: Option<usize>,
+    /// Insert the consumer after the markdown heading with this text, e.g.
+    /// `Installation`.
+    #[arg(long)]
+    heading: Option<String>,
+    /// The transformer chain of the consumer, e.g. `trim|code`. Defaults to a
+    /// chain for the type of file, e.g. rustdoc comments for `.rs` files.
+    #[arg(long = "with")]
+    transformers: Option<String>,
+  },
+  /// Check that existing text matches a provider before adopting it, by
+  /// reporting how the closest passage in every file matching a glob differs
+  /// from the rendered provider.
+  AdoptCheck {
+    /// The name of the provider.
+    name: String,
+    /// A glob of the files, relative to the root, e.g. `crates/*/readme.md`.
+    #[arg(long = "in")]
+    files: String,
+    /// The share of lines which may differ, from `0` to `1`.
+    #[arg(long, default_value_t = 0.0)]
+    tolerance: f64,
+  },
+  /// Inspect the configuration.
+  Config {
+    #[command(subcommand)]
+    command: ConfigCommands,
+  },
+  /// Maintain the documentation of a template pack.
+  Docs {
+    #[command(subcommand)]
+    command: DocsCommands,
+  },
+}
+
+#[derive(Subcommand)]
+pub enum DocsCommands {
+  /// Write the index of every provider in this repository, which is a
+  /// template pack, with a preview and a usage example.
+  Sync {
+    /// The path of the index, relative to the root. Defaults to the
+    /// `[docs] index` setting or `readme.md`.
+    #[arg(long)]
+    output: Option<PathBuf>,
+  },
+}
+
+#[derive(Subcommand)]
+pub enum ConfigCommands {
+  /// Print the configuration file.
+  Show {
+    /// Print every effective value along with where it comes from.
+    #[arg(long)]
+    resolved: bool,
+  },
+}
+
+/// Read a single document from stdin instead of the file system, e.g. to pipe
+/// an editor buffer through `mdt update --stdin --stdin-path readme.md`.
+#[derive(Debug, Clone, Args)]
+pub struct StdinArgs {
+  /// Read the content of the document from stdin. `mdt update` prints the
+  /// updated document to stdout and writes nothing.
+  #[arg(long, requires = "stdin_path")]
+  pub stdin: bool,
+  /// The path of the document read from stdin, relative to the root. The
+  /// file doesn't need to exist.
+  #[arg(long, requires = "stdin")]
+  pub stdin_path: Option<PathBuf>,
+}
+
+impl StdinArgs {
+  /// The path of the document read from stdin, when `--stdin` is set.
+  pub fn path(&self, root: &Path) -> Option<PathBuf> {
+    self
+      .stdin
+      .then_some(self.stdin_path.as_ref())
+      .flatten()
+      .map(|path| root.join(path))
+  }
+}
+
+/// Restrict the files which are scanned for consumers, e.g. to review the
+/// changes within `docs/**`. Definition files are always scanned.
+#[derive(Debug, Clone, Args)]
+pub struct FilterArgs {
+  /// Only scan the files matching the glob, relative to the root. Can be
+  /// repeated.
+  #[arg(long)]
+  pub only: Vec<String>,
+  /// Skip the files matching the glob, relative to the root. Can be repeated.
+  #[arg(long)]
+  pub skip: Vec<String>,
+}
+
+impl FilterArgs {
+  /// The filter of the arguments. The files which the user configuration
+  /// skips are skipped as well.
+  pub fn path_filter(&self, root: &Path, settings: &UserSettings) -> MdtResult<PathFilter> {
+    let skip = self
+      .skip
+      .iter()
+      .chain(&settings.skip)
+      .cloned()
+      .collect::<Vec<_>>();
+
+    PathFilter::new(root, &self.only, &skip)
+  }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum OutputFormat {
+  Text,
+  Json,
+}
+
+/// Parse when output is colored: `auto`, `always` or `never`.
+pub fn parse_color(value: &str) -> Result<ColorChoice, String> {
+  match value {
+    "auto" => Ok(ColorChoice::Auto),
+    "always" => Ok(ColorChoice::Always),
+    "never" => Ok(ColorChoice::Never),
+    _ => {
+      Err(format!(
+        "`{value}` isn't one of `auto`, `always` or `never`"
+      ))
+    }
+  }
+}
+
+/// Parse a duration with a unit, e.g. `500ms`, `2s` or `1m`.
+pub fn parse_duration(value: &str) -> Result<Duration, String> {
+  let value = value.trim();
+  let split = value
+    .find(|character: char| !character.is_ascii_digit())
+    .unwrap_or(value.len());
+  let (amount, unit) = value.split_at(split);
+  let amount = amount
+    .parse::<u64>()
+    .map_err(|_| format!("`{value}` doesn't start with a number"))?;
+
+  match unit {
+    "ms" => Ok(Duration::from_millis(amount)),
+    "s" => Ok(Duration::from_secs(amount)),
+    "m" => Ok(Duration::from_secs(amount * 60)),
+    _ => Err(format!("`{value}` needs a unit of `ms`, `s` or `m`")),
+  }
+}
+
+/// Parse a percentage such as `10%` or `10` into a number between 0 and 100.
+pub fn parse_percent(value: &str) -> Result<f64, String> {
+  let trimmed = value.trim();
+  let percent = trimmed
+    .strip_suffix('%')
+    .unwrap_or(trimmed)
+    .trim()
+    .parse::<f64>()
+    .map_err(|_| format!("`{value}` isn't a percentage"))?;
+
+  if (0.0..=100.0).contains(&percent) {
+    Ok(percent)
+  } else {
+    Err(format!("`{value}` must be between 0% and 100%"))
+  }
 }
@@ -1,22 +1,980 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::io::IsTerminal;
+use std::io::Read;
+use std::path::Path;
+use std::path::PathBuf;
+use std::process::Command;
+use std::process::Stdio;
+use std::time::SystemTime;
+use std::time::UNIX_EPOCH;
+
 use clap::Parser;
+use globset::GlobBuilder;
+use mdt::analyze_data_usage;
+use mdt::apply_project_patches;
+use mdt::attach_consumer;
+use mdt::check_project_scoped;
+use mdt::compare_for_adoption;
+use mdt::compute_asset_copies;
+use mdt::compute_block_updates;
+use mdt::compute_removal;
+use mdt::compute_rename;
+use mdt::compute_updates;
+use mdt::consumer_style;
+use mdt::copy_assets;
+use mdt::has_consumer;
+use mdt::insert_consumer;
+use mdt::is_in_namespace;
+use mdt::render_provider_index;
+use mdt::render_provider_with;
+use mdt::render_release_notes;
+use mdt::run_with_input;
+use mdt::scaffold_provider;
+use mdt::scan_project_filtered;
+use mdt::scan_workspace;
+use mdt::scanned_files;
+use mdt::search_providers;
+use mdt::user_config_path;
+use mdt::write_updates;
+use mdt::AnyEmptyResult;
+use mdt::AnyError;
+use mdt::AnyResult;
+use mdt::AttachAnchor;
+use mdt::AttachOutcome;
+use mdt::CheckOptions;
+use mdt::CheckScope;
+use mdt::ColorChoice;
+use mdt::ConsumerRemoval;
+use mdt::LineDifference;
+use mdt::MdtConfig;
+use mdt::MdtError;
+use mdt::PathFilter;
+use mdt::Project;
+use mdt::Sample;
+use mdt::Severity;
+use mdt::UpdateResult;
+use mdt::UserSettings;
+use mdt::CONFIG_FILE_NAME;
+use mdt::DEFAULT_INDEX_FILE;
+use mdt::DEFAULT_TEMPLATE_FILE;
+use mdt::TEMPLATES_DIRECTORY;
 use mdt_cli::Commands;
+use mdt_cli::ConfigCommands;
+use mdt_cli::DocsCommands;
 use mdt_cli::MdtCli;
+use mdt_cli::OutputFormat;
+use mdt_cli::StdinArgs;
+use miette::Diagnostic;
 
 fn main() {
   let args = MdtCli::parse();
+  let settings = UserSettings::load(user_config_path().as_deref());
+  let color = args
+    .color
+    .or_else(|| settings.as_ref().ok().map(|settings| settings.color))
+    .unwrap_or_default();
+  let result = settings
+    .map_err(AnyError::from)
+    .and_then(|settings| run(&args, &settings, color));
+
+  if let Err(error) = result {
+    report(&error, color.enabled(std::io::stderr().is_terminal()));
+    std::process::exit(1);
+  }
+}
+
+/// Print the error along with the code and help of its diagnostic, e.g.
+/// `error[mdt::unknown_block]: no block named `nope` exists`.
+fn report(error: &AnyError, color: bool) {
+  let label = |text: &str, code| paint(text, code, color);
+  let Some(error) = error.downcast_ref::<MdtError>() else {
+    eprintln!("{}: {error}", label("error", RED));
+    return;
+  };
+
+  match error.code() {
+    Some(code) => eprintln!("{}: {error}", label(&format!("error[{code}]"), RED)),
+    None => eprintln!("{}: {error}", label("error", RED)),
+  }
+
+  if let Some(help) = error.help() {
+    eprintln!("{}: {help}", label("help", CYAN));
+  }
+}
+
+/// The ansi codes of the colors of output labels.
+const RED: &str = "1;31";
+const YELLOW: &str = "1;33";
+const CYAN: &str = "1;36";
+
+/// Wrap the text in the ansi color when colors are enabled.
+fn paint(text: &str, code: &str, color: bool) -> String {
+  if color {
+    format!("\x1b[{code}m{text}\x1b[0m")
+  } else {
+    text.to_string()
+  }
+}
+
+fn run(args: &MdtCli, settings: &UserSettings, color: ColorChoice) -> AnyEmptyResult {
+  // An empty `MDT_PROFILE` selects no profile.
+  let profile = args
+    .profile
+    .as_deref()
+    .filter(|profile| !profile.is_empty());
 
   match args.command {
     Some(Commands::Init) => {
       println!("initializing project!");
     }
-    Some(Commands::Check) => {
-      // Check the mdt code blocks
+    Some(Commands::New { ref name, ref file }) => {
+      let root = args.root();
+      let project = scan_root(&root, profile)?;
+      let relative = Path::new(TEMPLATES_DIRECTORY).join(
+        file
+          .as_deref()
+          .unwrap_or_else(|| Path::new(DEFAULT_TEMPLATE_FILE)),
+      );
+      let path = root.join(&relative);
+      let content = if path.exists() {
+        fs::read_to_string(&path)?
+      } else {
+        String::new()
+      };
+      let updated = scaffold_provider(&project, &path, &content, name)?;
+
+      if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+      }
+
+      fs::write(&path, updated)?;
+      println!("added `{name}` to {}", relative.display());
+    }
+    Some(Commands::Check {
+      strict,
+      deny_deprecated,
+      ref package,
+      budget,
+      sample,
+      seed,
+      recursive,
+      ref stdin,
+      ref filter,
+    }) => {
+      let root = args.root();
+      let filter = filter.path_filter(&root, settings)?;
+      let scope = CheckScope {
+        budget,
+        sample: sample.map(|percent| {
+          Sample {
+            percent,
+            seed: seed.unwrap_or_else(time_seed),
+          }
+        }),
+      };
+      let check_project = |project: &Project, document, package| {
+        let mut options = project.config.check;
+        options.strict |= strict;
+        options.deny_deprecated |= deny_deprecated;
+
+        check(
+          project,
+          document,
+          package,
+          &filter,
+          options,
+          scope,
+          color.enabled(std::io::stdout().is_terminal()),
+        )
+      };
+
+      // The workspace is only checked as a whole, since `--stdin` and
+      // `--package` select part of a single project.
+      let failed = if stdin.stdin || package.is_some() {
+        let (project, document) = scan(&root, stdin, &filter, profile)?;
+        check_project(&project, document.as_ref(), package.as_deref())?
+      } else {
+        let mut failed = false;
+
+        for (member, project) in scan_workspace(&root, recursive, &filter, profile)? {
+          print_member(&member);
+          failed |= check_project(&project, None, None)?;
+        }
+
+        failed
+      };
+
+      if failed {
+        std::process::exit(1);
+      }
+    }
+    Some(Commands::Update {
+      ref stdin,
+      ref filter,
+    }) => {
+      let root = args.root();
+      let filter = filter.path_filter(&root, settings)?;
+
+      if stdin.stdin {
+        let (project, document) = scan(&root, stdin, &filter, profile)?;
+        let result = compute_updates(&project)?;
+
+        // The updated document is printed so messages go to stderr.
+        if let Some((path, content)) = document {
+          print!("{}", result.updated_files.get(&path).unwrap_or(&content));
+
+          for (_, name) in result.conflicts.iter().filter(|(file, _)| file == &path) {
+            eprintln!("conflict: `{name}` was edited by hand and wasn't updated");
+          }
+        }
+
+        return Ok(());
+      }
+
+      let mut conflicted = false;
+
+      for (member, project) in scan_workspace(&root, false, &filter, profile)? {
+        print_member(&member);
+        conflicted |= update(&project, &compute_updates(&project)?)?;
+      }
+
+      if conflicted {
+        std::process::exit(1);
+      }
+    }
+    Some(Commands::Sync { ref name, ref file }) => {
+      let root = args.root();
+      let project = scan_root(&root, profile)?;
+      let file = file.as_ref().map(|file| root.join(file));
+      let result = compute_block_updates(&project, name, file.as_deref())?;
+      write_updates(&result)?;
+
+      if result.is_empty() {
+        println!("`{name}` is up to date");
+      } else {
+        println!(
+          "updated {} block(s) in {} file(s)",
+          result.updated_count,
+          result.updated_files.len()
+        );
+      }
+
+      for (file, name) in &result.conflicts {
+        println!(
+          "conflict: `{name}` in {} was edited by hand and wasn't updated",
+          project.relative_path(file).display()
+        );
+      }
+
+      if !result.conflicts.is_empty() {
+        std::process::exit(1);
+      }
+    }
+    Some(Commands::Rename {
+      ref old,
+      ref new,
+      dry_run,
+    }) => {
+      let root = args.root();
+      let project = scan_root(&root, profile)?;
+      let patches = compute_rename(&project, old, new)?;
+      let result = apply_project_patches(&project, &patches);
+      let action = if dry_run { "would rename" } else { "renamed" };
+
+      for file in result.updated_files.keys() {
+        println!("{action}: {}", project.relative_path(file).display());
+      }
+
+      if !dry_run {
+        write_updates(&result)?;
+      }
+    }
+    Some(Commands::Rm {
+      ref name,
+      keep_content,
+      strip,
+    }) => {
+      let root = args.root();
+      let project = scan_root(&root, profile)?;
+      // The flags are in one group so at most one of them is set.
+      let consumers = if strip && !keep_content {
+        ConsumerRemoval::Strip
+      } else {
+        ConsumerRemoval::Keep
+      };
+      let removal = compute_removal(&project, name, consumers)?;
+      write_updates(&apply_project_patches(&project, &removal.patches))?;
+      let relative = |file: &PathBuf| project.relative_path(file).display().to_string();
+
+      if let Some(provider) = project.providers.get(name) {
+        println!("removed: `{name}` from {}", relative(&provider.file));
+      }
+
+      for (file, consumer) in &removal.stripped {
+        println!("stripped: `{consumer}` in {}", relative(file));
+      }
+
+      for (file, consumer) in &removal.orphans {
+        println!("orphan: `{consumer}` in {}", relative(file));
+      }
+    }
+    Some(Commands::Info { ref package }) => {
+      let project = scan_root(args.root(), profile)?;
+      let package = package_filter(&project, package.as_deref())?;
+      let usage = analyze_data_usage(&project);
+      let namespaces = project.data.keys().cloned().collect::<Vec<_>>();
+      let providers = project
+        .providers
+        .values()
+        .filter(|provider| in_package(&project, &provider.file, package))
+        .count();
+      let consumers = project
+        .consumers
+        .iter()
+        .filter(|consumer| in_package(&project, &consumer.file, package))
+        .collect::<Vec<_>>();
+
+      println!("providers: {providers}");
+      println!("consumers: {}", consumers.len());
+      println!(
+        "held: {}",
+        consumers
+          .iter()
+          .filter(|consumer| consumer.is_frozen())
+          .count()
+      );
+
+      for candidate in &project.packages {
+        if package.map_or(false, |package| package != candidate.name) {
+          continue;
+        }
+
+        let providers = project
+          .providers
+          .values()
+          .filter(|provider| in_package(&project, &provider.file, Some(&candidate.name)))
+          .count();
+        let consumers = project
+          .consumers
+          .iter()
+          .filter(|consumer| in_package(&project, &consumer.file, Some(&candidate.name)))
+          .count();
+
+        println!(
+          "package `{}`: {providers} provider(s), {consumers} consumer(s)",
+          candidate.name
+        );
+      }
+
+      println!("data namespaces: {}", namespaces.join(", "));
+
+      for namespace in usage.unused {
+        println!("unused data: `{namespace}` is never referenced");
+      }
+
+      for missing in usage.missing {
+        println!(
+          "missing data: `{}` is referenced by `{}` but doesn't exist",
+          missing.variable, missing.provider
+        );
+      }
+
+      for deprecation in &project.deprecations {
+        println!(
+          "deprecated: {deprecation} ({})",
+          project.relative_path(&deprecation.file).display()
+        );
+      }
+    }
+    Some(Commands::List {
+      ref tags,
+      ref namespace,
+      ref package,
+    }) => {
+      let project = scan_root(args.root(), profile)?;
+      let package = package_filter(&project, package.as_deref())?;
+      let mut providers = project
+        .providers
+        .values()
+        .filter(|provider| provider.has_tags(tags))
+        .filter(|provider| {
+          namespace.as_ref().map_or(true, |namespace| {
+            is_in_namespace(&provider.block.name, namespace)
+          })
+        })
+        .filter(|provider| in_package(&project, &provider.file, package))
+        .collect::<Vec<_>>();
+      providers.sort_by(|a, b| a.block.name.cmp(&b.block.name));
+
+      let lines = providers
+        .into_iter()
+        .map(|provider| {
+          let line = match provider.description.as_ref() {
+            Some(description) => format!("{}: {description}", provider.block.name),
+            None => provider.block.name.clone(),
+          };
+
+          (&provider.file, line)
+        })
+        .collect();
+
+      print_by_package(&project, lines);
+    }
+    Some(Commands::Render {
+      ref name,
+      ref transformers,
+      ref post_webhook,
+    }) => {
+      let project = scan_root(args.root(), profile)?;
+      let content = render_provider_with(&project, name, transformers)?;
+
+      match post_webhook {
+        Some(url) => {
+          post_to_webhook(url, &content)?;
+          println!("posted `{name}` to the webhook");
+        }
+        None => println!("{content}"),
+      }
+    }
+    Some(Commands::ReleaseNotes {
+      ref tag,
+      ref provider,
+      push,
+    }) => {
+      let project = scan_root(args.root(), profile)?;
+      let Some(name) = provider
+        .as_ref()
+        .or(project.config.release.provider.as_ref())
+      else {
+        return Err(
+          MdtError::InvalidConfig(
+            "set `provider` in the `[release]` section of mdt.toml or pass `--provider`".into(),
+          )
+          .into(),
+        );
+      };
+
+      let notes = render_release_notes(&project, name, tag)?;
+
+      if push {
+        push_release_notes(&args.root(), tag, &notes)?;
+        println!("published the release notes for {tag}");
+      } else {
+        println!("{notes}");
+      }
+    }
+    Some(Commands::Search {
+      ref query,
+      ref tags,
+      format,
+    }) => {
+      let project = scan_root(args.root(), profile)?;
+      let results = search_providers(&project, query, tags);
+
+      if format == OutputFormat::Json {
+        println!("{}", serde_json::to_string_pretty(&results)?);
+        return Ok(());
+      }
+
+      if results.is_empty() {
+        println!("no providers match `{query}`");
+      }
+
+      for result in results {
+        println!(
+          "{} ({}, {} consumer(s))",
+          result.name,
+          result.file.display(),
+          result.consumer_count
+        );
+
+        for snippet in result.snippets {
+          println!("  {}: {}", snippet.line, snippet.highlighted("**"));
+        }
+      }
+    }
+    Some(Commands::Attach {
+      ref name,
+      ref to,
+      ref after,
+    }) => {
+      let root = args.root();
+      let project = scan_root(&root, profile)?;
+      let rendered = render_provider_with(&project, name, "")?;
+      let anchor = after.clone().map_or(AttachAnchor::End, AttachAnchor::After);
+
+      for file in matching_project_files(&project, to)? {
+        let relative = file.strip_prefix(&root).unwrap_or(&file);
+        let content = fs::read_to_string(&file)?;
+
+        match attach_consumer(&file, &content, name, &rendered, &anchor, &project.config)? {
+          AttachOutcome::Attached(updated) => {
+            fs::write(&file, updated)?;
+            println!("attached: {}", relative.display());
+          }
+          AttachOutcome::AlreadyAttached => {
+            println!("skipped: {} already has a consumer", relative.display());
+          }
+          AttachOutcome::AnchorNotFound => {
+            println!(
+              "skipped: {} has no line starting with `{}`",
+              relative.display(),
+              after.as_deref().unwrap_or_default()
+            );
+          }
+        }
+      }
     }
-    Some(Commands::Update) => {
-      // Update the mdt code blocks
+    Some(Commands::Add {
+      ref name,
+      ref file,
+      line,
+      ref heading,
+      ref transformers,
+    }) => {
+      let root = args.root();
+      let project = scan_root(&root, profile)?;
+
+      if !project.providers.contains_key(name) {
+        return Err(MdtError::UnknownBlock(name.clone()).into());
+      }
+
+      let path = root.join(file);
+      let content = fs::read_to_string(&path)?;
+      let anchor = match (line, heading) {
+        (Some(line), _) => AttachAnchor::Line(line),
+        (None, Some(heading)) => AttachAnchor::Heading(heading.clone()),
+        (None, None) => AttachAnchor::End,
+      };
+      let chain = transformers
+        .as_deref()
+        .unwrap_or_else(|| consumer_style(&path).1);
+
+      match insert_consumer(&path, &content, name, chain, &anchor, &project.config)? {
+        AttachOutcome::Attached(updated) => {
+          fs::write(&path, updated)?;
+        }
+        AttachOutcome::AlreadyAttached => {
+          println!("skipped: {} already has a consumer", file.display());
+          return Ok(());
+        }
+        AttachOutcome::AnchorNotFound => {
+          let missing = match line {
+            Some(line) => format!("line {line}"),
+            None => format!("heading `{}`", heading.as_deref().unwrap_or_default()),
+          };
+          println!("skipped: {} has no {missing}", file.display());
+          std::process::exit(1);
+        }
+      }
+
+      let project = scan_root(&root, profile)?;
+      write_updates(&compute_block_updates(&project, name, Some(&path))?)?;
+      println!("added `{name}` to {}", file.display());
+    }
+    Some(Commands::AdoptCheck {
+      ref name,
+      ref files,
+      tolerance,
+    }) => {
+      let root = args.root();
+      let project = scan_root(&root, profile)?;
+      let rendered = render_provider_with(&project, name, "")?;
+      let mut failed = false;
+
+      for file in matching_project_files(&project, files)? {
+        let relative = file.strip_prefix(&root).unwrap_or(&file);
+        let content = fs::read_to_string(&file)?;
+
+        if has_consumer(&file, &content, name, &project.config)? {
+          println!("skipped: {} already has a consumer", relative.display());
+          continue;
+        }
+
+        let Some(report) = compare_for_adoption(&content, &rendered) else {
+          failed = true;
+          println!(
+            "missing: {} has no text matching `{name}`",
+            relative.display()
+          );
+          continue;
+        };
+
+        let status = if report.is_within(tolerance) {
+          "matched"
+        } else {
+          failed = true;
+          "differs"
+        };
+
+        println!(
+          "{status}: {}:{}-{} ({:.0}% similar)",
+          relative.display(),
+          report.lines.start,
+          report.lines.end - 1,
+          report.similarity * 100.0
+        );
+
+        for difference in &report.differences {
+          match difference {
+            LineDifference::Missing(line) => println!("  - {line}"),
+            LineDifference::Extra(line) => println!("  + {line}"),
+          }
+        }
+      }
+
+      if failed {
+        std::process::exit(1);
+      }
+    }
+    Some(Commands::Config {
+      command: ConfigCommands::Show { resolved },
+    }) => {
+      if resolved {
+        for value in MdtConfig::resolve(args.root(), profile)? {
+          println!("{} = {}  # {}", value.key, value.value, value.origin);
+        }
+
+        return Ok(());
+      }
+
+      let path = args.root().join(CONFIG_FILE_NAME);
+
+      if path.is_file() {
+        print!("{}", fs::read_to_string(path)?);
+      } else {
+        println!("no {CONFIG_FILE_NAME} found");
+      }
+    }
+    Some(Commands::Docs {
+      command: DocsCommands::Sync { ref output },
+    }) => {
+      let project = scan_root(args.root(), profile)?;
+      let index = output
+        .clone()
+        .or_else(|| project.config.docs.index.clone())
+        .unwrap_or_else(|| PathBuf::from(DEFAULT_INDEX_FILE));
+      let path = args.root().join(&index);
+      let content = render_provider_index(&project, &path)?;
+
+      if fs::read_to_string(&path).ok().as_deref() == Some(content.as_str()) {
+        println!("the provider index is up to date");
+      } else {
+        fs::write(&path, content)?;
+        println!("wrote the provider index to {}", index.display());
+      }
     }
     None => {
       println!("No subcommand specified");
     }
   }
+
+  Ok(())
+}
+
+/// Check the project with the options and print what is stale, with colored
+/// labels when `color` is set. Only the problems within the document, package
+/// and filter are reported. Returns whether the check failed.
+fn check(
+  project: &Project,
+  document: Option<&(PathBuf, String)>,
+  package: Option<&str>,
+  filter: &PathFilter,
+  options: CheckOptions,
+  scope: CheckScope,
+  color: bool,
+) -> AnyResult<bool> {
+  let package = package_filter(project, package)?;
+
+  let mut result = check_project_scoped(project, options, scope)?;
+  let included = |file: &Path| {
+    document.map_or(true, |(path, _)| file == path)
+      && in_package(project, file, package)
+      && filter.matches(file)
+  };
+
+  result.stale.retain(|(file, _)| included(file));
+  result.held.retain(|(file, _)| included(file));
+  result
+    .diagnostics
+    .retain(|diagnostic| included(&diagnostic.file));
+
+  let mut lines = vec![];
+
+  let label = |text: &str, code| paint(text, code, color);
+
+  for (file, name) in &result.stale {
+    lines.push((
+      file,
+      format!(
+        "{}: `{name}` in {}",
+        label("stale", YELLOW),
+        project.relative_path(file).display()
+      ),
+    ));
+  }
+
+  for (file, name) in &result.held {
+    lines.push((
+      file,
+      format!(
+        "{}: `{name}` in {}",
+        label("held", CYAN),
+        project.relative_path(file).display()
+      ),
+    ));
+  }
+
+  for diagnostic in &result.diagnostics {
+    let severity = match diagnostic.severity {
+      Severity::Error => label("error", RED),
+      Severity::Warning => label("warning", YELLOW),
+    };
+
+    lines.push((
+      &diagnostic.file,
+      format!(
+        "{severity}: {} ({})",
+        diagnostic.message,
+        project.relative_path(&diagnostic.file).display()
+      ),
+    ));
+  }
+
+  print_by_package(project, lines);
+
+  if let Some(coverage) = &result.coverage {
+    println!(
+      "checked {} file(s) within the budget, skipped {}",
+      coverage.checked,
+      coverage.skipped.len()
+    );
+
+    for file in &coverage.skipped {
+      println!("skipped: {}", project.relative_path(file).display());
+    }
+  }
+
+  if let Some(report) = &result.sample {
+    println!(
+      "sampled {} of {} consumer(s) with seed {}, rerun with `--seed {}` to reproduce",
+      report.sampled.len(),
+      report.total,
+      report.seed,
+      report.seed
+    );
+
+    for (file, name) in &report.sampled {
+      println!(
+        "sampled: `{name}` in {}",
+        project.relative_path(file).display()
+      );
+    }
+  }
+
+  if result.is_failure() {
+    return Ok(true);
+  }
+
+  println!("all blocks are up to date");
+
+  Ok(false)
+}
+
+/// Write the updates of the project and print what changed. Returns whether
+/// any consumer has a conflict.
+fn update(project: &Project, result: &UpdateResult) -> AnyResult<bool> {
+  write_updates(result)?;
+
+  if project.config.assets.copy {
+    let copies = compute_asset_copies(project);
+    copy_assets(&copies)?;
+
+    for copy in &copies {
+      println!("copied {}", project.relative_path(&copy.to).display());
+    }
+  }
+
+  if result.is_empty() {
+    println!("all blocks are up to date");
+  } else {
+    println!(
+      "updated {} block(s) in {} file(s)",
+      result.updated_count,
+      result.updated_files.len()
+    );
+  }
+
+  for (file, name) in &result.conflicts {
+    println!(
+      "conflict: `{name}` in {} was edited by hand and wasn't updated",
+      project.relative_path(file).display()
+    );
+  }
+
+  Ok(!result.conflicts.is_empty())
+}
+
+/// Print the heading for the output of a workspace member. The root has an
+/// empty path and no heading.
+fn print_member(member: &Path) {
+  if !member.as_os_str().is_empty() {
+    println!("project: {}", member.display());
+  }
+}
+
+/// Scan every file of the project with the profile applied.
+fn scan_root(root: impl AsRef<Path>, profile: Option<&str>) -> AnyResult<Project> {
+  Ok(scan_project_filtered(
+    root,
+    &BTreeMap::new(),
+    &PathFilter::default(),
+    profile,
+  )?)
+}
+
+/// Scan the project, skipping the files which don't pass the filter. With
+/// `--stdin` the document read from stdin is used in place of the file at
+/// `--stdin-path` and is returned along with its path.
+fn scan(
+  root: &Path,
+  stdin: &StdinArgs,
+  filter: &PathFilter,
+  profile: Option<&str>,
+) -> AnyResult<(Project, Option<(PathBuf, String)>)> {
+  let Some(path) = stdin.path(root) else {
+    return Ok((
+      scan_project_filtered(root, &BTreeMap::new(), filter, profile)?,
+      None,
+    ));
+  };
+
+  let mut content = String::new();
+  std::io::stdin().read_to_string(&mut content)?;
+
+  let overlays = BTreeMap::from([(path.clone(), content.clone())]);
+  let project = scan_project_filtered(root, &overlays, filter, profile)?;
+
+  Ok((project, Some((path, content))))
+}
+
+/// Check that the package exists when one is given.
+fn package_filter<'a>(project: &Project, package: Option<&'a str>) -> AnyResult<Option<&'a str>> {
+  if let Some(name) = package {
+    if !project.packages.iter().any(|package| package.name == name) {
+      return Err(MdtError::UnknownPackage(name.to_string()).into());
+    }
+  }
+
+  Ok(package)
+}
+
+/// Whether the file is within the package, when there is one.
+fn in_package(project: &Project, file: &Path, package: Option<&str>) -> bool {
+  package.map_or(true, |package| project.package_name(file) == Some(package))
+}
+
+/// Print the lines in order. When the project has packages the lines are
+/// grouped under a heading for their package, after the lines for files
+/// outside of every package.
+fn print_by_package(project: &Project, mut lines: Vec<(&PathBuf, String)>) {
+  if project.packages.is_empty() {
+    for (_, line) in lines {
+      println!("{line}");
+    }
+
+    return;
+  }
+
+  lines.sort_by_key(|(file, _)| project.package_name(file));
+  let mut current = None;
+
+  for (file, line) in lines {
+    let package = project.package_name(file);
+
+    if package.is_some() && package != current {
+      println!("package: {}", package.unwrap_or_default());
+    }
+
+    current = package;
+    println!("{line}");
+  }
+}
+
+/// The scanned files of the project which match the glob, relative to the
+/// root, in sorted order. Files skipped by the scan, e.g. those ignored by a
+/// `.mdtignore` or within a nested project, never match.
+fn matching_project_files(project: &Project, glob: &str) -> AnyResult<Vec<PathBuf>> {
+  let glob = GlobBuilder::new(glob)
+    .literal_separator(true)
+    .build()?
+    .compile_matcher();
+  let mut files = scanned_files(&project.root, &project.config)?;
+  files.retain(|file| glob.is_match(project.relative_path(file)));
+
+  Ok(files)
+}
+
+/// Publish the notes with the GitHub cli, creating the release when it
+/// doesn't exist yet.
+fn push_release_notes(root: &Path, tag: &str, notes: &str) -> AnyEmptyResult {
+  let exists = Command::new("gh")
+    .args(["release", "view", tag])
+    .current_dir(root)
+    .stdout(Stdio::null())
+    .stderr(Stdio::null())
+    .status()?
+    .success();
+
+  let mut command = Command::new("gh");
+  command.current_dir(root);
+
+  if exists {
+    command.args(["release", "edit", tag, "--notes-file", "-"]);
+  } else {
+    command.args([
+      "release",
+      "create",
+      tag,
+      "--title",
+      tag,
+      "--notes-file",
+      "-",
+    ]);
+  }
+
+  run_with_input(&mut command, notes)?;
+
+  Ok(())
+}
+
+/// Post the text to a webhook with `curl`, using the `{ "text": ... }` payload
+/// that Slack and most chat services accept.
+fn post_to_webhook(url: &str, text: &str) -> AnyEmptyResult {
+  let payload = serde_json::json!({ "text": text }).to_string();
+  run_with_input(
+    Command::new("curl").args([
+      "--silent",
+      "--show-error",
+      "--fail",
+      "--request",
+      "POST",
+      "--header",
+      "Content-Type: application/json",
+      "--data-binary",
+      "@-",
+      url,
+    ]),
+    &payload,
+  )?;
+
+  Ok(())
+}
+
+/// A seed for sampling when none is given, from the current time.
+fn time_seed() -> u64 {
+  SystemTime::now()
+    .duration_since(UNIX_EPOCH)
+    .map_or(0, |elapsed| elapsed.as_nanos() as u64)
 }
@@ -0,0 +1,133 @@
+//! Acceptance tests which run the cli against the projects in
+//! `tests/fixtures` and snapshot everything a user sees: the output, the exit
+//! code and the files which change. Review snapshot changes with
+//! `cargo insta review`.
+
+use std::fs;
+use std::path::Path;
+
+use assert_cmd::Command;
+use mdt::AnyEmptyResult;
+use mdt::AnyResult;
+use tempfile::TempDir;
+
+#[test]
+fn init() -> AnyEmptyResult {
+  let directory = tempfile::tempdir()?;
+  insta::assert_snapshot!(transcript(directory.path(), &[&["init"]])?);
+
+  Ok(())
+}
+
+#[test]
+fn check_and_update() -> AnyEmptyResult {
+  let directory = fixture("basic")?;
+  let root = directory.path();
+  let output = transcript(
+    root,
+    &[&["check"], &["check", "--strict"], &["update"], &["check"]],
+  )?;
+  insta::assert_snapshot!(output);
+  insta::assert_snapshot!(
+    "check_and_update_readme",
+    fs::read_to_string(root.join("readme.md"))?
+  );
+  insta::assert_snapshot!(
+    "check_and_update_guide",
+    fs::read_to_string(root.join("docs/guide.md"))?
+  );
+
+  Ok(())
+}
+
+#[test]
+fn list_and_info() -> AnyEmptyResult {
+  let directory = fixture("basic")?;
+  let output = transcript(
+    directory.path(),
+    &[
+      &["list"],
+      &["info"],
+      &["search", "cargo"],
+      &["search", "cargo", "--format", "json"],
+      &["config", "show"],
+    ],
+  )?;
+  insta::assert_snapshot!(output);
+
+  Ok(())
+}
+
+#[test]
+fn invalid_projects() -> AnyEmptyResult {
+  let directory = fixture("duplicate")?;
+  let output = transcript(
+    directory.path(),
+    &[&["check"], &["update"], &["check", "--sample", "200%"]],
+  )?;
+  insta::assert_snapshot!(output);
+
+  Ok(())
+}
+
+/// Copy the fixture to a temporary directory so commands can change it.
+fn fixture(name: &str) -> AnyResult<TempDir> {
+  let directory = tempfile::tempdir()?;
+  let source = Path::new(env!("CARGO_MANIFEST_DIR"))
+    .join("tests/fixtures")
+    .join(name);
+  copy_directory(&source, directory.path())?;
+
+  Ok(directory)
+}
+
+fn copy_directory(source: &Path, target: &Path) -> AnyEmptyResult {
+  fs::create_dir_all(target)?;
+
+  for entry in fs::read_dir(source)? {
+    let entry = entry?;
+    let path = entry.path();
+    let target = target.join(entry.file_name());
+
+    if entry.file_type()?.is_dir() {
+      copy_directory(&path, &target)?;
+    } else {
+      fs::copy(&path, &target)?;
+    }
+  }
+
+  Ok(())
+}
+
+/// Run each command from the root and record the command, its exit code and
+/// its output.
+fn transcript(root: &Path, commands: &[&[&str]]) -> AnyResult<String> {
+  let mut transcript = String::new();
+
+  for args in commands {
+    let output = Command::cargo_bin("mdt")?
+      .args(*args)
+      .current_dir(root)
+      .env("NO_COLOR", "1")
+      .output()?;
+    let code = output
+      .status
+      .code()
+      .map_or_else(|| "signal".to_string(), |code| code.to_string());
+
+    transcript.push_str(&format!("$ mdt {}\nexit: {code}\n", args.join(" ")));
+
+    for (name, stream) in [("stdout", &output.stdout), ("stderr", &output.stderr)] {
+      if !stream.is_empty() {
+        transcript.push_str(&format!(
+          "--- {name}\n{}\n",
+          String::from_utf8_lossy(stream).trim_end()
+        ));
+      }
+    }
+
+    transcript.push('\n');
+  }
+
+  Ok(transcript)
+}
@@ -0,0 +1,83 @@
+use std::fs;
+
+use assert_cmd::Command;
+use mdt::AnyEmptyResult;
+
+#[test]
+fn can_add_a_consumer() -> AnyEmptyResult {
+  let directory = tempfile::tempdir()?;
+  let root = directory.path();
+  fs::write(
+    root.join("template.t.md"),
+    "<!-- {@install} -->\n\nnpm i mdt\n\n<!-- {/install} -->\n",
+  )?;
+  fs::write(
+    root.join("readme.md"),
+    "# mdt\n\n## Installation\n\n## Usage\n",
+  )?;
+  fs::write(root.join("lib.rs"), "pub mod cli;\n\npub fn install() {}\n")?;
+
+  let mut cmd = Command::cargo_bin("mdt")?;
+  cmd
+    .args([
+      "add",
+      "install",
+      "readme.md",
+      "--heading",
+      "Installation",
+      "--path",
+    ])
+    .arg(root)
+    .assert()
+    .success()
+    .stdout("added `install` to readme.md\n");
+
+  similar_asserts::assert_eq!(
+    fs::read_to_string(root.join("readme.md"))?,
+    "# mdt\n\n## Installation\n\n<!-- {=install} -->\n\nnpm i mdt\n\n<!-- {/install} -->\n\n## \
+     Usage\n"
+  );
+
+  let mut cmd = Command::cargo_bin("mdt")?;
+  cmd
+    .args(["add", "install", "lib.rs", "--line", "3", "--path"])
+    .arg(root)
+    .assert()
+    .success()
+    .stdout("added `install` to lib.rs\n");
+
+  similar_asserts::assert_eq!(
+    fs::read_to_string(root.join("lib.rs"))?,
+    "pub mod cli;\n\n/// <!-- {=install|trim|replace:\"\\n\":\"\\n/// \"|wrap:\"\\n/// \"} \
+     -->\n/// npm i mdt\n/// <!-- {/install} -->\npub fn install() {}\n"
+  );
+
+  let mut cmd = Command::cargo_bin("mdt")?;
+  cmd.args(["check", "--path"]).arg(root).assert().success();
+
+  let mut cmd = Command::cargo_bin("mdt")?;
+  cmd
+    .args(["add", "install", "readme.md", "--path"])
+    .arg(root)
+    .assert()
+    .success()
+    .stdout("skipped: readme.md already has a consumer\n");
+
+  fs::write(root.join("other.md"), "# Other\n")?;
+  let mut cmd = Command::cargo_bin("mdt")?;
+  cmd
+    .args([
+      "add",
+      "install",
+      "other.md",
+      "--heading",
+      "Missing",
+      "--path",
+    ])
+    .arg(root)
+    .assert()
+    .failure()
+    .stdout("skipped: other.md has no heading `Missing`\n");
+
+  Ok(())
+}
@@ -0,0 +1,85 @@
+use std::fs;
+
+use assert_cmd::Command;
+use mdt::AnyEmptyResult;
+
+#[test]
+fn reports_differences_before_adoption() -> AnyEmptyResult {
+  let directory = tempfile::tempdir()?;
+  let root = directory.path();
+  fs::create_dir_all(root.join("crates/a"))?;
+  fs::create_dir_all(root.join("crates/b"))?;
+  fs::write(
+    root.join("template.t.md"),
+    "<!-- {@install} -->\n```sh\ncargo install mdt_cli\n```\n<!-- {/install} -->\n",
+  )?;
+  fs::write(
+    root.join("crates/a/readme.md"),
+    "# a\n\n```sh\ncargo install mdt_cli\n```\n\nAbout a.\n",
+  )?;
+  fs::write(
+    root.join("crates/b/readme.md"),
+    "# b\n\n```sh\ncargo install mdt\n```\n",
+  )?;
+
+  let mut cmd = Command::cargo_bin("mdt")?;
+  let assert = cmd
+    .args(["adopt-check", "install", "--in", "crates/*/readme.md"])
+    .arg("--path")
+    .arg(root)
+    .assert()
+    .failure();
+  assert.stdout(
+    "matched: crates/a/readme.md:3-5 (100% similar)\ndiffers: crates/b/readme.md:3-5 (67% \
+     similar)\n  - cargo install mdt_cli\n  + cargo install mdt\n",
+  );
+
+  let mut cmd = Command::cargo_bin("mdt")?;
+  cmd
+    .args([
+      "adopt-check",
+      "install",
+      "--in",
+      "crates/*/readme.md",
+      "--tolerance",
+      "0.4",
+    ])
+    .arg("--path")
+    .arg(root)
+    .assert()
+    .success();
+
+  Ok(())
+}
+
+#[test]
+fn adopt_check_skips_files_which_are_not_scanned() -> AnyEmptyResult {
+  let directory = tempfile::tempdir()?;
+  let root = directory.path();
+  fs::create_dir_all(root.join("crates/a"))?;
+  fs::create_dir_all(root.join("crates/b"))?;
+  fs::write(
+    root.join("template.t.md"),
+    "<!-- {@install} -->\n```sh\ncargo install mdt_cli\n```\n<!-- {/install} -->\n",
+  )?;
+  fs::write(
+    root.join("mdt.toml"),
+    "[include]\npatterns = [\"*.t.md\", \"crates/a/**\"]\n",
+  )?;
+  fs::write(
+    root.join("crates/a/readme.md"),
+    "# a\n\n```sh\ncargo install mdt_cli\n```\n",
+  )?;
+  fs::write(root.join("crates/b/readme.md"), "# b\n")?;
+
+  let mut cmd = Command::cargo_bin("mdt")?;
+  let assert = cmd
+    .args(["adopt-check", "install", "--in", "crates/*/readme.md"])
+    .arg("--path")
+    .arg(root)
+    .assert()
+    .success();
+  assert.stdout("matched: crates/a/readme.md:3-5 (100% similar)\n");
+
+  Ok(())
+}
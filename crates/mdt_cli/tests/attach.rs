@@ -0,0 +1,93 @@
+use std::fs;
+
+use assert_cmd::Command;
+use mdt::AnyEmptyResult;
+
+#[test]
+fn can_attach_consumers() -> AnyEmptyResult {
+  let directory = tempfile::tempdir()?;
+  let root = directory.path();
+  fs::create_dir_all(root.join("crates/a"))?;
+  fs::create_dir_all(root.join("crates/b"))?;
+  fs::create_dir_all(root.join("crates/c"))?;
+  fs::write(
+    root.join("template.t.md"),
+    "<!-- {@license} -->\nUnlicense\n<!-- {/license} -->\n",
+  )?;
+  fs::write(root.join("crates/a/readme.md"), "# a\n\nAbout a.\n")?;
+  fs::write(
+    root.join("crates/b/readme.md"),
+    "# b\n<!-- {=license} -->\n<!-- {/license} -->\n",
+  )?;
+  fs::write(root.join("crates/c/readme.md"), "No heading.\n")?;
+
+  let mut cmd = Command::cargo_bin("mdt")?;
+  let assert = cmd
+    .args([
+      "attach",
+      "license",
+      "--to",
+      "crates/*/readme.md",
+      "--after",
+      "# ",
+    ])
+    .arg("--path")
+    .arg(root)
+    .assert()
+    .success();
+  assert.stdout(
+    "attached: crates/a/readme.md\nskipped: crates/b/readme.md already has a consumer\nskipped: \
+     crates/c/readme.md has no line starting with `# `\n",
+  );
+
+  similar_asserts::assert_eq!(
+    fs::read_to_string(root.join("crates/a/readme.md"))?,
+    "# a\n\n<!-- {=license} -->\nUnlicense\n<!-- {/license} -->\n\nAbout a.\n"
+  );
+  similar_asserts::assert_eq!(
+    fs::read_to_string(root.join("crates/c/readme.md"))?,
+    "No heading.\n"
+  );
+
+  Ok(())
+}
+
+#[test]
+fn attach_skips_files_which_are_not_scanned() -> AnyEmptyResult {
+  let directory = tempfile::tempdir()?;
+  let root = directory.path();
+  fs::create_dir_all(root.join("crates/a"))?;
+  fs::create_dir_all(root.join("crates/b"))?;
+  fs::create_dir_all(root.join("crates/c"))?;
+  fs::write(
+    root.join("template.t.md"),
+    "<!-- {@license} -->\nUnlicense\n<!-- {/license} -->\n",
+  )?;
+  fs::write(root.join(".mdtignore"), "crates/b/\n")?;
+  fs::write(root.join("crates/c/mdt.toml"), "")?;
+
+  for name in ["a", "b", "c"] {
+    fs::write(
+      root.join(format!("crates/{name}/readme.md")),
+      format!("# {name}\n"),
+    )?;
+  }
+
+  let mut cmd = Command::cargo_bin("mdt")?;
+  let assert = cmd
+    .args(["attach", "license", "--to", "crates/*/readme.md"])
+    .arg("--path")
+    .arg(root)
+    .assert()
+    .success();
+  assert.stdout("attached: crates/a/readme.md\n");
+
+  for name in ["b", "c"] {
+    similar_asserts::assert_eq!(
+      fs::read_to_string(root.join(format!("crates/{name}/readme.md")))?,
+      format!("# {name}\n")
+    );
+  }
+
+  Ok(())
+}
@@ -0,0 +1,270 @@
+use std::fs;
+
+use assert_cmd::Command;
+use mdt::AnyEmptyResult;
+use mdt::AnyResult;
+
+#[test]
+fn can_check() -> AnyEmptyResult {
+  let directory = tempfile::tempdir()?;
+  let root = directory.path();
+  fs::write(
+    root.join("template.t.md"),
+    "<!-- {@greeting} -->\nHello, see [docs](./docs.md)\n<!-- {/greeting} -->\n<!-- {@unused} \
+     -->\n<!-- {/unused} -->\n",
+  )?;
+  fs::write(
+    root.join("readme.md"),
+    "<!-- {=greeting} -->\nHello, see [docs](./docs.md)   \n\n<!-- {/greeting} -->\n",
+  )?;
+
+  let mut cmd = Command::cargo_bin("mdt")?;
+  let assert = cmd.arg("check").arg("--path").arg(root).assert().success();
+  assert.stdout(
+    "warning: provider `unused` has no consumers (template.t.md)\nall blocks are up to date\n",
+  );
+
+  let mut cmd = Command::cargo_bin("mdt")?;
+  let assert = cmd
+    .args(["check", "--strict", "--path"])
+    .arg(root)
+    .assert()
+    .failure();
+  assert.stdout(
+    "stale: `greeting` in readme.md\nerror: consumer `greeting` links to `./docs.md` which \
+     doesn't exist (readme.md)\nerror: provider `unused` has no consumers (template.t.md)\n",
+  );
+
+  Ok(())
+}
+
+#[test]
+fn frozen_consumers_are_held() -> AnyEmptyResult {
+  let directory = tempfile::tempdir()?;
+  let root = directory.path();
+  let readme = "<!-- {=greeting|frozen} -->\nHello, world!\n<!-- {/greeting} -->\n";
+  fs::write(
+    root.join("template.t.md"),
+    "<!-- {@greeting} -->\nHello\n<!-- {/greeting} -->\n",
+  )?;
+  fs::write(root.join("readme.md"), readme)?;
+
+  let mut cmd = Command::cargo_bin("mdt")?;
+  let assert = cmd.arg("check").arg("--path").arg(root).assert().success();
+  assert.stdout("held: `greeting` in readme.md\nall blocks are up to date\n");
+
+  let mut cmd = Command::cargo_bin("mdt")?;
+  cmd.arg("update").arg("--path").arg(root).assert().success();
+  similar_asserts::assert_eq!(fs::read_to_string(root.join("readme.md"))?, readme);
+
+  let mut cmd = Command::cargo_bin("mdt")?;
+  let assert = cmd.arg("info").arg("--path").arg(root).assert().success();
+  assert.stdout("providers: 1\nconsumers: 1\nheld: 1\ndata namespaces: \n");
+
+  Ok(())
+}
+
+#[test]
+fn can_check_within_a_budget() -> AnyEmptyResult {
+  let directory = tempfile::tempdir()?;
+  let root = directory.path();
+  let git = |args: &[&str]| {
+    std::process::Command::new("git")
+      .args(args)
+      .current_dir(root)
+      .output()
+  };
+  fs::write(
+    root.join("template.t.md"),
+    "<!-- {@greeting} -->\nHello\n<!-- {/greeting} -->\n",
+  )?;
+
+  for readme in ["a.md", "b.md", "c.md"] {
+    fs::write(
+      root.join(readme),
+      "<!-- {=greeting} -->\nHello\n<!-- {/greeting} -->\n",
+    )?;
+  }
+
+  git(&["init", "--quiet"])?;
+  git(&["add", "."])?;
+  git(&[
+    "-c",
+    "user.name=mdt",
+    "-c",
+    "user.email=mdt@example.com",
+    "commit",
+    "--quiet",
+    "-m",
+    "initial",
+  ])?;
+  fs::write(
+    root.join("b.md"),
+    "<!-- {=greeting} -->\nStale\n<!-- {/greeting} -->\n",
+  )?;
+
+  let mut cmd = Command::cargo_bin("mdt")?;
+  let assert = cmd
+    .args(["check", "--budget", "0ms", "--path"])
+    .arg(root)
+    .assert()
+    .failure();
+  assert.stdout(
+    "stale: `greeting` in b.md\nchecked 1 file(s) within the budget, skipped 2\nskipped: \
+     a.md\nskipped: c.md\n",
+  );
+
+  let mut cmd = Command::cargo_bin("mdt")?;
+  let output = cmd
+    .args(["check", "--budget", "2h", "--path"])
+    .arg(root)
+    .assert()
+    .failure()
+    .get_output()
+    .clone();
+  assert!(String::from_utf8(output.stderr)?.contains("needs a unit"));
+
+  Ok(())
+}
+
+#[test]
+fn can_check_a_seeded_sample() -> AnyEmptyResult {
+  let directory = tempfile::tempdir()?;
+  let root = directory.path();
+  fs::write(
+    root.join("template.t.md"),
+    "<!-- {@greeting} -->\nHello\n<!-- {/greeting} -->\n",
+  )?;
+
+  for readme in ["a.md", "b.md", "c.md", "d.md"] {
+    fs::write(
+      root.join(readme),
+      "<!-- {=greeting} -->\nHello\n<!-- {/greeting} -->\n",
+    )?;
+  }
+
+  let sample = |seed: &str| -> AnyResult<String> {
+    let output = Command::cargo_bin("mdt")?
+      .args(["check", "--sample", "50%", "--seed", seed, "--path"])
+      .arg(root)
+      .assert()
+      .success()
+      .get_output()
+      .clone();
+
+    Ok(String::from_utf8(output.stdout)?)
+  };
+
+  let stdout = sample("42")?;
+  assert!(stdout
+    .starts_with("sampled 2 of 4 consumer(s) with seed 42, rerun with `--seed 42` to reproduce\n"));
+  assert_eq!(stdout.matches("sampled: `greeting` in ").count(), 2);
+  assert_eq!(sample("42")?, stdout);
+
+  let mut cmd = Command::cargo_bin("mdt")?;
+  let output = cmd
+    .args(["check", "--sample", "150%", "--path"])
+    .arg(root)
+    .assert()
+    .failure()
+    .get_output()
+    .clone();
+  assert!(String::from_utf8(output.stderr)?.contains("between 0% and 100%"));
+
+  Ok(())
+}
+
+#[test]
+fn can_deny_deprecated_features() -> AnyEmptyResult {
+  let directory = tempfile::tempdir()?;
+  let root = directory.path();
+  fs::write(root.join("mdt.toml"), "[chains]\nrustdoc = \"trim\"\n")?;
+  let message = "the `[chains]` table is deprecated since 0.1.0 and will be removed in 1.0.0, \
+                 rename it to `[transformer_aliases]` (mdt.toml)";
+
+  let mut cmd = Command::cargo_bin("mdt")?;
+  cmd
+    .args(["check", "--path"])
+    .arg(root)
+    .assert()
+    .success()
+    .stdout(format!("warning: {message}\nall blocks are up to date\n"));
+
+  let mut cmd = Command::cargo_bin("mdt")?;
+  cmd
+    .args(["check", "--deny-deprecated", "--path"])
+    .arg(root)
+    .assert()
+    .failure()
+    .stdout(format!("error: {message}\n"));
+
+  let mut cmd = Command::cargo_bin("mdt")?;
+  let output = cmd
+    .args(["info", "--path"])
+    .arg(root)
+    .assert()
+    .success()
+    .get_output()
+    .clone();
+  assert!(String::from_utf8(output.stdout)?.contains(
+    "deprecated: the `[chains]` table is deprecated since 0.1.0 and will be removed in 1.0.0, \
+     rename it to `[transformer_aliases]` (mdt.toml)\n"
+  ));
+
+  Ok(())
+}
+
+#[test]
+fn can_check_workspace_members() -> AnyEmptyResult {
+  let directory = tempfile::tempdir()?;
+  let root = directory.path();
+  let provider = "<!-- {@greeting} -->\nHello\n<!-- {/greeting} -->\n";
+  fs::write(
+    root.join("mdt.toml"),
+    "[workspace]\nmembers = [\"packages/*\"]\n",
+  )?;
+  fs::write(root.join("template.t.md"), provider)?;
+  fs::write(
+    root.join("readme.md"),
+    "<!-- {=greeting} -->\nHello\n<!-- {/greeting} -->\n",
+  )?;
+
+  for member in ["packages/a", "packages/b", "tools/c"] {
+    fs::create_dir_all(root.join(member))?;
+    fs::write(root.join(member).join("mdt.toml"), "")?;
+    fs::write(root.join(member).join("template.t.md"), provider)?;
+    fs::write(
+      root.join(member).join("readme.md"),
+      "<!-- {=greeting} -->\n<!-- {/greeting} -->\n",
+    )?;
+  }
+
+  fs::write(
+    root.join("packages/a/readme.md"),
+    "<!-- {=greeting} -->\nHello\n<!-- {/greeting} -->\n",
+  )?;
+
+  let mut cmd = Command::cargo_bin("mdt")?;
+  cmd
+    .args(["check", "--path"])
+    .arg(root)
+    .assert()
+    .failure()
+    .stdout(
+      "all blocks are up to date\nproject: packages/a\nall blocks are up to date\nproject: \
+       packages/b\nstale: `greeting` in readme.md\n",
+    );
+
+  let mut cmd = Command::cargo_bin("mdt")?;
+  let output = cmd
+    .args(["check", "--recursive", "--path"])
+    .arg(root)
+    .assert()
+    .failure()
+    .get_output()
+    .clone();
+  let stdout = String::from_utf8(output.stdout)?;
+  assert!(stdout.contains("project: tools/c\nstale: `greeting` in readme.md\n"));
+
+  Ok(())
+}
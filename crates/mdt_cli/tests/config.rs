@@ -0,0 +1,172 @@
+use std::fs;
+
+use assert_cmd::Command;
+use mdt::AnyEmptyResult;
+
+#[test]
+fn can_show_resolved_config() -> AnyEmptyResult {
+  let directory = tempfile::tempdir()?;
+  let root = directory.path();
+  fs::write(
+    root.join("mdt.toml"),
+    "[check]\nstrict = true\n\n[chains]\nrustdoc = 'trim|indent:\"//! \"'\n",
+  )?;
+
+  let mut cmd = Command::cargo_bin("mdt")?;
+  let assert = cmd
+    .arg("config")
+    .arg("show")
+    .arg("--resolved")
+    .arg("--path")
+    .arg(root)
+    .assert()
+    .success();
+  assert.stdout(
+    "assets.copy = false  # default\ncheck.alt_text = false  # default\ncheck.check_links = true  \
+     # derived\ncheck.deny_deprecated = false  # default\ncheck.orphan_consumers = true  # \
+     derived\ncheck.strict = true  # mdt.toml\ncheck.strict_whitespace = true  # \
+     derived\ncheck.undefined_variables = true  # derived\ncheck.unused_providers = true  # \
+     derived\ninclude.patterns = []  # default\nnaming.reserved_prefixes = []  # \
+     default\nscan.extra_extensions = []  # default\ntransformer_aliases.rustdoc = \
+     \"trim|indent:\\\"//! \\\"\"  # mdt.toml\ntransformers.deny = []  # \
+     default\nupdate.content_hash = false  # default\nworkspace.members = []  # \
+     default\nworkspace.scope = \"workspace\"  # default\n",
+  );
+
+  Ok(())
+}
+
+#[test]
+fn user_config_applies_below_the_project() -> AnyEmptyResult {
+  let directory = tempfile::tempdir()?;
+  let root = directory.path().join("project");
+  let user = directory.path().join("config.toml");
+  fs::create_dir_all(&root)?;
+  fs::write(&user, "[naming]\nreserved_prefixes = [\"internal\"]\n")?;
+  fs::write(root.join("mdt.toml"), "[check]\nstrict = true\n")?;
+
+  let mut cmd = Command::cargo_bin("mdt")?;
+  let output = cmd
+    .args(["config", "show", "--resolved", "--path"])
+    .arg(&root)
+    .env("MDT_USER_CONFIG", &user)
+    .assert()
+    .success()
+    .get_output()
+    .clone();
+  let stdout = String::from_utf8(output.stdout)?;
+  assert!(stdout.contains(&format!(
+    "naming.reserved_prefixes = [\"internal\"]  # {}\n",
+    user.canonicalize()?.display()
+  )));
+  assert!(stdout.contains("check.strict = true  # mdt.toml\n"));
+
+  let mut cmd = Command::cargo_bin("mdt")?;
+  let output = cmd
+    .args(["config", "show", "--resolved", "--path"])
+    .arg(&root)
+    .env("MDT_USER_CONFIG", "")
+    .assert()
+    .success()
+    .get_output()
+    .clone();
+  assert!(String::from_utf8(output.stdout)?.contains("naming.reserved_prefixes = []  # default\n"));
+
+  Ok(())
+}
+
+#[test]
+fn can_show_resolved_config_with_a_profile() -> AnyEmptyResult {
+  let directory = tempfile::tempdir()?;
+  let root = directory.path();
+  fs::write(
+    root.join("mdt.toml"),
+    "[data]\nrelease = \"draft.toml\"\n\n[profile.release.data]\nrelease = \"release.toml\"\n",
+  )?;
+
+  let mut cmd = Command::cargo_bin("mdt")?;
+  let output = cmd
+    .args([
+      "config",
+      "show",
+      "--resolved",
+      "--profile",
+      "release",
+      "--path",
+    ])
+    .arg(root)
+    .assert()
+    .success()
+    .get_output()
+    .clone();
+  assert!(
+    String::from_utf8(output.stdout)?.contains("data.release = \"release.toml\"  # mdt.toml\n")
+  );
+
+  let mut cmd = Command::cargo_bin("mdt")?;
+  let output = cmd
+    .args(["config", "show", "--resolved", "--path"])
+    .arg(root)
+    .env("MDT_PROFILE", "")
+    .assert()
+    .success()
+    .get_output()
+    .clone();
+  assert!(String::from_utf8(output.stdout)?.contains("data.release = \"draft.toml\"  # mdt.toml\n"));
+
+  Ok(())
+}
+
+#[test]
+fn user_settings_apply_to_the_cli() -> AnyEmptyResult {
+  let directory = tempfile::tempdir()?;
+  let root = directory.path().join("project");
+  let user = directory.path().join("config.toml");
+  fs::create_dir_all(root.join("docs"))?;
+  fs::write(&user, "[user]\ncolor = \"always\"\nskip = [\"docs/**\"]\n")?;
+  fs::write(
+    root.join("template.t.md"),
+    "<!-- {@greeting} -->\nHello\n<!-- {/greeting} -->\n",
+  )?;
+  fs::write(
+    root.join("readme.md"),
+    "<!-- {=greeting} -->\n<!-- {/greeting} -->\n",
+  )?;
+  fs::write(
+    root.join("docs/readme.md"),
+    "<!-- {=greeting} -->\n<!-- {/greeting} -->\n",
+  )?;
+
+  let mut cmd = Command::cargo_bin("mdt")?;
+  cmd
+    .args(["check", "--path"])
+    .arg(&root)
+    .env("MDT_USER_CONFIG", &user)
+    .assert()
+    .failure()
+    .stdout("\x1b[1;33mstale\x1b[0m: `greeting` in readme.md\n");
+
+  let mut cmd = Command::cargo_bin("mdt")?;
+  cmd
+    .args(["check", "--color", "never", "--path"])
+    .arg(&root)
+    .env("MDT_USER_CONFIG", &user)
+    .assert()
+    .failure()
+    .stdout("stale: `greeting` in readme.md\n");
+
+  fs::write(&user, "[user]\ncolour = \"always\"\n")?;
+
+  let mut cmd = Command::cargo_bin("mdt")?;
+  let output = cmd
+    .args(["check", "--path"])
+    .arg(&root)
+    .env("MDT_USER_CONFIG", &user)
+    .assert()
+    .failure()
+    .get_output()
+    .clone();
+  assert!(String::from_utf8(output.stderr)?.contains("unknown field `colour`"));
+
+  Ok(())
+}
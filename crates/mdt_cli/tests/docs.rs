@@ -0,0 +1,62 @@
+use std::fs;
+
+use assert_cmd::Command;
+use mdt::AnyEmptyResult;
+use similar_asserts::assert_eq;
+
+#[test]
+fn can_sync_the_provider_index() -> AnyEmptyResult {
+  let directory = tempfile::tempdir()?;
+  let root = directory.path();
+  fs::write(root.join("mdt.toml"), "[docs]\nindex = \"readme.md\"\n")?;
+  fs::write(
+    root.join("templates.t.md"),
+    "<!-- {@install} -->\n{#- doc: How to install the crate. -#}\ncargo install mdt\n<!-- \
+     {/install} -->\n\n<!-- {@license} -->\nUnlicense\n<!-- {/license} -->\n",
+  )?;
+
+  let mut cmd = Command::cargo_bin("mdt")?;
+  let output = cmd
+    .args(["check", "--path"])
+    .arg(root)
+    .assert()
+    .failure()
+    .get_output()
+    .clone();
+  assert!(String::from_utf8(output.stdout)?.contains("the provider index is out of date"));
+
+  let mut cmd = Command::cargo_bin("mdt")?;
+  cmd
+    .args(["docs", "sync", "--path"])
+    .arg(root)
+    .assert()
+    .success()
+    .stdout("wrote the provider index to readme.md\n");
+
+  assert_eq!(
+    fs::read_to_string(root.join("readme.md"))?,
+    "# Providers\n\nThis index is generated with `mdt docs sync`. Each preview is kept up to date \
+     by `mdt update`.\n\n## `install`\n\nHow to install the crate.\n\n<!-- {=install} -->\ncargo \
+     install mdt\n<!-- {/install} -->\n\n```md\n<!-- {=install} -->\n<!-- {/install} \
+     -->\n```\n\n## `license`\n\n<!-- {=license} -->\nUnlicense\n<!-- {/license} \
+     -->\n\n```md\n<!-- {=license} -->\n<!-- {/license} -->\n```\n"
+  );
+
+  let mut cmd = Command::cargo_bin("mdt")?;
+  cmd
+    .args(["check", "--path"])
+    .arg(root)
+    .assert()
+    .success()
+    .stdout("all blocks are up to date\n");
+
+  let mut cmd = Command::cargo_bin("mdt")?;
+  cmd
+    .args(["docs", "sync", "--path"])
+    .arg(root)
+    .assert()
+    .success()
+    .stdout("the provider index is up to date\n");
+
+  Ok(())
+}
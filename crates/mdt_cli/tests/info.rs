@@ -0,0 +1,59 @@
+use std::fs;
+
+use assert_cmd::Command;
+use mdt::AnyEmptyResult;
+
+#[test]
+fn reports_unused_and_missing_data() -> AnyEmptyResult {
+  let directory = tempfile::tempdir()?;
+  let root = directory.path();
+  fs::write(
+    root.join("mdt.toml"),
+    "[data]\npkg = \"package.json\"\ndocs = \"docs.toml\"\n",
+  )?;
+  fs::write(root.join("package.json"), r#"{ "version": "1.0.0" }"#)?;
+  fs::write(root.join("docs.toml"), "url = \"https://example.com\"\n")?;
+  fs::write(
+    root.join("template.t.md"),
+    "<!-- {@install} -->\nmdt@{{ pkg.version }} {{ pkg.verison }}\n<!-- {/install} -->\n",
+  )?;
+  fs::write(
+    root.join("readme.md"),
+    "<!-- {=install} -->\n<!-- {/install} -->\n",
+  )?;
+
+  let mut cmd = Command::cargo_bin("mdt")?;
+  let assert = cmd.arg("info").arg("--path").arg(root).assert().success();
+  assert.stdout(
+    "providers: 1\nconsumers: 1\nheld: 0\ndata namespaces: docs, pkg\nunused data: `docs` is never \
+     referenced\nmissing data: `pkg.verison` is referenced by `install` but doesn't exist\n",
+  );
+
+  let mut cmd = Command::cargo_bin("mdt")?;
+  cmd.arg("update").arg("--path").arg(root).assert().success();
+  similar_asserts::assert_eq!(
+    fs::read_to_string(root.join("readme.md"))?,
+    "<!-- {=install} -->\nmdt@1.0.0 \n<!-- {/install} -->\n"
+  );
+
+  Ok(())
+}
+
+#[test]
+fn errors_are_reported_with_their_code() -> AnyEmptyResult {
+  let directory = tempfile::tempdir()?;
+
+  let mut cmd = Command::cargo_bin("mdt")?;
+  cmd
+    .args(["info", "--package", "missing", "--path"])
+    .arg(directory.path())
+    .assert()
+    .failure()
+    .stdout("")
+    .stderr(
+      "error[mdt::unknown_package]: no package named `missing` exists\nhelp: packages are \
+       directories with a `Cargo.toml` or `package.json` which has a name\n",
+    );
+
+  Ok(())
+}
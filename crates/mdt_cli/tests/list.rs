@@ -0,0 +1,55 @@
+use std::fs;
+
+use assert_cmd::Command;
+use mdt::AnyEmptyResult;
+
+#[test]
+fn can_list_providers() -> AnyEmptyResult {
+  let directory = tempfile::tempdir()?;
+  let root = directory.path();
+  fs::write(
+    root.join("template.t.md"),
+    "---\ndescription: Shared snippets\n---\n\n<!-- {@install} -->\n{#- doc: How to install the \
+     cli -#}\n\ncargo install mdt\n<!-- {/install} -->\n\n<!-- {@badges} -->\n<!-- {/badges} -->\n",
+  )?;
+  fs::write(
+    root.join("notes.t.md"),
+    "---\ntags: [legal]\n---\n<!-- {@notes} -->\n<!-- {/notes} -->\n",
+  )?;
+
+  let mut cmd = Command::cargo_bin("mdt")?;
+  let assert = cmd.arg("list").arg("--path").arg(root).assert().success();
+  assert.stdout("badges: Shared snippets\ninstall: How to install the cli\nnotes\n");
+
+  let mut cmd = Command::cargo_bin("mdt")?;
+  let assert = cmd
+    .args(["list", "--tag", "legal", "--path"])
+    .arg(root)
+    .assert()
+    .success();
+  assert.stdout("notes\n");
+
+  Ok(())
+}
+
+#[test]
+fn can_list_providers_in_namespace() -> AnyEmptyResult {
+  let directory = tempfile::tempdir()?;
+  let root = directory.path();
+  fs::write(
+    root.join("template.t.md"),
+    "<!-- {@cli/install} -->\n<!-- {/cli/install} -->\n<!-- {@cli:usage} -->\n<!-- {/cli:usage} \
+     -->\n<!-- {@lsp/install} -->\n<!-- {/lsp/install} -->\n<!-- {@install} -->\n<!-- {/install} \
+     -->\n",
+  )?;
+
+  let mut cmd = Command::cargo_bin("mdt")?;
+  let assert = cmd
+    .args(["list", "--namespace", "cli", "--path"])
+    .arg(root)
+    .assert()
+    .success();
+  assert.stdout("cli/install\ncli:usage\n");
+
+  Ok(())
+}
@@ -0,0 +1,44 @@
+use std::fs;
+
+use assert_cmd::Command;
+use mdt::AnyEmptyResult;
+
+#[test]
+fn can_scaffold_a_provider() -> AnyEmptyResult {
+  let directory = tempfile::tempdir()?;
+  let root = directory.path();
+
+  let mut cmd = Command::cargo_bin("mdt")?;
+  cmd
+    .args(["new", "install", "--path"])
+    .arg(root)
+    .assert()
+    .success()
+    .stdout("added `install` to .templates/template.t.md\n");
+
+  let mut cmd = Command::cargo_bin("mdt")?;
+  cmd
+    .args(["new", "usage", "--path"])
+    .arg(root)
+    .assert()
+    .success()
+    .stdout("added `usage` to .templates/template.t.md\n");
+
+  let template = root.join(".templates/template.t.md");
+  let expected =
+    "<!-- {@install} -->\n\n<!-- {/install} -->\n\n<!-- {@usage} -->\n\n<!-- {/usage} -->\n";
+  similar_asserts::assert_eq!(fs::read_to_string(&template)?, expected);
+
+  let mut cmd = Command::cargo_bin("mdt")?;
+  let output = cmd
+    .args(["new", "install", "--file", "other.t.md", "--path"])
+    .arg(root)
+    .assert()
+    .failure();
+  let stderr = String::from_utf8_lossy(&output.get_output().stderr).to_string();
+  assert!(stderr.contains("install2"), "{stderr}");
+  assert!(!root.join(".templates/other.t.md").exists());
+  similar_asserts::assert_eq!(fs::read_to_string(&template)?, expected);
+
+  Ok(())
+}
@@ -0,0 +1,86 @@
+use std::fs;
+use std::path::Path;
+
+use assert_cmd::Command;
+use mdt::AnyEmptyResult;
+
+fn create_monorepo(root: &Path) -> AnyEmptyResult {
+  fs::create_dir_all(root.join("crates/a"))?;
+  fs::create_dir_all(root.join("packages/b"))?;
+  fs::write(
+    root.join("Cargo.toml"),
+    "[workspace]\nmembers = [\"crates/*\"]\n",
+  )?;
+  fs::write(
+    root.join("crates/a/Cargo.toml"),
+    "[package]\nname = \"a\"\n",
+  )?;
+  fs::write(root.join("packages/b/package.json"), r#"{ "name": "b" }"#)?;
+  fs::write(
+    root.join("template.t.md"),
+    "<!-- {@install} -->\ncargo install mdt\n<!-- {/install} -->\n",
+  )?;
+  fs::write(
+    root.join("crates/a/notes.t.md"),
+    "<!-- {@notes} -->\nnotes\n<!-- {/notes} -->\n",
+  )?;
+
+  for readme in ["readme.md", "crates/a/readme.md", "packages/b/readme.md"] {
+    fs::write(
+      root.join(readme),
+      "<!-- {=install} -->\n<!-- {/install} -->\n<!-- {=notes} -->\n<!-- {/notes} -->\n",
+    )?;
+  }
+
+  Ok(())
+}
+
+#[test]
+fn groups_results_by_package() -> AnyEmptyResult {
+  let directory = tempfile::tempdir()?;
+  let root = directory.path();
+  create_monorepo(root)?;
+
+  let mut cmd = Command::cargo_bin("mdt")?;
+  let assert = cmd.arg("check").arg("--path").arg(root).assert().failure();
+  assert.stdout(
+    "stale: `install` in readme.md\nstale: `notes` in readme.md\npackage: a\nstale: `install` in \
+     crates/a/readme.md\nstale: `notes` in crates/a/readme.md\npackage: b\nstale: `install` in \
+     packages/b/readme.md\nstale: `notes` in packages/b/readme.md\n",
+  );
+
+  let mut cmd = Command::cargo_bin("mdt")?;
+  let assert = cmd
+    .args(["check", "--package", "b", "--path"])
+    .arg(root)
+    .assert()
+    .failure();
+  assert.stdout(
+    "package: b\nstale: `install` in packages/b/readme.md\nstale: `notes` in \
+     packages/b/readme.md\n",
+  );
+
+  let mut cmd = Command::cargo_bin("mdt")?;
+  let assert = cmd.arg("list").arg("--path").arg(root).assert().success();
+  assert.stdout("install\npackage: a\nnotes\n");
+
+  let mut cmd = Command::cargo_bin("mdt")?;
+  let assert = cmd
+    .args(["info", "--package", "a", "--path"])
+    .arg(root)
+    .assert()
+    .success();
+  assert.stdout(
+    "providers: 1\nconsumers: 2\nheld: 0\npackage `a`: 1 provider(s), 2 consumer(s)\ndata \
+     namespaces: \n",
+  );
+
+  let mut cmd = Command::cargo_bin("mdt")?;
+  cmd
+    .args(["list", "--package", "c", "--path"])
+    .arg(root)
+    .assert()
+    .failure();
+
+  Ok(())
+}
@@ -0,0 +1,43 @@
+use std::fs;
+
+use assert_cmd::Command;
+use mdt::AnyEmptyResult;
+
+#[test]
+fn can_render_release_notes() -> AnyEmptyResult {
+  let directory = tempfile::tempdir()?;
+  let root = directory.path();
+  fs::write(
+    root.join("mdt.toml"),
+    "[release]\nprovider = \"releaseNotes\"\n",
+  )?;
+  fs::write(
+    root.join("template.t.md"),
+    "<!-- {@releaseNotes} -->\nInstall with `cargo install mdt@{{ release.version }}`.\n<!-- \
+     {/releaseNotes} -->\n",
+  )?;
+
+  let mut cmd = Command::cargo_bin("mdt")?;
+  let assert = cmd
+    .args(["release-notes", "--tag", "v1.2.0", "--path"])
+    .arg(root)
+    .assert()
+    .success();
+  assert.stdout("Install with `cargo install mdt@1.2.0`.\n");
+
+  let mut cmd = Command::cargo_bin("mdt")?;
+  cmd
+    .args([
+      "release-notes",
+      "--tag",
+      "v1.2.0",
+      "--provider",
+      "missing",
+      "--path",
+    ])
+    .arg(root)
+    .assert()
+    .failure();
+
+  Ok(())
+}
@@ -0,0 +1,56 @@
+use std::fs;
+
+use assert_cmd::Command;
+use mdt::AnyEmptyResult;
+
+#[test]
+fn can_rename_a_block() -> AnyEmptyResult {
+  let directory = tempfile::tempdir()?;
+  let root = directory.path();
+  let template = "<!-- {@install} -->\nnpm i\n<!-- {/install} -->\n";
+  let readme = "<!-- {=install|trim} -->\nnpm i\n<!-- {/install} -->\n";
+  fs::create_dir_all(root.join("node_modules/pkg"))?;
+  fs::write(root.join("template.t.md"), template)?;
+  fs::write(root.join("readme.md"), readme)?;
+  fs::write(root.join("node_modules/pkg/readme.md"), readme)?;
+
+  let mut cmd = Command::cargo_bin("mdt")?;
+  cmd
+    .args(["rename", "install", "setup", "--dry-run", "--path"])
+    .arg(root)
+    .assert()
+    .success()
+    .stdout("would rename: readme.md\nwould rename: template.t.md\n");
+
+  similar_asserts::assert_eq!(fs::read_to_string(root.join("readme.md"))?, readme);
+
+  let mut cmd = Command::cargo_bin("mdt")?;
+  cmd
+    .args(["rename", "install", "setup", "--path"])
+    .arg(root)
+    .assert()
+    .success()
+    .stdout("renamed: readme.md\nrenamed: template.t.md\n");
+
+  similar_asserts::assert_eq!(
+    fs::read_to_string(root.join("template.t.md"))?,
+    "<!-- {@setup} -->\nnpm i\n<!-- {/setup} -->\n"
+  );
+  similar_asserts::assert_eq!(
+    fs::read_to_string(root.join("readme.md"))?,
+    "<!-- {=setup|trim} -->\nnpm i\n<!-- {/setup} -->\n"
+  );
+  similar_asserts::assert_eq!(
+    fs::read_to_string(root.join("node_modules/pkg/readme.md"))?,
+    readme
+  );
+
+  let mut cmd = Command::cargo_bin("mdt")?;
+  cmd
+    .args(["rename", "install", "other", "--path"])
+    .arg(root)
+    .assert()
+    .failure();
+
+  Ok(())
+}
@@ -0,0 +1,24 @@
+use std::fs;
+
+use assert_cmd::Command;
+use mdt::AnyEmptyResult;
+
+#[test]
+fn can_render_a_provider() -> AnyEmptyResult {
+  let directory = tempfile::tempdir()?;
+  let root = directory.path();
+  fs::write(
+    root.join("template.t.md"),
+    "<!-- {@news} -->\n## News\n\nSee [the docs](https://mdt.dev)\n<!-- {/news} -->\n",
+  )?;
+
+  let mut cmd = Command::cargo_bin("mdt")?;
+  let assert = cmd
+    .args(["render", "news", "--with", "slackFormat", "--path"])
+    .arg(root)
+    .assert()
+    .success();
+  assert.stdout("*News*\n\nSee <https://mdt.dev|the docs>\n");
+
+  Ok(())
+}
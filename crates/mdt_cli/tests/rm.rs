@@ -0,0 +1,88 @@
+use std::fs;
+
+use assert_cmd::Command;
+use mdt::AnyEmptyResult;
+
+#[test]
+fn can_remove_a_provider() -> AnyEmptyResult {
+  let directory = tempfile::tempdir()?;
+  let root = directory.path();
+  let template = "# Templates\n\n<!-- {@install} -->\nnpm i\n<!-- {/install} -->\n\n<!-- {@usage} \
+                  -->\nrun\n<!-- {/usage} -->\n";
+  let readme = "# Readme\n\n<!-- {=install} -->\nnpm i\n<!-- {/install} -->\n\nDone.\n";
+  fs::write(root.join("template.t.md"), template)?;
+  fs::write(root.join("readme.md"), readme)?;
+  fs::write(
+    root.join("guide.md"),
+    "<!-- {=install+usage} -->\nnpm i\nrun\n<!-- {/install+usage} -->\n",
+  )?;
+
+  let mut cmd = Command::cargo_bin("mdt")?;
+  cmd
+    .args(["rm", "install", "--strip", "--path"])
+    .arg(root)
+    .assert()
+    .success()
+    .stdout(
+      "removed: `install` from template.t.md\nstripped: `install` in readme.md\norphan: \
+       `install+usage` in guide.md\n",
+    );
+
+  similar_asserts::assert_eq!(
+    fs::read_to_string(root.join("template.t.md"))?,
+    "# Templates\n\n<!-- {@usage} -->\nrun\n<!-- {/usage} -->\n"
+  );
+  similar_asserts::assert_eq!(
+    fs::read_to_string(root.join("readme.md"))?,
+    "# Readme\n\nnpm i\n\nDone.\n"
+  );
+
+  let mut cmd = Command::cargo_bin("mdt")?;
+  cmd
+    .args(["rm", "usage", "--path"])
+    .arg(root)
+    .assert()
+    .success()
+    .stdout("removed: `usage` from template.t.md\norphan: `install+usage` in guide.md\n");
+
+  let mut cmd = Command::cargo_bin("mdt")?;
+  cmd
+    .args(["rm", "usage", "--path"])
+    .arg(root)
+    .assert()
+    .failure();
+
+  Ok(())
+}
+
+#[test]
+fn keep_content_conflicts_with_strip() -> AnyEmptyResult {
+  let directory = tempfile::tempdir()?;
+  let root = directory.path();
+  let template = "<!-- {@install} -->\nnpm i\n<!-- {/install} -->\n";
+  let readme = "<!-- {=install} -->\nnpm i\n<!-- {/install} -->\n";
+  fs::write(root.join("template.t.md"), template)?;
+  fs::write(root.join("readme.md"), readme)?;
+
+  let mut cmd = Command::cargo_bin("mdt")?;
+  let output = cmd
+    .args(["rm", "install", "--keep-content", "--strip", "--path"])
+    .arg(root)
+    .assert()
+    .failure()
+    .get_output()
+    .clone();
+  assert!(String::from_utf8(output.stderr)?.contains("cannot be used with"));
+  assert_eq!(fs::read_to_string(root.join("template.t.md"))?, template);
+
+  let mut cmd = Command::cargo_bin("mdt")?;
+  cmd
+    .args(["rm", "install", "--keep-content", "--path"])
+    .arg(root)
+    .assert()
+    .success()
+    .stdout("removed: `install` from template.t.md\norphan: `install` in readme.md\n");
+  assert_eq!(fs::read_to_string(root.join("readme.md"))?, readme);
+
+  Ok(())
+}
@@ -0,0 +1,39 @@
+use std::fs;
+
+use assert_cmd::Command;
+use mdt::AnyEmptyResult;
+
+#[test]
+fn can_search_providers() -> AnyEmptyResult {
+  let directory = tempfile::tempdir()?;
+  let root = directory.path();
+  fs::write(
+    root.join("template.t.md"),
+    "<!-- {@install} -->\nRun `cargo install mdt`.\n<!-- {/install} -->\n<!-- {@license} \
+     -->\nUnlicense\n<!-- {/license} -->\n",
+  )?;
+  fs::write(
+    root.join("readme.md"),
+    "<!-- {=install} -->\n<!-- {/install} -->\n",
+  )?;
+
+  let mut cmd = Command::cargo_bin("mdt")?;
+  let assert = cmd
+    .args(["search", "cargo", "--path"])
+    .arg(root)
+    .assert()
+    .success();
+  assert.stdout("install (template.t.md, 1 consumer(s))\n  2: Run `**cargo** install mdt`.\n");
+
+  let mut cmd = Command::cargo_bin("mdt")?;
+  let output = cmd
+    .args(["search", "unlicense", "--format", "json", "--path"])
+    .arg(root)
+    .output()?;
+  let json: serde_json::Value = serde_json::from_slice(&output.stdout)?;
+  similar_asserts::assert_eq!(json[0]["name"], "license");
+  similar_asserts::assert_eq!(json[0]["file"], "template.t.md");
+  similar_asserts::assert_eq!(json[0]["snippets"][0]["highlights"][0]["start"], 0);
+
+  Ok(())
+}
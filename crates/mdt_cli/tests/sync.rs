@@ -0,0 +1,48 @@
+use std::fs;
+
+use assert_cmd::Command;
+use mdt::AnyEmptyResult;
+
+#[test]
+fn can_sync_a_block() -> AnyEmptyResult {
+  let directory = tempfile::tempdir()?;
+  let root = directory.path();
+  let readme = "<!-- {=install} -->\n<!-- {/install} -->\n<!-- {=usage} -->\n<!-- {/usage} -->\n";
+  fs::write(
+    root.join("template.t.md"),
+    "<!-- {@install} -->\nnpm i\n<!-- {/install} -->\n<!-- {@usage} -->\nrun\n<!-- {/usage} -->\n",
+  )?;
+  fs::write(root.join("readme.md"), readme)?;
+  fs::write(root.join("other.md"), readme)?;
+
+  let mut cmd = Command::cargo_bin("mdt")?;
+  cmd
+    .args(["sync", "install", "--file", "readme.md", "--path"])
+    .arg(root)
+    .assert()
+    .success()
+    .stdout("updated 1 block(s) in 1 file(s)\n");
+
+  similar_asserts::assert_eq!(
+    fs::read_to_string(root.join("readme.md"))?,
+    "<!-- {=install} -->\nnpm i\n<!-- {/install} -->\n<!-- {=usage} -->\n<!-- {/usage} -->\n"
+  );
+  similar_asserts::assert_eq!(fs::read_to_string(root.join("other.md"))?, readme);
+
+  let mut cmd = Command::cargo_bin("mdt")?;
+  cmd
+    .args(["sync", "install", "--file", "readme.md", "--path"])
+    .arg(root)
+    .assert()
+    .success()
+    .stdout("`install` is up to date\n");
+
+  let mut cmd = Command::cargo_bin("mdt")?;
+  cmd
+    .args(["sync", "missing", "--path"])
+    .arg(root)
+    .assert()
+    .failure();
+
+  Ok(())
+}
@@ -0,0 +1,273 @@
+use std::fs;
+
+use assert_cmd::Command;
+use mdt::AnyEmptyResult;
+
+#[test]
+fn can_update() -> AnyEmptyResult {
+  let directory = tempfile::tempdir()?;
+  let root = directory.path();
+  fs::write(
+    root.join("template.t.md"),
+    "<!-- {@greeting} -->\n\n## Hello\n\n<!-- {/greeting} -->\n",
+  )?;
+  fs::write(
+    root.join("readme.md"),
+    "# Readme\n\n<!-- {=greeting|headingShift:1} -->\nstale\n<!-- {/greeting} -->\n",
+  )?;
+  fs::write(
+    root.join("other.md"),
+    "<!-- {=greeting} -->\n\n## Hello\n\n<!-- {/greeting} -->\n",
+  )?;
+
+  let mut cmd = Command::cargo_bin("mdt")?;
+  let assert = cmd.arg("update").arg("--path").arg(root).assert().success();
+  assert.stdout("updated 1 block(s) in 1 file(s)\n");
+
+  let readme = fs::read_to_string(root.join("readme.md"))?;
+  similar_asserts::assert_eq!(
+    readme,
+    "# Readme\n\n<!-- {=greeting|headingShift:1} -->\n\n### Hello\n\n<!-- {/greeting} -->\n"
+  );
+
+  let mut cmd = Command::cargo_bin("mdt")?;
+  let assert = cmd.arg("update").arg("--path").arg(root).assert().success();
+  assert.stdout("all blocks are up to date\n");
+
+  Ok(())
+}
+
+#[test]
+fn can_copy_assets() -> AnyEmptyResult {
+  let directory = tempfile::tempdir()?;
+  let root = directory.path();
+  fs::create_dir_all(root.join("templates/assets"))?;
+  fs::create_dir_all(root.join("docs"))?;
+  fs::write(root.join("mdt.toml"), "[assets]\ncopy = true\n")?;
+  fs::write(root.join("templates/assets/logo.svg"), "<svg></svg>")?;
+  fs::write(
+    root.join("templates/template.t.md"),
+    "<!-- {@logo} -->\n![Logo](assets/logo.svg)\n<!-- {/logo} -->\n",
+  )?;
+  fs::write(
+    root.join("docs/readme.md"),
+    "<!-- {=logo} -->\n<!-- {/logo} -->\n",
+  )?;
+
+  let mut cmd = Command::cargo_bin("mdt")?;
+  cmd.arg("update").arg("--path").arg(root).assert().success();
+
+  assert_eq!(
+    fs::read_to_string(root.join("docs/assets/logo.svg"))?,
+    "<svg></svg>"
+  );
+
+  Ok(())
+}
+
+#[test]
+fn can_update_from_stdin() -> AnyEmptyResult {
+  let directory = tempfile::tempdir()?;
+  let root = directory.path();
+  let readme = "# Readme\n\n<!-- {=greeting} -->\n<!-- {/greeting} -->\n";
+  fs::write(
+    root.join("template.t.md"),
+    "<!-- {@greeting} -->\nHello\n<!-- {/greeting} -->\n",
+  )?;
+  fs::write(root.join("readme.md"), readme)?;
+
+  let mut cmd = Command::cargo_bin("mdt")?;
+  let assert = cmd
+    .args(["update", "--stdin", "--stdin-path", "docs/new.md", "--path"])
+    .arg(root)
+    .write_stdin("<!-- {=greeting} -->\nstale\n<!-- {/greeting} -->\n")
+    .assert()
+    .success();
+  assert.stdout("<!-- {=greeting} -->\nHello\n<!-- {/greeting} -->\n");
+
+  let mut cmd = Command::cargo_bin("mdt")?;
+  let assert = cmd
+    .args(["check", "--stdin", "--stdin-path", "readme.md", "--path"])
+    .arg(root)
+    .write_stdin("<!-- {=greeting} -->\nHello\n<!-- {/greeting} -->\n")
+    .assert()
+    .success();
+  assert.stdout("all blocks are up to date\n");
+
+  similar_asserts::assert_eq!(fs::read_to_string(root.join("readme.md"))?, readme);
+  assert!(!root.join("docs").exists());
+
+  Ok(())
+}
+
+#[test]
+fn can_update_with_a_profile() -> AnyEmptyResult {
+  let directory = tempfile::tempdir()?;
+  let root = directory.path();
+  fs::write(
+    root.join("mdt.toml"),
+    "[data]\nrelease = \"draft.toml\"\n\n[profile.release.data]\nrelease = \"release.toml\"\n",
+  )?;
+  fs::write(root.join("draft.toml"), "version = \"1.0.0-draft\"\n")?;
+  fs::write(root.join("release.toml"), "version = \"1.0.0\"\n")?;
+  fs::write(
+    root.join("template.t.md"),
+    "<!-- {@version} -->{{ release.version }}<!-- {/version} -->\n",
+  )?;
+  fs::write(
+    root.join("readme.md"),
+    "<!-- {=version} --><!-- {/version} -->\n",
+  )?;
+
+  let mut cmd = Command::cargo_bin("mdt")?;
+  cmd.arg("update").arg("--path").arg(root).assert().success();
+  similar_asserts::assert_eq!(
+    fs::read_to_string(root.join("readme.md"))?,
+    "<!-- {=version} -->1.0.0-draft<!-- {/version} -->\n"
+  );
+
+  let mut cmd = Command::cargo_bin("mdt")?;
+  cmd
+    .args(["update", "--profile", "release", "--path"])
+    .arg(root)
+    .assert()
+    .success();
+  similar_asserts::assert_eq!(
+    fs::read_to_string(root.join("readme.md"))?,
+    "<!-- {=version} -->1.0.0<!-- {/version} -->\n"
+  );
+
+  let mut cmd = Command::cargo_bin("mdt")?;
+  cmd
+    .args(["check", "--path"])
+    .arg(root)
+    .env("MDT_PROFILE", "release")
+    .assert()
+    .success();
+
+  let mut cmd = Command::cargo_bin("mdt")?;
+  let assert = cmd
+    .args(["check", "--profile", "nightly", "--path"])
+    .arg(root)
+    .assert()
+    .failure();
+  let stderr = String::from_utf8_lossy(&assert.get_output().stderr).to_string();
+  assert!(stderr.contains("the profile `nightly` doesn't exist"));
+
+  Ok(())
+}
+
+#[test]
+fn can_update_workspace_members() -> AnyEmptyResult {
+  let directory = tempfile::tempdir()?;
+  let root = directory.path();
+  let member = root.join("packages/cli");
+  fs::create_dir_all(&member)?;
+  fs::write(
+    root.join("mdt.toml"),
+    "[workspace]\nmembers = [\"packages/*\"]\n",
+  )?;
+  fs::write(
+    root.join("template.t.md"),
+    "<!-- {@license} -->\nMIT\n<!-- {/license} -->\n",
+  )?;
+  fs::write(
+    root.join("readme.md"),
+    "<!-- {=packages/cli/install} -->\n<!-- {/packages/cli/install} -->\n",
+  )?;
+  fs::write(member.join("mdt.toml"), "")?;
+  fs::write(
+    member.join("template.t.md"),
+    "<!-- {@install} -->\ncargo install cli\n<!-- {/install} -->\n",
+  )?;
+  fs::write(
+    member.join("readme.md"),
+    "<!-- {=install} -->\n<!-- {/install} -->\n<!-- {=license} -->\n<!-- {/license} -->\n",
+  )?;
+
+  let mut cmd = Command::cargo_bin("mdt")?;
+  cmd
+    .arg("update")
+    .arg("--path")
+    .arg(root)
+    .assert()
+    .success()
+    .stdout(
+      "updated 1 block(s) in 1 file(s)\nproject: packages/cli\nupdated 2 block(s) in 1 file(s)\n",
+    );
+
+  similar_asserts::assert_eq!(
+    fs::read_to_string(root.join("readme.md"))?,
+    "<!-- {=packages/cli/install} -->\ncargo install cli\n<!-- {/packages/cli/install} -->\n"
+  );
+  similar_asserts::assert_eq!(
+    fs::read_to_string(member.join("readme.md"))?,
+    "<!-- {=install} -->\ncargo install cli\n<!-- {/install} -->\n<!-- {=license} -->\nMIT\n<!-- \
+     {/license} -->\n"
+  );
+
+  let mut cmd = Command::cargo_bin("mdt")?;
+  cmd
+    .arg("check")
+    .arg("--path")
+    .arg(root)
+    .assert()
+    .success()
+    .stdout("all blocks are up to date\nproject: packages/cli\nall blocks are up to date\n");
+
+  Ok(())
+}
+
+#[test]
+fn can_update_only_matching_files() -> AnyEmptyResult {
+  let directory = tempfile::tempdir()?;
+  let root = directory.path();
+  let stale = "<!-- {=greeting} -->\n<!-- {/greeting} -->\n";
+  let updated = "<!-- {=greeting} -->\nHello\n<!-- {/greeting} -->\n";
+  fs::create_dir_all(root.join("docs/drafts"))?;
+  fs::write(
+    root.join("template.t.md"),
+    "<!-- {@greeting} -->\nHello\n<!-- {/greeting} -->\n",
+  )?;
+
+  for file in ["readme.md", "docs/guide.md", "docs/drafts/next.md"] {
+    fs::write(root.join(file), stale)?;
+  }
+
+  let mut cmd = Command::cargo_bin("mdt")?;
+  cmd
+    .args([
+      "update",
+      "--only",
+      "docs/**",
+      "--skip",
+      "docs/drafts/**",
+      "--path",
+    ])
+    .arg(root)
+    .assert()
+    .success()
+    .stdout("updated 1 block(s) in 1 file(s)\n");
+
+  similar_asserts::assert_eq!(fs::read_to_string(root.join("docs/guide.md"))?, updated);
+  similar_asserts::assert_eq!(fs::read_to_string(root.join("readme.md"))?, stale);
+  similar_asserts::assert_eq!(fs::read_to_string(root.join("docs/drafts/next.md"))?, stale);
+
+  let mut cmd = Command::cargo_bin("mdt")?;
+  cmd
+    .args(["check", "--only", "docs/*.md", "--path"])
+    .arg(root)
+    .assert()
+    .success()
+    .stdout("all blocks are up to date\n");
+
+  let mut cmd = Command::cargo_bin("mdt")?;
+  cmd
+    .args(["check", "--only", "readme.md", "--path"])
+    .arg(root)
+    .assert()
+    .failure()
+    .stdout("stale: `greeting` in readme.md\n");
+
+  Ok(())
+}
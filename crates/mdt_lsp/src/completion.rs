@@ -0,0 +1,181 @@
+use mdt::Project;
+use mdt::BUILT_IN_TRANSFORMERS;
+use serde_json::Value;
+use tower_lsp::lsp_types::CompletionItem;
+use tower_lsp::lsp_types::CompletionItemKind;
+use tower_lsp::lsp_types::CompletionTextEdit;
+use tower_lsp::lsp_types::Range;
+use tower_lsp::lsp_types::TextEdit;
+
+use crate::lsp_position;
+
+/// The characters which ask the client for completions.
+pub(crate) const TRIGGER_CHARACTERS: &[&str] = &["=", "/", "+", "|", "@", "."];
+
+/// The completions at the byte offset of the content. Provider names are
+/// completed after `{=`, `{/` and `+`, transformers after `|` within any tag
+/// and data keys within `{{ }}`. Transformers which the project denies are
+/// left out.
+pub(crate) fn completions(project: &Project, content: &str, offset: usize) -> Vec<CompletionItem> {
+  let Some(before) = content.get(..offset) else {
+    return vec![];
+  };
+  let line = before.rsplit('\n').next().unwrap_or_default();
+  let completion =
+    |label: String, kind: CompletionItemKind, detail: Option<String>, typed: &str| {
+      let range = Range::new(
+        lsp_position(content, offset - typed.len()),
+        lsp_position(content, offset),
+      );
+
+      CompletionItem {
+        text_edit: Some(CompletionTextEdit::Edit(TextEdit {
+          range,
+          new_text: label.clone(),
+        })),
+        label,
+        kind: Some(kind),
+        detail,
+        ..CompletionItem::default()
+      }
+    };
+
+  if let Some(expression) = line
+    .rfind("{{")
+    .and_then(|start| line.get(start + 2..))
+    .filter(|expression| !expression.contains("}}"))
+  {
+    let typed = trailing(expression, |ch| {
+      ch.is_alphanumeric() || ch == '_' || ch == '.'
+    });
+    let (path, typed) = typed.rsplit_once('.').unwrap_or(("", typed));
+
+    return data_keys(project, path)
+      .into_iter()
+      .filter(|(key, _)| key.starts_with(typed))
+      .map(|(key, detail)| completion(key, CompletionItemKind::FIELD, detail, typed))
+      .collect();
+  }
+
+  let Some(tag) = line
+    .rfind('{')
+    .and_then(|start| line.get(start + 1..))
+    .filter(|tag| !tag.contains('}'))
+  else {
+    return vec![];
+  };
+  let Some(kind) = tag.chars().next().filter(|kind| "=@/~".contains(*kind)) else {
+    return vec![];
+  };
+  let body = tag.get(1..).unwrap_or_default();
+
+  if let Some(typed) = body.rsplit_once('|').map(|(_, typed)| typed) {
+    if typed.contains([':', '"', ' ']) {
+      return vec![];
+    }
+
+    return transformers(project)
+      .into_iter()
+      .filter(|(name, _)| name.starts_with(typed))
+      .map(|(name, detail)| completion(name, CompletionItemKind::FUNCTION, Some(detail), typed))
+      .collect();
+  }
+
+  let typed = body.rsplit('+').next().unwrap_or_default();
+
+  if !matches!(kind, '=' | '/') || typed.contains(char::is_whitespace) {
+    return vec![];
+  }
+
+  let mut providers = project
+    .providers
+    .values()
+    .filter(|provider| provider.block.name.starts_with(typed))
+    .collect::<Vec<_>>();
+  providers.sort_by(|a, b| a.block.name.cmp(&b.block.name));
+
+  providers
+    .into_iter()
+    .map(|provider| {
+      let detail = project.relative_path(&provider.file).display().to_string();
+      completion(
+        provider.block.name.clone(),
+        CompletionItemKind::REFERENCE,
+        Some(detail),
+        typed,
+      )
+    })
+    .collect()
+}
+
+/// The transformers which tags in the project can use, with a description of
+/// each. Aliases are prefixed with `@`.
+fn transformers(project: &Project) -> Vec<(String, String)> {
+  let config = &project.config;
+  let built_in = BUILT_IN_TRANSFORMERS
+    .iter()
+    .filter(|name| config.transformers.is_allowed(name))
+    .map(|name| (name.to_string(), "built-in transformer".to_string()));
+  let custom = config
+    .transformers
+    .custom
+    .keys()
+    .filter(|name| config.transformers.is_allowed(name))
+    .map(|name| (name.clone(), "custom transformer".to_string()));
+  let aliases = config
+    .transformer_aliases
+    .iter()
+    .map(|(name, chain)| (format!("@{name}"), format!("alias for `{chain}`")));
+
+  built_in.chain(custom).chain(aliases).collect()
+}
+
+/// The keys of the data at the dotted path, e.g. `pkg` for the keys of the
+/// `pkg` namespace, with the value of each scalar. The namespaces are
+/// returned for an empty path.
+fn data_keys(project: &Project, path: &str) -> Vec<(String, Option<String>)> {
+  let detail = |value: &Value| {
+    match value {
+      Value::Object(_) | Value::Array(_) => None,
+      value => Some(value.to_string()),
+    }
+  };
+
+  if path.is_empty() {
+    return project
+      .data
+      .iter()
+      .map(|(key, value)| (key.clone(), detail(value)))
+      .collect();
+  }
+
+  let mut segments = path.split('.');
+  let mut value = segments
+    .next()
+    .and_then(|namespace| project.data.get(namespace));
+
+  for segment in segments {
+    value = value.and_then(|value| value.get(segment));
+  }
+
+  let Some(Value::Object(object)) = value else {
+    return vec![];
+  };
+
+  object
+    .iter()
+    .map(|(key, value)| (key.clone(), detail(value)))
+    .collect()
+}
+
+/// The longest suffix of the text whose characters match the predicate.
+fn trailing(text: &str, predicate: impl Fn(char) -> bool) -> &str {
+  let start = text
+    .char_indices()
+    .rev()
+    .take_while(|(_, ch)| predicate(*ch))
+    .last()
+    .map_or(text.len(), |(index, _)| index);
+
+  text.get(start..).unwrap_or_default()
+}
@@ -0,0 +1,408 @@
+use std::io;
+use std::time::Duration;
+
+use serde_json::json;
+use serde_json::Value;
+use tokio::io::AsyncBufReadExt;
+use tokio::io::AsyncReadExt;
+use tokio::io::AsyncWriteExt;
+use tokio::io::BufReader;
+use tokio::io::DuplexStream;
+use tokio::io::ReadHalf;
+use tokio::io::WriteHalf;
+use tokio::task::JoinHandle;
+use tower_lsp::lsp_types::Url;
+use tower_lsp::Server;
+
+use crate::service;
+
+/// How long to wait for a message from the server before giving up.
+const TIMEOUT: Duration = Duration::from_secs(5);
+
+/// A scripted client session with the language server over an in-memory
+/// transport, for integration tests.
+///
+/// ```no_run
+/// # async fn session() -> std::io::Result<()> {
+/// use mdt_lsp::LspSession;
+///
+/// let mut session = LspSession::start();
+/// session.initialize("file:///project").await?;
+/// session
+///   .open("file:///project/readme.md", "# Title\n")
+///   .await?;
+/// session.shutdown().await?;
+/// # Ok(())
+/// # }
+/// ```
+pub struct LspSession {
+  reader: BufReader<ReadHalf<DuplexStream>>,
+  writer: WriteHalf<DuplexStream>,
+  next_id: i64,
+  /// The notifications received from the server which haven't been taken.
+  notifications: Vec<Value>,
+  server: JoinHandle<()>,
+}
+
+impl LspSession {
+  /// Start the server on a background task and connect to it.
+  pub fn start() -> Self {
+    let (client, server) = tokio::io::duplex(64 * 1024);
+    let (server_reader, server_writer) = tokio::io::split(server);
+    let (reader, writer) = tokio::io::split(client);
+    let (service, socket) = service();
+    let server = tokio::spawn(async move {
+      Server::new(server_reader, server_writer, socket)
+        .serve(service)
+        .await;
+    });
+
+    Self {
+      reader: BufReader::new(reader),
+      writer,
+      next_id: 0,
+      notifications: vec![],
+      server,
+    }
+  }
+
+  /// Send a request and wait for its response. Returns the `result`, or the
+  /// `error` object when the request failed. `Value::Null` params are
+  /// omitted.
+  pub async fn request(&mut self, method: &str, params: Value) -> io::Result<Value> {
+    self.next_id += 1;
+    let id = self.next_id;
+    self.send(message(Some(id), method, params)).await?;
+
+    loop {
+      let message = self.receive().await?;
+
+      if message.get("method").is_some() {
+        self.handle_server_message(message).await?;
+        continue;
+      }
+
+      if message.get("id") != Some(&json!(id)) {
+        continue;
+      }
+
+      let Value::Object(mut message) = message else {
+        continue;
+      };
+
+      return Ok(
+        message
+          .remove("result")
+          .or_else(|| message.remove("error"))
+          .unwrap_or_default(),
+      );
+    }
+  }
+
+  /// Send a notification, which has no response.
+  pub async fn notify(&mut self, method: &str, params: Value) -> io::Result<()> {
+    self.send(message(None, method, params)).await
+  }
+
+  /// Initialize the server for the workspace at the root uri and return the
+  /// server capabilities.
+  pub async fn initialize(&mut self, root_uri: &str) -> io::Result<Value> {
+    self.initialize_with_options(root_uri, Value::Null).await
+  }
+
+  /// Initialize the server with `initializationOptions`, e.g.
+  /// `{ "cacheLimit": 1024 }`.
+  pub async fn initialize_with_options(
+    &mut self,
+    root_uri: &str,
+    options: Value,
+  ) -> io::Result<Value> {
+    let result = self
+      .request(
+        "initialize",
+        json!({
+          "processId": null,
+          "rootUri": root_uri,
+          "capabilities": {},
+          "initializationOptions": options
+        }),
+      )
+      .await?;
+    self.notify("initialized", json!({})).await?;
+
+    Ok(result)
+  }
+
+  pub async fn open(&mut self, uri: &str, text: &str) -> io::Result<()> {
+    self
+      .notify(
+        "textDocument/didOpen",
+        json!({
+          "textDocument": { "uri": uri, "languageId": "markdown", "version": 1, "text": text }
+        }),
+      )
+      .await
+  }
+
+  /// Replace the full text of an open document.
+  pub async fn change(&mut self, uri: &str, version: i32, text: &str) -> io::Result<()> {
+    self
+      .notify(
+        "textDocument/didChange",
+        json!({
+          "textDocument": { "uri": uri, "version": version },
+          "contentChanges": [{ "text": text }]
+        }),
+      )
+      .await
+  }
+
+  pub async fn close(&mut self, uri: &str) -> io::Result<()> {
+    self
+      .notify(
+        "textDocument/didClose",
+        json!({ "textDocument": { "uri": uri } }),
+      )
+      .await
+  }
+
+  /// The memory statistics from the custom `mdt/status` request.
+  pub async fn status(&mut self) -> io::Result<Value> {
+    self.request("mdt/status", Value::Null).await
+  }
+
+  pub async fn save(&mut self, uri: &str) -> io::Result<()> {
+    self
+      .notify(
+        "textDocument/didSave",
+        json!({ "textDocument": { "uri": uri } }),
+      )
+      .await
+  }
+
+  pub async fn completion(&mut self, uri: &str, line: u32, character: u32) -> io::Result<Value> {
+    self
+      .request(
+        "textDocument/completion",
+        json!({
+          "textDocument": { "uri": uri },
+          "position": { "line": line, "character": character }
+        }),
+      )
+      .await
+  }
+
+  pub async fn rename(
+    &mut self,
+    uri: &str,
+    line: u32,
+    character: u32,
+    new_name: &str,
+  ) -> io::Result<Value> {
+    self
+      .request(
+        "textDocument/rename",
+        json!({
+          "textDocument": { "uri": uri },
+          "position": { "line": line, "character": character },
+          "newName": new_name
+        }),
+      )
+      .await
+  }
+
+  pub async fn definition(&mut self, uri: &str, line: u32, character: u32) -> io::Result<Value> {
+    self
+      .request(
+        "textDocument/definition",
+        json!({
+          "textDocument": { "uri": uri },
+          "position": { "line": line, "character": character }
+        }),
+      )
+      .await
+  }
+
+  /// Request the code actions for the position in the document.
+  pub async fn code_action(&mut self, uri: &str, line: u32, character: u32) -> io::Result<Value> {
+    let position = json!({ "line": line, "character": character });
+
+    self
+      .request(
+        "textDocument/codeAction",
+        json!({
+          "textDocument": { "uri": uri },
+          "range": { "start": position, "end": position },
+          "context": { "diagnostics": [] }
+        }),
+      )
+      .await
+  }
+
+  /// Take the diagnostics most recently published for the document. Pending
+  /// messages are read first by sending a request which the server must
+  /// answer.
+  pub async fn diagnostics(&mut self, uri: &str) -> io::Result<Option<Value>> {
+    self.flush().await?;
+    let uri = Url::parse(uri)
+      .map_err(|error| io::Error::new(io::ErrorKind::InvalidInput, error))?
+      .to_string();
+    let published = self
+      .take_notifications("textDocument/publishDiagnostics")
+      .into_iter()
+      .filter(|params| params.get("uri").and_then(Value::as_str) == Some(uri.as_str()))
+      .last()
+      .and_then(|mut params| params.get_mut("diagnostics").map(Value::take));
+
+    Ok(published)
+  }
+
+  /// Wait for the server to send a notification with the method and take its
+  /// params. Notifications received earlier are taken first.
+  pub async fn wait_for(&mut self, method: &str) -> io::Result<Value> {
+    loop {
+      if let Some(index) = self
+        .notifications
+        .iter()
+        .position(|notification| notification.get("method").and_then(Value::as_str) == Some(method))
+      {
+        let mut notification = self.notifications.remove(index);
+        return Ok(
+          notification
+            .get_mut("params")
+            .map(Value::take)
+            .unwrap_or_default(),
+        );
+      }
+
+      let message = self.receive().await?;
+
+      if message.get("method").is_some() {
+        self.handle_server_message(message).await?;
+      }
+    }
+  }
+
+  /// Take the params of every notification with the method received so far.
+  pub fn take_notifications(&mut self, method: &str) -> Vec<Value> {
+    let (taken, kept) = self
+      .notifications
+      .drain(..)
+      .partition::<Vec<_>, _>(|notification| {
+        notification.get("method").and_then(Value::as_str) == Some(method)
+      });
+    self.notifications = kept;
+
+    taken
+      .into_iter()
+      .map(|mut notification| {
+        notification
+          .get_mut("params")
+          .map(Value::take)
+          .unwrap_or_default()
+      })
+      .collect()
+  }
+
+  /// Shut the server down and wait for it to exit. The server stops once the
+  /// transport is closed.
+  pub async fn shutdown(mut self) -> io::Result<()> {
+    self.request("shutdown", Value::Null).await?;
+    self.notify("exit", Value::Null).await?;
+    self.writer.shutdown().await?;
+    drop(self.writer);
+    drop(self.reader);
+
+    tokio::time::timeout(TIMEOUT, self.server)
+      .await
+      .map_err(|_| timed_out())?
+      .map_err(|error| io::Error::new(io::ErrorKind::Other, error))
+  }
+
+  /// Read every message the server sent before now. An unknown request is
+  /// answered with an error, which guarantees the earlier messages arrived.
+  async fn flush(&mut self) -> io::Result<()> {
+    self.request("mdt/flush", Value::Null).await.map(|_| ())
+  }
+
+  /// Record notifications and answer the requests the server sends to the
+  /// client with an empty result.
+  async fn handle_server_message(&mut self, message: Value) -> io::Result<()> {
+    let Some(id) = message.get("id") else {
+      self.notifications.push(message);
+      return Ok(());
+    };
+
+    let response = json!({ "jsonrpc": "2.0", "id": id, "result": null });
+    self.send(response).await
+  }
+
+  async fn send(&mut self, message: Value) -> io::Result<()> {
+    let body = message.to_string();
+    let frame = format!("Content-Length: {}\r\n\r\n{body}", body.len());
+    self.writer.write_all(frame.as_bytes()).await?;
+    self.writer.flush().await
+  }
+
+  async fn receive(&mut self) -> io::Result<Value> {
+    tokio::time::timeout(TIMEOUT, read_message(&mut self.reader))
+      .await
+      .map_err(|_| timed_out())?
+  }
+}
+
+fn message(id: Option<i64>, method: &str, params: Value) -> Value {
+  let mut message = json!({ "jsonrpc": "2.0", "method": method });
+
+  if let Some(id) = id {
+    message["id"] = json!(id);
+  }
+
+  if !params.is_null() {
+    message["params"] = params;
+  }
+
+  message
+}
+
+/// Read one message framed with a `Content-Length` header.
+async fn read_message(reader: &mut BufReader<ReadHalf<DuplexStream>>) -> io::Result<Value> {
+  let mut length = None;
+
+  loop {
+    let mut line = String::new();
+
+    if reader.read_line(&mut line).await? == 0 {
+      return Err(io::ErrorKind::UnexpectedEof.into());
+    }
+
+    let line = line.trim_end();
+
+    if line.is_empty() {
+      break;
+    }
+
+    if let Some(value) = line.strip_prefix("Content-Length:") {
+      length = value.trim().parse::<usize>().ok();
+    }
+  }
+
+  let Some(length) = length else {
+    return Err(io::Error::new(
+      io::ErrorKind::InvalidData,
+      "missing the `Content-Length` header",
+    ));
+  };
+
+  let mut body = vec![0; length];
+  reader.read_exact(&mut body).await?;
+
+  serde_json::from_slice(&body).map_err(|error| io::Error::new(io::ErrorKind::InvalidData, error))
+}
+
+fn timed_out() -> io::Error {
+  io::Error::new(
+    io::ErrorKind::TimedOut,
+    "the language server didn't respond",
+  )
+}
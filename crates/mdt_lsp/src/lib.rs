@@ -1,23 +1,408 @@
+use std::collections::BTreeMap;
+use std::collections::HashMap;
+use std::collections::HashSet;
+use std::fs;
+use std::path::Path;
+use std::path::PathBuf;
+use std::sync::RwLock;
+
+use completion::completions;
+use completion::TRIGGER_CHARACTERS;
+pub use harness::*;
+use mdt::block_name_at;
+use mdt::compute_rename;
+use mdt::find_data_definition;
+use mdt::parse_file_with_diagnostics;
+use mdt::provider_impact;
+use mdt::scan_project_with_overlays;
+use mdt::MdtConfig;
+use mdt::MdtError;
+use mdt::ParseDiagnostic;
+use mdt::Project;
+use mdt::VariableIndex;
+use mdt::CONFIG_FILE_NAME;
+use serde_json::json;
+use serde_json::Value;
+use tower_lsp::jsonrpc::Error as LspError;
 use tower_lsp::jsonrpc::Result as LspResult;
 use tower_lsp::lsp_types::*;
 use tower_lsp::Client;
+use tower_lsp::ClientSocket;
 use tower_lsp::LanguageServer;
+use tower_lsp::LspService;
+
+mod completion;
+mod harness;
 
 #[derive(Debug)]
 pub struct MdtLanguageServer {
   client: Client,
+  state: RwLock<ServerState>,
+}
+
+/// The default number of bytes of file content the server keeps from a scan.
+pub const DEFAULT_CACHE_LIMIT: usize = 16 * 1024 * 1024;
+
+/// Create the service with the custom requests of the server, e.g.
+/// `mdt/status`.
+pub fn service() -> (LspService<MdtLanguageServer>, ClientSocket) {
+  LspService::build(MdtLanguageServer::new)
+    .custom_method("mdt/status", MdtLanguageServer::status)
+    .finish()
+}
+
+#[derive(Debug)]
+struct ServerState {
+  root: Option<PathBuf>,
+  /// The project from the last successful scan. It is kept when a later scan
+  /// fails so that features keep working with the last known state.
+  project: Option<Project>,
+  /// The file with a diagnostic for the last failed scan.
+  failed_file: Option<Url>,
+  /// The text of the open documents, which is dropped when they are closed.
+  documents: HashMap<Url, String>,
+  /// The number of bytes of file content kept from a scan. It can be set
+  /// with the `cacheLimit` initialization option.
+  cache_limit: usize,
+  /// The number of files whose content was dropped to stay within the cache
+  /// limit. Requests which need every file, e.g. rename, scan the project
+  /// again instead.
+  evicted: usize,
+  /// The definition files with published impact diagnostics, which are
+  /// cleared when the file is saved or closed.
+  impacted: HashSet<Url>,
+}
+
+impl Default for ServerState {
+  fn default() -> Self {
+    Self {
+      root: None,
+      project: None,
+      failed_file: None,
+      documents: HashMap::new(),
+      cache_limit: DEFAULT_CACHE_LIMIT,
+      evicted: 0,
+      impacted: HashSet::new(),
+    }
+  }
+}
+
+impl ServerState {
+  /// The unsaved text of the open documents by path.
+  fn overlays(&self) -> BTreeMap<PathBuf, String> {
+    self
+      .documents
+      .iter()
+      .filter_map(|(uri, text)| Some((uri.to_file_path().ok()?, text.clone())))
+      .collect()
+  }
+
+  /// The text of the document, preferring its unsaved text to the content
+  /// from the last scan and the content on disk.
+  fn document_text(&self, uri: &Url) -> Option<String> {
+    if let Some(text) = self.documents.get(uri) {
+      return Some(text.clone());
+    }
+
+    let file = uri.to_file_path().ok()?;
+
+    self
+      .project
+      .as_ref()
+      .and_then(|project| project.files.get(&file).cloned())
+      .or_else(|| fs::read_to_string(file).ok())
+  }
+
+  /// Keep the project from a scan, dropping the content of the largest files
+  /// which aren't open until the rest fits within the cache limit.
+  fn store(&mut self, mut project: Project) {
+    let open = self.overlays();
+    let mut cached = project.files.values().map(String::len).sum::<usize>();
+    let mut candidates = project
+      .files
+      .iter()
+      .filter(|(file, _)| !open.contains_key(*file))
+      .map(|(file, content)| (content.len(), file.clone()))
+      .collect::<Vec<_>>();
+    candidates.sort_by(|a, b| b.cmp(a));
+    self.evicted = 0;
+
+    for (size, file) in candidates {
+      if cached <= self.cache_limit {
+        break;
+      }
+
+      if project.files.remove(&file).is_some() {
+        self.evicted += 1;
+        cached -= size;
+      }
+    }
+
+    self.project = Some(project);
+  }
 }
 
 impl MdtLanguageServer {
   pub fn new(client: Client) -> Self {
-    Self { client }
+    Self {
+      client,
+      state: RwLock::default(),
+    }
+  }
+
+  /// Memory statistics for the documents and the project held by the server.
+  pub async fn status(&self) -> LspResult<Value> {
+    let Ok(state) = self.state.read() else {
+      return Ok(Value::Null);
+    };
+    let project = state.project.as_ref();
+
+    Ok(json!({
+      "documents": state.documents.len(),
+      "documentBytes": state.documents.values().map(String::len).sum::<usize>(),
+      "cachedFiles": project.map_or(0, |project| project.files.len()),
+      "cachedBytes": project.map_or(0, |project| {
+        project.files.values().map(String::len).sum::<usize>()
+      }),
+      "evictedFiles": state.evicted,
+      "cacheLimit": state.cache_limit,
+      "providers": project.map_or(0, |project| project.providers.len()),
+      "consumers": project.map_or(0, |project| project.consumers.len()),
+    }))
+  }
+
+  /// Publish an informational diagnostic on each provider of an edited
+  /// definition file with the number of consumers the unsaved edit would make
+  /// stale. Edits which can't be parsed publish nothing.
+  async fn publish_impact(&self, uri: Url) {
+    let Some(diagnostics) = self.state.write().ok().and_then(|mut state| {
+      let file = uri.to_file_path().ok()?;
+      let state = &mut *state;
+      let content = state.documents.get(&uri)?;
+      let impacts = provider_impact(state.project.as_mut()?, &file, content).ok()?;
+
+      Some(
+        impacts
+          .into_iter()
+          .filter(|impact| !impact.consumers.is_empty())
+          .map(|impact| {
+            Diagnostic {
+              range: lsp_range(&impact.opening),
+              severity: Some(DiagnosticSeverity::INFORMATION),
+              source: Some("mdt".into()),
+              message: format!(
+                "this change will mark {} consumer(s) in {} file(s) stale",
+                impact.consumers.len(),
+                impact.file_count()
+              ),
+              ..Diagnostic::default()
+            }
+          })
+          .collect::<Vec<_>>(),
+      )
+    }) else {
+      return;
+    };
+
+    let published = self.state.write().ok().map_or(false, |mut state| {
+      if diagnostics.is_empty() {
+        state.impacted.remove(&uri)
+      } else {
+        state.impacted.insert(uri.clone());
+        true
+      }
+    });
+
+    if published {
+      self
+        .client
+        .publish_diagnostics(uri, diagnostics, None)
+        .await;
+    }
+  }
+
+  /// Clear the impact diagnostics of the document.
+  async fn clear_impact(&self, uri: &Url) {
+    let cleared = self
+      .state
+      .write()
+      .ok()
+      .map_or(false, |mut state| state.impacted.remove(uri));
+
+    if cleared {
+      self
+        .client
+        .publish_diagnostics(uri.clone(), vec![], None)
+        .await;
+    }
+  }
+
+  /// Scan the workspace. When the scan fails the cause is shown to the user
+  /// and published as a diagnostic on the file responsible, if there is one.
+  async fn scan(&self) {
+    let Some((root, overlays)) = self
+      .state
+      .read()
+      .ok()
+      .and_then(|state| Some((state.root.clone()?, state.overlays())))
+    else {
+      return;
+    };
+
+    let result = scan_project_with_overlays(&root, &overlays);
+    let previous = self
+      .state
+      .write()
+      .ok()
+      .and_then(|mut state| state.failed_file.take());
+
+    if let Some(uri) = previous {
+      self.client.publish_diagnostics(uri, vec![], None).await;
+    }
+
+    let error = match result {
+      Ok(project) => {
+        if let Ok(mut state) = self.state.write() {
+          state.store(project);
+        }
+
+        return;
+      }
+      Err(error) => error,
+    };
+
+    let message = format!("mdt: the project couldn't be scanned: {error}");
+    self
+      .client
+      .show_message(MessageType::ERROR, message.clone())
+      .await;
+
+    let Some(uri) = error_file(&error, &root).and_then(|file| Url::from_file_path(file).ok())
+    else {
+      return;
+    };
+
+    let diagnostic = Diagnostic {
+      severity: Some(DiagnosticSeverity::ERROR),
+      source: Some("mdt".into()),
+      message,
+      ..Diagnostic::default()
+    };
+    self
+      .client
+      .publish_diagnostics(uri.clone(), vec![diagnostic], None)
+      .await;
+
+    if let Ok(mut state) = self.state.write() {
+      state.failed_file = Some(uri);
+    }
   }
 }
 
+/// The byte offset of an lsp position, whose character counts utf-16 code
+/// units. Positions past the end of a line are at its end.
+fn byte_offset(content: &str, position: Position) -> Option<usize> {
+  let mut line_start = 0;
+
+  for _ in 0..position.line {
+    line_start += content.get(line_start..)?.find('\n')? + 1;
+  }
+
+  let line = content.get(line_start..)?.split('\n').next()?;
+  let character = usize::try_from(position.character).ok()?;
+  let mut units = 0;
+
+  for (index, char) in line.char_indices() {
+    if units >= character {
+      return Some(line_start + index);
+    }
+
+    units += char.len_utf16();
+  }
+
+  Some(line_start + line.len())
+}
+
+/// The lsp position of a byte offset in the content.
+fn lsp_position(content: &str, offset: usize) -> Position {
+  let before = content.get(..offset).unwrap_or(content);
+  let line = before.matches('\n').count();
+  let character = before
+    .rsplit('\n')
+    .next()
+    .unwrap_or_default()
+    .encode_utf16()
+    .count();
+
+  Position::new(
+    u32::try_from(line).unwrap_or_default(),
+    u32::try_from(character).unwrap_or_default(),
+  )
+}
+
+/// Convert a position in a file, with 1-indexed lines and columns, to an lsp
+/// range.
+fn lsp_range(position: &mdt::Position) -> Range {
+  let point = |point: &mdt::Point| {
+    Position::new(
+      u32::try_from(point.line.saturating_sub(1)).unwrap_or_default(),
+      u32::try_from(point.column.saturating_sub(1)).unwrap_or_default(),
+    )
+  };
+
+  Range::new(point(&position.start), point(&position.end))
+}
+
+/// The file which caused the scan to fail.
+fn error_file(error: &MdtError, root: &Path) -> Option<PathBuf> {
+  let file = match error {
+    MdtError::DuplicateProvider { second, .. } => second.clone(),
+    MdtError::BlockNameCollision { file, .. } => file.clone(),
+    MdtError::DataFile { path, .. } | MdtError::IgnoreFile { path, .. } => path.clone(),
+    MdtError::InvalidConfig(_) => PathBuf::from(CONFIG_FILE_NAME),
+    _ => return None,
+  };
+
+  Some(root.join(file))
+}
+
 #[tower_lsp::async_trait]
 impl LanguageServer for MdtLanguageServer {
-  async fn initialize(&self, _: InitializeParams) -> LspResult<InitializeResult> {
-    Ok(InitializeResult::default())
+  async fn initialize(&self, params: InitializeParams) -> LspResult<InitializeResult> {
+    let root = params.root_uri.and_then(|uri| uri.to_file_path().ok());
+    let cache_limit = params
+      .initialization_options
+      .as_ref()
+      .and_then(|options| options.get("cacheLimit"))
+      .and_then(Value::as_u64)
+      .and_then(|limit| usize::try_from(limit).ok());
+
+    if let Ok(mut state) = self.state.write() {
+      state.root = root;
+      state.cache_limit = cache_limit.unwrap_or(DEFAULT_CACHE_LIMIT);
+    }
+
+    Ok(InitializeResult {
+      capabilities: ServerCapabilities {
+        text_document_sync: Some(TextDocumentSyncCapability::Options(
+          TextDocumentSyncOptions {
+            open_close: Some(true),
+            change: Some(TextDocumentSyncKind::FULL),
+            save: Some(TextDocumentSyncSaveOptions::Supported(true)),
+            ..TextDocumentSyncOptions::default()
+          },
+        )),
+        rename_provider: Some(OneOf::Left(true)),
+        code_action_provider: Some(CodeActionProviderCapability::Simple(true)),
+        definition_provider: Some(OneOf::Left(true)),
+        completion_provider: Some(CompletionOptions {
+          trigger_characters: Some(TRIGGER_CHARACTERS.iter().map(ToString::to_string).collect()),
+          ..CompletionOptions::default()
+        }),
+        ..ServerCapabilities::default()
+      },
+      ..InitializeResult::default()
+    })
   }
 
   async fn initialized(&self, _: InitializedParams) {
@@ -25,6 +410,231 @@ impl LanguageServer for MdtLanguageServer {
       .client
       .log_message(MessageType::INFO, "server initialized!")
       .await;
+    self.scan().await;
+  }
+
+  async fn did_open(&self, params: DidOpenTextDocumentParams) {
+    if let Ok(mut state) = self.state.write() {
+      let document = params.text_document;
+      state.documents.insert(document.uri, document.text);
+    }
+  }
+
+  async fn did_change(&self, params: DidChangeTextDocumentParams) {
+    // The server uses full document sync so the last change is the text.
+    let Some(change) = params.content_changes.into_iter().last() else {
+      return;
+    };
+
+    let uri = params.text_document.uri;
+
+    if let Ok(mut state) = self.state.write() {
+      state.documents.insert(uri.clone(), change.text);
+    }
+
+    self.publish_impact(uri).await;
+  }
+
+  async fn did_close(&self, params: DidCloseTextDocumentParams) {
+    let uri = params.text_document.uri;
+
+    if let Ok(mut state) = self.state.write() {
+      state.documents.remove(&uri);
+    }
+
+    self.clear_impact(&uri).await;
+  }
+
+  async fn did_save(&self, params: DidSaveTextDocumentParams) {
+    self.clear_impact(&params.text_document.uri).await;
+    self.scan().await;
+  }
+
+  /// Rename the block whose tag is under the cursor in every file, with the
+  /// same edits as `mdt rename`. The project is scanned again so the edits
+  /// match the open documents and the files dropped from the cache.
+  async fn rename(&self, params: RenameParams) -> LspResult<Option<WorkspaceEdit>> {
+    let document = params.text_document_position;
+    let Some((root, overlays)) = self
+      .state
+      .read()
+      .ok()
+      .and_then(|state| Some((state.root.clone()?, state.overlays())))
+    else {
+      return Ok(None);
+    };
+    let Ok(file) = document.text_document.uri.to_file_path() else {
+      return Ok(None);
+    };
+
+    let invalid = |error: MdtError| LspError::invalid_params(error.to_string());
+    let project = scan_project_with_overlays(&root, &overlays).map_err(invalid)?;
+    let Some(offset) = project
+      .files
+      .get(&file)
+      .and_then(|content| byte_offset(content, document.position))
+    else {
+      return Ok(None);
+    };
+    let Some(old_name) = block_name_at(&project, &file, offset).map_err(invalid)? else {
+      return Ok(None);
+    };
+
+    let mut changes = HashMap::<Url, Vec<TextEdit>>::new();
+
+    for patch in compute_rename(&project, &old_name, &params.new_name).map_err(invalid)? {
+      let (Some(content), Ok(uri)) = (
+        project.files.get(&patch.file),
+        Url::from_file_path(&patch.file),
+      ) else {
+        continue;
+      };
+
+      changes.entry(uri).or_default().push(TextEdit {
+        range: Range::new(
+          lsp_position(content, patch.range.start),
+          lsp_position(content, patch.range.end),
+        ),
+        new_text: patch.replacement,
+      });
+    }
+
+    Ok(Some(WorkspaceEdit {
+      changes: Some(changes),
+      ..WorkspaceEdit::default()
+    }))
+  }
+
+  /// Complete the provider names, transformers and data keys within tags.
+  async fn completion(&self, params: CompletionParams) -> LspResult<Option<CompletionResponse>> {
+    let document = params.text_document_position;
+    let items = self.state.read().ok().and_then(|state| {
+      let project = state.project.as_ref()?;
+      let content = state.document_text(&document.text_document.uri)?;
+      let offset = byte_offset(&content, document.position)?;
+
+      Some(completions(project, &content, offset))
+    });
+
+    Ok(items.map(CompletionResponse::Array))
+  }
+
+  /// Go from a data variable within a provider, e.g. `{{ pkg.version }}`, to
+  /// its key in the data file, or from the name in a tag to its provider.
+  async fn goto_definition(
+    &self,
+    params: GotoDefinitionParams,
+  ) -> LspResult<Option<GotoDefinitionResponse>> {
+    let document = params.text_document_position_params;
+    let Ok(file) = document.text_document.uri.to_file_path() else {
+      return Ok(None);
+    };
+
+    let definition = self.state.read().ok().and_then(|state| {
+      let project = state.project.as_ref()?;
+      let offset = byte_offset(project.files.get(&file)?, document.position)?;
+
+      if let Some(usage) = VariableIndex::build(project).usage_at(&file, offset) {
+        let location = find_data_definition(project, &usage.variable)?;
+        let content = fs::read_to_string(&location.file).ok()?;
+
+        return Some((location.file, content, location.range));
+      }
+
+      let name = block_name_at(project, &file, offset).ok()??;
+      let provider = project.providers.get(&name)?;
+      let content = state.document_text(&Url::from_file_path(&provider.file).ok()?)?;
+      let position = provider.block.opening_name;
+
+      Some((
+        provider.file.clone(),
+        content,
+        position.start.offset..position.end.offset,
+      ))
+    });
+    let Some((file, content, range)) = definition else {
+      return Ok(None);
+    };
+    let Ok(uri) = Url::from_file_path(file) else {
+      return Ok(None);
+    };
+
+    Ok(Some(GotoDefinitionResponse::Scalar(Location::new(
+      uri,
+      Range::new(
+        lsp_position(&content, range.start),
+        lsp_position(&content, range.end),
+      ),
+    ))))
+  }
+
+  /// Offer to rename a closing tag which doesn't match its block, e.g. the
+  /// `{/instal}` closing `{=install}`, to the name of the block.
+  async fn code_action(&self, params: CodeActionParams) -> LspResult<Option<CodeActionResponse>> {
+    let uri = params.text_document.uri;
+    let Some((content, config)) = self.state.read().ok().and_then(|state| {
+      let config = state
+        .project
+        .as_ref()
+        .map_or_else(MdtConfig::default, |project| project.config.clone());
+
+      Some((state.document_text(&uri)?, config))
+    }) else {
+      return Ok(None);
+    };
+    let Ok(file) = uri.to_file_path() else {
+      return Ok(None);
+    };
+    let Ok((_, diagnostics)) = parse_file_with_diagnostics(&file, &content, &config) else {
+      return Ok(None);
+    };
+
+    let actions = diagnostics
+      .into_iter()
+      .filter_map(|diagnostic| {
+        let message = diagnostic.to_string();
+
+        match diagnostic {
+          ParseDiagnostic::MismatchedClosingTag {
+            expected, position, ..
+          } => {
+            let range = Range::new(
+              lsp_position(&content, position.start.offset),
+              lsp_position(&content, position.end.offset),
+            );
+
+            if range.start > params.range.end || range.end < params.range.start {
+              return None;
+            }
+
+            let edit = TextEdit {
+              range,
+              new_text: expected.clone(),
+            };
+
+            Some(CodeActionOrCommand::CodeAction(CodeAction {
+              title: format!("Rename the closing tag to `{expected}`"),
+              kind: Some(CodeActionKind::QUICKFIX),
+              diagnostics: Some(vec![Diagnostic {
+                range,
+                severity: Some(DiagnosticSeverity::ERROR),
+                source: Some("mdt".into()),
+                message,
+                ..Diagnostic::default()
+              }]),
+              edit: Some(WorkspaceEdit {
+                changes: Some(HashMap::from([(uri.clone(), vec![edit])])),
+                ..WorkspaceEdit::default()
+              }),
+              is_preferred: Some(true),
+              ..CodeAction::default()
+            }))
+          }
+        }
+      })
+      .collect::<Vec<_>>();
+
+    Ok(Some(actions))
   }
 
   async fn shutdown(&self) -> LspResult<()> {
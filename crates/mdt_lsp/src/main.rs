@@ -1,5 +1,4 @@
-use mdt_lsp::MdtLanguageServer;
-use tower_lsp::LspService;
+use mdt_lsp::service;
 use tower_lsp::Server;
 
 #[tokio::main]
@@ -7,6 +6,6 @@ async fn main() {
   let stdin = tokio::io::stdin();
   let stdout = tokio::io::stdout();
 
-  let (service, socket) = LspService::new(MdtLanguageServer::new);
+  let (service, socket) = service();
   Server::new(stdin, stdout, socket).serve(service).await;
 }
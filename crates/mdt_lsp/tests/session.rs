@@ -0,0 +1,409 @@
+use std::fs;
+
+use mdt_lsp::LspSession;
+use serde_json::json;
+use serde_json::Value;
+use tower_lsp::lsp_types::Url;
+
+#[tokio::test]
+async fn scripted_session() -> std::io::Result<()> {
+  let uri = "file:///project/readme.md";
+  let mut session = LspSession::start();
+
+  let capabilities = session.initialize("file:///project").await?;
+  insta::assert_snapshot!(serde_json::to_string_pretty(&capabilities)?);
+
+  session
+    .open(uri, "<!-- {=install} -->\n<!-- {/install} -->\n")
+    .await?;
+  session
+    .change(uri, 2, "<!-- {=usage} -->\n<!-- {/usage} -->\n")
+    .await?;
+  session.save(uri).await?;
+
+  assert_eq!(session.diagnostics(uri).await?, None);
+  assert_eq!(session.completion(uri, 0, 7).await?, json!(null));
+  assert_eq!(
+    session.take_notifications("window/logMessage"),
+    vec![json!({ "type": 3, "message": "server initialized!" })]
+  );
+
+  session.shutdown().await
+}
+
+#[tokio::test]
+async fn scan_failures_are_reported() -> Result<(), Box<dyn std::error::Error>> {
+  let directory = tempfile::tempdir()?;
+  let root = directory.path();
+  let provider = "<!-- {@install} -->\ncargo install mdt\n<!-- {/install} -->\n";
+  fs::write(root.join("a.t.md"), provider)?;
+  fs::write(root.join("b.t.md"), provider)?;
+
+  let root_uri = Url::from_directory_path(root).map_err(|_| "invalid root")?;
+  let file_uri = Url::from_file_path(root.join("b.t.md")).map_err(|_| "invalid file")?;
+  let mut session = LspSession::start();
+  session.initialize(root_uri.as_str()).await?;
+
+  let message = session.wait_for("window/showMessage").await?;
+  assert_eq!(message.get("type"), Some(&json!(1)));
+  assert!(message
+    .get("message")
+    .and_then(|message| message.as_str())
+    .map_or(false, |message| {
+      message.contains("duplicate provider `install`")
+    }));
+
+  let published = session.wait_for("textDocument/publishDiagnostics").await?;
+  assert_eq!(published.get("uri"), Some(&json!(file_uri.as_str())));
+
+  fs::remove_file(root.join("b.t.md"))?;
+  session.save(file_uri.as_str()).await?;
+  let cleared = session.wait_for("textDocument/publishDiagnostics").await?;
+  assert_eq!(
+    cleared,
+    json!({ "uri": file_uri.as_str(), "diagnostics": [] })
+  );
+
+  session.shutdown().await?;
+
+  Ok(())
+}
+
+#[tokio::test]
+async fn memory_is_bounded() -> Result<(), Box<dyn std::error::Error>> {
+  let directory = tempfile::tempdir()?;
+  let root = directory.path();
+  let consumer = "<!-- {=install} -->\n<!-- {/install} -->\n";
+  fs::write(
+    root.join("template.t.md"),
+    "<!-- {@install} -->\ncargo install mdt\n<!-- {/install} -->\n",
+  )?;
+  fs::write(root.join("a.md"), consumer)?;
+  fs::write(root.join("b.md"), format!("# B\n\n{consumer}"))?;
+
+  let root_uri = Url::from_directory_path(root).map_err(|_| "invalid root")?;
+  let uri = Url::from_file_path(root.join("b.md")).map_err(|_| "invalid file")?;
+  let mut session = LspSession::start();
+  session
+    .initialize_with_options(root_uri.as_str(), json!({ "cacheLimit": 100 }))
+    .await?;
+  session
+    .open(uri.as_str(), &format!("# B\n\n{consumer}"))
+    .await?;
+  session.save(uri.as_str()).await?;
+
+  let status = session.status().await?;
+  assert_eq!(status.get("documents"), Some(&json!(1)));
+  assert_eq!(status.get("providers"), Some(&json!(1)));
+  assert_eq!(status.get("consumers"), Some(&json!(2)));
+  assert_eq!(status.get("cacheLimit"), Some(&json!(100)));
+  // The largest closed file is dropped first and the open document is kept.
+  assert_eq!(status.get("evictedFiles"), Some(&json!(1)));
+  assert_eq!(status.get("cachedFiles"), Some(&json!(2)));
+
+  session.close(uri.as_str()).await?;
+  let status = session.status().await?;
+  assert_eq!(status.get("documents"), Some(&json!(0)));
+  assert_eq!(status.get("documentBytes"), Some(&json!(0)));
+
+  session.shutdown().await?;
+
+  Ok(())
+}
+
+#[tokio::test]
+async fn provider_edits_report_their_impact() -> Result<(), Box<dyn std::error::Error>> {
+  let directory = tempfile::tempdir()?;
+  let root = directory.path();
+  let provider = "<!-- {@install} -->\ncargo install mdt\n<!-- {/install} -->\n";
+  let consumer = "<!-- {=install} -->\ncargo install mdt\n<!-- {/install} -->\n";
+  fs::write(root.join("template.t.md"), provider)?;
+  fs::write(root.join("a.md"), consumer)?;
+  fs::write(root.join("b.md"), format!("{consumer}\n{consumer}"))?;
+
+  let root_uri = Url::from_directory_path(root).map_err(|_| "invalid root")?;
+  let uri = Url::from_file_path(root.join("template.t.md")).map_err(|_| "invalid file")?;
+  let mut session = LspSession::start();
+  session.initialize(root_uri.as_str()).await?;
+  session.open(uri.as_str(), provider).await?;
+  session.save(uri.as_str()).await?;
+  session
+    .change(
+      uri.as_str(),
+      2,
+      "# Install\n\n<!-- {@install} -->\ncargo install mdt_cli\n<!-- {/install} -->\n",
+    )
+    .await?;
+
+  assert_eq!(
+    session.wait_for("textDocument/publishDiagnostics").await?,
+    json!({
+      "uri": uri.as_str(),
+      "diagnostics": [{
+        "range": {
+          "start": { "line": 2, "character": 0 },
+          "end": { "line": 2, "character": 19 }
+        },
+        "severity": 3,
+        "source": "mdt",
+        "message": "this change will mark 3 consumer(s) in 2 file(s) stale"
+      }]
+    })
+  );
+
+  session.change(uri.as_str(), 3, provider).await?;
+  assert_eq!(
+    session.wait_for("textDocument/publishDiagnostics").await?,
+    json!({ "uri": uri.as_str(), "diagnostics": [] })
+  );
+  assert_eq!(session.diagnostics(uri.as_str()).await?, None);
+
+  session.shutdown().await?;
+
+  Ok(())
+}
+
+#[tokio::test]
+async fn blocks_can_be_renamed() -> Result<(), Box<dyn std::error::Error>> {
+  let directory = tempfile::tempdir()?;
+  let root = directory.path();
+  fs::write(
+    root.join("template.t.md"),
+    "<!-- {@install} -->\ncargo install mdt\n<!-- {/install} -->\n",
+  )?;
+  fs::write(
+    root.join("readme.md"),
+    "<!-- {=install} -->\ncargo install mdt\n<!-- {/install} -->\n",
+  )?;
+
+  let root_uri = Url::from_directory_path(root).map_err(|_| "invalid root")?;
+  let template_uri = Url::from_file_path(root.join("template.t.md")).map_err(|_| "invalid file")?;
+  let readme_uri = Url::from_file_path(root.join("readme.md")).map_err(|_| "invalid file")?;
+  let mut session = LspSession::start();
+  session.initialize(root_uri.as_str()).await?;
+
+  let edit = |line: u32, start: u32, end: u32| {
+    json!({
+      "range": {
+        "start": { "line": line, "character": start },
+        "end": { "line": line, "character": end }
+      },
+      "newText": "setup"
+    })
+  };
+  assert_eq!(
+    session.rename(readme_uri.as_str(), 2, 9, "setup").await?,
+    json!({
+      "changes": {
+        readme_uri.as_str(): [edit(0, 7, 14), edit(2, 7, 14)],
+        template_uri.as_str(): [edit(0, 7, 14), edit(2, 7, 14)]
+      }
+    })
+  );
+
+  assert_eq!(
+    session.rename(readme_uri.as_str(), 1, 0, "setup").await?,
+    json!(null)
+  );
+  assert_eq!(
+    session
+      .rename(readme_uri.as_str(), 0, 9, "not valid")
+      .await?
+      .get("code"),
+    Some(&json!(-32602))
+  );
+
+  session.shutdown().await?;
+
+  Ok(())
+}
+
+#[tokio::test]
+async fn mismatched_closing_tags_can_be_fixed() -> Result<(), Box<dyn std::error::Error>> {
+  let directory = tempfile::tempdir()?;
+  let root = directory.path();
+  fs::write(
+    root.join("template.t.md"),
+    "<!-- {@install} -->\ncargo install mdt\n<!-- {/install} -->\n",
+  )?;
+  fs::write(root.join("readme.md"), "")?;
+
+  let root_uri = Url::from_directory_path(root).map_err(|_| "invalid root")?;
+  let uri = Url::from_file_path(root.join("readme.md")).map_err(|_| "invalid file")?;
+  let mut session = LspSession::start();
+  session.initialize(root_uri.as_str()).await?;
+  session
+    .open(
+      uri.as_str(),
+      "# Readme\n<!-- {=install} -->\ncargo install mdt\n<!-- {/instal} -->\n",
+    )
+    .await?;
+
+  let range = json!({
+    "start": { "line": 3, "character": 7 },
+    "end": { "line": 3, "character": 13 }
+  });
+  assert_eq!(
+    session.code_action(uri.as_str(), 3, 9).await?,
+    json!([{
+      "title": "Rename the closing tag to `install`",
+      "kind": "quickfix",
+      "diagnostics": [{
+        "range": range,
+        "severity": 1,
+        "source": "mdt",
+        "message": "the closing tag `instal` at 4:8 doesn't match the block `install`"
+      }],
+      "edit": {
+        "changes": { uri.as_str(): [{ "range": range, "newText": "install" }] }
+      },
+      "isPreferred": true
+    }])
+  );
+  assert_eq!(session.code_action(uri.as_str(), 1, 9).await?, json!([]));
+
+  session.shutdown().await?;
+
+  Ok(())
+}
+
+#[tokio::test]
+async fn definitions_can_be_found() -> Result<(), Box<dyn std::error::Error>> {
+  let directory = tempfile::tempdir()?;
+  let root = directory.path();
+  fs::write(root.join("mdt.toml"), "[data]\npkg = \"package.json\"\n")?;
+  fs::write(
+    root.join("package.json"),
+    "{\n  \"name\": \"mdt\",\n  \"version\": \"1.0.0\"\n}\n",
+  )?;
+  fs::write(
+    root.join("template.t.md"),
+    "<!-- {@install} -->\nnpm install mdt@{{ pkg.version }}\n<!-- {/install} -->\n",
+  )?;
+  fs::write(
+    root.join("readme.md"),
+    "# Readme\n\n<!-- {=install} -->\n<!-- {/install} -->\n",
+  )?;
+
+  let root_uri = Url::from_directory_path(root).map_err(|_| "invalid root")?;
+  let template_uri = Url::from_file_path(root.join("template.t.md")).map_err(|_| "invalid file")?;
+  let readme_uri = Url::from_file_path(root.join("readme.md")).map_err(|_| "invalid file")?;
+  let data_uri = Url::from_file_path(root.join("package.json")).map_err(|_| "invalid file")?;
+  let mut session = LspSession::start();
+  session.initialize(root_uri.as_str()).await?;
+
+  let location = |uri: &Url, line: u32, start: u32, end: u32| {
+    json!({
+      "uri": uri.as_str(),
+      "range": {
+        "start": { "line": line, "character": start },
+        "end": { "line": line, "character": end }
+      }
+    })
+  };
+  assert_eq!(
+    session.definition(template_uri.as_str(), 1, 24).await?,
+    location(&data_uri, 2, 2, 11)
+  );
+  assert_eq!(
+    session.definition(readme_uri.as_str(), 2, 9).await?,
+    location(&template_uri, 0, 7, 14)
+  );
+  assert_eq!(
+    session.definition(readme_uri.as_str(), 0, 2).await?,
+    json!(null)
+  );
+
+  session.shutdown().await?;
+
+  Ok(())
+}
+
+#[tokio::test]
+async fn tags_can_be_completed() -> Result<(), Box<dyn std::error::Error>> {
+  let directory = tempfile::tempdir()?;
+  let root = directory.path();
+  fs::write(
+    root.join("mdt.toml"),
+    "[data]\npkg = \"package.json\"\n\n[transformers]\ndeny = \
+     [\"replace\"]\n\n[transformers.shout]\ncommand = \"tr a-z \
+     A-Z\"\n\n[transformer_aliases]\nsnippet = \"trim|indent:\\\"  \\\"\"\n",
+  )?;
+  fs::write(
+    root.join("package.json"),
+    "{\n  \"name\": \"mdt\",\n  \"repository\": { \"url\": \"https://github.com/ifiokjr/mdt\" \
+     }\n}\n",
+  )?;
+  fs::write(
+    root.join("template.t.md"),
+    "<!-- {@install} -->\ncargo install mdt\n<!-- {/install} -->\n<!-- {@docs/usage} -->\nmdt \
+     check\n<!-- {/docs/usage} -->\n",
+  )?;
+  fs::write(root.join("readme.md"), "")?;
+
+  let root_uri = Url::from_directory_path(root).map_err(|_| "invalid root")?;
+  let uri = Url::from_file_path(root.join("readme.md")).map_err(|_| "invalid file")?;
+  let mut session = LspSession::start();
+  session.initialize(root_uri.as_str()).await?;
+  session
+    .open(
+      uri.as_str(),
+      "<!-- {=docs/} -->\n<!-- {=install|sh} -->\n<!-- {=install|} -->\n<!-- {=install|@} -->\n{{ \
+       pkg.repository. }}\n{{ p }}\n",
+    )
+    .await?;
+
+  let labels = |items: Value| {
+    items
+      .as_array()
+      .map(|items| {
+        items
+          .iter()
+          .filter_map(|item| item.get("label")?.as_str().map(String::from))
+          .collect::<Vec<_>>()
+      })
+      .unwrap_or_default()
+  };
+
+  let names = session.completion(uri.as_str(), 0, 12).await?;
+  assert_eq!(labels(names.clone()), vec!["docs/usage"]);
+  assert_eq!(
+    names.pointer("/0/textEdit"),
+    Some(&json!({
+      "range": {
+        "start": { "line": 0, "character": 7 },
+        "end": { "line": 0, "character": 12 }
+      },
+      "newText": "docs/usage"
+    }))
+  );
+  assert_eq!(
+    labels(session.completion(uri.as_str(), 1, 17).await?),
+    vec!["shout"]
+  );
+
+  let transformers = labels(session.completion(uri.as_str(), 2, 15).await?);
+  assert!(transformers.contains(&"dedent".to_string()));
+  assert!(transformers.contains(&"shout".to_string()));
+  assert!(!transformers.contains(&"replace".to_string()));
+  assert_eq!(
+    labels(session.completion(uri.as_str(), 3, 16).await?),
+    vec!["@snippet"]
+  );
+  assert_eq!(
+    labels(session.completion(uri.as_str(), 4, 18).await?),
+    vec!["url"]
+  );
+  assert_eq!(
+    labels(session.completion(uri.as_str(), 5, 4).await?),
+    vec!["pkg"]
+  );
+  assert_eq!(
+    labels(session.completion(uri.as_str(), 1, 2).await?),
+    Vec::<String>::new()
+  );
+
+  session.shutdown().await?;
+
+  Ok(())
+}